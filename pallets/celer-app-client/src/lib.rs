@@ -0,0 +1,152 @@
+//! Off-chain client of the Celer app runtime modules
+//!
+//! Game servers use this crate to build the initiate requests, compute the session id,
+//! produce the signing payload of an app state and assemble the co-signed `StateProof`
+//! exactly as the runtime modules expect, without duplicating the module internals.
+//! Every function is generic over the runtime, which implements the `Trait` of the module.
+
+mod mock;
+#[cfg(test)]
+mod tests;
+
+pub mod single_session;
+pub mod multi_session;
+pub mod single_gomoku;
+pub mod multi_gomoku;
+
+use sp_core::Pair;
+use sp_runtime::traits::{IdentifyAccount, Verify};
+use std::fmt;
+
+/// Failures of building a state proof
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ClientError {
+    /// Signer is not a player of the app
+    NotPlayer,
+    /// Signature does not verify against its player
+    InvalidSignature,
+    /// Player at the index has not signed the app state
+    MissingSignature(usize),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::NotPlayer => write!(f, "signer is not a player"),
+            ClientError::InvalidSignature => write!(f, "signature does not verify against its player"),
+            ClientError::MissingSignature(index) => write!(f, "player {} has not signed", index),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+/// Sort players into the ascending order required by the runtime modules
+///
+/// Parameter:
+/// `players`: AccountId of players
+pub fn sort_players<AccountId: Ord>(mut players: Vec<AccountId>) -> Vec<AccountId> {
+    players.sort();
+    players
+}
+
+/// Collects the signatures of the players over a signing payload in player order
+///
+/// Signatures arrive in any order, from local keys or from remote players, and are
+/// verified against their player before they are accepted.
+pub struct SignatureCollector<AccountId, Signature> {
+    players: Vec<AccountId>,
+    payload: Vec<u8>,
+    sigs: Vec<Option<Signature>>,
+}
+
+impl<AccountId, Signature> SignatureCollector<AccountId, Signature>
+where
+    AccountId: Eq,
+    Signature: Verify,
+    Signature::Signer: IdentifyAccount<AccountId = AccountId>,
+{
+    /// Start collecting signatures
+    ///
+    /// Parameters:
+    /// `players`: AccountId of players in ascending order
+    /// `payload`: Signing payload of the app state
+    pub fn new(players: Vec<AccountId>, payload: Vec<u8>) -> Self {
+        let sigs = players.iter().map(|_| None).collect();
+        SignatureCollector {
+            players: players,
+            payload: payload,
+            sigs: sigs,
+        }
+    }
+
+    /// Signing payload the players sign
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Add the signature of a player
+    ///
+    /// Parameters:
+    /// `player`: AccountId of the signer
+    /// `sig`: Signature of the signer over the payload
+    pub fn add_signature(&mut self, player: &AccountId, sig: Signature) -> Result<(), ClientError> {
+        let index = match self.players.iter().position(|p| p == player) {
+            Some(index) => index,
+            None => return Err(ClientError::NotPlayer),
+        };
+        if !sig.verify(&self.payload[..], player) {
+            return Err(ClientError::InvalidSignature);
+        }
+        self.sigs[index] = Some(sig);
+
+        Ok(())
+    }
+
+    /// Sign the payload with a local key of a player
+    ///
+    /// Parameter:
+    /// `pair`: Key pair of the signer
+    pub fn sign<P: Pair>(&mut self, pair: &P) -> Result<(), ClientError>
+    where
+        AccountId: From<P::Public>,
+        Signature: From<P::Signature>,
+    {
+        let sig = pair.sign(&self.payload);
+        self.add_signature(&pair.public().into(), sig.into())
+    }
+
+    /// Signatures of all players in player order
+    pub fn into_sigs(self) -> Result<Vec<Signature>, ClientError> {
+        self.sigs
+            .into_iter()
+            .enumerate()
+            .map(|(index, sig)| sig.ok_or(ClientError::MissingSignature(index)))
+            .collect()
+    }
+}
+
+/// Sign a payload with the local keys of all players
+///
+/// Parameters:
+/// `players`: AccountId of players in ascending order
+/// `payload`: Signing payload of the app state
+/// `pairs`: Key pairs of the players in any order
+pub fn co_sign<AccountId, Signature, P>(
+    players: Vec<AccountId>,
+    payload: Vec<u8>,
+    pairs: &[P],
+) -> Result<Vec<Signature>, ClientError>
+where
+    AccountId: Eq + From<P::Public>,
+    Signature: Verify + From<P::Signature>,
+    Signature::Signer: IdentifyAccount<AccountId = AccountId>,
+    P: Pair,
+{
+    let mut collector = SignatureCollector::new(players, payload);
+    for pair in pairs {
+        collector.sign(pair)?;
+    }
+
+    collector.into_sigs()
+}
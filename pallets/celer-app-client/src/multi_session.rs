@@ -0,0 +1,117 @@
+//! Client of the multi session app runtime module
+
+use multi_session_app::{
+    AppStateOf, Module, SessionInitiateRequestOf, StateProof, StateProofOf, Trait,
+};
+use sp_core::Pair;
+use crate::{co_sign, sort_players, ClientError};
+
+/// Build the request initiating a session and its session id
+///
+/// Parameters:
+/// `nonce`: Nonce of session
+/// `players`: AccountId of players in any order
+/// `timeout`: Timeout of the session
+/// `time_bank`: Blocks of every player for the on-chain phase, `None` for a fixed timeout per action
+pub fn initiate_request<T: Trait>(
+    nonce: u128,
+    players: Vec<T::AccountId>,
+    timeout: T::BlockNumber,
+    time_bank: Option<T::BlockNumber>,
+) -> (SessionInitiateRequestOf<T>, T::Hash) {
+    let players = sort_players(players);
+    let initiate_request = SessionInitiateRequestOf::<T> {
+        nonce: nonce,
+        player_num: players.len() as u8,
+        players: players,
+        timeout: timeout,
+        time_bank: time_bank,
+    };
+    let session_id = session_id::<T>(&initiate_request);
+
+    (initiate_request, session_id)
+}
+
+/// Get the session id of a session
+///
+/// Parameter:
+/// `initiate_request`: Request initiating the session
+pub fn session_id<T: Trait>(initiate_request: &SessionInitiateRequestOf<T>) -> T::Hash {
+    Module::<T>::get_session_id(initiate_request)
+}
+
+/// Get the bytes of a session state the players sign
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+pub fn signing_payload<T: Trait>(app_state: &AppStateOf<T>, genesis_hash: T::Hash) -> Vec<u8> {
+    Module::<T>::signing_payload(app_state.clone(), genesis_hash)
+}
+
+/// Get the bytes of a session state the players sign as the final state
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+pub fn final_signing_payload<T: Trait>(app_state: &AppStateOf<T>, genesis_hash: T::Hash) -> Vec<u8> {
+    Module::<T>::final_signing_payload(app_state.clone(), genesis_hash)
+}
+
+/// Assemble a state proof from signatures in player order
+///
+/// Parameters:
+/// `app_state`: app state
+/// `sigs`: Signatures of the players in player order
+pub fn state_proof<T: Trait>(app_state: AppStateOf<T>, sigs: Vec<T::Signature>) -> StateProofOf<T> {
+    StateProof {
+        app_state: app_state,
+        sigs: sigs,
+    }
+}
+
+/// Co-sign a session state with the local keys of all players
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+/// `players`: AccountId of players in ascending order
+/// `pairs`: Key pairs of the players in any order
+pub fn sign_state_proof<T: Trait, P: Pair>(
+    app_state: AppStateOf<T>,
+    genesis_hash: T::Hash,
+    players: Vec<T::AccountId>,
+    pairs: &[P],
+) -> Result<StateProofOf<T>, ClientError>
+where
+    T::AccountId: From<P::Public>,
+    T::Signature: From<P::Signature>,
+{
+    let payload = signing_payload::<T>(&app_state, genesis_hash);
+    let sigs = co_sign(players, payload, pairs)?;
+
+    Ok(state_proof::<T>(app_state, sigs))
+}
+
+/// Co-sign a session state as the final state with the local keys of all players
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+/// `players`: AccountId of players in ascending order
+/// `pairs`: Key pairs of the players in any order
+pub fn sign_final_state_proof<T: Trait, P: Pair>(
+    app_state: AppStateOf<T>,
+    genesis_hash: T::Hash,
+    players: Vec<T::AccountId>,
+    pairs: &[P],
+) -> Result<StateProofOf<T>, ClientError>
+where
+    T::AccountId: From<P::Public>,
+    T::Signature: From<P::Signature>,
+{
+    let payload = final_signing_payload::<T>(&app_state, genesis_hash);
+    let sigs = co_sign(players, payload, pairs)?;
+
+    Ok(state_proof::<T>(app_state, sigs))
+}
@@ -0,0 +1,153 @@
+//! Client of the single gomoku runtime module
+
+use ::single_gomoku::{
+    AppInitiateRequestOf, AppStateOf, Module, RuleSet, StateProof, StateProofOf, Trait,
+};
+use sp_core::Pair;
+use crate::{co_sign, sort_players, ClientError};
+
+/// Build the request initiating an app and the session id of the app
+///
+/// Parameters:
+/// `nonce`: Nonce of app
+/// `players`: AccountId of players in any order
+/// `timeout`: Timeout of the app
+/// `min_stone_offchain`: Minimum number of stones placed off-chain before an on-chain dispute
+/// `max_stone_onchain`: Maximum number of stones placed on-chain before a draw
+/// `board_size`: Number of rows and columns of the board
+/// `win_length`: Number of stones in a row that wins
+/// `rule_set`: Rule set deciding wins and forbidden moves
+/// `time_bank`: Blocks of every player for the on-chain phase, `None` for a fixed timeout per action
+/// `unilateral`: Accept states signed only by the player who moved
+pub fn initiate_request<T: Trait>(
+    nonce: u128,
+    players: Vec<T::AccountId>,
+    timeout: T::BlockNumber,
+    min_stone_offchain: u8,
+    max_stone_onchain: u8,
+    board_size: u8,
+    win_length: u8,
+    rule_set: RuleSet,
+    time_bank: Option<T::BlockNumber>,
+    unilateral: bool,
+) -> (AppInitiateRequestOf<T>, T::Hash) {
+    let players = sort_players(players);
+    let initiate_request = AppInitiateRequestOf::<T> {
+        nonce: nonce,
+        players: players,
+        timeout: timeout,
+        min_stone_offchain: min_stone_offchain,
+        max_stone_onchain: max_stone_onchain,
+        board_size: board_size,
+        win_length: win_length,
+        rule_set: rule_set,
+        time_bank: time_bank,
+        unilateral: unilateral,
+    };
+    let session_id = session_id::<T>(&initiate_request);
+
+    (initiate_request, session_id)
+}
+
+/// Get the session id of an app
+///
+/// Parameter:
+/// `initiate_request`: Request initiating the app
+pub fn session_id<T: Trait>(initiate_request: &AppInitiateRequestOf<T>) -> T::Hash {
+    Module::<T>::get_session_id(initiate_request)
+}
+
+/// Get the bytes of an app state the players sign
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+pub fn signing_payload<T: Trait>(app_state: &AppStateOf<T>, genesis_hash: T::Hash) -> Vec<u8> {
+    Module::<T>::signing_payload(app_state.clone(), genesis_hash)
+}
+
+/// Get the bytes of an app state the players sign as the final state
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+pub fn final_signing_payload<T: Trait>(app_state: &AppStateOf<T>, genesis_hash: T::Hash) -> Vec<u8> {
+    Module::<T>::final_signing_payload(app_state.clone(), genesis_hash)
+}
+
+/// Assemble a state proof from signatures in player order
+///
+/// Parameters:
+/// `app_state`: app state
+/// `sigs`: Signatures of the players in player order
+pub fn state_proof<T: Trait>(app_state: AppStateOf<T>, sigs: Vec<T::Signature>) -> StateProofOf<T> {
+    StateProof {
+        app_state: app_state,
+        sigs: sigs,
+    }
+}
+
+/// Co-sign an app state with the local keys of all players
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+/// `players`: AccountId of players in ascending order
+/// `pairs`: Key pairs of the players in any order
+pub fn sign_state_proof<T: Trait, P: Pair>(
+    app_state: AppStateOf<T>,
+    genesis_hash: T::Hash,
+    players: Vec<T::AccountId>,
+    pairs: &[P],
+) -> Result<StateProofOf<T>, ClientError>
+where
+    T::AccountId: From<P::Public>,
+    T::Signature: From<P::Signature>,
+{
+    let payload = signing_payload::<T>(&app_state, genesis_hash);
+    let sigs = co_sign(players, payload, pairs)?;
+
+    Ok(state_proof::<T>(app_state, sigs))
+}
+
+/// Sign an app state alone as the player who moved, accepted by apps initiated as `unilateral`
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+/// `pair`: Key pair of the player who moved
+pub fn sign_unilateral_state_proof<T: Trait, P: Pair>(
+    app_state: AppStateOf<T>,
+    genesis_hash: T::Hash,
+    pair: &P,
+) -> StateProofOf<T>
+where
+    T::Signature: From<P::Signature>,
+{
+    let payload = signing_payload::<T>(&app_state, genesis_hash);
+
+    state_proof::<T>(app_state, vec![pair.sign(&payload).into()])
+}
+
+/// Co-sign an app state as the final state with the local keys of all players
+///
+/// Parameters:
+/// `app_state`: app state
+/// `genesis_hash`: Hash of the genesis block of the chain
+/// `players`: AccountId of players in ascending order
+/// `pairs`: Key pairs of the players in any order
+pub fn sign_final_state_proof<T: Trait, P: Pair>(
+    app_state: AppStateOf<T>,
+    genesis_hash: T::Hash,
+    players: Vec<T::AccountId>,
+    pairs: &[P],
+) -> Result<StateProofOf<T>, ClientError>
+where
+    T::AccountId: From<P::Public>,
+    T::Signature: From<P::Signature>,
+{
+    let payload = final_signing_payload::<T>(&app_state, genesis_hash);
+    let sigs = co_sign(players, payload, pairs)?;
+
+    Ok(state_proof::<T>(app_state, sigs))
+}
@@ -0,0 +1,239 @@
+use super::*;
+use mock::*;
+use sp_core::{sr25519, Pair};
+use frame_support::assert_ok;
+
+#[test]
+fn test_pass_single_session_state_proof() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let players = vec![alice_pair.public(), bob_pair.public()];
+
+        let (initiate_request, session_id)
+            = single_session::initiate_request::<TestRuntime>(0, players, 2);
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(initiate_request.players[0]),
+            initiate_request.clone()
+        ));
+        assert_eq!(SingleSessionApp::get_session_id(&initiate_request), session_id);
+
+        let app_state = single_session_app::AppState {
+            nonce: 0,
+            seq_num: 1,
+            state: 5,
+            timeout: 2,
+            session_id: session_id,
+        };
+        let state_proof = single_session::sign_state_proof::<TestRuntime, _>(
+            app_state,
+            System::block_hash(0),
+            initiate_request.players.clone(),
+            &[bob_pair, alice_pair]
+        ).unwrap();
+        assert_ok!(SingleSessionApp::update_by_state(
+            Origin::signed(initiate_request.players[0]),
+            state_proof
+        ));
+        assert_eq!(SingleSessionApp::get_state(session_id), Some(5));
+    })
+}
+
+#[test]
+fn test_pass_single_session_final_state_proof() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let players = vec![alice_pair.public(), bob_pair.public()];
+
+        let (initiate_request, session_id)
+            = single_session::initiate_request::<TestRuntime>(0, players, 2);
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(initiate_request.players[0]),
+            initiate_request.clone()
+        ));
+
+        let app_state = single_session_app::AppState {
+            nonce: 0,
+            seq_num: 1,
+            state: 5,
+            timeout: 2,
+            session_id: session_id,
+        };
+        assert_ne!(
+            single_session::final_signing_payload::<TestRuntime>(&app_state, System::block_hash(0)),
+            single_session::signing_payload::<TestRuntime>(&app_state, System::block_hash(0))
+        );
+        let state_proof = single_session::sign_final_state_proof::<TestRuntime, _>(
+            app_state,
+            System::block_hash(0),
+            initiate_request.players.clone(),
+            &[alice_pair, bob_pair]
+        ).unwrap();
+        assert_ok!(SingleSessionApp::finalize_by_agreement(
+            Origin::signed(initiate_request.players[0]),
+            state_proof
+        ));
+        assert_eq!(SingleSessionApp::finalized_at(session_id), Some(0));
+    })
+}
+
+#[test]
+fn test_pass_multi_session_state_proof() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let players = vec![alice_pair.public(), bob_pair.public()];
+
+        let (initiate_request, session_id)
+            = multi_session::initiate_request::<TestRuntime>(0, players, 2, None);
+        assert_eq!(initiate_request.player_num, 2);
+        assert_ok!(MultiSessionApp::session_initiate(
+            Origin::signed(initiate_request.players[0]),
+            initiate_request.clone()
+        ));
+
+        let app_state = multi_session_app::AppState {
+            seq_num: 1,
+            state: 5,
+            timeout: 2,
+            session_id: session_id,
+        };
+        let state_proof = multi_session::sign_state_proof::<TestRuntime, _>(
+            app_state,
+            System::block_hash(0),
+            initiate_request.players.clone(),
+            &[alice_pair, bob_pair]
+        ).unwrap();
+        assert_ok!(MultiSessionApp::update_by_state(
+            Origin::signed(initiate_request.players[0]),
+            state_proof
+        ));
+        assert_eq!(MultiSessionApp::get_state(session_id), Some(5));
+    })
+}
+
+#[test]
+fn test_pass_single_gomoku_state_proof() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let players = vec![alice_pair.public(), bob_pair.public()];
+
+        let (initiate_request, session_id)
+            = single_gomoku::initiate_request::<TestRuntime>(
+                0,
+                players,
+                2,
+                0,
+                5,
+                ::single_gomoku::DEFAULT_BOARD_SIZE,
+                ::single_gomoku::DEFAULT_WIN_LENGTH,
+                ::single_gomoku::RuleSet::Freestyle,
+                None,
+                false,
+            );
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(initiate_request.players[0]),
+            initiate_request.clone()
+        ));
+
+        let mut board_state = vec![0; ::single_gomoku::BOARD_STATE_LEN];
+        board_state[1] = 1;
+        let app_state = ::single_gomoku::AppState {
+            nonce: 0,
+            seq_num: 1,
+            board_state: board_state,
+            timeout: 2,
+            session_id: session_id,
+        };
+        let state_proof = single_gomoku::sign_state_proof::<TestRuntime, _>(
+            app_state,
+            System::block_hash(0),
+            initiate_request.players.clone(),
+            &[alice_pair, bob_pair]
+        ).unwrap();
+        assert_ok!(SingleGomoku::update_by_state(
+            Origin::signed(initiate_request.players[0]),
+            state_proof
+        ));
+        assert_eq!(SingleGomoku::get_seq_num(session_id), Some(1));
+    })
+}
+
+#[test]
+fn test_pass_multi_gomoku_state_proof() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let players = vec![alice_pair.public(), bob_pair.public()];
+
+        let (initiate_request, session_id)
+            = multi_gomoku::initiate_request::<TestRuntime>(
+                0,
+                players,
+                2,
+                0,
+                5,
+                ::multi_gomoku::DEFAULT_BOARD_SIZE,
+                ::multi_gomoku::DEFAULT_WIN_LENGTH,
+                ::multi_gomoku::RuleSet::Freestyle,
+                None,
+                false,
+            );
+        assert_ok!(MultiGomoku::app_initiate(
+            Origin::signed(initiate_request.players[0]),
+            initiate_request.clone()
+        ));
+
+        let mut board_state = vec![0; ::multi_gomoku::BOARD_STATE_LEN];
+        board_state[1] = 1;
+        board_state[2] = 1;
+        let app_state = ::multi_gomoku::AppState {
+            seq_num: 1,
+            board_state: board_state,
+            timeout: 2,
+            session_id: session_id,
+        };
+        let state_proof = multi_gomoku::sign_state_proof::<TestRuntime, _>(
+            app_state,
+            System::block_hash(0),
+            initiate_request.players.clone(),
+            &[alice_pair, bob_pair]
+        ).unwrap();
+        assert_ok!(MultiGomoku::update_by_state(
+            Origin::signed(initiate_request.players[0]),
+            state_proof
+        ));
+        assert_eq!(MultiGomoku::get_seq_num(session_id), Some(1));
+    })
+}
+
+#[test]
+fn test_signature_collector() {
+    let alice_pair = account_pair("Alice");
+    let bob_pair = account_pair("Bob");
+    let players = sort_players(vec![alice_pair.public(), bob_pair.public()]);
+    let payload = b"app state".to_vec();
+
+    let mut collector = SignatureCollector::<AccountId, sr25519::Signature>::new(players.clone(), payload.clone());
+    assert_eq!(collector.sign(&account_pair("Carl")), Err(ClientError::NotPlayer));
+    assert_eq!(
+        collector.add_signature(&players[0], account_pair("Carl").sign(&payload)),
+        Err(ClientError::InvalidSignature)
+    );
+
+    // signatures are ordered by player, not by arrival
+    assert_ok!(collector.add_signature(&bob_pair.public(), bob_pair.sign(&payload)));
+    let missing = if players[0] == bob_pair.public() { 1 } else { 0 };
+    let mut partial = SignatureCollector::<AccountId, sr25519::Signature>::new(players.clone(), payload.clone());
+    assert_ok!(partial.sign(&bob_pair));
+    assert_eq!(partial.into_sigs().err(), Some(ClientError::MissingSignature(missing)));
+
+    assert_ok!(collector.sign(&alice_pair));
+    let sigs = collector.into_sigs().unwrap();
+    assert_eq!(
+        celer_app_core::valid_signers(&sigs, &payload, &players),
+        Ok(())
+    );
+}
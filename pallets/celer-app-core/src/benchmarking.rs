@@ -0,0 +1,45 @@
+//! Player key helpers for the benchmarks of the app runtime modules
+
+pub use sp_core::sr25519;
+use sp_runtime::KeyTypeId;
+use sp_std::vec::Vec;
+
+/// Key type of the player keys generated in the benchmarks
+pub const BENCHMARK_KEY_TYPE: KeyTypeId = KeyTypeId(*b"capp");
+
+/// Generate player keys in the keystore
+///
+/// Parameter:
+/// `num`: Number of players
+///
+/// Return AccountId of players in ascending order and their keys in the same order
+pub fn sorted_players<AccountId>(num: u32) -> (Vec<AccountId>, Vec<sr25519::Public>)
+where
+    AccountId: From<sr25519::Public> + Ord,
+{
+    let mut keys: Vec<sr25519::Public> = (0..num)
+        .map(|_| sp_io::crypto::sr25519_generate(BENCHMARK_KEY_TYPE, None))
+        .collect();
+    keys.sort_by_key(|key| AccountId::from(*key));
+    let players = keys.iter().map(|key| AccountId::from(*key)).collect();
+
+    (players, keys)
+}
+
+/// Co-sign an encoded app state
+///
+/// Parameters:
+/// `keys`: Keys of players generated by `sorted_players`
+/// `encoded`: Encoded app state
+pub fn co_sign<Signature>(keys: &[sr25519::Public], encoded: &[u8]) -> Vec<Signature>
+where
+    Signature: From<sr25519::Signature>,
+{
+    keys.iter()
+        .map(|key| {
+            sp_io::crypto::sr25519_sign(BENCHMARK_KEY_TYPE, key, encoded)
+                .expect("key is generated in the keystore; qed")
+                .into()
+        })
+        .collect()
+}
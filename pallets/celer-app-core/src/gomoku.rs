@@ -0,0 +1,711 @@
+//! Win detection and forbidden moves of the gomoku rule sets, and the gomoku
+//! logic of the generic app runtime module
+//!
+//! The board is given by its cells in row-major order, `cells[x * board_size + y]`,
+//! each cell is empty (=0) or holds the stone of the first (=1, black) or the
+//! second (=2, white) player.
+
+use codec::{Decode, Encode};
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+use crate::{AppLogicError, StateChannelApp};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// Empty cell
+pub const EMPTY: u8 = 0;
+
+/// Stone of the player moving first
+pub const BLACK: u8 = 1;
+
+/// Stone of the player moving second
+pub const WHITE: u8 = 2;
+
+/// Smallest board size chosen at initiation
+pub const MIN_BOARD_SIZE: u8 = 3;
+
+/// Largest board size chosen at initiation
+pub const MAX_BOARD_SIZE: u8 = 19;
+
+/// Shortest winning row chosen at initiation
+pub const MIN_WIN_LENGTH: u8 = 3;
+
+/// Check the board size and win length chosen at initiation
+///
+/// Parameters:
+/// `board_size`: Number of rows and columns of the board
+/// `win_length`: Number of stones in a row that wins
+pub fn valid_board_config(board_size: u8, win_length: u8) -> bool {
+    MIN_BOARD_SIZE <= board_size && board_size <= MAX_BOARD_SIZE
+        && MIN_WIN_LENGTH <= win_length && win_length <= board_size
+}
+
+/// Rule set of a gomoku game, chosen at initiation
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum RuleSet {
+    /// A run of at least `win_length` stones wins
+    Freestyle,
+    /// Exactly `win_length` stones win, an overline does not
+    Standard,
+    /// Black wins with exactly `win_length` stones and must not play a double-three,
+    /// double-four or overline, white wins with a run of at least `win_length` stones
+    Renju,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet::Freestyle
+    }
+}
+
+/// Result of placing a stone
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum MoveVerdict {
+    /// The stone completes a winning run
+    Win,
+    /// The stone is a forbidden move of black under Renju
+    Forbidden,
+    /// The game goes on
+    Continue,
+}
+
+/// Failures of the validation of an off-chain board
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum BoardError {
+    /// A cell is neither empty, black nor white
+    InvalidCell,
+    /// Turn is not the player to move given the stones on the board
+    InvalidTurn,
+    /// Numbers of black and white stones cannot result from alternating moves
+    InvalidStoneCount,
+    /// Winner does not match the winning runs on the board
+    InvalidWinner,
+}
+
+impl BoardError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BoardError::InvalidCell => "invalid board cell",
+            BoardError::InvalidTurn => "invalid turn",
+            BoardError::InvalidStoneCount => "invalid stone count",
+            BoardError::InvalidWinner => "invalid winner state",
+        }
+    }
+}
+
+/// Directions of the lines through a cell: vertical, horizontal, main-diagonal, anti-diagonal
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// Line of cells through the placed stone, addressed by the offset from the stone
+///
+/// Up to two empty cells of the line can be filled with the color of the stone
+/// to look ahead without copying the board.
+#[derive(Clone, Copy)]
+struct Line<'a> {
+    cells: &'a [u8],
+    board_size: i32,
+    x: i32,
+    y: i32,
+    dx: i32,
+    dy: i32,
+    color: u8,
+    filled: [Option<i32>; 2],
+}
+
+impl<'a> Line<'a> {
+    /// Content of the cell at the offset, `None` out of the board
+    fn cell(&self, offset: i32) -> Option<u8> {
+        if self.filled.contains(&Some(offset)) {
+            return Some(self.color);
+        }
+        let x = self.x + self.dx * offset;
+        let y = self.y + self.dy * offset;
+        if x < 0 || y < 0 || x >= self.board_size || y >= self.board_size {
+            return None;
+        }
+        self.cells.get((x * self.board_size + y) as usize).cloned()
+    }
+
+    fn is_stone(&self, offset: i32) -> bool {
+        self.cell(offset) == Some(self.color)
+    }
+
+    fn is_empty(&self, offset: i32) -> bool {
+        self.cell(offset) == Some(EMPTY)
+    }
+
+    /// Line with the empty cell at the offset filled
+    fn fill(&self, offset: i32) -> Line<'a> {
+        let mut line = *self;
+        if line.filled[0].is_none() {
+            line.filled[0] = Some(offset);
+        } else {
+            line.filled[1] = Some(offset);
+        }
+        line
+    }
+
+    /// First and last offset of the run of stones through the offset
+    fn run(&self, offset: i32) -> (i32, i32) {
+        let mut start = offset;
+        while self.is_stone(start - 1) {
+            start -= 1;
+        }
+        let mut end = offset;
+        while self.is_stone(end + 1) {
+            end += 1;
+        }
+        (start, end)
+    }
+
+    /// Check if the run through the placed stone wins
+    ///
+    /// Parameters:
+    /// `win_length`: Number of stones in a row that wins
+    /// `exact`: Whether an overline does not win
+    fn is_win(&self, win_length: i32, exact: bool) -> bool {
+        let (start, end) = self.run(0);
+        let len = end - start + 1;
+        len == win_length || (len > win_length && !exact)
+    }
+
+    /// Number of fours through the placed stone
+    ///
+    /// A four is a set of `win_length - 1` stones which one more stone turns into a run
+    /// of exactly `win_length`. A straight four, open at both ends, counts once, while
+    /// `X_XXX_X` counts twice.
+    fn fours(&self, win_length: i32) -> usize {
+        // the stones of a four, as the run left by the five-making cell
+        let mut fours: Vec<(i32, i32, Option<i32>)> = Vec::new();
+        for offset in (1 - win_length)..win_length {
+            if !self.is_empty(offset) {
+                continue;
+            }
+            let (start, end) = self.fill(offset).run(offset);
+            if end - start + 1 != win_length || start > 0 || end < 0 {
+                continue;
+            }
+            let four = if offset == start {
+                (start + 1, end, None)
+            } else if offset == end {
+                (start, end - 1, None)
+            } else {
+                (start, end, Some(offset))
+            };
+            if !fours.contains(&four) {
+                fours.push(four);
+            }
+        }
+        fours.len()
+    }
+
+    /// Check if the run through the placed stone is a straight four
+    fn is_straight_four(&self, win_length: i32) -> bool {
+        let (start, end) = self.run(0);
+        end - start + 2 == win_length
+            && self.is_empty(start - 1)
+            && self.is_empty(end + 1)
+            && !self.is_stone(start - 2)
+            && !self.is_stone(end + 2)
+    }
+
+    /// Check if the placed stone forms an open three
+    ///
+    /// A three is open when one more stone turns it into a straight four. Whether
+    /// that stone would itself be a forbidden move is not considered.
+    fn is_open_three(&self, win_length: i32) -> bool {
+        ((2 - win_length)..(win_length - 1))
+            .filter(|&offset| self.is_empty(offset))
+            .any(|offset| self.fill(offset).is_straight_four(win_length))
+    }
+}
+
+/// Lines in all directions through the stone at (x, y)
+fn lines<'a>(cells: &'a [u8], board_size: i32, x: i32, y: i32, color: u8) -> Vec<Line<'a>> {
+    DIRECTIONS.iter().map(|&(dx, dy)| Line {
+        cells: cells,
+        board_size: board_size,
+        x: x,
+        y: y,
+        dx: dx,
+        dy: dy,
+        color: color,
+        filled: [None, None],
+    }).collect()
+}
+
+/// Check if only a run of exactly `win_length` stones of the color wins
+fn is_exact_win(rule_set: RuleSet, color: u8) -> bool {
+    match rule_set {
+        RuleSet::Freestyle => false,
+        RuleSet::Standard => true,
+        RuleSet::Renju => color == BLACK,
+    }
+}
+
+/// Judge the stone just placed at (x, y)
+///
+/// Parameters:
+/// `cells`: Cells of the board with the stone placed
+/// `board_size`: Number of rows and columns of the board
+/// `win_length`: Number of stones in a row that wins
+/// `rule_set`: Rule set of the game
+/// `x`: x coordinate of the stone
+/// `y`: y coordinate of the stone
+pub fn judge_move(
+    cells: &[u8],
+    board_size: u8,
+    win_length: u8,
+    rule_set: RuleSet,
+    x: u8,
+    y: u8,
+) -> MoveVerdict {
+    let board_size = board_size as i32;
+    let win_length = win_length as i32;
+    let (x, y) = (x as i32, y as i32);
+    let color = match cells.get((x * board_size + y) as usize) {
+        Some(&color) if color != EMPTY => color,
+        _ => return MoveVerdict::Continue,
+    };
+    let exact = is_exact_win(rule_set, color);
+    let lines = lines(cells, board_size, x, y, color);
+
+    // a five wins even if the stone is forbidden in another direction
+    if lines.iter().any(|line| line.is_win(win_length, exact)) {
+        return MoveVerdict::Win;
+    }
+    if rule_set != RuleSet::Renju || color != BLACK {
+        return MoveVerdict::Continue;
+    }
+
+    let overline = lines.iter().any(|line| {
+        let (start, end) = line.run(0);
+        end - start + 1 > win_length
+    });
+
+    let fours: Vec<usize> = lines.iter().map(|line| line.fours(win_length)).collect();
+    let threes = lines.iter()
+        .zip(fours.iter())
+        .filter(|(line, &four)| four == 0 && line.is_open_three(win_length))
+        .count();
+    if overline || fours.iter().sum::<usize>() >= 2 || threes >= 2 {
+        return MoveVerdict::Forbidden;
+    }
+
+    MoveVerdict::Continue
+}
+
+/// Check if there is a winning run of the color anywhere on the board
+///
+/// Parameters:
+/// `cells`: Cells of the board
+/// `board_size`: Number of rows and columns of the board
+/// `win_length`: Number of stones in a row that wins
+/// `rule_set`: Rule set of the game
+/// `color`: Color of the stones
+pub fn has_winning_run(
+    cells: &[u8],
+    board_size: u8,
+    win_length: u8,
+    rule_set: RuleSet,
+    color: u8,
+) -> bool {
+    let board_size = board_size as i32;
+    let win_length = win_length as i32;
+    let exact = is_exact_win(rule_set, color);
+    (0..board_size * board_size)
+        .filter(|&index| cells.get(index as usize) == Some(&color))
+        .any(|index| {
+            lines(cells, board_size, index / board_size, index % board_size, color)
+                .iter()
+                .any(|line| line.is_win(win_length, exact))
+        })
+}
+
+/// Validate a board submitted off-chain
+///
+/// Black moves first, so black has as many stones as white when black is to
+/// move and one stone more when white is to move. A finished game has turn 0,
+/// its winner made the last move and has a winning run on the board; a finished
+/// draw has at least `draw_stones` stones and no winning run; an unfinished game
+/// has no winning run of either color.
+///
+/// Parameters:
+/// `cells`: Cells of the board
+/// `board_size`: Number of rows and columns of the board
+/// `win_length`: Number of stones in a row that wins
+/// `rule_set`: Rule set of the game
+/// `winner`: Winner color, 0 if no winner
+/// `turn`: Color to move, 0 if the game is over
+/// `draw_stones`: Number of stones at which a game without a winner is over
+pub fn validate_board(
+    cells: &[u8],
+    board_size: u8,
+    win_length: u8,
+    rule_set: RuleSet,
+    winner: u8,
+    turn: u8,
+    draw_stones: usize,
+) -> Result<(), BoardError> {
+    if cells.iter().any(|&cell| cell != EMPTY && cell != BLACK && cell != WHITE) {
+        return Err(BoardError::InvalidCell);
+    }
+    let black_num = cells.iter().filter(|&&cell| cell == BLACK).count();
+    let white_num = cells.iter().filter(|&&cell| cell == WHITE).count();
+    let last_color = if black_num == white_num + 1 {
+        BLACK
+    } else if black_num == white_num {
+        WHITE
+    } else {
+        return Err(BoardError::InvalidStoneCount);
+    };
+
+    let black_wins = has_winning_run(cells, board_size, win_length, rule_set, BLACK);
+    let white_wins = has_winning_run(cells, board_size, win_length, rule_set, WHITE);
+    match winner {
+        EMPTY => {
+            if turn == EMPTY {
+                // a finished draw
+                if black_num + white_num < draw_stones {
+                    return Err(BoardError::InvalidTurn);
+                }
+            } else if turn != BLACK && turn != WHITE {
+                return Err(BoardError::InvalidTurn);
+            } else if turn == last_color {
+                return Err(BoardError::InvalidStoneCount);
+            }
+            if black_wins || white_wins {
+                return Err(BoardError::InvalidWinner);
+            }
+        },
+        BLACK | WHITE => {
+            if turn != EMPTY {
+                return Err(BoardError::InvalidTurn);
+            }
+            let (winner_wins, loser_wins) = if winner == BLACK {
+                (black_wins, white_wins)
+            } else {
+                (white_wins, black_wins)
+            };
+            if !winner_wins || loser_wins || winner != last_color {
+                return Err(BoardError::InvalidWinner);
+            }
+        },
+        _ => return Err(BoardError::InvalidWinner),
+    }
+
+    Ok(())
+}
+
+/// Find the stone that extends a board by exactly one move
+///
+/// Returns the (x, y) coordinates of the stone if `next` equals `prev` with one
+/// more stone of `color` on a cell empty in `prev`, and `None` otherwise.
+///
+/// Parameters:
+/// `prev`: Cells of the board before the move
+/// `next`: Cells of the board after the move
+/// `board_size`: Number of rows and columns of the board
+/// `color`: Color of the player to move
+pub fn find_move(prev: &[u8], next: &[u8], board_size: u8, color: u8) -> Option<(u8, u8)> {
+    if board_size == 0 || prev.len() != next.len() {
+        return None;
+    }
+    let mut changed = prev.iter().zip(next.iter()).enumerate()
+        .filter(|(_, (before, after))| before != after);
+    match (changed.next(), changed.next()) {
+        (Some((index, (&EMPTY, &after))), None) if after == color => {
+            let board_size = board_size as usize;
+            Some(((index / board_size) as u8, (index % board_size) as u8))
+        },
+        _ => None,
+    }
+}
+
+/// Rules of a gomoku game needed to replay its on-chain moves
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct GameRules {
+    /// Number of rows and columns of the board
+    pub board_size: u8,
+    /// Number of stones in a row that wins
+    pub win_length: u8,
+    /// Rule set of the game
+    pub rule_set: RuleSet,
+    /// Maximal number of stones placed on-chain before the game is a draw
+    pub max_stone_onchain: u8,
+}
+
+/// Board of a gomoku game before or after replaying moves
+#[derive(Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct GameBoard {
+    /// Cells of the board
+    pub cells: Vec<u8>,
+    /// Winner color, 0 if no winner
+    pub winner: u8,
+    /// Color to move, 0 if the game is over
+    pub turn: u8,
+}
+
+/// Move of the on-chain move log of a gomoku app
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct MoveRecord<AccountId, BlockNumber> {
+    /// Sequence number of the app after the move
+    pub seq_num: u128,
+    /// Player who placed the stone
+    pub player: AccountId,
+    /// x coordinate of the stone
+    pub x: u8,
+    /// y coordinate of the stone
+    pub y: u8,
+    /// Block in which the stone was placed
+    pub block: BlockNumber,
+}
+
+/// Failures of replaying moves, with the index of the rejected move
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum ReplayError {
+    /// Initial board does not match the board size
+    InvalidBoard,
+    /// Game is already over
+    GameOver(usize),
+    /// Stone is out of the board
+    OutOfBoundary(usize),
+    /// Cell of the stone is occupied
+    SlotOccupied(usize),
+    /// Stone is a forbidden move of black under Renju
+    ForbiddenMove(usize),
+}
+
+/// Replay on-chain moves on a board the same way as `update_by_action`
+///
+/// The players alternate starting with the turn of the initial board. A move
+/// completing a winning run sets the winner, a full board or more than
+/// `max_stone_onchain` moves end the game in a draw. Action timeouts are not
+/// moves and are not replayed.
+///
+/// Parameters:
+/// `rules`: Rules of the game
+/// `initial`: Board settled by the last off-chain state
+/// `moves`: (x, y) coordinates of the moves in order
+pub fn replay(
+    rules: &GameRules,
+    initial: GameBoard,
+    moves: &[(u8, u8)],
+) -> Result<GameBoard, ReplayError> {
+    let cell_num = rules.board_size as usize * rules.board_size as usize;
+    if initial.cells.len() != cell_num {
+        return Err(ReplayError::InvalidBoard);
+    }
+
+    let mut board = initial;
+    for (index, &(x, y)) in moves.iter().enumerate() {
+        if board.turn != BLACK && board.turn != WHITE {
+            return Err(ReplayError::GameOver(index));
+        }
+        if x >= rules.board_size || y >= rules.board_size {
+            return Err(ReplayError::OutOfBoundary(index));
+        }
+        let cell = x as usize * rules.board_size as usize + y as usize;
+        if board.cells[cell] != EMPTY {
+            return Err(ReplayError::SlotOccupied(index));
+        }
+
+        board.cells[cell] = board.turn;
+        match judge_move(&board.cells, rules.board_size, rules.win_length, rules.rule_set, x, y) {
+            MoveVerdict::Forbidden => return Err(ReplayError::ForbiddenMove(index)),
+            MoveVerdict::Win => {
+                board.winner = board.turn;
+                board.turn = EMPTY;
+            },
+            MoveVerdict::Continue => {
+                let stone_num = board.cells.iter().filter(|&&cell| cell != EMPTY).count();
+                board.turn = if stone_num == cell_num || index + 1 > rules.max_stone_onchain as usize {
+                    EMPTY
+                } else if board.turn == BLACK {
+                    WHITE
+                } else {
+                    BLACK
+                };
+            },
+        }
+    }
+
+    Ok(board)
+}
+
+/// Config of a gomoku game played through the generic app runtime module
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct GomokuAppConfig {
+    /// Rules of the game, checked by `valid_board_config` at initiation
+    pub rules: GameRules,
+    /// Minimal number of stones of an unfinished off-chain board
+    pub min_stone_offchain: u8,
+}
+
+/// Failures of the gomoku logic of the generic app runtime module
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum GomokuAppError {
+    /// Board size or win length fails `valid_board_config`
+    InvalidConfig,
+    /// Length of the board state does not match the board size
+    InvalidBoardStateLength,
+    /// Off-chain board fails the board validation
+    InvalidBoard(BoardError),
+    /// Unfinished off-chain board has fewer stones than `min_stone_offchain`
+    NotEnoughOffchainStones,
+    /// No board is settled yet
+    NoSettledBoard,
+    /// Player is not the player to move
+    NotYourTurn,
+    /// Move is rejected by `replay`
+    InvalidMove(ReplayError),
+}
+
+impl From<GomokuAppError> for AppLogicError {
+    fn from(err: GomokuAppError) -> AppLogicError {
+        match err {
+            GomokuAppError::InvalidConfig => AppLogicError::InvalidConfig,
+            GomokuAppError::InvalidBoardStateLength
+            | GomokuAppError::InvalidBoard(_)
+            | GomokuAppError::NotEnoughOffchainStones => AppLogicError::InvalidOffchainState,
+            GomokuAppError::NoSettledBoard
+            | GomokuAppError::InvalidMove(_) => AppLogicError::InvalidAction,
+            GomokuAppError::NotYourTurn => AppLogicError::NotYourTurn,
+        }
+    }
+}
+
+/// On-chain state of a gomoku game played through the generic app runtime module
+#[derive(Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct GomokuAppState {
+    /// Config chosen at initiation
+    pub config: GomokuAppConfig,
+    /// u8 winner + u8 turn + board_size*board_size cells, `None` until a board is settled
+    pub board_state: Option<Vec<u8>>,
+    /// Number of stones placed on-chain
+    pub stone_num_onchain: u16,
+}
+
+/// Gomoku logic for the generic app runtime module
+///
+/// The off-chain state is the board state laid out as in the single gomoku runtime
+/// module, an action is the (x, y) coordinates of a stone of the player to move, and
+/// the outcome is whether the winner equals the query data. Boards are validated by
+/// `validate_board` and moves are applied by `replay`, so the game follows the same
+/// rules as the gomoku runtime modules.
+pub struct GomokuApp;
+
+impl StateChannelApp for GomokuApp {
+    type Config = GomokuAppConfig;
+    type OffchainState = Vec<u8>;
+    type State = GomokuAppState;
+    type Action = (u8, u8);
+    type Query = u8;
+    type Error = GomokuAppError;
+
+    fn initial_state(config: GomokuAppConfig) -> Result<GomokuAppState, GomokuAppError> {
+        if !valid_board_config(config.rules.board_size, config.rules.win_length) {
+            return Err(GomokuAppError::InvalidConfig);
+        }
+
+        Ok(GomokuAppState {
+            config: config,
+            board_state: None,
+            stone_num_onchain: 0,
+        })
+    }
+
+    fn validate_offchain_state(
+        state: &GomokuAppState,
+        board_state: &Vec<u8>,
+    ) -> Result<(), GomokuAppError> {
+        let rules = &state.config.rules;
+        let cell_num = rules.board_size as usize * rules.board_size as usize;
+        if board_state.len() != cell_num + 2 {
+            return Err(GomokuAppError::InvalidBoardStateLength);
+        }
+        validate_board(
+            &board_state[2..],
+            rules.board_size,
+            rules.win_length,
+            rules.rule_set,
+            board_state[0],
+            board_state[1],
+            cell_num,
+        ).map_err(GomokuAppError::InvalidBoard)?;
+        let stone_num = board_state[2..].iter().filter(|&&cell| cell != EMPTY).count();
+        if board_state[0] == EMPTY && stone_num < state.config.min_stone_offchain as usize {
+            return Err(GomokuAppError::NotEnoughOffchainStones);
+        }
+
+        Ok(())
+    }
+
+    fn settle_offchain_state(state: &mut GomokuAppState, board_state: Vec<u8>) {
+        state.board_state = Some(board_state);
+        state.stone_num_onchain = 0;
+    }
+
+    fn apply_action(
+        state: &mut GomokuAppState,
+        player_index: u8,
+        (x, y): (u8, u8),
+    ) -> Result<(), GomokuAppError> {
+        let board_state = match state.board_state.as_ref() {
+            Some(board_state) => board_state,
+            None => return Err(GomokuAppError::NoSettledBoard),
+        };
+        if board_state[1] != player_index.saturating_add(1) {
+            return Err(GomokuAppError::NotYourTurn);
+        }
+
+        // the draw of `replay` counts the stones placed on-chain from the settled board
+        let mut rules = state.config.rules;
+        rules.max_stone_onchain = (rules.max_stone_onchain as u16)
+            .saturating_sub(state.stone_num_onchain) as u8;
+        let initial = GameBoard {
+            cells: board_state[2..].to_vec(),
+            winner: board_state[0],
+            turn: board_state[1],
+        };
+        let board = replay(&rules, initial, &[(x, y)]).map_err(GomokuAppError::InvalidMove)?;
+
+        let mut new_board_state = Vec::with_capacity(board.cells.len() + 2);
+        new_board_state.push(board.winner);
+        new_board_state.push(board.turn);
+        new_board_state.extend(board.cells);
+        state.board_state = Some(new_board_state);
+        state.stone_num_onchain += 1;
+
+        Ok(())
+    }
+
+    fn is_terminal(state: &GomokuAppState) -> bool {
+        match &state.board_state {
+            Some(board_state) => board_state[1] == EMPTY,
+            None => false,
+        }
+    }
+
+    fn on_action_timeout(state: &mut GomokuAppState) {
+        if let Some(board_state) = state.board_state.as_mut() {
+            // the player who did not take the turn loses
+            if board_state[1] == BLACK {
+                board_state[0] = WHITE;
+            } else if board_state[1] == WHITE {
+                board_state[0] = BLACK;
+            }
+            board_state[1] = EMPTY;
+        }
+    }
+
+    fn outcome(state: &GomokuAppState, query_data: u8) -> Result<Vec<u8>, GomokuAppError> {
+        match &state.board_state {
+            Some(board_state) => Ok((board_state[0] == query_data).encode()),
+            None => Ok(false.encode()),
+        }
+    }
+}
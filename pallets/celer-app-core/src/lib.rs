@@ -0,0 +1,564 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+pub mod benchmarking;
+
+pub mod gomoku;
+pub mod lifecycle;
+pub mod session;
+
+use codec::{Codec, Decode, Encode, EncodeLike};
+use sp_runtime::traits::{AtLeast32BitUnsigned, IdentifyAccount, Saturating, Verify, Zero};
+use sp_runtime::{DispatchError, DispatchResult, ModuleId, RuntimeDebug};
+use sp_std::{fmt::Debug, vec::Vec};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+pub use gomoku::{
+    find_move, judge_move, replay, valid_board_config, validate_board, BoardError, GameBoard, GameRules, GomokuApp,
+    GomokuAppConfig, GomokuAppError, GomokuAppState, MoveRecord, MoveVerdict, ReplayError, RuleSet, MAX_BOARD_SIZE,
+    MIN_BOARD_SIZE, MIN_WIN_LENGTH,
+};
+pub use lifecycle::{DepositOf, LifecycleError, SessionLifecycle};
+pub use session::{
+    NumericOutcomeType, NumericSessionError, NumericSessionInfo, NumericSessionLogic, NumericSessionState,
+    SingleSessionLogic,
+};
+
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum AppStatus {
+    Idle = 0,
+    Settle = 1,
+    Action = 2,
+    Finalized = 3,
+}
+
+/// Failures of the dispute state machine
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum DisputeError {
+    /// App is already finalized
+    AlreadyFinalized,
+    /// Sequence number is not larger than the on-chain one
+    InvalidSeqNum,
+    /// App is neither in action mode nor past the settle period
+    NotInActionMode,
+    /// Action deadline has not passed yet
+    DeadlineNotPassed,
+    /// Settle period and the following action period have not passed yet
+    WhileSettling,
+    /// Number of signatures does not match the number of players
+    InvalidSignatureCount,
+    /// A signature does not verify against its player
+    InvalidSignature,
+    /// Fewer than two players or players not in ascending order
+    InvalidPlayers,
+}
+
+impl DisputeError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisputeError::AlreadyFinalized => "app state is finalized",
+            DisputeError::InvalidSeqNum => "invalid sequence number",
+            DisputeError::NotInActionMode => "app not in action mode",
+            DisputeError::DeadlineNotPassed => "deadline does not pass",
+            DisputeError::WhileSettling => "while settling",
+            DisputeError::InvalidSignatureCount => "invalid number of players",
+            DisputeError::InvalidSignature => "Check co-sigs failed",
+            DisputeError::InvalidPlayers => "players are not in ascending order",
+        }
+    }
+}
+
+impl From<DisputeError> for DispatchError {
+    fn from(err: DisputeError) -> DispatchError {
+        DispatchError::Other(err.as_str())
+    }
+}
+
+/// Kind of a failure of the logic of a state channel app
+///
+/// The error of every `StateChannelApp` converts into one of these kinds, which the
+/// generic app runtime module maps to its module errors.
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum AppLogicError {
+    /// Config chosen at initiation is rejected by the app logic
+    InvalidConfig,
+    /// Off-chain state can not be settled on the on-chain state
+    InvalidOffchainState,
+    /// Action can not be applied to the on-chain state
+    InvalidAction,
+    /// Player is not the player to move
+    NotYourTurn,
+    /// Outcome can not be encoded as the query asks
+    InvalidQuery,
+}
+
+/// Idle -> Settle -> Action -> Finalized state machine of a dispute
+///
+/// Implemented by the on-chain info of every app. The app specific state
+/// transition is applied by the runtime module after the machine has accepted
+/// the off-chain state or the on-chain action.
+pub trait DisputeStateMachine<BlockNumber: AtLeast32BitUnsigned + Copy> {
+    fn status(&self) -> AppStatus;
+    fn set_status(&mut self, status: AppStatus);
+    fn seq_num(&self) -> u128;
+    fn set_seq_num(&mut self, seq_num: u128);
+    fn timeout(&self) -> BlockNumber;
+    fn deadline(&self) -> BlockNumber;
+    fn set_deadline(&mut self, deadline: BlockNumber);
+
+    /// Number of blocks the player to move has for the next action
+    ///
+    /// The timeout of the app unless the app keeps a `GameClock`.
+    fn action_period(&self) -> BlockNumber {
+        self.timeout()
+    }
+
+    /// Submit and settle off-chain state
+    ///
+    /// Parameters:
+    /// `seq_num`: Sequence number of the co-signed off-chain state
+    /// `block_number`: Current block number
+    fn intend_settle(
+        &mut self,
+        seq_num: u128,
+        block_number: BlockNumber,
+    ) -> Result<(), DisputeError> {
+        if self.status() == AppStatus::Finalized {
+            return Err(DisputeError::AlreadyFinalized);
+        }
+        if self.seq_num() >= seq_num {
+            return Err(DisputeError::InvalidSeqNum);
+        }
+
+        self.set_seq_num(seq_num);
+        self.set_deadline(block_number.saturating_add(self.timeout()));
+        self.set_status(AppStatus::Settle);
+
+        Ok(())
+    }
+
+    /// Apply an action to the on-chain state
+    ///
+    /// Parameter:
+    /// `block_number`: Current block number
+    fn apply_action(&mut self, block_number: BlockNumber) -> Result<(), DisputeError> {
+        let status = self.status();
+        if status == AppStatus::Finalized {
+            return Err(DisputeError::AlreadyFinalized);
+        }
+        if status != AppStatus::Action
+            && !(status == AppStatus::Settle && block_number > self.deadline())
+        {
+            return Err(DisputeError::NotInActionMode);
+        }
+
+        self.set_seq_num(self.seq_num().saturating_add(1));
+        self.set_deadline(block_number.saturating_add(self.action_period()));
+        self.set_status(AppStatus::Action);
+
+        Ok(())
+    }
+
+    /// Finalize with an off-chain state co-signed as final by all players
+    ///
+    /// The final state may carry the on-chain sequence number, since it ends the app
+    /// instead of continuing it.
+    ///
+    /// Parameter:
+    /// `seq_num`: Sequence number of the final state
+    fn finalize_by_agreement(&mut self, seq_num: u128) -> Result<(), DisputeError> {
+        if self.status() == AppStatus::Finalized {
+            return Err(DisputeError::AlreadyFinalized);
+        }
+        if self.seq_num() > seq_num {
+            return Err(DisputeError::InvalidSeqNum);
+        }
+
+        self.set_seq_num(seq_num);
+        self.set_status(AppStatus::Finalized);
+
+        Ok(())
+    }
+
+    /// Check whether the app can be finalized on action timeout
+    ///
+    /// Parameter:
+    /// `block_number`: Current block number
+    ///
+    /// Return false if there is no pending dispute (Idle or Finalized)
+    fn is_action_timeout(&self, block_number: BlockNumber) -> Result<bool, DisputeError> {
+        match self.status() {
+            AppStatus::Action => {
+                if block_number <= self.deadline() {
+                    return Err(DisputeError::DeadlineNotPassed);
+                }
+                Ok(true)
+            }
+            AppStatus::Settle => {
+                if block_number <= self.deadline().saturating_add(self.action_period()) {
+                    return Err(DisputeError::WhileSettling);
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Get state settle finalized time
+    fn settle_finalized_time(&self) -> Option<BlockNumber> {
+        if self.status() == AppStatus::Settle {
+            return Some(self.deadline());
+        }
+
+        None
+    }
+
+    /// Get action deadline
+    fn action_deadline(&self) -> Option<BlockNumber> {
+        match self.status() {
+            AppStatus::Action => Some(self.deadline()),
+            AppStatus::Settle => Some(self.deadline().saturating_add(self.action_period())),
+            _ => None,
+        }
+    }
+}
+
+/// Game specific logic of a state channel app
+///
+/// The generic app runtime module owns the app info, the signatures, the
+/// timeouts and the `is_finalized`/`get_outcome` condition interface, and
+/// delegates every state transition to an implementation of this trait.
+pub trait StateChannelApp {
+    /// Parameters chosen by the players at initiation
+    type Config: Codec + EncodeLike + Clone + Eq + Debug;
+    /// Off-chain state co-signed by all players
+    type OffchainState: Codec + EncodeLike + Clone + Eq + Debug;
+    /// On-chain state of the app
+    type State: Codec + EncodeLike + Clone + Eq + Debug;
+    /// On-chain action of a player
+    type Action: Codec + EncodeLike + Clone + Eq + Debug;
+    /// Outcome query data
+    type Query: Codec + Clone + Eq + Debug;
+    /// Failures of the app logic
+    type Error: Into<AppLogicError> + Clone + Eq + Debug;
+
+    /// Create the on-chain state of a newly initiated app, fails on an invalid config
+    fn initial_state(config: Self::Config) -> Result<Self::State, Self::Error>;
+
+    /// Check an off-chain state before it is settled on-chain
+    fn validate_offchain_state(
+        state: &Self::State,
+        offchain_state: &Self::OffchainState,
+    ) -> Result<(), Self::Error>;
+
+    /// Load a validated off-chain state into the on-chain state
+    fn settle_offchain_state(state: &mut Self::State, offchain_state: Self::OffchainState);
+
+    /// Apply an action of the player at `player_index` to the on-chain state
+    fn apply_action(
+        state: &mut Self::State,
+        player_index: u8,
+        action: Self::Action,
+    ) -> Result<(), Self::Error>;
+
+    /// Whether the state is final and the app can be finalized immediately
+    fn is_terminal(state: &Self::State) -> bool;
+
+    /// Update the state when the player to act did not act before the deadline
+    fn on_action_timeout(_state: &mut Self::State) {}
+
+    /// Encoded outcome of the state for the given query
+    ///
+    /// Fails if the outcome can not be encoded as the query asks.
+    fn outcome(state: &Self::State, query: Self::Query) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// Condition and query interface of an app runtime module
+///
+/// Implemented by the `Module` of every app so that a runtime can serve all of
+/// its apps through the `CelerAppApi` runtime API.
+pub trait CelerAppQuery<Hash, BlockNumber> {
+    /// Query whether the app is finalized
+    fn is_finalized(args_query_finalization: Vec<u8>) -> Result<bool, DispatchError>;
+
+    /// Query the encoded app outcome
+    fn get_outcome(args_query_outcome: Vec<u8>) -> Result<Vec<u8>, DispatchError>;
+
+    /// Get the app state as bytes, `key` selects a part of the state for apps
+    /// which support it and is ignored by the others
+    fn get_state(session_id: Hash, key: u8) -> Option<Vec<u8>>;
+
+    /// Get app status
+    fn get_status(session_id: Hash) -> Option<AppStatus>;
+
+    /// Get state settle finalized time
+    fn get_settle_finalized_time(session_id: Hash) -> Option<BlockNumber>;
+
+    /// Get action deadline
+    fn get_action_deadline(session_id: Hash) -> Option<BlockNumber>;
+
+    /// Get app sequence number
+    fn get_seq_num(session_id: Hash) -> Option<u128>;
+}
+
+/// Release hook for the consumer of an app outcome
+///
+/// Called by the runtime module which resolved a payment with the outcome of a
+/// finalized app. A consumed app is cleared once its retention period passed.
+pub trait ConsumeOutcome<Hash> {
+    /// Mark the outcome of a finalized app as consumed
+    fn consume_outcome(session_id: Hash) -> DispatchResult;
+}
+
+/// Outcome of a game app
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum GameOutcome {
+    /// Player at the index of the players won
+    Winner(u8),
+    /// Game is finalized without a winner
+    Draw,
+    /// Game is not finalized
+    Undecided,
+}
+
+impl GameOutcome {
+    /// Payout of a player out of the total amount
+    ///
+    /// The winner takes the total amount and a draw splits it equally, the
+    /// remainder going one by one to the first players. Returns `None` if the
+    /// game is undecided or the player index is out of range.
+    ///
+    /// Parameters:
+    /// `player_index`: Index of the player
+    /// `player_num`: Number of players
+    /// `total`: Total amount paid out
+    pub fn payout(&self, player_index: u8, player_num: u8, total: u128) -> Option<u128> {
+        if player_index >= player_num {
+            return None;
+        }
+        match self {
+            GameOutcome::Winner(winner) if *winner == player_index => Some(total),
+            GameOutcome::Winner(_) => Some(0),
+            GameOutcome::Draw => {
+                let player_num = player_num as u128;
+                let remainder = if (player_index as u128) < total % player_num { 1 } else { 0 };
+                Some(total / player_num + remainder)
+            },
+            GameOutcome::Undecided => None,
+        }
+    }
+}
+
+/// Version of the query args carrying a `GameQuery`
+///
+/// The versioned args encode the version after the session id, so they are longer
+/// than the legacy args of a session id and a `u8` winner.
+pub const GAME_QUERY_VERSION: u8 = 1;
+
+/// Query data of the outcome of a game app
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub enum GameQuery {
+    /// Encoded boolean, whether the winner of the game state equals the query data
+    IsWinner(u8),
+    /// Encoded `GameOutcome`
+    Outcome,
+    /// Encoded `u128` payout of the player at the index out of the total amount
+    Payout {
+        player_index: u8,
+        total: u128,
+    },
+}
+
+/// Chess clock of the on-chain phase of an app
+///
+/// Every player has a bank of blocks for the whole on-chain phase. The clock of the
+/// player to move runs from `started_at`, and the blocks taken for a move are
+/// subtracted from the bank of the player who moved.
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
+pub struct GameClock<BlockNumber> {
+    /// Remaining blocks of the players in player order
+    pub banks: Vec<BlockNumber>,
+    /// Block at which the clock of the player to move started
+    pub started_at: BlockNumber,
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> GameClock<BlockNumber> {
+    /// Create a clock with the same bank for every player
+    ///
+    /// Parameters:
+    /// `player_num`: Number of players
+    /// `time_bank`: Blocks of every player for the on-chain phase
+    pub fn new(player_num: usize, time_bank: BlockNumber) -> Self {
+        GameClock {
+            banks: (0..player_num).map(|_| time_bank).collect(),
+            started_at: BlockNumber::zero(),
+        }
+    }
+
+    /// Start the clock of the player to move
+    ///
+    /// Parameter:
+    /// `block_number`: Block from which the blocks of the player are counted
+    pub fn start(&mut self, block_number: BlockNumber) {
+        self.started_at = block_number;
+    }
+
+    /// Remaining blocks of a player
+    ///
+    /// Parameter:
+    /// `player_index`: Index of the player
+    pub fn bank(&self, player_index: usize) -> Option<BlockNumber> {
+        self.banks.get(player_index).cloned()
+    }
+
+    /// Take the blocks of a move from the bank of the player and start the clock of the next player
+    ///
+    /// Return false without changing the clock if the blocks exceed the bank of the player.
+    ///
+    /// Parameters:
+    /// `player_index`: Index of the player who moves
+    /// `block_number`: Block of the move
+    pub fn charge(&mut self, player_index: usize, block_number: BlockNumber) -> bool {
+        let used = block_number.saturating_sub(self.started_at);
+        match self.banks.get_mut(player_index) {
+            Some(bank) if used <= *bank => {
+                *bank = bank.saturating_sub(used);
+                self.started_at = block_number;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Storage deposit reserved from the initiator of an app
+///
+/// The deposit of an app is `base + per_player * player_num + per_byte * state_len`.
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct SessionDepositConfig<Balance> {
+    /// Deposit of every app
+    pub base: Balance,
+    /// Deposit per player of the app
+    pub per_player: Balance,
+    /// Deposit per byte of the app state (board)
+    pub per_byte: Balance,
+}
+
+impl<Balance: AtLeast32BitUnsigned + Copy> SessionDepositConfig<Balance> {
+    /// Deposit of an app
+    ///
+    /// Parameters:
+    /// `player_num`: Number of players
+    /// `state_len`: Byte length of the app state
+    pub fn amount(&self, player_num: usize, state_len: usize) -> Balance {
+        self.base
+            .saturating_add(self.per_player.saturating_mul(Balance::from(player_num as u32)))
+            .saturating_add(self.per_byte.saturating_mul(Balance::from(state_len as u32)))
+    }
+}
+
+/// Tag at the start of every signed app state payload
+pub const STATE_PAYLOAD_TAG: [u8; 8] = *b"celerapp";
+
+/// Tag at the start of every payload signed to agree on the final app state
+pub const FINAL_STATE_PAYLOAD_TAG: [u8; 8] = *b"celerfin";
+
+/// Version of the signed app state payload, bumped whenever its layout changes
+pub const STATE_PAYLOAD_VERSION: u8 = 1;
+
+/// Payload of an off-chain app state signed by the players
+///
+/// The payload is `STATE_PAYLOAD_TAG ++ STATE_PAYLOAD_VERSION ++ app_id ++ genesis_hash ++ encoded_state`,
+/// so a signature is only valid for one app runtime module on one chain.
+///
+/// Parameters:
+/// `app_id`: ModuleId of the app runtime module
+/// `genesis_hash`: Hash of the genesis block of the chain
+/// `encoded_state`: Encoded app state
+pub fn state_signing_payload<Hash: Encode>(
+    app_id: ModuleId,
+    genesis_hash: &Hash,
+    encoded_state: &[u8],
+) -> Vec<u8> {
+    tagged_payload(STATE_PAYLOAD_TAG, app_id, genesis_hash, encoded_state)
+}
+
+/// Payload of an off-chain app state the players sign as the final state of the app
+///
+/// Same as `state_signing_payload` but starting with `FINAL_STATE_PAYLOAD_TAG`, so the
+/// signatures of an ordinary state cannot finalize the app.
+///
+/// Parameters:
+/// `app_id`: ModuleId of the app runtime module
+/// `genesis_hash`: Hash of the genesis block of the chain
+/// `encoded_state`: Encoded app state
+pub fn final_state_signing_payload<Hash: Encode>(
+    app_id: ModuleId,
+    genesis_hash: &Hash,
+    encoded_state: &[u8],
+) -> Vec<u8> {
+    tagged_payload(FINAL_STATE_PAYLOAD_TAG, app_id, genesis_hash, encoded_state)
+}
+
+fn tagged_payload<Hash: Encode>(
+    tag: [u8; 8],
+    app_id: ModuleId,
+    genesis_hash: &Hash,
+    encoded_state: &[u8],
+) -> Vec<u8> {
+    let mut payload = tag.to_vec();
+    payload.push(STATE_PAYLOAD_VERSION);
+    payload.extend(app_id.encode());
+    payload.extend(genesis_hash.encode());
+    payload.extend_from_slice(encoded_state);
+
+    payload
+}
+
+/// Verify off-chain state signatures
+///
+/// Parameters:
+/// `signatures`: Signatures from the players, in player order
+/// `encoded`: Signed payload of the app state, see `state_signing_payload`
+/// `signers`: AccountId of players
+pub fn valid_signers<Signature, AccountId>(
+    signatures: &[Signature],
+    encoded: &[u8],
+    signers: &[AccountId],
+) -> Result<(), DisputeError>
+where
+    Signature: Verify,
+    Signature::Signer: IdentifyAccount<AccountId = AccountId>,
+{
+    if signatures.len() != signers.len() {
+        return Err(DisputeError::InvalidSignatureCount);
+    }
+    for (signature, signer) in signatures.iter().zip(signers.iter()) {
+        if !signature.verify(encoded, signer) {
+            return Err(DisputeError::InvalidSignature);
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that there are at least two players in strictly ascending order
+///
+/// Parameter:
+/// `players`: AccountId of players
+pub fn ensure_ordered_players<AccountId: Ord>(players: &[AccountId]) -> Result<(), DisputeError> {
+    if players.len() < 2 {
+        return Err(DisputeError::InvalidPlayers);
+    }
+    if players.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(DisputeError::InvalidPlayers);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,374 @@
+//! Lifecycle of an app after its dispute, shared by the app runtime modules
+//!
+//! A runtime module implements `SessionLifecycle` with its storage maps and
+//! events, the provided functions reserve the storage deposit of an app, finalize
+//! the app once its action deadline passed, in `on_initialize` or through the
+//! unsigned transactions of the off-chain worker, record its finalization, queue
+//! the app once its outcome is consumed and clear it from storage returning the deposit.
+//! The id of a cleared app stays recorded, so the app can not be initiated again and
+//! the states signed for it can not be replayed.
+
+use codec::FullCodec;
+use frame_support::{
+    debug,
+    storage::{IterableStorageMap, StorageMap},
+    traits::{Currency, ReservableCurrency},
+    weights::Weight,
+};
+use sp_runtime::traits::{AtLeast32BitUnsigned, One, Saturating};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionPriority, TransactionValidity, ValidTransaction,
+};
+use sp_runtime::{DispatchError, DispatchResult, RuntimeDebug};
+use sp_std::{cmp, fmt::Debug, vec::Vec};
+use crate::{AppStatus, DisputeStateMachine, SessionDepositConfig};
+
+/// Balance of the storage deposit of an app
+pub type DepositOf<L> =
+    <<L as SessionLifecycle>::Currency as Currency<<L as SessionLifecycle>::AccountId>>::Balance;
+
+/// Failures of clearing an app
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum LifecycleError {
+    /// App is not finalized
+    NotFinalized,
+    /// Outcome of the app is not consumed
+    OutcomeNotConsumed,
+    /// Retention period of the app has not passed
+    RetentionPeriodNotPassed,
+}
+
+impl LifecycleError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleError::NotFinalized => "app is not finalized",
+            LifecycleError::OutcomeNotConsumed => "outcome is not consumed",
+            LifecycleError::RetentionPeriodNotPassed => "retention period does not pass",
+        }
+    }
+}
+
+impl From<LifecycleError> for DispatchError {
+    fn from(err: LifecycleError) -> DispatchError {
+        DispatchError::Other(err.as_str())
+    }
+}
+
+/// Storage and events of a runtime module whose apps are cleared after finalization
+///
+/// Implemented by the `Module` of every app runtime module, which keeps only
+/// the app specific parts and calls the provided functions.
+pub trait SessionLifecycle {
+    type AccountId: FullCodec;
+    type Hash: FullCodec + Copy + PartialEq + Debug;
+    type BlockNumber: AtLeast32BitUnsigned + FullCodec + Copy;
+    /// Info of an app
+    type Info: DisputeStateMachine<Self::BlockNumber> + FullCodec;
+    /// Info of every app
+    type InfoMap: StorageMap<Self::Hash, Self::Info, Query = Option<Self::Info>>
+        + IterableStorageMap<Self::Hash, Self::Info>;
+    /// Block number at which the app is finalized
+    type FinalizedAt: StorageMap<Self::Hash, Self::BlockNumber, Query = Option<Self::BlockNumber>>;
+    /// Whether the outcome of the finalized app is consumed
+    type OutcomeConsumed: StorageMap<Self::Hash, bool, Query = bool>;
+    /// Id of apps whose action deadline passes before the block, finalized in its `on_initialize`
+    type DeadlineQueue: StorageMap<Self::BlockNumber, Vec<Self::Hash>, Query = Vec<Self::Hash>>;
+    /// Id of apps to be cleared in `on_initialize` of the block
+    type ClearQueue: StorageMap<Self::BlockNumber, Vec<Self::Hash>, Query = Vec<Self::Hash>>;
+    /// Currency in which the storage deposit of an app is reserved
+    type Currency: ReservableCurrency<Self::AccountId>;
+    /// Initiator and reserved storage deposit of the app
+    type Deposits: StorageMap<
+        Self::Hash,
+        (Self::AccountId, DepositOf<Self>),
+        Query = Option<(Self::AccountId, DepositOf<Self>)>,
+    >;
+    /// Whether the app is cleared, kept so the id can not be initiated again
+    type ClearedSessions: StorageMap<Self::Hash, bool, Query = bool>;
+
+    /// Tag prefix of the unsigned finalizations in the transaction pool
+    const UNSIGNED_TAG_PREFIX: &'static str;
+
+    /// Current block number
+    fn block_number() -> Self::BlockNumber;
+
+    /// Number of blocks a finalized app is kept after finalization
+    fn retention_period() -> Self::BlockNumber;
+
+    /// Maximum number of expired apps finalized in `on_initialize` of a block
+    fn max_finalize_per_block() -> u32;
+
+    /// Maximum number of apps cleared in `on_initialize` of a block
+    fn max_clear_per_block() -> u32;
+
+    /// Priority of the finalizations the off-chain worker submits unsigned
+    fn unsigned_priority() -> TransactionPriority;
+
+    /// Storage deposit reserved from the initiator of an app
+    fn session_deposit() -> SessionDepositConfig<DepositOf<Self>>;
+
+    /// Weight of finalizing the expired apps queued for a block
+    ///
+    /// Parameter:
+    /// `queued`: Number of expired apps
+    fn finalize_expired_weight(queued: u32) -> Weight;
+
+    /// Weight of clearing the apps queued for a block
+    ///
+    /// Parameter:
+    /// `queued`: Number of cleared apps
+    fn sweep_weight(queued: u32) -> Weight;
+
+    /// Finalize an app whose action deadline passed with the app specific outcome
+    ///
+    /// Parameters:
+    /// `session_id`: Id of app
+    /// `info`: Info of app
+    fn finalize_on_timeout(session_id: Self::Hash, info: Self::Info) -> DispatchResult;
+
+    /// Submit `finalize_on_action_timeout_unsigned` of an app as an unsigned transaction
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn submit_finalization(session_id: Self::Hash) -> Result<(), ()>;
+
+    /// Emit `Finalized`
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn on_finalized(session_id: Self::Hash);
+
+    /// Emit `OutcomeConsumed`
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn on_outcome_consumed(session_id: Self::Hash);
+
+    /// Remove the module specific storage of a cleared app and emit `SessionCleared`
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn on_cleared(session_id: Self::Hash);
+
+    /// Check whether an app with the id exists or existed and was cleared
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn is_session_used(session_id: Self::Hash) -> bool {
+        Self::InfoMap::contains_key(session_id) || Self::ClearedSessions::get(session_id)
+    }
+
+    /// Reserve the storage deposit of a new app from its initiator
+    ///
+    /// Parameters:
+    /// `session_id`: Id of app
+    /// `initiator`: Account initiating the app
+    /// `player_num`: Number of players
+    /// `state_len`: Byte length of the app state
+    fn reserve_deposit(
+        session_id: Self::Hash,
+        initiator: Self::AccountId,
+        player_num: usize,
+        state_len: usize
+    ) -> DispatchResult {
+        let deposit = Self::session_deposit().amount(player_num, state_len);
+        Self::Currency::reserve(&initiator, deposit)?;
+        Self::Deposits::insert(session_id, (initiator, deposit));
+
+        Ok(())
+    }
+
+    /// Queue an app in the deadline queue of the first block after its action deadline
+    ///
+    /// Parameters:
+    /// `session_id`: Id of app
+    /// `info`: Info of app
+    fn queue_deadline(session_id: Self::Hash, info: &Self::Info) {
+        if let Some(deadline) = info.action_deadline() {
+            Self::DeadlineQueue::mutate(deadline.saturating_add(One::one()), |queue| {
+                if !queue.contains(&session_id) {
+                    queue.push(session_id);
+                }
+            });
+        }
+    }
+
+    /// Record the finalization block of a finalized app and emit `Finalized`
+    ///
+    /// Parameters:
+    /// `session_id`: Id of app
+    /// `status`: Status of app after the update
+    fn record_finalized(session_id: Self::Hash, status: &AppStatus) {
+        if *status == AppStatus::Finalized {
+            Self::FinalizedAt::insert(session_id, Self::block_number());
+            Self::on_finalized(session_id);
+        }
+    }
+
+    /// Mark the outcome of a finalized app as consumed and queue the app for
+    /// the first block after its retention period
+    ///
+    /// Consuming the outcome again is a no-op.
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn queue_clear(session_id: Self::Hash) -> Result<(), LifecycleError> {
+        let finalized_at = match Self::FinalizedAt::get(session_id) {
+            Some(block_number) => block_number,
+            None => return Err(LifecycleError::NotFinalized),
+        };
+        if Self::OutcomeConsumed::get(session_id) {
+            return Ok(());
+        }
+        Self::OutcomeConsumed::insert(session_id, true);
+
+        let next_block = Self::block_number() + One::one();
+        let clear_at = cmp::max(finalized_at + Self::retention_period(), next_block);
+        Self::ClearQueue::mutate(clear_at, |queue| queue.push(session_id));
+        Self::on_outcome_consumed(session_id);
+
+        Ok(())
+    }
+
+    /// Check whether a finalized app can be cleared
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn ensure_clearable(session_id: Self::Hash) -> Result<(), LifecycleError> {
+        let finalized_at = match Self::FinalizedAt::get(session_id) {
+            Some(block_number) => block_number,
+            None => return Err(LifecycleError::NotFinalized),
+        };
+        if !Self::OutcomeConsumed::get(session_id) {
+            return Err(LifecycleError::OutcomeNotConsumed);
+        }
+        if Self::block_number() < finalized_at + Self::retention_period() {
+            return Err(LifecycleError::RetentionPeriodNotPassed);
+        }
+
+        Ok(())
+    }
+
+    /// Remove a finalized app from storage and return its deposit
+    ///
+    /// The id is recorded in `ClearedSessions`, so states co-signed for the app can
+    /// not be replayed on an app initiated again with the same id.
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn clear(session_id: Self::Hash) {
+        Self::InfoMap::remove(session_id);
+        Self::ClearedSessions::insert(session_id, true);
+        Self::FinalizedAt::remove(session_id);
+        Self::OutcomeConsumed::remove(session_id);
+        if let Some((initiator, deposit)) = Self::Deposits::take(session_id) {
+            Self::Currency::unreserve(&initiator, deposit);
+        }
+        Self::on_cleared(session_id);
+    }
+
+    /// Finalize the apps whose action deadline passed before a block
+    ///
+    /// At most `max_finalize_per_block` apps are finalized, the rest is moved to
+    /// the queue of the next block. An app whose deadline moved after it was
+    /// queued is skipped, it is queued again at its new deadline.
+    ///
+    /// Parameter:
+    /// `block_number`: Current block number
+    fn finalize_expired(block_number: Self::BlockNumber) -> Weight {
+        let mut queue = Self::DeadlineQueue::take(block_number);
+        let max_finalize = Self::max_finalize_per_block() as usize;
+        if queue.len() > max_finalize {
+            let remaining = queue.split_off(max_finalize);
+            Self::DeadlineQueue::mutate(block_number + One::one(), |next| next.extend(remaining));
+        }
+
+        let queued = queue.len() as u32;
+        for session_id in queue {
+            if let Some(info) = Self::InfoMap::get(session_id) {
+                if let Ok(true) = info.is_action_timeout(block_number) {
+                    let _ = Self::finalize_on_timeout(session_id, info);
+                }
+            }
+        }
+
+        Self::finalize_expired_weight(queued)
+    }
+
+    /// Submit the unsigned finalization of the expired apps `on_initialize` of the next block leaves over
+    ///
+    /// `on_initialize` of the next block finalizes the first `max_finalize_per_block` apps
+    /// of its deadline queue. Every app is scanned, and the finalization of each app whose
+    /// action deadline passed and that is not among them is submitted, wherever it is queued.
+    ///
+    /// Parameter:
+    /// `block_number`: Number of the imported block
+    fn submit_expired(block_number: Self::BlockNumber) {
+        let max_finalize = Self::max_finalize_per_block() as usize;
+        let next_finalized: Vec<Self::Hash> = Self::DeadlineQueue::get(block_number + One::one())
+            .into_iter()
+            .take(max_finalize)
+            .collect();
+        for (session_id, info) in Self::InfoMap::iter() {
+            if next_finalized.contains(&session_id) {
+                continue;
+            }
+            if let Ok(true) = info.is_action_timeout(block_number) {
+                if Self::submit_finalization(session_id).is_err() {
+                    debug::warn!("Failed to submit the finalization of {:?}", session_id);
+                }
+            }
+        }
+    }
+
+    /// Accept the unsigned finalization of an app whose action deadline passed
+    ///
+    /// At most one finalization of an app is kept in the pool.
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn validate_finalization(session_id: &Self::Hash) -> TransactionValidity {
+        let info = match Self::InfoMap::get(session_id) {
+            Some(info) => info,
+            None => return InvalidTransaction::Stale.into(),
+        };
+        match info.is_action_timeout(Self::block_number()) {
+            Ok(true) => {},
+            Ok(false) => return InvalidTransaction::Stale.into(),
+            Err(_) => return InvalidTransaction::Future.into(),
+        }
+
+        ValidTransaction::with_tag_prefix(Self::UNSIGNED_TAG_PREFIX)
+            .priority(Self::unsigned_priority())
+            .and_provides(session_id)
+            .longevity(5)
+            .propagate(true)
+            .build()
+    }
+
+    /// Clear the apps queued for a block
+    ///
+    /// At most `max_clear_per_block` apps are cleared, the rest is moved to
+    /// the queue of the next block.
+    ///
+    /// Parameter:
+    /// `block_number`: Current block number
+    fn sweep(block_number: Self::BlockNumber) -> Weight {
+        let mut queue = Self::ClearQueue::take(block_number);
+        let max_clear = Self::max_clear_per_block() as usize;
+        if queue.len() > max_clear {
+            let remaining = queue.split_off(max_clear);
+            Self::ClearQueue::mutate(block_number + One::one(), |next| next.extend(remaining));
+        }
+
+        let queued = queue.len() as u32;
+        for session_id in queue {
+            // skip apps already cleared by clear_session
+            if Self::FinalizedAt::contains_key(session_id) {
+                Self::clear(session_id);
+            }
+        }
+
+        Self::sweep_weight(queued)
+    }
+}
@@ -0,0 +1,160 @@
+//! Session app logics of the single session app runtime module and the generic
+//! app runtime module
+
+use codec::{Decode, Encode};
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+use crate::{AppLogicError, StateChannelApp};
+
+/// Single session app logic
+///
+/// The state is a single byte, state 1 or 2 finalizes the app and the outcome
+/// is whether the state equals the query data. An action replaces the state, so
+/// action 1 or 2 finalizes the app with that outcome.
+pub struct SingleSessionLogic;
+
+impl StateChannelApp for SingleSessionLogic {
+    type Config = ();
+    type OffchainState = u8;
+    type State = u8;
+    type Action = u8;
+    type Query = u8;
+    type Error = AppLogicError;
+
+    fn initial_state(_config: ()) -> Result<u8, AppLogicError> {
+        Ok(0)
+    }
+
+    fn validate_offchain_state(_state: &u8, _offchain_state: &u8) -> Result<(), AppLogicError> {
+        Ok(())
+    }
+
+    fn settle_offchain_state(state: &mut u8, offchain_state: u8) {
+        *state = offchain_state;
+    }
+
+    fn apply_action(state: &mut u8, _player_index: u8, action: u8) -> Result<(), AppLogicError> {
+        *state = action;
+        Ok(())
+    }
+
+    fn is_terminal(state: &u8) -> bool {
+        *state == 1 || *state == 2
+    }
+
+    fn outcome(state: &u8, query: u8) -> Result<Vec<u8>, AppLogicError> {
+        Ok((*state == query).encode())
+    }
+}
+
+/// Settled state of the numeric outcome session app
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct NumericSessionState {
+    /// Payout amount or score of the session
+    pub amount: u128,
+    /// Whether the players finished the session
+    pub finished: bool,
+}
+
+/// On-chain state of the numeric outcome session app
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub struct NumericSessionInfo {
+    /// Upper bound of the amount, chosen by the players at initiation
+    pub max_amount: u128,
+    pub state: NumericSessionState,
+}
+
+/// Encoding of the numeric outcome
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub enum NumericOutcomeType {
+    /// SCALE-encoded `u32`, an amount above `u32::max_value()` is rejected
+    U32,
+    /// SCALE-encoded `u128`
+    U128,
+}
+
+/// Failures of the numeric outcome session app logic
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum NumericSessionError {
+    /// Amount of the off-chain state exceeds the max amount
+    AmountExceedsMax,
+    /// Action does not finish the session
+    InvalidAction,
+    /// Amount does not fit the queried `u32` outcome
+    AmountExceedsU32,
+}
+
+impl From<NumericSessionError> for AppLogicError {
+    fn from(err: NumericSessionError) -> AppLogicError {
+        match err {
+            NumericSessionError::AmountExceedsMax => AppLogicError::InvalidOffchainState,
+            NumericSessionError::InvalidAction => AppLogicError::InvalidAction,
+            NumericSessionError::AmountExceedsU32 => AppLogicError::InvalidQuery,
+        }
+    }
+}
+
+/// Numeric outcome session app logic for the generic app runtime module
+///
+/// The co-signed off-chain state carries the payout amount or score of the session,
+/// bounded by the `max_amount` config. An action of any player with `true` finishes
+/// the session at the settled amount, and the outcome is the amount encoded as
+/// requested by the query data. It works for any number of players.
+pub struct NumericSessionLogic;
+
+impl StateChannelApp for NumericSessionLogic {
+    type Config = u128;
+    type OffchainState = NumericSessionState;
+    type State = NumericSessionInfo;
+    type Action = bool;
+    type Query = NumericOutcomeType;
+    type Error = NumericSessionError;
+
+    fn initial_state(max_amount: u128) -> Result<NumericSessionInfo, NumericSessionError> {
+        Ok(NumericSessionInfo {
+            max_amount: max_amount,
+            state: NumericSessionState {
+                amount: 0,
+                finished: false,
+            },
+        })
+    }
+
+    fn validate_offchain_state(
+        info: &NumericSessionInfo,
+        offchain_state: &NumericSessionState
+    ) -> Result<(), NumericSessionError> {
+        if offchain_state.amount > info.max_amount {
+            return Err(NumericSessionError::AmountExceedsMax);
+        }
+        Ok(())
+    }
+
+    fn settle_offchain_state(info: &mut NumericSessionInfo, offchain_state: NumericSessionState) {
+        info.state = offchain_state;
+    }
+
+    fn apply_action(info: &mut NumericSessionInfo, _player_index: u8, finish: bool) -> Result<(), NumericSessionError> {
+        if !finish {
+            return Err(NumericSessionError::InvalidAction);
+        }
+        info.state.finished = true;
+        Ok(())
+    }
+
+    fn is_terminal(info: &NumericSessionInfo) -> bool {
+        info.state.finished
+    }
+
+    fn outcome(info: &NumericSessionInfo, query: NumericOutcomeType) -> Result<Vec<u8>, NumericSessionError> {
+        match query {
+            NumericOutcomeType::U32 => {
+                if info.state.amount > u32::max_value() as u128 {
+                    return Err(NumericSessionError::AmountExceedsU32);
+                }
+                Ok((info.state.amount as u32).encode())
+            },
+            NumericOutcomeType::U128 => Ok(info.state.amount.encode()),
+        }
+    }
+}
@@ -0,0 +1,680 @@
+use super::*;
+use sp_core::{sr25519, Pair};
+
+#[derive(Clone, Debug)]
+struct MockInfo {
+    seq_num: u128,
+    timeout: u64,
+    deadline: u64,
+    status: AppStatus,
+}
+
+impl DisputeStateMachine<u64> for MockInfo {
+    fn status(&self) -> AppStatus { self.status.clone() }
+    fn set_status(&mut self, status: AppStatus) { self.status = status; }
+    fn seq_num(&self) -> u128 { self.seq_num }
+    fn set_seq_num(&mut self, seq_num: u128) { self.seq_num = seq_num; }
+    fn timeout(&self) -> u64 { self.timeout }
+    fn deadline(&self) -> u64 { self.deadline }
+    fn set_deadline(&mut self, deadline: u64) { self.deadline = deadline; }
+}
+
+fn idle_info() -> MockInfo {
+    MockInfo {
+        seq_num: 0,
+        timeout: 2,
+        deadline: 0,
+        status: AppStatus::Idle,
+    }
+}
+
+fn account_pair(s: &str) -> sr25519::Pair {
+    sr25519::Pair::from_string(&format!("//{}", s), None).expect("static values are valid: qed")
+}
+
+#[test]
+fn test_pass_intend_settle_and_apply_action() {
+    let mut info = idle_info();
+    assert_eq!(info.intend_settle(3, 1), Ok(()));
+    assert_eq!(info.status(), AppStatus::Settle);
+    assert_eq!(info.settle_finalized_time(), Some(3));
+    assert_eq!(info.action_deadline(), Some(5));
+
+    assert_eq!(info.apply_action(3), Err(DisputeError::NotInActionMode));
+    assert_eq!(info.apply_action(4), Ok(()));
+    assert_eq!(info.seq_num(), 4);
+    assert_eq!(info.status(), AppStatus::Action);
+    assert_eq!(info.action_deadline(), Some(6));
+    assert_eq!(info.settle_finalized_time(), None);
+}
+
+#[test]
+fn test_fail_intend_settle_with_invalid_seq_num() {
+    let mut info = idle_info();
+    assert_eq!(info.intend_settle(3, 1), Ok(()));
+    assert_eq!(info.intend_settle(3, 1), Err(DisputeError::InvalidSeqNum));
+}
+
+#[test]
+fn test_fail_after_finalized() {
+    let mut info = idle_info();
+    info.set_status(AppStatus::Finalized);
+    assert_eq!(info.intend_settle(1, 1), Err(DisputeError::AlreadyFinalized));
+    assert_eq!(info.apply_action(1), Err(DisputeError::AlreadyFinalized));
+    assert_eq!(info.is_action_timeout(100), Ok(false));
+}
+
+#[test]
+fn test_finalize_by_agreement() {
+    let mut info = idle_info();
+    assert_eq!(info.finalize_by_agreement(0), Ok(()));
+    assert_eq!(info.status(), AppStatus::Finalized);
+    assert_eq!(info.finalize_by_agreement(1), Err(DisputeError::AlreadyFinalized));
+
+    // the final state may have the on-chain sequence number but not an older one
+    let mut info = idle_info();
+    assert_eq!(info.intend_settle(3, 1), Ok(()));
+    assert_eq!(info.finalize_by_agreement(2), Err(DisputeError::InvalidSeqNum));
+    assert_eq!(info.finalize_by_agreement(3), Ok(()));
+    assert_eq!(info.seq_num(), 3);
+    assert_eq!(info.settle_finalized_time(), None);
+}
+
+#[test]
+fn test_is_action_timeout() {
+    let mut info = idle_info();
+    assert_eq!(info.is_action_timeout(100), Ok(false));
+
+    assert_eq!(info.intend_settle(1, 1), Ok(()));
+    assert_eq!(info.is_action_timeout(5), Err(DisputeError::WhileSettling));
+    assert_eq!(info.is_action_timeout(6), Ok(true));
+
+    assert_eq!(info.apply_action(4), Ok(()));
+    assert_eq!(info.is_action_timeout(6), Err(DisputeError::DeadlineNotPassed));
+    assert_eq!(info.is_action_timeout(7), Ok(true));
+}
+
+#[test]
+fn test_deadline_saturates_with_max_timeout() {
+    let mut info = idle_info();
+    info.timeout = u64::max_value();
+    assert_eq!(info.intend_settle(u128::max_value(), 1), Ok(()));
+    assert_eq!(info.settle_finalized_time(), Some(u64::max_value()));
+    assert_eq!(info.is_action_timeout(100), Err(DisputeError::WhileSettling));
+    assert_eq!(info.apply_action(100), Err(DisputeError::NotInActionMode));
+
+    info.deadline = 1;
+    assert_eq!(info.apply_action(100), Ok(()));
+    assert_eq!(info.seq_num(), u128::max_value());
+    assert_eq!(info.action_deadline(), Some(u64::max_value()));
+}
+
+#[test]
+fn test_game_clock() {
+    let mut clock = GameClock::<u64>::new(2, 10);
+    clock.start(5);
+    assert_eq!(clock.bank(0), Some(10));
+    assert_eq!(clock.bank(2), None);
+
+    // player 0 takes 4 blocks, player 1 takes 10 blocks
+    assert!(clock.charge(0, 9));
+    assert_eq!(clock.banks, vec![6, 10]);
+    assert!(clock.charge(1, 19));
+    assert_eq!(clock.banks, vec![6, 0]);
+    assert_eq!(clock.started_at, 19);
+
+    // a move exceeding the bank does not change the clock
+    assert!(!clock.charge(0, 26));
+    assert_eq!(clock.banks, vec![6, 0]);
+    assert_eq!(clock.started_at, 19);
+    assert!(!clock.charge(2, 19));
+    assert!(clock.charge(0, 25));
+    assert_eq!(clock.banks, vec![0, 0]);
+}
+
+#[test]
+fn test_valid_signers() {
+    let alice = account_pair("Alice");
+    let bob = account_pair("Bob");
+    let signers = vec![alice.public(), bob.public()];
+    let encoded = b"app state".to_vec();
+    let sigs = vec![alice.sign(&encoded), bob.sign(&encoded)];
+
+    assert_eq!(valid_signers(&sigs, &encoded, &signers), Ok(()));
+    assert_eq!(
+        valid_signers(&sigs[..1], &encoded, &signers),
+        Err(DisputeError::InvalidSignatureCount)
+    );
+    let swapped = vec![sigs[1].clone(), sigs[0].clone()];
+    assert_eq!(
+        valid_signers(&swapped, &encoded, &signers),
+        Err(DisputeError::InvalidSignature)
+    );
+}
+
+#[test]
+fn test_state_signing_payload() {
+    let encoded_state = vec![1u8, 2, 3];
+    let payload = state_signing_payload(ModuleId(*b"_single_"), &[7u8; 32], &encoded_state);
+
+    assert_eq!(&payload[..8], b"celerapp");
+    assert_eq!(payload[8], STATE_PAYLOAD_VERSION);
+    assert_eq!(&payload[9..17], b"_single_");
+    assert_eq!(&payload[17..49], &[7u8; 32]);
+    assert_eq!(&payload[49..], &encoded_state[..]);
+
+    // a signature is bound to the app runtime module and the chain
+    let alice = account_pair("Alice");
+    let sig = alice.sign(&payload);
+    let other_app = state_signing_payload(ModuleId(*b"s_gomoku"), &[7u8; 32], &encoded_state);
+    let other_chain = state_signing_payload(ModuleId(*b"_single_"), &[8u8; 32], &encoded_state);
+    assert_eq!(valid_signers(&[sig.clone()], &payload, &[alice.public()]), Ok(()));
+    assert_eq!(
+        valid_signers(&[sig.clone()], &other_app, &[alice.public()]),
+        Err(DisputeError::InvalidSignature)
+    );
+    assert_eq!(
+        valid_signers(&[sig.clone()], &other_chain, &[alice.public()]),
+        Err(DisputeError::InvalidSignature)
+    );
+
+    // a signature of an ordinary state does not agree on the final state
+    let final_payload = final_state_signing_payload(ModuleId(*b"_single_"), &[7u8; 32], &encoded_state);
+    assert_eq!(&final_payload[..8], b"celerfin");
+    assert_eq!(&final_payload[8..], &payload[8..]);
+    assert_eq!(
+        valid_signers(&[sig], &final_payload, &[alice.public()]),
+        Err(DisputeError::InvalidSignature)
+    );
+}
+
+#[test]
+fn test_ensure_ordered_players() {
+    assert_eq!(ensure_ordered_players(&[1u8, 2, 3]), Ok(()));
+    assert_eq!(ensure_ordered_players(&[1u8]), Err(DisputeError::InvalidPlayers));
+    assert_eq!(ensure_ordered_players(&[2u8, 1]), Err(DisputeError::InvalidPlayers));
+    assert_eq!(ensure_ordered_players(&[1u8, 1]), Err(DisputeError::InvalidPlayers));
+}
+
+#[test]
+fn test_session_deposit_amount() {
+    let config = SessionDepositConfig::<u64> {
+        base: 10,
+        per_player: 2,
+        per_byte: 1,
+    };
+    assert_eq!(config.amount(2, 1), 15);
+    assert_eq!(config.amount(3, 228), 244);
+
+    let config = SessionDepositConfig::<u64> {
+        base: u64::max_value(),
+        per_player: 1,
+        per_byte: 1,
+    };
+    assert_eq!(config.amount(2, 1), u64::max_value());
+}
+
+#[test]
+fn test_game_outcome_payout() {
+    assert_eq!(GameOutcome::Winner(1).payout(1, 2, 100), Some(100));
+    assert_eq!(GameOutcome::Winner(1).payout(0, 2, 100), Some(0));
+    assert_eq!(GameOutcome::Draw.payout(0, 2, 101), Some(51));
+    assert_eq!(GameOutcome::Draw.payout(1, 2, 101), Some(50));
+    assert_eq!(GameOutcome::Draw.payout(2, 3, 100), Some(33));
+    assert_eq!(GameOutcome::Draw.payout(2, 2, 100), None);
+    assert_eq!(GameOutcome::Undecided.payout(0, 2, 100), None);
+}
+
+/// 15x15 board with the stones (x, y, color)
+fn gomoku_board(stones: &[(u8, u8, u8)]) -> Vec<u8> {
+    let mut cells = vec![0; 225];
+    for &(x, y, color) in stones {
+        cells[x as usize * 15 + y as usize] = color;
+    }
+    cells
+}
+
+/// Place a stone at (x, y) and judge it with five in a row
+fn place(stones: &[(u8, u8, u8)], rule_set: RuleSet, x: u8, y: u8, color: u8) -> MoveVerdict {
+    let mut cells = gomoku_board(stones);
+    cells[x as usize * 15 + y as usize] = color;
+    judge_move(&cells, 15, 5, rule_set, x, y)
+}
+
+#[test]
+fn test_judge_move_five_and_overline() {
+    let four = [(7, 3, 1), (7, 4, 1), (7, 5, 1), (7, 6, 1)];
+    let overline = [(7, 2, 1), (7, 3, 1), (7, 4, 1), (7, 5, 1), (7, 6, 1)];
+    let white_overline = [(7, 2, 2), (7, 3, 2), (7, 4, 2), (7, 5, 2), (7, 6, 2)];
+
+    // all directions through the stone
+    assert_eq!(place(&[(4, 7, 1), (5, 7, 1), (6, 7, 1), (8, 7, 1)], RuleSet::Standard, 7, 7, 1), MoveVerdict::Win);
+    assert_eq!(place(&[(5, 5, 1), (6, 6, 1), (8, 8, 1), (9, 9, 1)], RuleSet::Standard, 7, 7, 1), MoveVerdict::Win);
+    assert_eq!(place(&[(5, 9, 1), (6, 8, 1), (8, 6, 1), (9, 5, 1)], RuleSet::Standard, 7, 7, 1), MoveVerdict::Win);
+    assert_eq!(place(&[(7, 3, 1), (7, 4, 1), (7, 5, 1)], RuleSet::Freestyle, 7, 6, 1), MoveVerdict::Continue);
+    // a run of another color does not count
+    assert_eq!(place(&[(7, 3, 2), (7, 4, 2), (7, 5, 2), (7, 6, 2)], RuleSet::Freestyle, 7, 7, 1), MoveVerdict::Continue);
+
+    for &rule_set in [RuleSet::Freestyle, RuleSet::Standard, RuleSet::Renju].iter() {
+        assert_eq!(place(&four, rule_set, 7, 7, 1), MoveVerdict::Win);
+    }
+    assert_eq!(place(&overline, RuleSet::Freestyle, 7, 7, 1), MoveVerdict::Win);
+    assert_eq!(place(&overline, RuleSet::Standard, 7, 7, 1), MoveVerdict::Continue);
+    assert_eq!(place(&white_overline, RuleSet::Standard, 7, 7, 2), MoveVerdict::Continue);
+    assert_eq!(place(&overline, RuleSet::Renju, 7, 7, 1), MoveVerdict::Forbidden);
+    assert_eq!(place(&white_overline, RuleSet::Renju, 7, 7, 2), MoveVerdict::Win);
+    // the overline is made by joining two runs
+    assert_eq!(place(&[(7, 1, 1), (7, 2, 1), (7, 3, 1), (7, 5, 1), (7, 6, 1)], RuleSet::Renju, 7, 4, 1), MoveVerdict::Forbidden);
+
+    // smaller win length on a smaller board
+    let mut cells = vec![0; 9];
+    cells[0] = 1;
+    cells[4] = 1;
+    cells[8] = 1;
+    assert_eq!(judge_move(&cells, 3, 3, RuleSet::Standard, 2, 2), MoveVerdict::Win);
+    cells[1] = 1;
+    assert_eq!(judge_move(&cells, 3, 3, RuleSet::Standard, 0, 1), MoveVerdict::Continue);
+}
+
+#[test]
+fn test_judge_move_renju_double_four() {
+    // fours in two lines
+    let stones = [(7, 3, 1), (7, 4, 1), (7, 5, 1), (4, 6, 1), (5, 6, 1), (6, 6, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 6, 1), MoveVerdict::Forbidden);
+    assert_eq!(place(&stones, RuleSet::Standard, 7, 6, 1), MoveVerdict::Continue);
+    assert_eq!(place(&stones, RuleSet::Freestyle, 7, 6, 1), MoveVerdict::Continue);
+
+    // a closed four counts as a four
+    let stones = [(7, 2, 2), (7, 3, 1), (7, 4, 1), (7, 5, 1), (4, 6, 1), (5, 6, 1), (6, 6, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 6, 1), MoveVerdict::Forbidden);
+
+    // X_XXX_X makes two fours in one line
+    let stones = [(7, 3, 1), (7, 5, 1), (7, 7, 1), (7, 9, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 6, 1), MoveVerdict::Forbidden);
+
+    // XX_XX_XX makes two fours in one line
+    let stones = [(7, 2, 1), (7, 3, 1), (7, 6, 1), (7, 8, 1), (7, 9, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 5, 1), MoveVerdict::Forbidden);
+
+    // a straight four is a single four
+    let stones = [(7, 4, 1), (7, 5, 1), (7, 6, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 1), MoveVerdict::Continue);
+
+    // white may make a double four
+    let stones = [(7, 3, 2), (7, 4, 2), (7, 5, 2), (4, 6, 2), (5, 6, 2), (6, 6, 2)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 6, 2), MoveVerdict::Continue);
+}
+
+#[test]
+fn test_judge_move_renju_double_three() {
+    // open threes in two lines
+    let stones = [(7, 5, 1), (7, 6, 1), (5, 7, 1), (6, 7, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 1), MoveVerdict::Forbidden);
+    assert_eq!(place(&stones, RuleSet::Standard, 7, 7, 1), MoveVerdict::Continue);
+
+    // a split three X_XX counts as a three
+    let stones = [(7, 4, 1), (7, 6, 1), (5, 7, 1), (6, 7, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 1), MoveVerdict::Forbidden);
+
+    // diagonal threes
+    let stones = [(5, 5, 1), (6, 6, 1), (5, 9, 1), (6, 8, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 1), MoveVerdict::Forbidden);
+
+    // a three blocked by white is not open
+    let stones = [(7, 4, 2), (7, 5, 1), (7, 6, 1), (5, 7, 1), (6, 7, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 1), MoveVerdict::Continue);
+
+    // a three blocked by the edge of the board is not open
+    let stones = [(7, 0, 1), (7, 1, 1), (5, 2, 1), (6, 2, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 2, 1), MoveVerdict::Continue);
+
+    // a three whose fours would only extend to overlines is not open
+    let stones = [(7, 1, 1), (7, 4, 1), (7, 5, 1), (7, 9, 1), (5, 6, 1), (6, 6, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 6, 1), MoveVerdict::Continue);
+
+    // a four and a three are allowed
+    let stones = [(7, 4, 1), (7, 5, 1), (7, 6, 1), (5, 7, 1), (6, 7, 1)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 1), MoveVerdict::Continue);
+
+    // white may make a double three
+    let stones = [(7, 5, 2), (7, 6, 2), (5, 7, 2), (6, 7, 2)];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 2), MoveVerdict::Continue);
+}
+
+#[test]
+fn test_judge_move_renju_five_takes_precedence() {
+    // five in a row while the column becomes an overline
+    let stones = [
+        (7, 3, 1), (7, 4, 1), (7, 5, 1), (7, 6, 1),
+        (2, 7, 1), (3, 7, 1), (4, 7, 1), (5, 7, 1), (6, 7, 1),
+    ];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 1), MoveVerdict::Win);
+
+    // five in a row while making a double three
+    let stones = [
+        (7, 3, 1), (7, 4, 1), (7, 5, 1), (7, 6, 1),
+        (5, 7, 1), (6, 7, 1), (5, 5, 1), (6, 6, 1),
+    ];
+    assert_eq!(place(&stones, RuleSet::Renju, 7, 7, 1), MoveVerdict::Win);
+}
+
+#[test]
+fn test_validate_board() {
+    let five = [(7, 3, 1), (7, 4, 1), (7, 5, 1), (7, 6, 1), (7, 7, 1), (0, 0, 2), (0, 2, 2), (0, 4, 2), (0, 6, 2)];
+    let validate = |stones: &[(u8, u8, u8)], rule_set: RuleSet, winner: u8, turn: u8| {
+        validate_board(&gomoku_board(stones), 15, 5, rule_set, winner, turn, 225)
+    };
+
+    // empty board, black to move
+    assert_eq!(validate(&[], RuleSet::Freestyle, 0, 1), Ok(()));
+    assert_eq!(validate(&[], RuleSet::Freestyle, 0, 2), Err(BoardError::InvalidStoneCount));
+    assert_eq!(validate(&[], RuleSet::Freestyle, 0, 0), Err(BoardError::InvalidTurn));
+    assert_eq!(validate(&[], RuleSet::Freestyle, 0, 3), Err(BoardError::InvalidTurn));
+    assert_eq!(validate(&[(7, 7, 3)], RuleSet::Freestyle, 0, 1), Err(BoardError::InvalidCell));
+
+    // turn parity
+    assert_eq!(validate(&[(7, 7, 1)], RuleSet::Freestyle, 0, 2), Ok(()));
+    assert_eq!(validate(&[(7, 7, 1)], RuleSet::Freestyle, 0, 1), Err(BoardError::InvalidStoneCount));
+    assert_eq!(validate(&[(7, 7, 1), (7, 8, 2)], RuleSet::Freestyle, 0, 1), Ok(()));
+    assert_eq!(validate(&[(7, 7, 2)], RuleSet::Freestyle, 0, 1), Err(BoardError::InvalidStoneCount));
+    assert_eq!(validate(&[(7, 7, 1), (7, 8, 1)], RuleSet::Freestyle, 0, 2), Err(BoardError::InvalidStoneCount));
+
+    // winner consistency
+    assert_eq!(validate(&five, RuleSet::Freestyle, 1, 0), Ok(()));
+    assert_eq!(validate(&five, RuleSet::Freestyle, 1, 2), Err(BoardError::InvalidTurn));
+    assert_eq!(validate(&five, RuleSet::Freestyle, 0, 2), Err(BoardError::InvalidWinner));
+    assert_eq!(validate(&five, RuleSet::Freestyle, 2, 0), Err(BoardError::InvalidWinner));
+    assert_eq!(validate(&five, RuleSet::Freestyle, 3, 0), Err(BoardError::InvalidWinner));
+    assert_eq!(validate(&five[1..], RuleSet::Freestyle, 2, 0), Err(BoardError::InvalidWinner));
+    assert_eq!(validate(&[], RuleSet::Freestyle, 1, 0), Err(BoardError::InvalidWinner));
+
+    // the winner made the last move
+    let white_five = [(7, 3, 2), (7, 4, 2), (7, 5, 2), (7, 6, 2), (7, 7, 2), (0, 0, 1), (0, 2, 1), (0, 4, 1), (0, 6, 1), (0, 8, 1)];
+    assert_eq!(validate(&white_five, RuleSet::Freestyle, 2, 0), Ok(()));
+    assert_eq!(validate(&white_five[..9], RuleSet::Freestyle, 2, 0), Err(BoardError::InvalidStoneCount));
+    assert_eq!(validate(&white_five[1..], RuleSet::Freestyle, 1, 0), Err(BoardError::InvalidWinner));
+
+    // an overline wins only under freestyle or for white under Renju
+    let overline = [(7, 2, 1), (7, 3, 1), (7, 4, 1), (7, 5, 1), (7, 6, 1), (7, 7, 1), (0, 0, 2), (0, 2, 2), (0, 4, 2), (0, 6, 2), (0, 8, 2)];
+    assert_eq!(validate(&overline, RuleSet::Freestyle, 1, 0), Ok(()));
+    assert_eq!(validate(&overline, RuleSet::Standard, 1, 0), Err(BoardError::InvalidWinner));
+    assert_eq!(validate(&overline, RuleSet::Standard, 0, 2), Ok(()));
+    assert_eq!(validate(&overline, RuleSet::Renju, 0, 2), Ok(()));
+}
+
+#[test]
+fn test_validate_finished_draw() {
+    // 1 2 1
+    // 1 2 2
+    // 2 1 1
+    let full = [1, 2, 1, 1, 2, 2, 2, 1, 1];
+    assert_eq!(validate_board(&full, 3, 3, RuleSet::Freestyle, 0, 0, 9), Ok(()));
+    assert_eq!(validate_board(&full, 3, 3, RuleSet::Freestyle, 0, 1, 9), Err(BoardError::InvalidStoneCount));
+
+    // a board below the stone limit is not over
+    let partial = [1, 2, 1, 1, 2, 2, 2, 1, 0];
+    assert_eq!(validate_board(&partial, 3, 3, RuleSet::Freestyle, 0, 0, 9), Err(BoardError::InvalidTurn));
+    assert_eq!(validate_board(&partial, 3, 3, RuleSet::Freestyle, 0, 0, 8), Ok(()));
+    assert_eq!(validate_board(&partial, 3, 3, RuleSet::Freestyle, 0, 1, 9), Ok(()));
+
+    // a board with a winning run is not a draw
+    let won = [1, 1, 1, 2, 2, 0, 0, 0, 0];
+    assert_eq!(validate_board(&won, 3, 3, RuleSet::Freestyle, 0, 0, 0), Err(BoardError::InvalidWinner));
+}
+
+#[test]
+fn test_find_move() {
+    let prev = gomoku_board(&[(7, 7, 1)]);
+    assert_eq!(find_move(&prev, &gomoku_board(&[(7, 7, 1), (7, 8, 2)]), 15, 2), Some((7, 8)));
+    // a stone of the other color, no stone or two stones
+    assert_eq!(find_move(&prev, &gomoku_board(&[(7, 7, 1), (7, 8, 1)]), 15, 2), None);
+    assert_eq!(find_move(&prev, &prev, 15, 2), None);
+    assert_eq!(find_move(&prev, &gomoku_board(&[(7, 7, 1), (7, 8, 2), (7, 9, 2)]), 15, 2), None);
+    // a stone replaced or removed
+    assert_eq!(find_move(&prev, &gomoku_board(&[(7, 7, 2)]), 15, 2), None);
+    assert_eq!(find_move(&prev, &gomoku_board(&[(7, 8, 2)]), 15, 2), None);
+    assert_eq!(find_move(&prev, &prev[1..], 15, 2), None);
+}
+
+#[test]
+fn test_replay() {
+    let rules = GameRules {
+        board_size: 15,
+        win_length: 5,
+        rule_set: RuleSet::Freestyle,
+        max_stone_onchain: 10,
+    };
+    let initial = GameBoard {
+        cells: gomoku_board(&[(7, 3, 1), (7, 4, 1), (0, 0, 2), (0, 1, 2)]),
+        winner: 0,
+        turn: 1,
+    };
+
+    let board = replay(&rules, initial.clone(), &[(7, 5), (0, 2), (7, 6), (0, 3)]).unwrap();
+    assert_eq!(board.turn, 1);
+    assert_eq!(board.winner, 0);
+    assert_eq!(board.cells, gomoku_board(&[
+        (7, 3, 1), (7, 4, 1), (7, 5, 1), (7, 6, 1),
+        (0, 0, 2), (0, 1, 2), (0, 2, 2), (0, 3, 2),
+    ]));
+
+    let board = replay(&rules, initial.clone(), &[(7, 5), (0, 2), (7, 6), (0, 3), (7, 7)]).unwrap();
+    assert_eq!(board.winner, 1);
+    assert_eq!(board.turn, 0);
+    assert_eq!(
+        replay(&rules, initial.clone(), &[(7, 5), (0, 2), (7, 6), (0, 3), (7, 7), (0, 4)]),
+        Err(ReplayError::GameOver(5))
+    );
+    assert_eq!(replay(&rules, initial.clone(), &[(7, 5), (7, 5)]), Err(ReplayError::SlotOccupied(1)));
+    assert_eq!(replay(&rules, initial.clone(), &[(15, 0)]), Err(ReplayError::OutOfBoundary(0)));
+    assert_eq!(
+        replay(&rules, GameBoard { cells: vec![0; 9], winner: 0, turn: 1 }, &[]),
+        Err(ReplayError::InvalidBoard)
+    );
+
+    // the game is a draw after max_stone_onchain moves
+    let rules = GameRules { max_stone_onchain: 1, ..rules };
+    let board = replay(&rules, initial.clone(), &[(7, 5), (0, 2)]).unwrap();
+    assert_eq!((board.winner, board.turn), (0, 0));
+    assert_eq!(replay(&rules, initial, &[(7, 5), (0, 2), (7, 6)]), Err(ReplayError::GameOver(2)));
+
+    // forbidden moves of black are rejected under Renju
+    let rules = GameRules { rule_set: RuleSet::Renju, max_stone_onchain: 10, ..rules };
+    let initial = GameBoard {
+        cells: gomoku_board(&[(7, 5, 1), (7, 6, 1), (5, 7, 1), (6, 7, 1), (0, 0, 2), (0, 2, 2), (0, 4, 2), (0, 6, 2)]),
+        winner: 0,
+        turn: 1,
+    };
+    assert_eq!(replay(&rules, initial, &[(7, 7)]), Err(ReplayError::ForbiddenMove(0)));
+}
+
+fn gomoku_app_state(rule_set: RuleSet, board_size: u8, min_stone_offchain: u8, max_stone_onchain: u8) -> GomokuAppState {
+    GomokuApp::initial_state(GomokuAppConfig {
+        rules: GameRules {
+            board_size: board_size,
+            win_length: if board_size < 5 { board_size } else { 5 },
+            rule_set: rule_set,
+            max_stone_onchain: max_stone_onchain,
+        },
+        min_stone_offchain: min_stone_offchain,
+    }).unwrap()
+}
+
+#[test]
+fn test_gomoku_app_rejects_invalid_config() {
+    let config = |board_size: u8, win_length: u8| GomokuAppConfig {
+        rules: GameRules {
+            board_size: board_size,
+            win_length: win_length,
+            rule_set: RuleSet::Freestyle,
+            max_stone_onchain: 5,
+        },
+        min_stone_offchain: 5,
+    };
+    assert_eq!(GomokuApp::initial_state(config(MIN_BOARD_SIZE - 1, 3)), Err(GomokuAppError::InvalidConfig));
+    assert_eq!(GomokuApp::initial_state(config(MAX_BOARD_SIZE + 1, 5)), Err(GomokuAppError::InvalidConfig));
+    assert_eq!(GomokuApp::initial_state(config(200, 5)), Err(GomokuAppError::InvalidConfig));
+    assert_eq!(GomokuApp::initial_state(config(15, MIN_WIN_LENGTH - 1)), Err(GomokuAppError::InvalidConfig));
+    assert_eq!(GomokuApp::initial_state(config(15, 16)), Err(GomokuAppError::InvalidConfig));
+    assert!(GomokuApp::initial_state(config(MAX_BOARD_SIZE, MAX_BOARD_SIZE)).is_ok());
+}
+
+#[test]
+fn test_gomoku_app() {
+    let mut state = gomoku_app_state(RuleSet::Freestyle, 15, 5, 5);
+    assert_eq!(GomokuApp::is_terminal(&state), false);
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (0, 0)), Err(GomokuAppError::NoSettledBoard));
+    assert_eq!(
+        GomokuApp::validate_offchain_state(&state, &vec![0; 226]),
+        Err(GomokuAppError::InvalidBoardStateLength)
+    );
+    let mut board_state = vec![0, 1];
+    board_state.extend(gomoku_board(&[(0, 0, 1)]));
+    assert_eq!(
+        GomokuApp::validate_offchain_state(&state, &board_state),
+        Err(GomokuAppError::InvalidBoard(BoardError::InvalidStoneCount))
+    );
+    let mut board_state = vec![0, 1];
+    board_state.extend(gomoku_board(&[(0, 0, 1), (0, 1, 2)]));
+    assert_eq!(
+        GomokuApp::validate_offchain_state(&state, &board_state),
+        Err(GomokuAppError::NotEnoughOffchainStones)
+    );
+
+    let mut board_state = vec![0, 2];
+    board_state.extend(gomoku_board(&[
+        (0, 0, 1), (0, 1, 1), (0, 2, 1), (0, 3, 1),
+        (6, 9, 2), (6, 10, 2), (6, 11, 2),
+    ]));
+    assert_eq!(GomokuApp::validate_offchain_state(&state, &board_state), Ok(()));
+    GomokuApp::settle_offchain_state(&mut state, board_state);
+    assert_eq!(GomokuApp::is_terminal(&state), false);
+
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (3, 12)), Err(GomokuAppError::NotYourTurn));
+    assert_eq!(GomokuApp::apply_action(&mut state, 1, (3, 12)), Ok(()));
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (3, 12)), Err(GomokuAppError::InvalidMove(ReplayError::SlotOccupied(0))));
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (15, 0)), Err(GomokuAppError::InvalidMove(ReplayError::OutOfBoundary(0))));
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (0, 4)), Ok(()));
+    assert_eq!(state.stone_num_onchain, 2);
+    assert_eq!(GomokuApp::is_terminal(&state), true);
+    assert_eq!(GomokuApp::outcome(&state, 1), Ok(true.encode()));
+    assert_eq!(GomokuApp::outcome(&state, 2), Ok(false.encode()));
+    assert_eq!(GomokuApp::apply_action(&mut state, 1, (5, 5)), Err(GomokuAppError::NotYourTurn));
+}
+
+#[test]
+fn test_gomoku_app_on_3x3_board() {
+    let mut state = gomoku_app_state(RuleSet::Freestyle, 3, 0, 9);
+    let mut board_state = vec![0; 11];
+    board_state[1] = 1; // turn
+    assert_eq!(GomokuApp::validate_offchain_state(&state, &board_state), Ok(()));
+    GomokuApp::settle_offchain_state(&mut state, board_state);
+
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (3, 0)), Err(GomokuAppError::InvalidMove(ReplayError::OutOfBoundary(0))));
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (0, 2)), Ok(()));
+    assert_eq!(GomokuApp::apply_action(&mut state, 1, (0, 0)), Ok(()));
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (1, 1)), Ok(()));
+    assert_eq!(GomokuApp::apply_action(&mut state, 1, (0, 1)), Ok(()));
+    assert_eq!(GomokuApp::is_terminal(&state), false);
+    // anti-diagonal (0, 2), (1, 1), (2, 0)
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (2, 0)), Ok(()));
+    assert_eq!(GomokuApp::is_terminal(&state), true);
+    assert_eq!(GomokuApp::outcome(&state, 1), Ok(true.encode()));
+}
+
+#[test]
+fn test_gomoku_app_draw_and_timeout() {
+    // the game is a draw after max_stone_onchain on-chain moves
+    let mut state = gomoku_app_state(RuleSet::Freestyle, 15, 0, 1);
+    let mut board_state = vec![0, 1];
+    board_state.extend(gomoku_board(&[]));
+    GomokuApp::settle_offchain_state(&mut state, board_state.clone());
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (7, 7)), Ok(()));
+    assert_eq!(GomokuApp::is_terminal(&state), false);
+    assert_eq!(GomokuApp::apply_action(&mut state, 1, (7, 8)), Ok(()));
+    assert_eq!(GomokuApp::is_terminal(&state), true);
+    assert_eq!(GomokuApp::outcome(&state, 0), Ok(true.encode()));
+
+    // the player to move loses on timeout
+    GomokuApp::settle_offchain_state(&mut state, board_state);
+    GomokuApp::on_action_timeout(&mut state);
+    assert_eq!(GomokuApp::is_terminal(&state), true);
+    assert_eq!(GomokuApp::outcome(&state, 2), Ok(true.encode()));
+}
+
+#[test]
+fn test_gomoku_app_rejects_forbidden_move() {
+    let mut state = gomoku_app_state(RuleSet::Renju, 15, 0, 5);
+    let mut board_state = vec![0, 1];
+    board_state.extend(gomoku_board(&[
+        (7, 5, 1), (7, 6, 1), (5, 7, 1), (6, 7, 1),
+        (0, 0, 2), (0, 2, 2), (0, 4, 2), (0, 6, 2),
+    ]));
+    assert_eq!(GomokuApp::validate_offchain_state(&state, &board_state), Ok(()));
+    GomokuApp::settle_offchain_state(&mut state, board_state);
+
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (7, 7)), Err(GomokuAppError::InvalidMove(ReplayError::ForbiddenMove(0))));
+    assert_eq!(GomokuApp::apply_action(&mut state, 0, (7, 8)), Ok(()));
+}
+
+#[test]
+fn test_single_session_logic() {
+    let mut state = SingleSessionLogic::initial_state(()).unwrap();
+    assert_eq!(state, 0);
+    assert_eq!(SingleSessionLogic::is_terminal(&state), false);
+
+    assert_eq!(SingleSessionLogic::validate_offchain_state(&state, &5), Ok(()));
+    SingleSessionLogic::settle_offchain_state(&mut state, 5);
+    assert_eq!(SingleSessionLogic::is_terminal(&state), false);
+    assert_eq!(SingleSessionLogic::outcome(&state, 5), Ok(true.encode()));
+
+    assert_eq!(SingleSessionLogic::apply_action(&mut state, 0, 2), Ok(()));
+    assert_eq!(SingleSessionLogic::is_terminal(&state), true);
+    assert_eq!(SingleSessionLogic::outcome(&state, 5), Ok(false.encode()));
+    assert_eq!(SingleSessionLogic::outcome(&state, 2), Ok(true.encode()));
+}
+
+#[test]
+fn test_numeric_session_logic() {
+    let mut info = NumericSessionLogic::initial_state(1_000).unwrap();
+    assert_eq!(info.state.amount, 0);
+    assert_eq!(NumericSessionLogic::is_terminal(&info), false);
+
+    let offchain_state = NumericSessionState {
+        amount: 1_001,
+        finished: false,
+    };
+    assert_eq!(
+        NumericSessionLogic::validate_offchain_state(&info, &offchain_state),
+        Err(NumericSessionError::AmountExceedsMax)
+    );
+
+    let offchain_state = NumericSessionState {
+        amount: 300,
+        finished: false,
+    };
+    assert_eq!(NumericSessionLogic::validate_offchain_state(&info, &offchain_state), Ok(()));
+    NumericSessionLogic::settle_offchain_state(&mut info, offchain_state);
+    assert_eq!(NumericSessionLogic::is_terminal(&info), false);
+
+    assert_eq!(NumericSessionLogic::apply_action(&mut info, 1, false), Err(NumericSessionError::InvalidAction));
+    assert_eq!(NumericSessionLogic::apply_action(&mut info, 1, true), Ok(()));
+    assert_eq!(NumericSessionLogic::is_terminal(&info), true);
+    assert_eq!(NumericSessionLogic::outcome(&info, NumericOutcomeType::U32), Ok(300u32.encode()));
+    assert_eq!(NumericSessionLogic::outcome(&info, NumericOutcomeType::U128), Ok(300u128.encode()));
+
+    // u32 outcome rejects an amount above u32::max_value()
+    let mut info = NumericSessionLogic::initial_state(u128::max_value()).unwrap();
+    NumericSessionLogic::settle_offchain_state(&mut info, NumericSessionState {
+        amount: u64::max_value() as u128,
+        finished: true,
+    });
+    assert_eq!(NumericSessionLogic::is_terminal(&info), true);
+    assert_eq!(NumericSessionLogic::outcome(&info, NumericOutcomeType::U32), Err(NumericSessionError::AmountExceedsU32));
+    assert_eq!(NumericSessionLogic::outcome(&info, NumericOutcomeType::U128), Ok((u64::max_value() as u128).encode()));
+    NumericSessionLogic::settle_offchain_state(&mut info, NumericSessionState {
+        amount: u32::max_value() as u128,
+        finished: true,
+    });
+    assert_eq!(NumericSessionLogic::outcome(&info, NumericOutcomeType::U32), Ok(u32::max_value().encode()));
+}
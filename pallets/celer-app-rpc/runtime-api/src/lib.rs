@@ -0,0 +1,40 @@
+//! Runtime API definition of the Celer app runtime modules.
+//!
+//! A runtime serves every app runtime module it contains through this API by
+//! dispatching on the `ModuleId` of the app to the module's `CelerAppQuery`
+//! implementation.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_runtime::{DispatchError, ModuleId};
+use sp_std::vec::Vec;
+pub use celer_app_core::{AppStatus, CelerAppQuery};
+
+sp_api::decl_runtime_apis! {
+    pub trait CelerAppApi<Hash, BlockNumber> where
+        Hash: Codec,
+        BlockNumber: Codec,
+    {
+        /// Query whether the app is finalized
+        fn is_finalized(app_id: ModuleId, args_query_finalization: Vec<u8>) -> Result<bool, DispatchError>;
+
+        /// Query the encoded app outcome
+        fn get_outcome(app_id: ModuleId, args_query_outcome: Vec<u8>) -> Result<Vec<u8>, DispatchError>;
+
+        /// Get app state
+        fn get_state(app_id: ModuleId, session_id: Hash, key: u8) -> Option<Vec<u8>>;
+
+        /// Get app status
+        fn get_status(app_id: ModuleId, session_id: Hash) -> Option<AppStatus>;
+
+        /// Get state settle finalized time
+        fn get_settle_finalized_time(app_id: ModuleId, session_id: Hash) -> Option<BlockNumber>;
+
+        /// Get action deadline
+        fn get_action_deadline(app_id: ModuleId, session_id: Hash) -> Option<BlockNumber>;
+
+        /// Get app sequence number
+        fn get_seq_num(app_id: ModuleId, session_id: Hash) -> Option<u128>;
+    }
+}
@@ -0,0 +1,269 @@
+//! JSON-RPC interface of the Celer app runtime modules.
+
+use std::sync::Arc;
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::Bytes;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT, DispatchError, ModuleId};
+pub use celer_app_runtime_api::CelerAppApi as CelerAppRuntimeApi;
+use celer_app_runtime_api::AppStatus;
+
+/// `app_id` is the 8 byte `ModuleId` of the app runtime module, e.g. `_single_`
+#[rpc]
+pub trait CelerAppApi<BlockHash, Hash, BlockNumber> {
+    /// Query whether the app is finalized
+    #[rpc(name = "celerApp_isFinalized")]
+    fn is_finalized(
+        &self,
+        app_id: String,
+        args_query_finalization: Bytes,
+        at: Option<BlockHash>,
+    ) -> Result<bool>;
+
+    /// Query the encoded app outcome
+    #[rpc(name = "celerApp_getOutcome")]
+    fn get_outcome(
+        &self,
+        app_id: String,
+        args_query_outcome: Bytes,
+        at: Option<BlockHash>,
+    ) -> Result<Bytes>;
+
+    /// Get app state
+    #[rpc(name = "celerApp_getState")]
+    fn get_state(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        key: u8,
+        at: Option<BlockHash>,
+    ) -> Result<Option<Bytes>>;
+
+    /// Get app status
+    #[rpc(name = "celerApp_getStatus")]
+    fn get_status(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        at: Option<BlockHash>,
+    ) -> Result<Option<AppStatus>>;
+
+    /// Get state settle finalized time
+    #[rpc(name = "celerApp_getSettleFinalizedTime")]
+    fn get_settle_finalized_time(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        at: Option<BlockHash>,
+    ) -> Result<Option<BlockNumber>>;
+
+    /// Get action deadline
+    #[rpc(name = "celerApp_getActionDeadline")]
+    fn get_action_deadline(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        at: Option<BlockHash>,
+    ) -> Result<Option<BlockNumber>>;
+
+    /// Get app sequence number
+    #[rpc(name = "celerApp_getSeqNum")]
+    fn get_seq_num(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        at: Option<BlockHash>,
+    ) -> Result<Option<u128>>;
+}
+
+/// Error type of this RPC api
+pub enum Error {
+    /// The call to runtime failed
+    RuntimeError,
+    /// The app runtime module returned an error
+    DispatchError,
+    /// The app id is not an 8 byte module id
+    InvalidAppId,
+}
+
+impl From<Error> for i64 {
+    fn from(e: Error) -> i64 {
+        match e {
+            Error::RuntimeError => 1,
+            Error::DispatchError => 2,
+            Error::InvalidAppId => 3,
+        }
+    }
+}
+
+/// Implementation of the Celer app RPC api
+pub struct CelerApp<C, B> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> CelerApp<C, B> {
+    /// Create new `CelerApp` with the given reference to the client
+    pub fn new(client: Arc<C>) -> Self {
+        CelerApp { client, _marker: Default::default() }
+    }
+}
+
+impl<C, Block> CelerApp<C, Block>
+where
+    Block: BlockT,
+    C: HeaderBackend<Block>,
+{
+    fn block_id(&self, at: Option<<Block as BlockT>::Hash>) -> BlockId<Block> {
+        // If the block hash is not supplied assume the best block
+        BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash))
+    }
+}
+
+impl<C, Block, Hash, BlockNumber> CelerAppApi<<Block as BlockT>::Hash, Hash, BlockNumber>
+    for CelerApp<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: CelerAppRuntimeApi<Block, Hash, BlockNumber>,
+    Hash: Codec,
+    BlockNumber: Codec,
+{
+    fn is_finalized(
+        &self,
+        app_id: String,
+        args_query_finalization: Bytes,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<bool> {
+        let app_id = parse_app_id(&app_id)?;
+        self.client.runtime_api()
+            .is_finalized(&self.block_id(at), app_id, args_query_finalization.to_vec())
+            .map_err(runtime_error)?
+            .map_err(dispatch_error)
+    }
+
+    fn get_outcome(
+        &self,
+        app_id: String,
+        args_query_outcome: Bytes,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Bytes> {
+        let app_id = parse_app_id(&app_id)?;
+        self.client.runtime_api()
+            .get_outcome(&self.block_id(at), app_id, args_query_outcome.to_vec())
+            .map_err(runtime_error)?
+            .map(Bytes)
+            .map_err(dispatch_error)
+    }
+
+    fn get_state(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        key: u8,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<Bytes>> {
+        let app_id = parse_app_id(&app_id)?;
+        self.client.runtime_api()
+            .get_state(&self.block_id(at), app_id, session_id, key)
+            .map(|state| state.map(Bytes))
+            .map_err(runtime_error)
+    }
+
+    fn get_status(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<AppStatus>> {
+        let app_id = parse_app_id(&app_id)?;
+        self.client.runtime_api()
+            .get_status(&self.block_id(at), app_id, session_id)
+            .map_err(runtime_error)
+    }
+
+    fn get_settle_finalized_time(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<BlockNumber>> {
+        let app_id = parse_app_id(&app_id)?;
+        self.client.runtime_api()
+            .get_settle_finalized_time(&self.block_id(at), app_id, session_id)
+            .map_err(runtime_error)
+    }
+
+    fn get_action_deadline(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<BlockNumber>> {
+        let app_id = parse_app_id(&app_id)?;
+        self.client.runtime_api()
+            .get_action_deadline(&self.block_id(at), app_id, session_id)
+            .map_err(runtime_error)
+    }
+
+    fn get_seq_num(
+        &self,
+        app_id: String,
+        session_id: Hash,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<u128>> {
+        let app_id = parse_app_id(&app_id)?;
+        self.client.runtime_api()
+            .get_seq_num(&self.block_id(at), app_id, session_id)
+            .map_err(runtime_error)
+    }
+}
+
+/// Convert the 8 byte name of an app runtime module into its `ModuleId`
+fn parse_app_id(app_id: &str) -> Result<ModuleId> {
+    let bytes = app_id.as_bytes();
+    if bytes.len() != 8 {
+        return Err(RpcError {
+            code: ErrorCode::ServerError(Error::InvalidAppId.into()),
+            message: "App id must be 8 bytes long.".into(),
+            data: Some(app_id.into()),
+        });
+    }
+    let mut id = [0u8; 8];
+    id.copy_from_slice(bytes);
+
+    Ok(ModuleId(id))
+}
+
+fn runtime_error<E: std::fmt::Debug>(e: E) -> RpcError {
+    RpcError {
+        code: ErrorCode::ServerError(Error::RuntimeError.into()),
+        message: "Unable to query the app.".into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+fn dispatch_error(e: DispatchError) -> RpcError {
+    RpcError {
+        code: ErrorCode::ServerError(Error::DispatchError.into()),
+        message: "App query failed.".into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_app_id() {
+        assert_eq!(parse_app_id("_single_").unwrap(), ModuleId(*b"_single_"));
+        assert_eq!(parse_app_id("s_gomoku").unwrap(), ModuleId(*b"s_gomoku"));
+
+        let err = parse_app_id("_single").unwrap_err();
+        assert_eq!(err.code, ErrorCode::ServerError(3));
+    }
+}
@@ -0,0 +1,807 @@
+use super::*;
+use mock::*;
+use sp_core::{sr25519, Pair, H256};
+use frame_support::{assert_ok, assert_noop, traits::OnInitialize};
+use celer_app_core::{GameRules, GomokuAppConfig, NumericOutcomeType, NumericSessionState, RuleSet};
+
+#[test]
+fn test_pass_initiate() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: (),
+        };
+        assert_ok!(GenericApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+
+        let session_id = GenericApp::get_session_id(&initiate_request);
+        assert_eq!(GenericApp::get_state(session_id).unwrap(), 0);
+        assert_eq!(GenericApp::get_status(session_id).unwrap(), AppStatus::Idle);
+        assert_noop!(
+            GenericApp::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::AppAlreadyExists
+        );
+    })
+}
+
+#[test]
+fn test_fail_initiate_with_unordered_players() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: vec![players[1], players[0]],
+            timeout: 2,
+            config: (),
+        };
+        assert_noop!(
+            GenericApp::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayers
+        );
+    })
+}
+
+#[test]
+fn test_pass_update_by_state_and_action() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: (),
+        };
+        assert_ok!(GenericApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+
+        let session_id = GenericApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(0, 1, 5, 2, session_id, players_pair);
+        assert_ok!(
+            GenericApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        assert_eq!(GenericApp::get_state(session_id).unwrap(), 5);
+        assert_eq!(GenericApp::get_status(session_id).unwrap(), AppStatus::Settle);
+        assert_eq!(GenericApp::get_settle_finalized_time(session_id).unwrap(), 3);
+        assert_eq!(GenericApp::is_finalized(session_id.encode()).unwrap(), false);
+
+        assert_noop!(
+            GenericApp::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                2
+            ),
+            Error::<TestRuntime>::NotInActionMode
+        );
+
+        System::set_block_number(4);
+        let stranger = account_pair("Carl").public();
+        assert_noop!(
+            GenericApp::update_by_action(
+                Origin::signed(stranger),
+                session_id,
+                2
+            ),
+            Error::<TestRuntime>::NotPlayer
+        );
+        assert_ok!(
+            GenericApp::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                2
+            )
+        );
+        assert_eq!(GenericApp::get_state(session_id).unwrap(), 2);
+        assert_eq!(GenericApp::get_seq_num(session_id).unwrap(), 2);
+        assert_eq!(GenericApp::is_finalized(session_id.encode()).unwrap(), true);
+
+        let args_query_outcome = GenericArgsQueryOutcome {
+            session_id: session_id,
+            query_data: 2u8,
+        };
+        assert_eq!(
+            GenericApp::get_outcome(args_query_outcome.encode()).unwrap(),
+            true.encode()
+        );
+    })
+}
+
+#[test]
+fn test_fail_update_by_state_with_invalid_seq_num() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: (),
+        };
+        assert_ok!(GenericApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+
+        let session_id = GenericApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(0, 0, 5, 2, session_id, players_pair.clone());
+        assert_noop!(
+            GenericApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidSeqNum
+        );
+
+        let state_proof = get_state_proof(1, 1, 5, 2, session_id, players_pair);
+        assert_noop!(
+            GenericApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::NonceMismatch
+        );
+    })
+}
+
+#[test]
+fn test_pass_update_by_state_with_terminal_state() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: (),
+        };
+        assert_ok!(GenericApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+
+        let session_id = GenericApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(0, 1, 1, 2, session_id, players_pair.clone());
+        assert_ok!(
+            GenericApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        assert_eq!(GenericApp::is_finalized(session_id.encode()).unwrap(), true);
+
+        let state_proof = get_state_proof(0, 2, 2, 2, session_id, players_pair);
+        assert_noop!(
+            GenericApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::AlreadyFinalized
+        );
+    })
+}
+
+#[test]
+fn test_pass_finalize_on_action_timeout() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: (),
+        };
+        assert_ok!(GenericApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+
+        let session_id = GenericApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(0, 1, 5, 2, session_id, players_pair);
+        assert_ok!(
+            GenericApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+
+        System::set_block_number(5);
+        assert_noop!(
+            GenericApp::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::WhileSettling
+        );
+
+        System::set_block_number(6);
+        assert_ok!(
+            GenericApp::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        assert_eq!(GenericApp::is_finalized(session_id.encode()).unwrap(), true);
+    })
+}
+
+#[test]
+fn test_celer_app_query() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: (),
+        };
+        assert_ok!(GenericApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+
+        let session_id = GenericApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(0, 1, 5, 2, session_id, players_pair);
+        assert_ok!(
+            GenericApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+
+        assert_eq!(<GenericApp as CelerAppQuery<H256, BlockNumber>>::is_finalized(session_id.encode()).unwrap(), false);
+        let args_query_outcome = GenericArgsQueryOutcome {
+            session_id: session_id,
+            query_data: 5u8
+        };
+        assert_eq!(
+            <GenericApp as CelerAppQuery<H256, BlockNumber>>::get_outcome(args_query_outcome.encode()).unwrap(),
+            true.encode()
+        );
+        assert_eq!(<GenericApp as CelerAppQuery<H256, BlockNumber>>::get_state(session_id, 0), Some(vec![5]));
+        assert_eq!(<GenericApp as CelerAppQuery<H256, BlockNumber>>::get_status(session_id), Some(AppStatus::Settle));
+        assert_eq!(<GenericApp as CelerAppQuery<H256, BlockNumber>>::get_settle_finalized_time(session_id), Some(3));
+        assert_eq!(<GenericApp as CelerAppQuery<H256, BlockNumber>>::get_action_deadline(session_id), Some(5));
+        assert_eq!(<GenericApp as CelerAppQuery<H256, BlockNumber>>::get_seq_num(session_id), Some(1));
+        assert_eq!(<GenericApp as CelerAppQuery<H256, BlockNumber>>::get_status(H256::zero()), None);
+    })
+}
+
+#[test]
+fn test_numeric_outcome() {
+    numeric::ExtBuilder::build().execute_with(|| {
+        numeric::System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: 10_000_000_000u128,
+        };
+        assert_ok!(numeric::GenericApp::app_initiate(
+            numeric::Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+
+        let session_id = numeric::GenericApp::get_session_id(&initiate_request);
+        let state = NumericSessionState {
+            amount: 5_000_000_000,
+            finished: false,
+        };
+        let state_proof = sign_state_proof::<numeric::TestRuntime>(0, 1, state, 2, session_id, players_pair.clone());
+        assert_ok!(
+            numeric::GenericApp::update_by_state(
+                numeric::Origin::signed(players[0]),
+                state_proof
+            )
+        );
+
+        numeric::System::set_block_number(4);
+        assert_ok!(
+            numeric::GenericApp::update_by_action(
+                numeric::Origin::signed(players[1]),
+                session_id,
+                true
+            )
+        );
+        assert_eq!(numeric::GenericApp::is_finalized(session_id.encode()).unwrap(), true);
+
+        let args_query_outcome = GenericArgsQueryOutcome {
+            session_id: session_id,
+            query_data: NumericOutcomeType::U128,
+        };
+        assert_eq!(
+            numeric::GenericApp::get_outcome(args_query_outcome.encode()).unwrap(),
+            5_000_000_000u128.encode()
+        );
+        // the amount does not fit in u32
+        let args_query_outcome = GenericArgsQueryOutcome {
+            session_id: session_id,
+            query_data: NumericOutcomeType::U32,
+        };
+        assert_eq!(
+            numeric::GenericApp::get_outcome(args_query_outcome.encode()),
+            Err(Error::<numeric::TestRuntime>::InvalidQuery.into())
+        );
+
+        // finished off-chain state with an amount fitting in u32
+        let initiate_request = AppInitiateRequest {
+            nonce: 1,
+            players: players.clone(),
+            timeout: 2,
+            config: 10_000_000_000u128,
+        };
+        assert_ok!(numeric::GenericApp::app_initiate(
+            numeric::Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = numeric::GenericApp::get_session_id(&initiate_request);
+        let state = NumericSessionState {
+            amount: 300,
+            finished: true,
+        };
+        let state_proof = sign_state_proof::<numeric::TestRuntime>(1, 1, state, 2, session_id, players_pair);
+        assert_ok!(
+            numeric::GenericApp::update_by_state(
+                numeric::Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        assert_eq!(numeric::GenericApp::is_finalized(session_id.encode()).unwrap(), true);
+        let args_query_outcome = GenericArgsQueryOutcome {
+            session_id: session_id,
+            query_data: NumericOutcomeType::U32,
+        };
+        assert_eq!(
+            numeric::GenericApp::get_outcome(args_query_outcome.encode()).unwrap(),
+            300u32.encode()
+        );
+    })
+}
+
+#[test]
+fn test_gomoku_app() {
+    gomoku::ExtBuilder::build().execute_with(|| {
+        gomoku::System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: GomokuAppConfig {
+                rules: GameRules {
+                    board_size: 15,
+                    win_length: 5,
+                    rule_set: RuleSet::Freestyle,
+                    max_stone_onchain: 5,
+                },
+                min_stone_offchain: 5,
+            },
+        };
+        // a board larger than MAX_BOARD_SIZE is rejected
+        let mut oversized_request = initiate_request.clone();
+        oversized_request.config.rules.board_size = 200;
+        assert_noop!(
+            gomoku::GenericApp::app_initiate(
+                gomoku::Origin::signed(players[0]),
+                oversized_request
+            ),
+            Error::<gomoku::TestRuntime>::InvalidConfig
+        );
+        assert_ok!(gomoku::GenericApp::app_initiate(
+            gomoku::Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = gomoku::GenericApp::get_session_id(&initiate_request);
+
+        // (0, 0) ~ (0, 3) of player 1 and (6, 9) ~ (6, 11) of player 2, turn of player 2
+        let mut board_state = vec![0; 227];
+        board_state[1] = 2;
+        for &(x, y, color) in [(0, 0, 1), (0, 1, 1), (0, 2, 1), (0, 3, 1), (6, 9, 2), (6, 10, 2), (6, 11, 2)].iter() {
+            board_state[2 + x * 15 + y] = color;
+        }
+        let mut invalid_board_state = board_state.clone();
+        invalid_board_state[1] = 1;
+        let state_proof = sign_state_proof::<gomoku::TestRuntime>(0, 1, invalid_board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            gomoku::GenericApp::update_by_state(
+                gomoku::Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<gomoku::TestRuntime>::InvalidOffchainState
+        );
+        let state_proof = sign_state_proof::<gomoku::TestRuntime>(0, 1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            gomoku::GenericApp::update_by_state(
+                gomoku::Origin::signed(players[0]),
+                state_proof
+            )
+        );
+
+        gomoku::System::set_block_number(4);
+        assert_noop!(
+            gomoku::GenericApp::update_by_action(
+                gomoku::Origin::signed(players[0]),
+                session_id,
+                (3, 12)
+            ),
+            Error::<gomoku::TestRuntime>::NotYourTurn
+        );
+        assert_ok!(
+            gomoku::GenericApp::update_by_action(
+                gomoku::Origin::signed(players[1]),
+                session_id,
+                (3, 12)
+            )
+        );
+        assert_noop!(
+            gomoku::GenericApp::update_by_action(
+                gomoku::Origin::signed(players[0]),
+                session_id,
+                (3, 12)
+            ),
+            Error::<gomoku::TestRuntime>::InvalidAction
+        );
+        assert_eq!(gomoku::GenericApp::is_finalized(session_id.encode()).unwrap(), false);
+        assert_ok!(
+            gomoku::GenericApp::update_by_action(
+                gomoku::Origin::signed(players[0]),
+                session_id,
+                (0, 4)
+            )
+        );
+        assert_eq!(gomoku::GenericApp::is_finalized(session_id.encode()).unwrap(), true);
+
+        let args_query_outcome = GenericArgsQueryOutcome {
+            session_id: session_id,
+            query_data: 1u8,
+        };
+        assert_eq!(
+            gomoku::GenericApp::get_outcome(args_query_outcome.encode()).unwrap(),
+            true.encode()
+        );
+        let args_query_outcome = GenericArgsQueryOutcome {
+            session_id: session_id,
+            query_data: 2u8,
+        };
+        assert_eq!(
+            gomoku::GenericApp::get_outcome(args_query_outcome.encode()).unwrap(),
+            false.encode()
+        );
+    })
+}
+
+#[test]
+fn test_initiate_reserves_deposit() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = initiate_app(0, players.clone());
+        // base + 2 players + 1 byte of state
+        assert_eq!(GenericApp::deposit_of(session_id), Some((players[0], 15)));
+        assert_eq!(Balances::reserved_balance(players[0]), 15);
+        assert_eq!(Balances::reserved_balance(players[1]), 0);
+        let expected_event = TestEvent::generic_app(RawEvent::AppInitiated(session_id, players.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        // initiator can not pay the deposit
+        let carl = account_pair("Carl").public();
+        let initiate_request = AppInitiateRequest {
+            nonce: 1,
+            players: players.clone(),
+            timeout: 2,
+            config: (),
+        };
+        assert_noop!(
+            GenericApp::app_initiate(
+                Origin::signed(carl),
+                initiate_request
+            ),
+            pallet_balances::Error::<TestRuntime, pallet_balances::DefaultInstance>::InsufficientBalance
+        );
+    })
+}
+
+#[test]
+fn test_finalize_by_agreement() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = initiate_app(0, players.clone());
+
+        // signatures of an ordinary state cannot finalize the app
+        let state_proof = get_state_proof(0, 2, 5, 2, session_id, players_pair.clone());
+        assert_noop!(
+            GenericApp::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidSignature
+        );
+
+        // the final state is finalized in the same block from Idle
+        let state_proof = sign_final_state_proof::<TestRuntime>(0, 2, 5, 2, session_id, players_pair.clone());
+        assert_ok!(GenericApp::finalize_by_agreement(
+            Origin::signed(players[0]),
+            state_proof
+        ));
+        assert_eq!(GenericApp::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(GenericApp::get_state(session_id), Some(5));
+        assert_eq!(GenericApp::get_seq_num(session_id), Some(2));
+        assert_eq!(GenericApp::finalized_at(session_id), Some(1));
+        let expected_event = TestEvent::generic_app(RawEvent::FinalizedByAgreement(session_id, 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        let state_proof = sign_final_state_proof::<TestRuntime>(0, 3, 5, 2, session_id, players_pair);
+        assert_noop!(
+            GenericApp::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::AlreadyFinalized
+        );
+    })
+}
+
+#[test]
+fn test_finalize_expired_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+        // settle period and action period end at block 5
+        assert_eq!(GenericApp::deadline_queue(6), session_ids);
+
+        // at most MaxFinalizePerBlock apps are finalized in a block
+        System::set_block_number(6);
+        GenericApp::on_initialize(6);
+        assert_eq!(GenericApp::get_status(session_ids[0]), Some(AppStatus::Finalized));
+        assert_eq!(GenericApp::get_status(session_ids[1]), Some(AppStatus::Finalized));
+        assert_eq!(GenericApp::get_status(session_ids[2]), Some(AppStatus::Settle));
+        assert_eq!(GenericApp::finalized_at(session_ids[0]), Some(6));
+        assert_eq!(GenericApp::deadline_queue(6), Vec::<H256>::new());
+        assert_eq!(GenericApp::deadline_queue(7), vec![session_ids[2]]);
+        let expected_event = TestEvent::generic_app(RawEvent::FinalizedOnTimeout(session_ids[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        System::set_block_number(7);
+        GenericApp::on_initialize(7);
+        assert_eq!(GenericApp::get_status(session_ids[2]), Some(AppStatus::Finalized));
+        assert_eq!(GenericApp::deadline_queue(7), Vec::<H256>::new());
+    })
+}
+
+#[test]
+fn test_clear_session() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        assert_noop!(
+            GenericApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::NotFinalized
+        );
+
+        System::set_block_number(6);
+        assert_ok!(
+            GenericApp::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        assert_noop!(
+            GenericApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::OutcomeNotConsumed
+        );
+
+        assert_ok!(<GenericApp as ConsumeOutcome<H256>>::consume_outcome(session_id));
+        assert_eq!(GenericApp::clear_queue(16), vec![session_id]);
+
+        System::set_block_number(15);
+        assert_noop!(
+            GenericApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::RetentionPeriodNotPassed
+        );
+
+        // the queued app is cleared in on_initialize
+        System::set_block_number(16);
+        GenericApp::on_initialize(16);
+        assert_eq!(GenericApp::get_status(session_id), None);
+        assert_eq!(GenericApp::finalized_at(session_id), None);
+        assert_eq!(GenericApp::deposit_of(session_id), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        let expected_event = TestEvent::generic_app(RawEvent::SessionCleared(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        // the cleared app can not be initiated again to replay its old states
+        assert_eq!(GenericApp::is_cleared(session_id), true);
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            config: (),
+        };
+        assert_noop!(
+            GenericApp::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::AppAlreadyExists
+        );
+        let state_proof = get_state_proof(0, 1, 5, 2, session_id, players_pair);
+        assert_noop!(
+            GenericApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::AppInfoNotExist
+        );
+    })
+}
+
+fn initiate_app(
+    nonce: u128,
+    players: Vec<AccountId>
+) -> H256 {
+    let initiate_request = AppInitiateRequest {
+        nonce: nonce,
+        players: players.clone(),
+        timeout: 2,
+        config: (),
+    };
+    assert_ok!(GenericApp::app_initiate(
+        Origin::signed(players[0]),
+        initiate_request.clone()
+    ));
+
+    GenericApp::get_session_id(&initiate_request)
+}
+
+fn settle_app(
+    nonce: u128,
+    players: Vec<AccountId>,
+    players_pair: Vec<sr25519::Pair>
+) -> H256 {
+    let session_id = initiate_app(nonce, players.clone());
+    let state_proof = get_state_proof(nonce, 1, 5, 2, session_id, players_pair);
+    assert_ok!(
+        GenericApp::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+
+    session_id
+}
+
+fn get_state_proof(
+    nonce: u128,
+    seq: u128,
+    state: u8,
+    timeout: BlockNumber,
+    session_id: H256,
+    players_pair: Vec<sr25519::Pair>
+) -> StateProof<BlockNumber, H256, u8, Signature> {
+    sign_state_proof::<TestRuntime>(nonce, seq, state, timeout, session_id, players_pair)
+}
+
+fn sign_state_proof<T: Trait<Signature = Signature>>(
+    nonce: u128,
+    seq: u128,
+    state: OffchainStateOf<T>,
+    timeout: T::BlockNumber,
+    session_id: T::Hash,
+    players_pair: Vec<sr25519::Pair>
+) -> StateProofOf<T> {
+    let app_state = AppState {
+        nonce: nonce,
+        seq_num: seq,
+        state: state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = Module::<T>::encode_app_state(app_state.clone());
+    let sig_1 = players_pair[0].sign(&encoded);
+    let sig_2 = players_pair[1].sign(&encoded);
+    let state_proof = StateProof {
+        app_state: app_state,
+        sigs: vec![sig_1, sig_2]
+    };
+
+    return state_proof;
+}
+
+fn sign_final_state_proof<T: Trait<Signature = Signature>>(
+    nonce: u128,
+    seq: u128,
+    state: OffchainStateOf<T>,
+    timeout: T::BlockNumber,
+    session_id: T::Hash,
+    players_pair: Vec<sr25519::Pair>
+) -> StateProofOf<T> {
+    let app_state = AppState {
+        nonce: nonce,
+        seq_num: seq,
+        state: state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = Module::<T>::encode_final_app_state(app_state.clone());
+    let sigs = players_pair.iter().map(|pair| pair.sign(&encoded)).collect();
+
+    StateProof {
+        app_state: app_state,
+        sigs: sigs,
+    }
+}
@@ -0,0 +1,125 @@
+//! Weights for generic_app
+//!
+//! The app logic is a parameter of the runtime module, so these weights are estimates
+//! for light app logic like `SingleSessionLogic`. A runtime plugging in heavier logic
+//! should provide weights benchmarked with its own `App`.
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::weights::{Weight, constants::RocksDbWeight};
+use frame_support::traits::Get;
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for generic_app
+pub trait WeightInfo {
+    fn app_initiate() -> Weight;
+    fn update_by_state() -> Weight;
+    fn finalize_by_agreement() -> Weight;
+    fn update_by_action() -> Weight;
+    fn finalize_on_action_timeout() -> Weight;
+    fn clear_session() -> Weight;
+    fn finalize_expired(n: u32) -> Weight;
+    fn on_initialize(n: u32) -> Weight;
+}
+
+/// Weights for generic_app using the Substrate node and recommended hardware
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Trait> WeightInfo for SubstrateWeight<T> {
+    fn app_initiate() -> Weight {
+        (20_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+    fn update_by_state() -> Weight {
+        (50_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_by_agreement() -> Weight {
+        (52_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn update_by_action() -> Weight {
+        (30_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_on_action_timeout() -> Weight {
+        (25_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn clear_session() -> Weight {
+        (28_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(5 as Weight))
+            .saturating_add(T::DbWeight::get().writes(6 as Weight))
+    }
+    fn finalize_expired(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((25_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+    fn on_initialize(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((21_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((6 as Weight).saturating_mul(n as Weight)))
+    }
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+    fn app_initiate() -> Weight {
+        (20_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(3 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(3 as Weight))
+    }
+    fn update_by_state() -> Weight {
+        (50_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_by_agreement() -> Weight {
+        (52_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn update_by_action() -> Weight {
+        (30_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_on_action_timeout() -> Weight {
+        (25_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn clear_session() -> Weight {
+        (28_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(5 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(6 as Weight))
+    }
+    fn finalize_expired(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((25_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+    fn on_initialize(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((21_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((6 as Weight).saturating_mul(n as Weight)))
+    }
+}
@@ -4,12 +4,12 @@ use super::*;
 use crate as multi_gomoku;
 use frame_support::{
     impl_outer_event, impl_outer_origin, impl_outer_dispatch, 
-    parameter_types, weights::Weight
+    parameter_types, storage::IterableStorageMap, weights::Weight
 };
 use frame_system as system;
-use sp_core::{sr25519, Pair, H256};
+use sp_core::{sr25519, Pair, H256, testing::KeyStore, traits::KeystoreExt};
 use pallet_balances;
-use sp_runtime::testing::Header;
+use sp_runtime::testing::{Header, TestXt};
 use sp_runtime::traits::{BlakeTwo256, IdentityLookup};
 use sp_runtime::Perbill;
 
@@ -46,6 +46,16 @@ parameter_types! {
     pub const MaximumBlockLength: u32 = 2 * 1024;
     pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
     pub const ExistentialDeposit: u64 = 1; // should be greater than zero
+    pub const RetentionPeriod: u64 = 10;
+    pub const MaxClearPerBlock: u32 = 2;
+    pub const MaxFinalizePerBlock: u32 = 2;
+    pub const UnsignedPriority: u64 = 100;
+    pub const MaxMoveLogLength: u32 = 4;
+    pub const SessionDeposit: SessionDepositConfig<u64> = SessionDepositConfig {
+        base: 10,
+        per_player: 2,
+        per_byte: 1,
+    };
 }
 
 impl frame_system::Trait for TestRuntime {
@@ -117,22 +127,47 @@ impl pallet_balances::Trait for TestRuntime {
     type WeightInfo = ();
 }
 
+pub(crate) type Extrinsic = TestXt<Call, ()>;
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for TestRuntime
+where
+    Call: From<LocalCall>,
+{
+    type OverarchingCall = Call;
+    type Extrinsic = Extrinsic;
+}
+
 impl Trait for TestRuntime {
     type Event = TestEvent;
     type Public = sr25519::Public;
     type Signature = sr25519::Signature;
+    type RetentionPeriod = RetentionPeriod;
+    type MaxClearPerBlock = MaxClearPerBlock;
+    type MaxFinalizePerBlock = MaxFinalizePerBlock;
+    type UnsignedPriority = UnsignedPriority;
+    type MaxMoveLogLength = MaxMoveLogLength;
+    type Currency = Balances;
+    type SessionDeposit = SessionDeposit;
+    type WeightInfo = ();
 }
 
 pub type MultiGomoku = Module<TestRuntime>;
 pub type System = frame_system::Module<TestRuntime>;
-type Balances = pallet_balances::Module<TestRuntime>;
+pub type Balances = pallet_balances::Module<TestRuntime>;
 
 pub struct ExtBuilder;
 impl ExtBuilder {
     pub fn build() -> sp_io::TestExternalities {
-        let t = system::GenesisConfig::default()
+        let mut t = system::GenesisConfig::default()
             .build_storage::<TestRuntime>().unwrap();
-        let ext = sp_io::TestExternalities::new(t);
+        pallet_balances::GenesisConfig::<TestRuntime> {
+            balances: vec![
+                (account_pair("Alice").public(), 1000),
+                (account_pair("Bob").public(), 1000),
+            ],
+        }.assimilate_storage(&mut t).unwrap();
+        let mut ext = sp_io::TestExternalities::new(t);
+        ext.register_extension(KeystoreExt(KeyStore::new()));
         ext
     }
 }
@@ -162,4 +197,45 @@ pub(crate) fn get_sorted_peer(
             vec![peer_2, peer_1],
         );
     }
-}
\ No newline at end of file
+}
+
+/// Check the invariants of the gomoku state of every app
+///
+/// The stone count equals the number of stones on the board and the on-chain
+/// stone count equals the number of actions applied since the app was initiated or settled.
+/// The move log holds at most `MaxMoveLogLength` of these actions, and replaying it from its
+/// base board, the settled board advanced past the dropped moves, gives the board of an unfinished game.
+pub(crate) fn check_gomoku_state_invariants() {
+    for (session_id, gomoku_info) in MultiGomokuInfoMap::<TestRuntime>::iter() {
+        let gomoku_state = gomoku_info.gomoku_state;
+        let stones = gomoku_state.board_state.as_ref()
+            .map(|board_state| MultiGomoku::count_stones(board_state))
+            .unwrap_or(0);
+        assert_eq!(gomoku_state.stone_num.unwrap_or(0), stones);
+        let onchain_moves = onchain_moves(session_id);
+        assert_eq!(gomoku_state.stone_num_onchain.unwrap_or(0), onchain_moves);
+
+        let move_log = MultiGomoku::move_log(session_id);
+        assert!(move_log.len() <= MaxMoveLogLength::get() as usize);
+        assert!(move_log.len() <= onchain_moves as usize);
+        if let (Some((base, moves_before)), Some(board_state)) = (MultiGomoku::move_log_base(session_id), gomoku_state.board_state) {
+            assert_eq!(moves_before as usize + move_log.len(), onchain_moves as usize);
+            if gomoku_info.status != AppStatus::Finalized {
+                let rules = MultiGomoku::get_game_rules(session_id).unwrap();
+                let rules = GameRules { max_stone_onchain: rules.max_stone_onchain.saturating_sub(moves_before as u8), ..rules };
+                assert_eq!(MultiGomoku::replay(&rules, base, &move_log), Ok(board_state));
+            }
+        }
+    }
+}
+
+/// Number of actions applied to the app since it was last initiated or settled
+fn onchain_moves(session_id: H256) -> u16 {
+    System::events().iter().fold(0, |moves, record| match &record.event {
+        TestEvent::multi_gomoku(RawEvent::AppInitiated(id, _, _))
+        | TestEvent::multi_gomoku(RawEvent::IntendSettle(id, _))
+        | TestEvent::multi_gomoku(RawEvent::FinalizedByAgreement(id, _)) if *id == session_id => 0,
+        TestEvent::multi_gomoku(RawEvent::ActionApplied(id, _, _, _)) if *id == session_id => moves + 1,
+        _ => moves,
+    })
+}
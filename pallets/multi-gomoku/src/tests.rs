@@ -1,11 +1,17 @@
 use super::*;
 use mock::*;
 use sp_core::{sr25519, Pair, H256};
-use frame_support::{assert_ok, assert_noop};
+use frame_support::{assert_ok, assert_noop, traits::OnInitialize};
+use frame_support::unsigned::ValidateUnsigned;
+use sp_core::offchain::{
+    testing::{TestOffchainExt, TestTransactionPoolExt}, OffchainExt, TransactionPoolExt,
+};
+use sp_runtime::traits::OffchainWorker;
 
 #[test]
 fn test_pass_initiate() {
     ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
         let alice_pair = account_pair("Alice");
         let bob_pair = account_pair("Bob");        
         let (players, _) 
@@ -18,12 +24,22 @@ fn test_pass_initiate() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(MultiGomoku::app_initiate(
             Origin::signed(players[0]),
-            initiate_request)
+            initiate_request.clone())
         );
+        check_gomoku_state_invariants();
+
+        let session_id = MultiGomoku::get_session_id(&initiate_request);
+        let expected_event = TestEvent::multi_gomoku(RawEvent::AppInitiated(session_id, players.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
     })
 }
 
@@ -54,6 +70,7 @@ fn test_pass_player1_submits_state_proof() {
         board_state[7] = white;
         board_state[8] = white;
         board_state[9] = black;
+        board_state[10] = black;
 
         let state_proof = get_state_proof(3, board_state, 2, session_id, players_pair);
         assert_ok!(
@@ -62,6 +79,7 @@ fn test_pass_player1_submits_state_proof() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
         let onchain_state = MultiGomoku::get_state(session_id, 2).unwrap();
         assert_eq!(onchain_state[0], none);
         assert_eq!(onchain_state[1], black);
@@ -72,6 +90,7 @@ fn test_pass_player1_submits_state_proof() {
         assert_eq!(onchain_state[7], white);
         assert_eq!(onchain_state[8], white);
         assert_eq!(onchain_state[9], black);
+        assert_eq!(onchain_state[10], black);
     })
 }
 
@@ -95,8 +114,9 @@ fn test_fail_update_by_state_with_invalid_seq() {
                 Origin::signed(players[0]),
                 state_proof
             ),
-            "invalid sequence number"
+            Error::<TestRuntime>::InvalidSeqNum
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -135,6 +155,7 @@ fn test_pass_intend_settle_with_higher_seq() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
         let onchain_state = MultiGomoku::get_state(session_id, 2).unwrap();
         assert_eq!(onchain_state[0], none);
         assert_eq!(onchain_state[1], white);
@@ -170,8 +191,9 @@ fn test_fail_player1_places_stone_at_3_12_before_settle_finalized_time() {
                 session_id,
                 vec![3, 12]
             ),
-            "app not in action mode"
+            Error::<TestRuntime>::NotInActionMode
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -198,6 +220,7 @@ fn test_pass_player1_places_stone_at_3_12_after_settle_finalized_time() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         let turn = MultiGomoku::get_state(session_id, 0).unwrap();
         assert_eq!(turn, vec![1]);
@@ -227,6 +250,7 @@ fn test_fail_player1_tries_to_place_another_stone() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         assert_noop!(
             MultiGomoku::update_by_action(
@@ -234,8 +258,9 @@ fn test_fail_player1_tries_to_place_another_stone() {
                 session_id,
                 vec![4, 12]
             ),
-            "Not your turn"
+            Error::<TestRuntime>::NotYourTurn
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -262,6 +287,7 @@ fn test_fail_player2_tries_to_place_stone_at_occupied_slot_3_12() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         assert_noop!(
             MultiGomoku::update_by_action(
@@ -269,8 +295,9 @@ fn test_fail_player2_tries_to_place_stone_at_occupied_slot_3_12() {
                 session_id,
                 vec![3, 12]
             ),
-            "slot is occupied"
+            Error::<TestRuntime>::SlotOccupied
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -297,6 +324,7 @@ fn test_player2_places_stone_at_0_4_and_wins() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         assert_ok!(
             MultiGomoku::update_by_action(
@@ -305,8 +333,20 @@ fn test_player2_places_stone_at_0_4_and_wins() {
                 vec![0, 4]
             )
         ); 
+        check_gomoku_state_invariants();
         let turn = MultiGomoku::get_state(session_id, 0).unwrap();
         assert_eq!(turn, vec![0]);
+
+        let expected_events = vec![
+            TestEvent::multi_gomoku(RawEvent::ActionApplied(session_id, players[0], 5, vec![3, 12])),
+            TestEvent::multi_gomoku(RawEvent::StonePlaced(session_id, 3, 12, 2)),
+            TestEvent::multi_gomoku(RawEvent::ActionApplied(session_id, players[1], 6, vec![0, 4])),
+            TestEvent::multi_gomoku(RawEvent::StonePlaced(session_id, 0, 4, 1)),
+            TestEvent::multi_gomoku(RawEvent::GameWon(session_id, players[1])),
+            TestEvent::multi_gomoku(RawEvent::Finalized(session_id)),
+        ];
+        let events: Vec<TestEvent> = System::events().into_iter().map(|a| a.event).collect();
+        assert_eq!(events, expected_events);
        
         assert_eq!(
             MultiGomoku::is_finalized(session_id.encode()).unwrap(), 
@@ -315,12 +355,89 @@ fn test_player2_places_stone_at_0_4_and_wins() {
         
         let args_query_outcome = MultiGomokuArgsQueryOutcome {
             session_id: session_id,
-            query_data: 1
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::IsWinner(1)
         };
         assert_eq!(
             MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(), 
             true.encode()    
         );
+
+        // black is the larger address
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome
+        };
+        assert_eq!(
+            MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Winner(1).encode()
+        );
+        let payout = |player_index: u8| MultiGomoku::get_outcome(
+            MultiGomokuArgsQueryOutcome {
+                session_id: session_id,
+                version: GAME_QUERY_VERSION,
+                query_data: GameQuery::Payout { player_index: player_index, total: 100 }
+            }.encode()
+        );
+        assert_eq!(payout(0).unwrap(), 0u128.encode());
+        assert_eq!(payout(1).unwrap(), 100u128.encode());
+        assert_noop!(payout(2), Error::<TestRuntime>::InvalidPlayerIndex);
+    })
+}
+
+#[test]
+fn test_draw_when_onchain_stones_exceed_max_stone_onchain() {
+    ExtBuilder::build().execute_with(|| {
+        let nonce = 2;
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");        
+        let (players, players_pair) 
+            = get_sorted_peer(alice_pair, bob_pair);
+        
+        let session_id = app_initiate(nonce, players.clone(), 2, 2, 5, 0);
+        
+        place_stone_and_update_by_state_two_times(session_id, players.clone(), players_pair);
+
+        let settle_finalized_time = MultiGomoku::get_settle_finalized_time(session_id).unwrap();
+        System::set_block_number(settle_finalized_time + 1);
+
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![3, 12]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(
+            MultiGomoku::is_finalized(session_id.encode()).unwrap(), 
+            true,    
+        );
+        assert_eq!(MultiGomoku::finalized_at(session_id), Some(settle_finalized_time + 1));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::Draw(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome
+        };
+        assert_eq!(
+            MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Draw.encode()
+        );
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Payout { player_index: 1, total: 101 }
+        };
+        assert_eq!(
+            MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            50u128.encode()
+        );
     })
 }
 
@@ -347,8 +464,9 @@ fn test_fail_not_player_places_stone() {
                 session_id,
                 vec![3, 12]
             ),
-            "Not your turn"
+            Error::<TestRuntime>::NotYourTurn
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -375,14 +493,16 @@ fn test_fail_finalize_on_action_timeout_before_action_deadline() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         assert_noop!(
             MultiGomoku::finalize_on_action_timeout(
                 Origin::signed(players[0]),
                 session_id
             ),
-            "deadline does not passes"
+            Error::<TestRuntime>::DeadlineNotPassed
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -421,6 +541,7 @@ fn test_pass_finalize_on_action_timeout_after_action_deadline() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
 
         let settle_finalized_time = MultiGomoku::get_settle_finalized_time(session_id).unwrap();
         System::set_block_number(settle_finalized_time + 1);
@@ -432,6 +553,7 @@ fn test_pass_finalize_on_action_timeout_after_action_deadline() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         let deadline = MultiGomoku::get_action_deadline(session_id).unwrap();
         System::set_block_number(deadline + 1);
@@ -442,15 +564,23 @@ fn test_pass_finalize_on_action_timeout_after_action_deadline() {
                 session_id
             )
         );
+        check_gomoku_state_invariants();
 
         assert_eq!(
             MultiGomoku::is_finalized(session_id.encode()).unwrap(), 
             true,    
         );
+        let expected_event = TestEvent::multi_gomoku(RawEvent::GameWon(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::FinalizedOnTimeout(session_id, players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
         
         let args_query_outcome = MultiGomokuArgsQueryOutcome {
             session_id: session_id,
-            query_data: 2
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::IsWinner(2)
         };
         assert_eq!(
             MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(), 
@@ -459,115 +589,1810 @@ fn test_pass_finalize_on_action_timeout_after_action_deadline() {
     })
 }
 
-fn app_initiate(
-    nonce: u128,
-    players: Vec<AccountId>,
-    player_num: u8,
-    timeout: BlockNumber,
-    min_stone_offchain: u8,
-    max_stone_onchain: u8
-) -> H256 {
-    let initiate_request = AppInitiateRequest {
-        nonce: nonce,
-        players: players.clone(),
-        player_num: player_num,
-        timeout: timeout,
-        min_stone_offchain: min_stone_offchain,
-        max_stone_onchain: max_stone_onchain,
-    };
+#[test]
+fn test_fail_initiate() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
 
-    assert_ok!(MultiGomoku::app_initiate(
-        Origin::signed(players[0]),
-        initiate_request.clone())
-    );
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 3,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            MultiGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayerNum
+        );
+        check_gomoku_state_invariants();
 
-    let session_id = MultiGomoku::get_session_id(initiate_request.nonce, initiate_request.players);
-    return session_id;
-}
+        // a game of three players is rejected
+        let mut three_players = vec![players[0], players[1], account_pair("Carl").public()];
+        three_players.sort();
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: three_players,
+            player_num: 3,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            MultiGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayerNum
+        );
+        check_gomoku_state_invariants();
 
-fn get_state_proof(
-    seq: u128,
-    board_state: Vec<u8>,
-    timeout: BlockNumber,
-    session_id: H256,
-    players_pair: Vec<sr25519::Pair>,
-) -> StateProof<BlockNumber, H256, Signature> {
-    let app_state = AppState {
-        seq_num: seq,
-        board_state: board_state,
-        timeout: timeout,
-        session_id: session_id,
-    };
-    let encoded = MultiGomoku::encode_app_state(app_state.clone());
-    let sig_1 = players_pair[0].sign(&encoded);
-    let sig_2 = players_pair[1].sign(&encoded);
-    let state_proof = StateProof {
-        app_state:  app_state,
-        sigs: vec![sig_1, sig_2]
-    };
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: vec![players[1], players[0]],
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            MultiGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayers
+        );
+        check_gomoku_state_invariants();
 
-    return state_proof;
+        app_initiate(0, players.clone(), 2, 2, 5, 5);
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            MultiGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::AppAlreadyExists
+        );
+        check_gomoku_state_invariants();
+    })
 }
 
-fn place_stone_and_update_by_state(
-    session_id: H256, 
-    players: Vec<AccountId>, 
-    players_pair: Vec<sr25519::Pair>
-) {
-    let none: u8 = 0;
-    let black: u8 = 1;
-    let white: u8 = 2;
-    let black_player_id1 = 2;
+#[test]
+fn test_pass_initiate_after_front_run_with_other_parameters() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
 
-    let mut board_state = vec![0; 228];
-    board_state[0] = none; 
-    board_state[1] = black; // turn color
-    board_state[2] = black_player_id1;
-    board_state[3] = white;
-    board_state[4] = white;
-    board_state[5] = black;
-    board_state[6] = black;
-    board_state[7] = white;
-    board_state[8] = white;
-    board_state[9] = black;
+        // the opponent initiates first with the same nonce and players but no timeout and no on-chain stones
+        let front_run_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 0,
+            min_stone_offchain: 5,
+            max_stone_onchain: 0,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(MultiGomoku::app_initiate(
+            Origin::signed(players[1]),
+            front_run_request.clone()
+        ));
+        check_gomoku_state_invariants();
 
-    let state_proof = get_state_proof(3, board_state, 2, session_id, players_pair);
-    assert_ok!(
-        MultiGomoku::update_by_state(
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(MultiGomoku::app_initiate(
             Origin::signed(players[0]),
-            state_proof
-        )
-    );
+            initiate_request.clone()
+        ));
+        check_gomoku_state_invariants();
+        let session_id = MultiGomoku::get_session_id(&initiate_request);
+        assert_ne!(session_id, MultiGomoku::get_session_id(&front_run_request));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::AppInitiated(session_id, players.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
 }
 
-fn place_stone_and_update_by_state_two_times(
-    session_id: H256,
-    players: Vec<AccountId>,
-    players_pair: Vec<sr25519::Pair>
-) {
-    let none: u8 = 0;
-    let black: u8 = 1;
-    let white: u8 = 2;
-    let black_player_id1 = 2;
+#[test]
+fn test_fail_update_by_state_with_invalid_state_proof() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
 
-    place_stone_and_update_by_state(session_id, players.clone(), players_pair.clone());
+        let session_id = app_initiate(0, players.clone(), 2, 2, 5, 5);
+        let state_proof = get_state_proof(1, vec![0; 10], 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidBoardStateLength
+        );
+        check_gomoku_state_invariants();
 
-    let mut board_state = vec![0; 228];
-    board_state[0] = none; // winner
-    board_state[1] = white; // turn color
-    board_state[2] = black_player_id1;
-    board_state[3] = black; // (0, 0)
-    board_state[4] = black; // (0, 1)
-    board_state[5] = black; // (0, 2)
-    board_state[6] = black; // (0, 3)
-    board_state[101] = white;
-    board_state[102] = white;
-    board_state[103] = white;
-    let state_proof = get_state_proof(4, board_state, 2, session_id, players_pair);
-    assert_ok!(
-        MultiGomoku::update_by_state(
-            Origin::signed(players[0]),
-            state_proof
-        )
-    );
-}
\ No newline at end of file
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1;
+        board_state[2] = 2;
+        for i in 3..9 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let mut state_proof = get_state_proof(1, board_state, 2, session_id, players_pair);
+        state_proof.sigs.pop();
+        assert_noop!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidSignatureCount
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_fail_update_by_state_signed_for_other_chain() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = app_initiate(0, players.clone(), 2, 2, 5, 5);
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1;
+        board_state[2] = 2;
+        let app_state = AppState {
+            seq_num: 1,
+            board_state: board_state,
+            timeout: 2,
+            session_id: session_id,
+        };
+        assert_eq!(
+            MultiGomoku::signing_payload(app_state.clone(), System::block_hash(0)),
+            MultiGomoku::encode_app_state(app_state.clone())
+        );
+
+        let encoded = MultiGomoku::signing_payload(app_state.clone(), H256::repeat_byte(1));
+        let state_proof = StateProof {
+            app_state: app_state,
+            sigs: vec![players_pair[0].sign(&encoded), players_pair[1].sign(&encoded)],
+        };
+        assert_noop!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidSignature
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_fail_update_by_action_with_invalid_action() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // black player is players[1]
+        let session_id = settle_app(0, players.clone(), players_pair);
+        System::set_block_number(4);
+        assert_noop!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![7]
+            ),
+            Error::<TestRuntime>::InvalidActionLength
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![15, 0]
+            ),
+            Error::<TestRuntime>::OutOfBoundary
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_fail_update_by_state_with_invalid_turn_color() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        let mut board_state = vec![0; 228];
+        board_state[1] = 3; // turn color of no player
+        board_state[2] = 1; // black player id
+        for i in 3..9 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(2, board_state, 2, session_id, players_pair);
+        assert_noop!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidTurn
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_fail_update_by_state_with_inconsistent_board() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+
+        // black player id of no player
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1; // turn color
+        board_state[2] = 0; // black player id
+        let state_proof = get_state_proof(2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidBlackId
+        );
+        check_gomoku_state_invariants();
+
+        // cell of no color
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1; // turn color
+        board_state[2] = 1; // black player id
+        board_state[3] = 3; // (0, 0)
+        board_state[4] = 2; // (0, 1)
+        let state_proof = get_state_proof(2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidBoardCell
+        );
+        check_gomoku_state_invariants();
+
+        // two white stones without a black one
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1; // turn color
+        board_state[2] = 1; // black player id
+        board_state[3] = 2; // (0, 0)
+        board_state[4] = 2; // (0, 1)
+        let state_proof = get_state_proof(2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidStoneCount
+        );
+        check_gomoku_state_invariants();
+
+        // white wins although black made the last move
+        let mut board_state = vec![0; 228];
+        board_state[0] = 2; // winner color
+        board_state[2] = 1; // black player id
+        for y in 0..6 {
+            board_state[3 + 30 + 2 * y] = 1; // (2, 2y)
+        }
+        for y in 0..5 {
+            board_state[3 + y] = 2; // (0, y)
+        }
+        let state_proof = get_state_proof(2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidWinnerState
+        );
+        check_gomoku_state_invariants();
+
+        // game over without a winner
+        let mut board_state = vec![0; 228];
+        board_state[1] = 0; // turn color
+        board_state[2] = 1; // black player id
+        let state_proof = get_state_proof(2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidTurn
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_black_wins_with_a_run_longer_than_five() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1; // turn color
+        board_state[2] = 1; // black player id
+        for y in &[0, 1, 2, 4, 5, 6] {
+            board_state[3 + y] = 1; // (0, y)
+            board_state[3 + 30 + 2 * y] = 2; // (2, 2y)
+        }
+        let state_proof = get_state_proof(2, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        System::set_block_number(4);
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![0, 3]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_state(session_id, 1).unwrap(), vec![1]);
+        assert_eq!(MultiGomoku::is_finalized(session_id.encode()).unwrap(), true);
+    })
+}
+
+#[test]
+fn test_celer_app_query() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = app_initiate(1, players.clone(), 2, 2, 5, 5);
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1;
+        board_state[2] = 2;
+        for i in 3..9 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+
+        assert_eq!(<MultiGomoku as CelerAppQuery<H256, BlockNumber>>::is_finalized(session_id.encode()).unwrap(), false);
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::IsWinner(0)
+        };
+        assert_eq!(
+            <MultiGomoku as CelerAppQuery<H256, BlockNumber>>::get_outcome(args_query_outcome.encode()).unwrap(),
+            true.encode()
+        );
+        assert_eq!(<MultiGomoku as CelerAppQuery<H256, BlockNumber>>::get_state(session_id, 0), Some(vec![1]));
+        assert_eq!(<MultiGomoku as CelerAppQuery<H256, BlockNumber>>::get_status(session_id), Some(AppStatus::Settle));
+        assert_eq!(<MultiGomoku as CelerAppQuery<H256, BlockNumber>>::get_settle_finalized_time(session_id), Some(3));
+        assert_eq!(<MultiGomoku as CelerAppQuery<H256, BlockNumber>>::get_action_deadline(session_id), Some(5));
+        assert_eq!(<MultiGomoku as CelerAppQuery<H256, BlockNumber>>::get_seq_num(session_id), Some(1));
+        assert_eq!(<MultiGomoku as CelerAppQuery<H256, BlockNumber>>::get_status(H256::zero()), None);
+    })
+}
+
+#[test]
+fn test_initiate_reserves_deposit() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair);
+        // base + 2 players + BOARD_STATE_LEN bytes of board
+        assert_eq!(MultiGomoku::deposit_of(session_id), Some((players[0], 242)));
+        assert_eq!(Balances::reserved_balance(players[0]), 242);
+        assert_eq!(Balances::free_balance(players[0]), 758);
+        assert_eq!(Balances::reserved_balance(players[1]), 0);
+
+        // initiator can not pay the deposit
+        let carl = account_pair("Carl").public();
+        let initiate_request = AppInitiateRequest {
+            nonce: 1,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            MultiGomoku::app_initiate(
+                Origin::signed(carl),
+                initiate_request
+            ),
+            pallet_balances::Error::<TestRuntime, pallet_balances::DefaultInstance>::InsufficientBalance
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_clear_session() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        assert_noop!(
+            MultiGomoku::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::NotFinalized
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            <MultiGomoku as ConsumeOutcome<H256>>::consume_outcome(session_id),
+            Error::<TestRuntime>::NotFinalized
+        );
+
+        System::set_block_number(6);
+        assert_ok!(
+            MultiGomoku::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::finalized_at(session_id), Some(6));
+        assert_noop!(
+            MultiGomoku::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::OutcomeNotConsumed
+        );
+        check_gomoku_state_invariants();
+
+        assert_ok!(<MultiGomoku as ConsumeOutcome<H256>>::consume_outcome(session_id));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::OutcomeConsumed(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_eq!(MultiGomoku::clear_queue(16), vec![session_id]);
+
+        System::set_block_number(15);
+        let stranger = account_pair("Carl").public();
+        assert_noop!(
+            MultiGomoku::clear_session(
+                Origin::signed(stranger),
+                session_id
+            ),
+            Error::<TestRuntime>::NotPlayer
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            MultiGomoku::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::RetentionPeriodNotPassed
+        );
+        check_gomoku_state_invariants();
+
+        System::set_block_number(16);
+        assert_ok!(
+            MultiGomoku::clear_session(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_id), None);
+        assert_eq!(MultiGomoku::finalized_at(session_id), None);
+        assert_eq!(MultiGomoku::outcome_consumed(session_id), false);
+        assert_eq!(MultiGomoku::deposit_of(session_id), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        let expected_event = TestEvent::multi_gomoku(RawEvent::SessionCleared(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_noop!(
+            MultiGomoku::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::MultiGomokuInfoNotExist
+        );
+        check_gomoku_state_invariants();
+
+        // queued id of the cleared app is skipped
+        MultiGomoku::on_initialize(16);
+        check_gomoku_state_invariants();
+        assert_eq!(
+            System::events().iter().filter(|a| a.event == expected_event).count(),
+            1
+        );
+
+        // the cleared app can not be initiated again to replay its old states
+        assert_eq!(MultiGomoku::is_cleared(session_id), true);
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_eq!(MultiGomoku::get_session_id(&initiate_request), session_id);
+        assert_noop!(
+            MultiGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::AppAlreadyExists
+        );
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1;
+        board_state[2] = 2;
+        for i in 3..9 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(1, board_state, 2, session_id, players_pair);
+        assert_noop!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::MultiGomokuInfoNotExist
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_clear_sessions_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+
+        System::set_block_number(6);
+        for session_id in session_ids.iter() {
+            assert_ok!(
+                MultiGomoku::finalize_on_action_timeout(
+                    Origin::signed(players[0]),
+                    *session_id
+                )
+            );
+            check_gomoku_state_invariants();
+            assert_ok!(<MultiGomoku as ConsumeOutcome<H256>>::consume_outcome(*session_id));
+        }
+        assert_eq!(MultiGomoku::clear_queue(16), session_ids);
+
+        // at most MaxClearPerBlock apps are cleared in a block
+        MultiGomoku::on_initialize(16);
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_ids[0]), None);
+        assert_eq!(MultiGomoku::get_status(session_ids[1]), None);
+        assert!(MultiGomoku::get_status(session_ids[2]).is_some());
+        assert_eq!(MultiGomoku::clear_queue(16), Vec::<H256>::new());
+        assert_eq!(MultiGomoku::clear_queue(17), vec![session_ids[2]]);
+
+        assert_eq!(Balances::reserved_balance(players[0]), 242);
+
+        MultiGomoku::on_initialize(17);
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_ids[2]), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        assert_eq!(MultiGomoku::clear_queue(17), Vec::<H256>::new());
+    })
+}
+
+#[test]
+fn test_finalize_expired_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+        // settle period and action period end at block 5
+        assert_eq!(MultiGomoku::deadline_queue(6), session_ids);
+
+        // the action moves the deadline of the first app to block 6
+        System::set_block_number(4);
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_ids[0],
+                vec![5, 5]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::deadline_queue(7), vec![session_ids[0]]);
+
+        // at most MaxFinalizePerBlock apps are finalized in a block,
+        // the first app is skipped since its deadline moved
+        System::set_block_number(6);
+        MultiGomoku::on_initialize(6);
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_ids[0]), Some(AppStatus::Action));
+        assert_eq!(MultiGomoku::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(MultiGomoku::get_status(session_ids[2]), Some(AppStatus::Settle));
+        assert_eq!(MultiGomoku::deadline_queue(6), Vec::<H256>::new());
+        assert_eq!(MultiGomoku::deadline_queue(7), vec![session_ids[0], session_ids[2]]);
+        let expected_event = TestEvent::multi_gomoku(RawEvent::FinalizedOnTimeout(session_ids[1], players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        System::set_block_number(7);
+        MultiGomoku::on_initialize(7);
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(MultiGomoku::is_finalized(session_ids[2].encode()).unwrap(), true);
+        assert_eq!(MultiGomoku::deadline_queue(7), Vec::<H256>::new());
+        let expected_event = TestEvent::multi_gomoku(RawEvent::FinalizedOnTimeout(session_ids[0], players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+#[allow(deprecated)] // ValidateUnsigned
+fn test_offchain_worker_submits_finalization() {
+    let (offchain, _) = TestOffchainExt::new();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    let mut ext = ExtBuilder::build();
+    ext.register_extension(OffchainExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+        let session_ids: Vec<H256> = (0..4)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+
+        // the action moves the deadline of the first app to block 6
+        System::set_block_number(4);
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_ids[0],
+                vec![5, 5]
+            )
+        );
+        check_gomoku_state_invariants();
+
+        // nothing is submitted before the action deadline passed
+        System::set_block_number(5);
+        MultiGomoku::offchain_worker(5);
+        assert!(pool_state.read().transactions.is_empty());
+
+        // the apps beyond MaxFinalizePerBlock are moved to the queue of the next block
+        System::set_block_number(6);
+        MultiGomoku::on_initialize(6);
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_ids[0]), Some(AppStatus::Action));
+        assert_eq!(MultiGomoku::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(
+            MultiGomoku::deadline_queue(7),
+            vec![session_ids[0], session_ids[2], session_ids[3]]
+        );
+
+        // on_initialize of the next block finalizes the first MaxFinalizePerBlock apps of its queue,
+        // the off-chain worker only submits the finalization of the expired app behind them
+        MultiGomoku::offchain_worker(6);
+        let tx = pool_state.write().transactions.pop().unwrap();
+        assert!(pool_state.read().transactions.is_empty());
+        let tx = Extrinsic::decode(&mut &*tx).unwrap();
+        assert_eq!(tx.signature, None);
+        let call = crate::Call::finalize_on_action_timeout_unsigned(session_ids[3]);
+        assert_eq!(tx.call, mock::Call::MultiGomoku(call.clone()));
+
+        assert!(MultiGomoku::validate_unsigned(TransactionSource::External, &call).is_ok());
+        assert_ok!(MultiGomoku::finalize_on_action_timeout_unsigned(Origin::none(), session_ids[3]));
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::is_finalized(session_ids[3].encode()).unwrap(), true);
+        let expected_event = TestEvent::multi_gomoku(RawEvent::FinalizedOnTimeout(session_ids[3], players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        // the finalization of a finalized app is stale
+        assert_eq!(
+            MultiGomoku::validate_unsigned(TransactionSource::External, &call),
+            InvalidTransaction::Stale.into()
+        );
+        System::set_block_number(7);
+        MultiGomoku::on_initialize(7);
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(MultiGomoku::is_finalized(session_ids[2].encode()).unwrap(), true);
+        MultiGomoku::offchain_worker(7);
+        assert!(pool_state.read().transactions.is_empty());
+    })
+}
+
+#[test]
+fn test_fail_initiate_with_invalid_board_config() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = |board_size: u8, win_length: u8| AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: board_size,
+            win_length: win_length,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            MultiGomoku::app_initiate(Origin::signed(players[0]), initiate_request(MIN_BOARD_SIZE - 1, 3)),
+            Error::<TestRuntime>::InvalidBoardSize
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            MultiGomoku::app_initiate(Origin::signed(players[0]), initiate_request(MAX_BOARD_SIZE + 1, 5)),
+            Error::<TestRuntime>::InvalidBoardSize
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            MultiGomoku::app_initiate(Origin::signed(players[0]), initiate_request(9, MIN_WIN_LENGTH - 1)),
+            Error::<TestRuntime>::InvalidWinLength
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            MultiGomoku::app_initiate(Origin::signed(players[0]), initiate_request(9, 10)),
+            Error::<TestRuntime>::InvalidWinLength
+        );
+        check_gomoku_state_invariants();
+        assert_ok!(MultiGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request(MAX_BOARD_SIZE, MAX_BOARD_SIZE)
+        ));
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_black_wins_with_four_in_a_row_on_9x9_board() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+        let none: u8 = 0;
+        let black: u8 = 1;
+        let white: u8 = 2;
+        let black_player_id0 = 1;
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 0,
+            max_stone_onchain: 5,
+            board_size: 9,
+            win_length: 4,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(MultiGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        check_gomoku_state_invariants();
+        let session_id = MultiGomoku::get_session_id(&initiate_request);
+
+        // board state of the default board size is rejected
+        let state_proof = get_state_proof(1, vec![0; BOARD_STATE_LEN], 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidBoardStateLength
+        );
+        check_gomoku_state_invariants();
+
+        let mut board_state = vec![0; board_state_len(9)];
+        assert_eq!(board_state.len(), 84);
+        board_state[0] = none; // winner
+        board_state[1] = white; // turn color
+        board_state[2] = black_player_id0;
+        board_state[3] = black; // (0, 0)
+        board_state[4] = black; // (0, 1)
+        board_state[5] = black; // (0, 2)
+        board_state[3 + 9 * 4 + 4] = white; // (4, 4)
+        board_state[3 + 9 * 4 + 5] = white; // (4, 5)
+        let state_proof = get_state_proof(1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+
+        let settle_finalized_time = MultiGomoku::get_settle_finalized_time(session_id).unwrap();
+        System::set_block_number(settle_finalized_time + 1);
+        assert_noop!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![0, 9]
+            ),
+            Error::<TestRuntime>::OutOfBoundary
+        );
+        check_gomoku_state_invariants();
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![8, 8]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![0, 3]
+            )
+        );
+        check_gomoku_state_invariants();
+
+        assert_eq!(
+            MultiGomoku::is_finalized(session_id.encode()).unwrap(),
+            true,
+        );
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome
+        };
+        assert_eq!(
+            MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Winner(0).encode()
+        );
+    })
+}
+
+#[test]
+fn test_fail_black_plays_forbidden_move_under_renju_rule_set() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+        let black: u8 = 1;
+        let white: u8 = 2;
+        let black_player_id1 = 2;
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 0,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Renju,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(MultiGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        check_gomoku_state_invariants();
+        let session_id = MultiGomoku::get_session_id(&initiate_request);
+
+        let mut board_state = vec![0; BOARD_STATE_LEN];
+        board_state[1] = black; // turn color
+        board_state[2] = black_player_id1;
+        // (7, 6) makes a double four of black
+        for &(x, y) in [(7, 3), (7, 4), (7, 5), (4, 6), (5, 6), (6, 6)].iter() {
+            board_state[3 + 15 * x + y] = black;
+        }
+        for &(x, y) in [(0, 0), (0, 2), (14, 14), (14, 12), (14, 10), (0, 4)].iter() {
+            board_state[3 + 15 * x + y] = white;
+        }
+        let state_proof = get_state_proof(1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        let settle_finalized_time = MultiGomoku::get_settle_finalized_time(session_id).unwrap();
+        System::set_block_number(settle_finalized_time + 1);
+
+        assert_noop!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![7, 6]
+            ),
+            Error::<TestRuntime>::ForbiddenMove
+        );
+        check_gomoku_state_invariants();
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![10, 10]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_state(session_id, 0).unwrap(), vec![white]);
+    })
+}
+
+fn app_initiate(
+    nonce: u128,
+    players: Vec<AccountId>,
+    player_num: u8,
+    timeout: BlockNumber,
+    min_stone_offchain: u8,
+    max_stone_onchain: u8
+) -> H256 {
+    let initiate_request = AppInitiateRequest {
+        nonce: nonce,
+        players: players.clone(),
+        player_num: player_num,
+        timeout: timeout,
+        min_stone_offchain: min_stone_offchain,
+        max_stone_onchain: max_stone_onchain,
+        board_size: DEFAULT_BOARD_SIZE,
+        win_length: DEFAULT_WIN_LENGTH,
+        rule_set: RuleSet::Freestyle,
+        time_bank: None,
+        unilateral: false,
+    };
+
+    assert_ok!(MultiGomoku::app_initiate(
+        Origin::signed(players[0]),
+        initiate_request.clone())
+    );
+    check_gomoku_state_invariants();
+
+    let session_id = MultiGomoku::get_session_id(&initiate_request);
+    return session_id;
+}
+
+#[test]
+fn test_move_log_and_replay() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        let initial_board = MultiGomoku::get_state(session_id, 2).unwrap();
+        assert_eq!(MultiGomoku::move_log(session_id), vec![]);
+        assert_eq!(MultiGomoku::move_log_base(session_id), Some((initial_board.clone(), 0)));
+
+        let moves = [(1, 7, 7), (0, 8, 8), (1, 7, 8), (0, 8, 9), (1, 7, 9)];
+        for (i, &(player, x, y)) in moves.iter().enumerate() {
+            System::set_block_number(4 + i as u64);
+            assert_ok!(
+                MultiGomoku::update_by_action(
+                    Origin::signed(players[player]),
+                    session_id,
+                    vec![x, y]
+                )
+            );
+            check_gomoku_state_invariants();
+            if i == 2 {
+                let rules = MultiGomoku::get_game_rules(session_id).unwrap();
+                let move_log = MultiGomoku::move_log(session_id);
+                assert_eq!(
+                    move_log,
+                    vec![
+                        MoveRecord { seq_num: 2, player: players[1], x: 7, y: 7, block: 4 },
+                        MoveRecord { seq_num: 3, player: players[0], x: 8, y: 8, block: 5 },
+                        MoveRecord { seq_num: 4, player: players[1], x: 7, y: 8, block: 6 },
+                    ]
+                );
+                assert_eq!(
+                    MultiGomoku::replay(&rules, initial_board.clone(), &move_log),
+                    Ok(MultiGomoku::get_state(session_id, 2).unwrap())
+                );
+                assert_eq!(
+                    MultiGomoku::replay(&rules, initial_board.clone(), &[move_log[0].clone(), move_log[0].clone()]),
+                    Err(ReplayError::SlotOccupied(1))
+                );
+                assert_eq!(
+                    MultiGomoku::replay(&rules, initial_board[1..].to_vec(), &move_log),
+                    Err(ReplayError::InvalidBoard)
+                );
+            }
+        }
+
+        // only the latest MaxMoveLogLength moves are kept
+        let move_log = MultiGomoku::move_log(session_id);
+        assert_eq!(move_log.len(), 4);
+        assert_eq!(move_log[0].seq_num, 3);
+        assert_eq!(move_log[3], MoveRecord { seq_num: 6, player: players[1], x: 7, y: 9, block: 8 });
+
+        // the base board of the move log advances past the dropped move
+        let (base, moves_before) = MultiGomoku::move_log_base(session_id).unwrap();
+        assert_eq!(moves_before, 1);
+        let mut expected_base = initial_board.clone();
+        expected_base[1] = 2; // turn of the first kept move
+        expected_base[115] = 1; // (7, 7)
+        assert_eq!(base, expected_base);
+        let rules = MultiGomoku::get_game_rules(session_id).unwrap();
+        let rules = GameRules { max_stone_onchain: rules.max_stone_onchain - moves_before as u8, ..rules };
+        assert_eq!(
+            MultiGomoku::replay(&rules, base, &move_log),
+            Ok(MultiGomoku::get_state(session_id, 2).unwrap())
+        );
+
+        // a settled off-chain state restarts the move log
+        let board_state = MultiGomoku::get_state(session_id, 2).unwrap();
+        let state_proof = get_state_proof(7, board_state.clone(), 2, session_id, players_pair);
+        assert_ok!(MultiGomoku::update_by_state(Origin::signed(players[0]), state_proof));
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::move_log(session_id), vec![]);
+        assert_eq!(MultiGomoku::move_log_base(session_id), Some((board_state, 0)));
+    })
+}
+
+#[test]
+fn test_replay_move_log_from_storage() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // the settled board is the base of the move log
+        let session_id = settle_app(0, players.clone(), players_pair);
+        let settled_board = MultiGomoku::get_state(session_id, 2).unwrap();
+        assert_eq!(MultiGomoku::move_log_base(session_id), Some((settled_board.clone(), 0)));
+        assert_eq!(replay_from_storage(session_id), settled_board);
+
+        let moves = [(1, 7, 7), (0, 8, 8), (1, 7, 8)];
+        for (i, &(player, x, y)) in moves.iter().enumerate() {
+            System::set_block_number(4 + i as u64);
+            assert_ok!(
+                MultiGomoku::update_by_action(
+                    Origin::signed(players[player]),
+                    session_id,
+                    vec![x, y]
+                )
+            );
+            // the base and the log in storage give the current board without the off-chain state
+            assert_eq!(replay_from_storage(session_id), MultiGomoku::get_state(session_id, 2).unwrap());
+        }
+        assert_eq!(MultiGomoku::move_log_base(session_id), Some((settled_board, 0)));
+    })
+}
+
+/// Replay the move log of an app from its base board, reading both from storage
+fn replay_from_storage(session_id: H256) -> Vec<u8> {
+    let (base, moves_before) = MultiGomoku::move_log_base(session_id).unwrap();
+    let rules = MultiGomoku::get_game_rules(session_id).unwrap();
+    let rules = GameRules { max_stone_onchain: rules.max_stone_onchain - moves_before as u8, ..rules };
+    MultiGomoku::replay(&rules, base, &MultiGomoku::move_log(session_id)).unwrap()
+}
+
+#[test]
+fn test_game_clock_time_banks() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: Some(5),
+            unilateral: false,
+        };
+        assert_ok!(MultiGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        check_gomoku_state_invariants();
+        let session_id = MultiGomoku::get_session_id(&initiate_request);
+        assert_eq!(MultiGomoku::get_time_banks(session_id), Some(vec![5, 5]));
+
+        // players[1] is black, its clock starts at the settle deadline 3
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1;
+        board_state[2] = 2;
+        for i in 3..9 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_action_deadline(session_id), Some(8));
+
+        System::set_block_number(5);
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![7, 7]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_time_banks(session_id), Some(vec![5, 3]));
+        assert_eq!(MultiGomoku::get_action_deadline(session_id), Some(10));
+
+        System::set_block_number(9);
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![8, 8]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_time_banks(session_id), Some(vec![1, 3]));
+        assert_eq!(MultiGomoku::get_action_deadline(session_id), Some(12));
+
+        // black has 3 blocks left
+        System::set_block_number(13);
+        assert_noop!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![7, 8]
+            ),
+            Error::<TestRuntime>::TimeBankExhausted
+        );
+        assert_ok!(
+            MultiGomoku::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_state(session_id, 1).unwrap(), vec![2]);
+        assert_eq!(MultiGomoku::is_finalized(session_id.encode()).unwrap(), true);
+        let expected_event = TestEvent::multi_gomoku(RawEvent::FinalizedOnTimeout(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_resign() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        assert_noop!(
+            MultiGomoku::resign(
+                Origin::signed(account_pair("Carl").public()),
+                session_id
+            ),
+            Error::<TestRuntime>::NotPlayer
+        );
+
+        // a player can resign when it is not its turn
+        assert_ok!(
+            MultiGomoku::resign(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(MultiGomoku::get_state(session_id, 0).unwrap(), vec![0]);
+        let expected_event = TestEvent::multi_gomoku(RawEvent::Resigned(session_id, players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::GameWon(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome,
+        };
+        assert_eq!(
+            MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Winner(1 as u8).encode()
+        );
+
+        assert_noop!(
+            MultiGomoku::resign(
+                Origin::signed(players[1]),
+                session_id
+            ),
+            Error::<TestRuntime>::AlreadyFinalized
+        );
+    })
+}
+
+#[test]
+fn test_legacy_query_args() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        let legacy_query = |query_data: u8| MultiGomoku::get_outcome(
+            MultiGomokuLegacyArgsQueryOutcome {
+                session_id: session_id,
+                query_data: query_data
+            }.encode()
+        );
+        // the former encoding of a session id and a u8 winner, 1 is not read as GameQuery::Outcome
+        assert_eq!((session_id, 1u8).encode(), MultiGomokuLegacyArgsQueryOutcome { session_id: session_id, query_data: 1 }.encode());
+        assert_eq!(legacy_query(0).unwrap(), true.encode());
+        assert_eq!(legacy_query(1).unwrap(), false.encode());
+
+        // players[0] resigns, players[1] of black id 2 wins with black
+        assert_ok!(MultiGomoku::resign(Origin::signed(players[0]), session_id));
+        assert_eq!(legacy_query(1).unwrap(), true.encode());
+        assert_eq!(legacy_query(2).unwrap(), false.encode());
+        assert_eq!(legacy_query(0).unwrap(), false.encode());
+        assert_noop!(
+            MultiGomoku::get_outcome((H256::from_low_u64_be(1), 1u8).encode()),
+            Error::<TestRuntime>::MultiGomokuInfoNotExist
+        );
+
+        // the versioned args of another version are rejected
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION + 1,
+            query_data: GameQuery::Outcome
+        };
+        assert_noop!(
+            MultiGomoku::get_outcome(args_query_outcome.encode()),
+            Error::<TestRuntime>::UnsupportedQueryVersion
+        );
+    })
+}
+
+#[test]
+fn test_offer_and_accept_draw() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        // players[1] is black and moves first
+        assert_noop!(
+            MultiGomoku::offer_draw(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::NotYourTurn
+        );
+        assert_ok!(
+            MultiGomoku::offer_draw(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        let expected_event = TestEvent::multi_gomoku(RawEvent::DrawOffered(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_noop!(
+            MultiGomoku::offer_draw(
+                Origin::signed(players[1]),
+                session_id
+            ),
+            Error::<TestRuntime>::DrawAlreadyOffered
+        );
+        assert_noop!(
+            MultiGomoku::accept_draw(
+                Origin::signed(players[1]),
+                session_id
+            ),
+            Error::<TestRuntime>::NoDrawOffer
+        );
+
+        // the offer stands after the move of the offerer and is declined by the move of the opponent
+        System::set_block_number(4);
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![7, 7]
+            )
+        );
+        assert_ok!(
+            MultiGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![8, 8]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            MultiGomoku::accept_draw(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::NoDrawOffer
+        );
+
+        assert_ok!(
+            MultiGomoku::offer_draw(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        assert_ok!(
+            MultiGomoku::accept_draw(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(MultiGomoku::get_state(session_id, 1).unwrap(), vec![0]);
+        assert_eq!(MultiGomoku::is_finalized(session_id.encode()).unwrap(), true);
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome,
+        };
+        assert_eq!(
+            MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Draw.encode()
+        );
+        let expected_event = TestEvent::multi_gomoku(RawEvent::Draw(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_finalize_by_agreement() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = app_initiate(0, players.clone(), 2, 2, 5, 5);
+        let mut board_state = vec![0; 228];
+        board_state[0] = 1; // winner color
+        board_state[1] = 0; // turn color
+        board_state[2] = 2; // black player id
+        for y in 0..5 {
+            board_state[3 + y] = 1; // (0, y)
+        }
+        for y in 0..4 {
+            board_state[3 + 30 + 2 * y] = 2; // (2, 2y)
+        }
+
+        // signatures of an ordinary state cannot finalize the app
+        let state_proof = get_state_proof(1, board_state.clone(), 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidSignature
+        );
+
+        // the final state is finalized in the same block from Idle
+        let state_proof = get_final_state_proof(1, board_state.clone(), 2, session_id, players_pair.clone());
+        assert_ok!(
+            MultiGomoku::finalize_by_agreement(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(MultiGomoku::is_finalized(session_id.encode()).unwrap(), true);
+        assert_eq!(MultiGomoku::finalized_at(session_id), Some(1));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::GameWon(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::multi_gomoku(RawEvent::FinalizedByAgreement(session_id, 1));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        let state_proof = get_final_state_proof(2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiGomoku::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::AlreadyFinalized
+        );
+
+        // an unfinished final board of a settled app is a draw
+        let session_id = settle_app(1, players.clone(), players_pair.clone());
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1;
+        board_state[2] = 2;
+        for i in 3..9 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_final_state_proof(1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            MultiGomoku::finalize_by_agreement(
+                Origin::signed(players[1]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_status(session_id), Some(AppStatus::Finalized));
+        let args_query_outcome = MultiGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome,
+        };
+        assert_eq!(
+            MultiGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Draw.encode()
+        );
+        let expected_event = TestEvent::multi_gomoku(RawEvent::Draw(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_unilateral_state_update() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            player_num: 2,
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: true,
+        };
+        assert_ok!(MultiGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        let session_id = MultiGomoku::get_session_id(&initiate_request);
+
+        // players[1] plays black
+        let mut board_state = vec![0; 228];
+        board_state[1] = 1;
+        board_state[2] = 2;
+        for i in 3..9 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(1, board_state.clone(), 2, session_id, players_pair.clone());
+        assert_ok!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+
+        let mut next_board_state = board_state.clone();
+        next_board_state[1] = 2; // turn color
+        next_board_state[3 + 15 * 7 + 7] = 1; // (7, 7)
+        let state_proof = get_unilateral_state_proof(2, next_board_state.clone(), 2, session_id, &players_pair[0]);
+        assert_noop!(
+            MultiGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidSignature
+        );
+        // the black id cannot change
+        let mut swapped = next_board_state.clone();
+        swapped[2] = 1;
+        let state_proof = get_unilateral_state_proof(2, swapped, 2, session_id, &players_pair[1]);
+        assert_noop!(
+            MultiGomoku::update_by_state(Origin::signed(players[1]), state_proof),
+            Error::<TestRuntime>::InvalidUnilateralMove
+        );
+        let mut white_stone = board_state.clone();
+        white_stone[1] = 2;
+        white_stone[3 + 15 * 7 + 7] = 2;
+        let state_proof = get_unilateral_state_proof(2, white_stone, 2, session_id, &players_pair[1]);
+        assert_noop!(
+            MultiGomoku::update_by_state(Origin::signed(players[1]), state_proof),
+            Error::<TestRuntime>::InvalidUnilateralMove
+        );
+
+        let state_proof = get_unilateral_state_proof(2, next_board_state, 2, session_id, &players_pair[1]);
+        assert_ok!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[1]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        let expected_event = TestEvent::multi_gomoku(RawEvent::IntendSettle(session_id, 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_eq!(MultiGomoku::get_seq_num(session_id), Some(2));
+        assert_eq!(MultiGomoku::get_state(session_id, 0).unwrap(), vec![2]);
+
+        // a newer co-signed state is accepted as usual
+        let mut board_state = board_state;
+        board_state[3 + 15 * 7 + 7] = 1;
+        board_state[3 + 15 * 8 + 8] = 2; // (8, 8)
+        let state_proof = get_state_proof(3, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            MultiGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(MultiGomoku::get_seq_num(session_id), Some(3));
+    })
+}
+
+fn get_state_proof(
+    seq: u128,
+    board_state: Vec<u8>,
+    timeout: BlockNumber,
+    session_id: H256,
+    players_pair: Vec<sr25519::Pair>,
+) -> StateProof<BlockNumber, H256, Signature> {
+    let app_state = AppState {
+        seq_num: seq,
+        board_state: board_state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = MultiGomoku::encode_app_state(app_state.clone());
+    let sig_1 = players_pair[0].sign(&encoded);
+    let sig_2 = players_pair[1].sign(&encoded);
+    let state_proof = StateProof {
+        app_state:  app_state,
+        sigs: vec![sig_1, sig_2]
+    };
+
+    return state_proof;
+}
+
+fn get_final_state_proof(
+    seq: u128,
+    board_state: Vec<u8>,
+    timeout: BlockNumber,
+    session_id: H256,
+    players_pair: Vec<sr25519::Pair>,
+) -> StateProof<BlockNumber, H256, Signature> {
+    let app_state = AppState {
+        seq_num: seq,
+        board_state: board_state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = MultiGomoku::encode_final_app_state(app_state.clone());
+    let sigs = players_pair.iter().map(|pair| pair.sign(&encoded)).collect();
+
+    StateProof {
+        app_state: app_state,
+        sigs: sigs,
+    }
+}
+
+fn get_unilateral_state_proof(
+    seq: u128,
+    board_state: Vec<u8>,
+    timeout: BlockNumber,
+    session_id: H256,
+    mover_pair: &sr25519::Pair,
+) -> StateProof<BlockNumber, H256, Signature> {
+    let app_state = AppState {
+        seq_num: seq,
+        board_state: board_state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = MultiGomoku::encode_app_state(app_state.clone());
+
+    StateProof {
+        app_state: app_state,
+        sigs: vec![mover_pair.sign(&encoded)],
+    }
+}
+
+fn place_stone_and_update_by_state(
+    session_id: H256, 
+    players: Vec<AccountId>, 
+    players_pair: Vec<sr25519::Pair>
+) {
+    let none: u8 = 0;
+    let black: u8 = 1;
+    let white: u8 = 2;
+    let black_player_id1 = 2;
+
+    let mut board_state = vec![0; 228];
+    board_state[0] = none; 
+    board_state[1] = black; // turn color
+    board_state[2] = black_player_id1;
+    board_state[3] = white;
+    board_state[4] = white;
+    board_state[5] = black;
+    board_state[6] = black;
+    board_state[7] = white;
+    board_state[8] = white;
+    board_state[9] = black;
+    board_state[10] = black;
+
+    let state_proof = get_state_proof(3, board_state, 2, session_id, players_pair);
+    assert_ok!(
+        MultiGomoku::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+    check_gomoku_state_invariants();
+}
+
+fn place_stone_and_update_by_state_two_times(
+    session_id: H256,
+    players: Vec<AccountId>,
+    players_pair: Vec<sr25519::Pair>
+) {
+    let none: u8 = 0;
+    let black: u8 = 1;
+    let white: u8 = 2;
+    let black_player_id1 = 2;
+
+    place_stone_and_update_by_state(session_id, players.clone(), players_pair.clone());
+
+    let mut board_state = vec![0; 228];
+    board_state[0] = none; // winner
+    board_state[1] = white; // turn color
+    board_state[2] = black_player_id1;
+    board_state[3] = black; // (0, 0)
+    board_state[4] = black; // (0, 1)
+    board_state[5] = black; // (0, 2)
+    board_state[6] = black; // (0, 3)
+    board_state[101] = white;
+    board_state[102] = white;
+    board_state[103] = white;
+    let state_proof = get_state_proof(4, board_state, 2, session_id, players_pair);
+    assert_ok!(
+        MultiGomoku::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+    check_gomoku_state_invariants();
+}
+
+fn settle_app(nonce: u128, players: Vec<AccountId>, players_pair: Vec<sr25519::Pair>) -> H256 {
+    let session_id = app_initiate(nonce, players.clone(), 2, 2, 5, 5);
+    let mut board_state = vec![0; 228];
+    board_state[1] = 1;
+    board_state[2] = 2;
+    for i in 3..9 {
+        board_state[i] = (i % 2 + 1) as u8;
+    }
+    let state_proof = get_state_proof(1, board_state, 2, session_id, players_pair);
+    assert_ok!(
+        MultiGomoku::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+    check_gomoku_state_invariants();
+
+    return session_id;
+}
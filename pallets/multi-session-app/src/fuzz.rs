@@ -0,0 +1,182 @@
+//! Fuzzing of the dispatchables with arbitrary state proofs and actions
+
+#![cfg(test)]
+
+use super::*;
+use mock::*;
+use sp_core::{sr25519, Pair, H256};
+use frame_support::{assert_ok, traits::OnInitialize};
+use proptest::prelude::*;
+
+/// A call to the runtime module with arbitrary input
+#[derive(Clone, Debug)]
+enum Op {
+    /// Initiate an app of arbitrary signers
+    Initiate { nonce: u128, players: Vec<usize>, player_num: u8, timeout: u64 },
+    /// Update by a state proof signed by the first `sig_num` signers
+    UpdateByState { seq_num: u128, state: u8, timeout: u64, sig_num: usize },
+    /// Finalize by a state proof signed as final by the first `sig_num` signers
+    FinalizeByAgreement { seq_num: u128, state: u8, timeout: u64, sig_num: usize },
+    /// Update by a state proof decoded from arbitrary bytes
+    UpdateByEncodedState(Vec<u8>),
+    UpdateByAction { signer: usize, action: u8 },
+    FinalizeOnActionTimeout,
+    FinalizeOnActionTimeoutUnsigned,
+    ConsumeOutcome,
+    ClearSession { signer: usize },
+    Query { query_data: u8, args: Vec<u8> },
+    /// Advance blocks and run `on_initialize` of each block
+    AdvanceBlocks(u64),
+}
+
+fn timeout() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        4 => 0u64..4,
+        1 => any::<u64>(),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        1 => (0u128..2, prop::collection::vec(0usize..3, 0..4), 0u8..4, timeout())
+            .prop_map(|(nonce, players, player_num, timeout)| {
+                Op::Initiate { nonce, players, player_num, timeout }
+            }),
+        4 => (0u128..6, any::<u8>(), timeout(), 0usize..4)
+            .prop_map(|(seq_num, state, timeout, sig_num)| {
+                Op::UpdateByState { seq_num, state, timeout, sig_num }
+            }),
+        1 => (0u128..6, any::<u8>(), timeout(), 0usize..4)
+            .prop_map(|(seq_num, state, timeout, sig_num)| {
+                Op::FinalizeByAgreement { seq_num, state, timeout, sig_num }
+            }),
+        1 => prop::collection::vec(any::<u8>(), 0..512).prop_map(Op::UpdateByEncodedState),
+        4 => (0usize..3, any::<u8>())
+            .prop_map(|(signer, action)| Op::UpdateByAction { signer, action }),
+        1 => Just(Op::FinalizeOnActionTimeout),
+        1 => Just(Op::FinalizeOnActionTimeoutUnsigned),
+        1 => Just(Op::ConsumeOutcome),
+        1 => (0usize..3).prop_map(|signer| Op::ClearSession { signer }),
+        1 => (any::<u8>(), prop::collection::vec(any::<u8>(), 0..40))
+            .prop_map(|(query_data, args)| Op::Query { query_data, args }),
+        2 => (1u64..12).prop_map(Op::AdvanceBlocks),
+    ]
+}
+
+/// Apply a call, errors are expected but a panic fails the case
+fn apply(op: Op, session_id: H256, signers: &[sr25519::Pair]) {
+    let origin = |signer: usize| Origin::signed(signers[signer].public());
+    match op {
+        Op::Initiate { nonce, players, player_num, timeout } => {
+            let initiate_request = SessionInitiateRequest {
+                nonce: nonce,
+                player_num: player_num,
+                players: players.iter().map(|&signer| signers[signer].public()).collect(),
+                timeout: timeout,
+                time_bank: None,
+            };
+            let _ = MultiSessionApp::session_initiate(origin(0), initiate_request);
+        }
+        Op::UpdateByState { seq_num, state, timeout, sig_num } => {
+            let app_state = AppState {
+                seq_num: seq_num,
+                state: state,
+                timeout: timeout,
+                session_id: session_id,
+            };
+            let encoded = MultiSessionApp::encode_app_state(app_state.clone());
+            let sigs = (0..sig_num).map(|i| signers[i % signers.len()].sign(&encoded)).collect();
+            let state_proof = StateProof {
+                app_state: app_state,
+                sigs: sigs,
+            };
+            let _ = MultiSessionApp::update_by_state(origin(0), state_proof);
+        }
+        Op::FinalizeByAgreement { seq_num, state, timeout, sig_num } => {
+            let app_state = AppState {
+                seq_num: seq_num,
+                state: state,
+                timeout: timeout,
+                session_id: session_id,
+            };
+            let encoded = MultiSessionApp::encode_final_app_state(app_state.clone());
+            let sigs = (0..sig_num).map(|i| signers[i % signers.len()].sign(&encoded)).collect();
+            let state_proof = StateProof {
+                app_state: app_state,
+                sigs: sigs,
+            };
+            let _ = MultiSessionApp::finalize_by_agreement(origin(0), state_proof);
+        }
+        Op::UpdateByEncodedState(bytes) => {
+            if let Ok(state_proof) = StateProofOf::<TestRuntime>::decode(&mut &bytes[..]) {
+                let _ = MultiSessionApp::update_by_state(origin(0), state_proof);
+            }
+        }
+        Op::UpdateByAction { signer, action } => {
+            let _ = MultiSessionApp::update_by_action(origin(signer), session_id, action);
+        }
+        Op::FinalizeOnActionTimeout => {
+            let _ = MultiSessionApp::finalize_on_action_timeout(origin(0), session_id);
+        }
+        Op::FinalizeOnActionTimeoutUnsigned => {
+            let _ = MultiSessionApp::finalize_on_action_timeout_unsigned(Origin::none(), session_id);
+        }
+        Op::ConsumeOutcome => {
+            let _ = <MultiSessionApp as ConsumeOutcome<H256>>::consume_outcome(session_id);
+        }
+        Op::ClearSession { signer } => {
+            let _ = MultiSessionApp::clear_session(origin(signer), session_id);
+        }
+        Op::Query { query_data, args } => {
+            let args_query_outcome = MultiSessionArgsQueryOutcome {
+                session_id: session_id,
+                query_data: query_data,
+            };
+            let _ = MultiSessionApp::get_state(session_id);
+            let _ = MultiSessionApp::is_finalized(session_id.encode());
+            let _ = MultiSessionApp::get_outcome(args_query_outcome.encode());
+            let _ = MultiSessionApp::is_finalized(args.clone());
+            let _ = MultiSessionApp::get_outcome(args);
+        }
+        Op::AdvanceBlocks(n) => {
+            for _ in 0 .. n {
+                let block_number = System::block_number() + 1;
+                System::set_block_number(block_number);
+                MultiSessionApp::on_initialize(block_number);
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn fuzz_dispatchables_do_not_panic(
+        ops in prop::collection::vec(op(), 1..32),
+    ) {
+        ExtBuilder::build().execute_with(|| {
+            System::set_block_number(1);
+            let (players, mut signers)
+                = get_sorted_peer(account_pair("Alice"), account_pair("Bob"));
+            signers.push(account_pair("Carl"));
+
+            let initiate_request = SessionInitiateRequest {
+                nonce: 0,
+                player_num: 2,
+                players: players.clone(),
+                timeout: 2,
+                time_bank: None,
+            };
+            assert_ok!(MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone())
+            );
+            let session_id = MultiSessionApp::get_session_id(&initiate_request);
+
+            for op in ops {
+                apply(op, session_id, &signers);
+            }
+        });
+    }
+}
@@ -4,27 +4,46 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+mod benchmarking;
+mod fuzz;
+pub mod weights;
+
 use codec::{Decode, Encode};
 use frame_support::{
     decl_module, decl_storage, decl_event, decl_error, ensure,
     storage::StorageMap,
-    traits::Get,
+    traits::{Currency, Get, ReservableCurrency},
+    weights::Weight,
+};
+use frame_system::{
+    self as system, ensure_none, ensure_signed,
+    offchain::{SendTransactionTypes, SubmitTransaction},
+};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionPriority, TransactionSource, TransactionValidity,
 };
-use frame_system::{self as system, ensure_signed};
 use sp_runtime::{DispatchResult, DispatchError};
 use sp_runtime::traits::{
     Hash, IdentifyAccount, AccountIdConversion, 
-    Member, Verify, Zero,
+    Member, Verify, Zero, AtLeast32BitUnsigned, Saturating,
 };
 use sp_runtime::{ModuleId, RuntimeDebug};
 use sp_std::{prelude::*, vec::Vec};
+use celer_app_core::{
+    CelerAppQuery, ConsumeOutcome, DisputeError, DisputeStateMachine, GameClock, LifecycleError,
+    SessionDepositConfig, SessionLifecycle,
+};
+pub use celer_app_core::AppStatus as SessionStatus;
+pub use weights::WeightInfo;
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct SessionInitiateRequest<AccountId, BlockNumber> {
-    nonce: u128,
-    player_num: u8,
-    players: Vec<AccountId>,
-    timeout: BlockNumber,
+    pub nonce: u128,
+    pub player_num: u8,
+    pub players: Vec<AccountId>,
+    pub timeout: BlockNumber,
+    /// Blocks of every player for the on-chain phase, `None` for a fixed timeout per action
+    pub time_bank: Option<BlockNumber>,
 }
 
 pub type SessionInitiateRequestOf<T> = SessionInitiateRequest<
@@ -34,10 +53,10 @@ pub type SessionInitiateRequestOf<T> = SessionInitiateRequest<
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct AppState<BlockNumber, Hash> {
-    seq_num: u128,
-    state: u8,
-    timeout: BlockNumber,
-    session_id: Hash,
+    pub seq_num: u128,
+    pub state: u8,
+    pub timeout: BlockNumber,
+    pub session_id: Hash,
 }
 
 pub type AppStateOf<T> = AppState<
@@ -47,8 +66,8 @@ pub type AppStateOf<T> = AppState<
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct StateProof<BlockNumber, Hash, Signature> {
-    app_state: AppState<BlockNumber, Hash>,
-    sigs: Vec<Signature>,
+    pub app_state: AppState<BlockNumber, Hash>,
+    pub sigs: Vec<Signature>,
 }
 
 pub type StateProofOf<T> = StateProof<
@@ -57,14 +76,6 @@ pub type StateProofOf<T> = StateProof<
     <T as Trait>::Signature,
 >;
 
-#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
-pub enum SessionStatus {
-    Idle = 0,
-    Settle = 1,
-    Action = 2,
-    Finalized = 3,
-}
-
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct SessionInfo<AccountId, BlockNumber> {
     state: u8,
@@ -74,6 +85,7 @@ pub struct SessionInfo<AccountId, BlockNumber> {
     timeout: BlockNumber,
     deadline: BlockNumber,
     status: SessionStatus,
+    clock: Option<GameClock<BlockNumber>>,
 }
 
 pub type SessionInfoOf<T> = SessionInfo<
@@ -81,6 +93,53 @@ pub type SessionInfoOf<T> = SessionInfo<
     <T as system::Trait>::BlockNumber,
 >;
 
+impl<AccountId, BlockNumber> DisputeStateMachine<BlockNumber> for SessionInfo<AccountId, BlockNumber>
+where
+    BlockNumber: AtLeast32BitUnsigned + Copy,
+{
+    fn status(&self) -> SessionStatus { self.status.clone() }
+    fn set_status(&mut self, status: SessionStatus) { self.status = status; }
+    fn seq_num(&self) -> u128 { self.seq_num }
+    fn set_seq_num(&mut self, seq_num: u128) { self.seq_num = seq_num; }
+    fn timeout(&self) -> BlockNumber { self.timeout }
+    fn deadline(&self) -> BlockNumber { self.deadline }
+    fn set_deadline(&mut self, deadline: BlockNumber) { self.deadline = deadline; }
+
+    fn action_period(&self) -> BlockNumber {
+        match self.clock.as_ref() {
+            Some(clock) => clock.bank(self.player_to_move()).unwrap_or(self.timeout),
+            None => self.timeout,
+        }
+    }
+}
+
+impl<AccountId, BlockNumber> SessionInfo<AccountId, BlockNumber> {
+    /// Index of the player to move, players take turns in player order by sequence number
+    ///
+    /// The turn order applies to every on-chain action, with or without a game clock.
+    fn player_to_move(&self) -> usize {
+        match self.player_num {
+            0 => 0,
+            player_num => (self.seq_num % player_num as u128) as usize,
+        }
+    }
+
+    /// Winning state of the opponent of a player who lost on timeout
+    ///
+    /// Only a session of two players has a winning state for the opponent, state 1 for
+    /// the first player and 2 for the second, a session of more players keeps its state.
+    ///
+    /// Parameter:
+    /// `loser_index`: Index of the player who did not move before the action deadline
+    fn opponent_winning_state(&self, loser_index: usize) -> Option<u8> {
+        match (self.player_num, loser_index) {
+            (2, 0) => Some(2),
+            (2, 1) => Some(1),
+            _ => None,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Encode, Decode, RuntimeDebug)]
 pub struct MultiSessionArgsQueryOutcome<Hash> {
     pub session_id: Hash,
@@ -91,16 +150,51 @@ pub type MultiSessionArgsQueryOutcomeOf<T> = MultiSessionArgsQueryOutcome<<T as
 
 pub const MULTI_SESSION_APP_ID: ModuleId = ModuleId(*b"_multi__");
 
-pub trait Trait: system::Trait {
+pub type BalanceOf<T> =
+    <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+pub trait Trait: SendTransactionTypes<Call<Self>> + system::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
     type Public: IdentifyAccount<AccountId = Self::AccountId>;
     type Signature: Verify<Signer = <Self as Trait>::Public> + Member + Decode + Encode; 
+    /// Number of blocks a finalized session is kept after finalization
+    type RetentionPeriod: Get<Self::BlockNumber>;
+    /// Maximum number of sessions cleared in `on_initialize` of a block
+    type MaxClearPerBlock: Get<u32>;
+    /// Maximum number of expired sessions finalized in `on_initialize` of a block
+    type MaxFinalizePerBlock: Get<u32>;
+    /// Priority of the finalizations the off-chain worker submits unsigned
+    type UnsignedPriority: Get<TransactionPriority>;
+    /// Currency in which the storage deposit of an app is reserved
+    type Currency: ReservableCurrency<Self::AccountId>;
+    /// Storage deposit reserved from the initiator of an app
+    type SessionDeposit: Get<SessionDepositConfig<BalanceOf<Self>>>;
+    /// Weight information for extrinsics in this pallet
+    type WeightInfo: WeightInfo;
 }
 
 decl_storage! {
     trait Store for Module<T: Trait> as MultiSessionApp {
         pub SessionInfoMap get(fn session_info):
             map hasher(blake2_128_concat) T::Hash => Option<SessionInfoOf<T>>;
+        /// Block number at which the session is finalized
+        pub FinalizedAt get(fn finalized_at):
+            map hasher(blake2_128_concat) T::Hash => Option<T::BlockNumber>;
+        /// Whether the outcome of the finalized session is consumed
+        pub OutcomeConsumed get(fn outcome_consumed):
+            map hasher(blake2_128_concat) T::Hash => bool;
+        /// Id of sessions to be cleared in `on_initialize` of the block
+        pub ClearQueue get(fn clear_queue):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<T::Hash>;
+        /// Id of sessions whose action deadline passes before the block, finalized in its `on_initialize`
+        pub DeadlineQueue get(fn deadline_queue):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<T::Hash>;
+        /// Initiator and reserved storage deposit of the app
+        pub Deposits get(fn deposit_of):
+            map hasher(blake2_128_concat) T::Hash => Option<(T::AccountId, BalanceOf<T>)>;
+        /// Whether the session is cleared, kept so the id can not be initiated again
+        pub ClearedSessions get(fn is_cleared):
+            map hasher(blake2_128_concat) T::Hash => bool;
     }
 }
 
@@ -120,22 +214,29 @@ decl_module!  {
         /// - Complexity: `O(1)`
         ///   - 1 storage insertion `SessionInfoMap`
         ///   - 1 storage reads `SessionInfoMap`
-        /// - Based on benchmark;
-        ///     19.78　µs
+        ///   - 1 storage read `ClearedSessions`
+        ///   - 1 storage insertion `Deposits`
+        ///   - 1 balance reservation of the initiator
         /// # </weight>
-        #[weight = 20_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::session_initiate(initiate_request.players.len() as u32)]
         fn session_initiate(
             origin,
             initiate_request: SessionInitiateRequestOf<T>
         ) -> DispatchResult {
-            let session_id = Self::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+            let initiator = ensure_signed(origin)?;
+
             ensure!(
-                SessionInfoMap::<T>::contains_key(&session_id) == false,
-                "session_id is used"
+                initiate_request.players.len() == initiate_request.player_num as usize,
+                Error::<T>::InvalidPlayerNum
             );
-            
             // check whether account is asscending order
-            Self::is_ordered_account(initiate_request.players.clone())?;
+            celer_app_core::ensure_ordered_players(&initiate_request.players).map_err(Error::<T>::from)?;
+
+            let session_id = Self::get_session_id(&initiate_request);
+            ensure!(
+                Self::is_session_used(session_id) == false,
+                Error::<T>::SessionAlreadyExists
+            );
 
             let session_info = SessionInfoOf::<T> {
                 state: 0,
@@ -145,8 +246,14 @@ decl_module!  {
                 timeout: initiate_request.timeout,
                 deadline: Zero::zero(),
                 status: SessionStatus::Idle,
+                clock: initiate_request.time_bank
+                    .map(|time_bank| GameClock::new(initiate_request.player_num as usize, time_bank)),
             };
-            SessionInfoMap::<T>::insert(session_id, session_info);
+            // reserve the storage deposit from the initiator
+            Self::reserve_deposit(session_id, initiator, session_info.players.len(), session_info.state.encode().len())?;
+            SessionInfoMap::<T>::insert(session_id, session_info.clone());
+
+            Self::deposit_event(RawEvent::AppInitiated(session_id, session_info.players, session_info.timeout));
         
             Ok(())
         }
@@ -163,11 +270,10 @@ decl_module!  {
         /// ## Weight
         /// - Complexity: `O(1)`
         ///   - 1 storage mutation `SessionInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
         ///   - 1 storage read `SessionInfoMap`
-        /// - Based on benchmark;
-        ///     48.44　µs
         /// # </weight>
-        #[weight = 49_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::update_by_state(state_proof.sigs.len() as u32)]
         fn update_by_state(
             origin,
             state_proof: StateProofOf<T>
@@ -183,6 +289,12 @@ decl_module!  {
             // submit and settle off-chain state
             let mut new_session_info = Self::intend_settle(session_info, state_proof.clone())?;
             
+            // the clock of the player to move starts when the settle period ends
+            let settle_deadline = new_session_info.deadline;
+            if let Some(clock) = new_session_info.clock.as_mut() {
+                clock.start(settle_deadline);
+            }
+
             let state = state_proof.app_state.state;
             if state == 1 || state == 2 {
                 new_session_info.state = state;
@@ -192,6 +304,8 @@ decl_module!  {
             }
             
             SessionInfoMap::<T>::mutate(&session_id, |session_info| *session_info = Some(new_session_info.clone()));
+            Self::record_finalized(session_id, &new_session_info.status);
+            Self::queue_deadline(session_id, &new_session_info);
 
             // emit IntendSettle event
             Self::deposit_event(Event::<T>::IntendSettle(session_id, new_session_info.seq_num));
@@ -200,8 +314,50 @@ decl_module!  {
         }
         
 
+        /// Finalize with an off-chain state co-signed by all players as the final state
+        ///
+        /// The players sign `final_signing_payload` of the state instead of `signing_payload`,
+        /// so the session is finalized in this block without waiting for the settle period.
+        ///
+        /// Parameters:
+        /// - `state_proof`: Off-chain session state signed as final by all players
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(N)`
+        ///      - `N` player number
+        ///   - 1 storage mutation `SessionInfoMap`
+        ///   - 1 storage insertion `FinalizedAt`
+        ///   - 1 storage read `SessionInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::finalize_by_agreement(state_proof.sigs.len() as u32)]
+        fn finalize_by_agreement(
+            origin,
+            state_proof: StateProofOf<T>
+        ) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let session_id = state_proof.app_state.session_id;
+            let session_info = match SessionInfoMap::<T>::get(session_id) {
+                Some(session) => session,
+                None => Err(Error::<T>::SessionInfoNotExist)?,
+            };
+
+            // finalize with the state all players agreed on
+            let mut new_session_info: SessionInfoOf<T> = Self::agree_final_state(session_info, state_proof.clone())?;
+            new_session_info.state = state_proof.app_state.state;
+
+            SessionInfoMap::<T>::mutate(&session_id, |session_info| *session_info = Some(new_session_info.clone()));
+            Self::deposit_event(Event::<T>::FinalizedByAgreement(session_id, new_session_info.seq_num));
+            Self::record_finalized(session_id, &new_session_info.status);
+
+            Ok(())
+        }
+
         /// Update state according to an on-chain action
         ///
+        /// Only the player to move by the turn order of `player_to_move` can apply an action.
+        ///
         /// Parameters:
         /// - `session_id`: Id of session
         /// - `action`: Action data
@@ -210,28 +366,40 @@ decl_module!  {
         /// ## Weight
         /// - Complexity: `O(1)`
         ///   - 1 storage mutation `SessionInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
         ///   - 1 storage read `SessionInfoMap`
-        /// - Based on benchmark;
-        ///     24.89　µs
         /// # </weight>
-        #[weight = 25_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::update_by_action()]
         fn update_by_action(
             origin,
             session_id: T::Hash,
             action: u8
         ) -> DispatchResult {
-            ensure_signed(origin)?;
+            let caller = ensure_signed(origin)?;
             let session_info = match SessionInfoMap::<T>::get(session_id) {
                 Some(session) => session,
                 None => Err(Error::<T>::SessionInfoNotExist)?,
             };
+            ensure!(
+                session_info.players.contains(&caller),
+                Error::<T>::NotPlayer
+            );
+            let player_index = session_info.player_to_move();
 
             // apply an action to the on-chain state
             let mut new_session_info = Self::apply_action(session_info)?;
+            ensure!(
+                new_session_info.players.get(player_index) == Some(&caller),
+                Error::<T>::NotYourTurn
+            );
+            Self::charge_clock(&mut new_session_info, player_index)?;
+            Self::deposit_event(RawEvent::ActionApplied(session_id, caller, new_session_info.seq_num, action));
         
             if action == 1 || action == 2 {
                 new_session_info.status = SessionStatus::Finalized;
             } 
+            Self::record_finalized(session_id, &new_session_info.status);
+            Self::queue_deadline(session_id, &new_session_info);
             SessionInfoMap::<T>::mutate(&session_id, |session_info| *session_info = Some(new_session_info));
 
             Ok(())
@@ -246,52 +414,130 @@ decl_module!  {
         /// ## Weight
         /// - Complexity: `O(1)`
         ///   - 1 storage mutation `SessionInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
         ///   - 1 storage read `SessionInfoMap`
-        /// - Based on benchmark;
-        ///     16.35　µs
         /// # </weight>
-        #[weight = 17_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::finalize_on_action_timeout()]
         fn finalize_on_action_timeout(
             origin,
             session_id: T::Hash
         ) -> DispatchResult {
             ensure_signed(origin)?;
-            let mut session_info = match SessionInfoMap::<T>::get(session_id) {
+            let session_info = match SessionInfoMap::<T>::get(session_id) {
                 Some(session) => session,
                 None => Err(Error::<T>::SessionInfoNotExist)?,
             };
             
             let block_number = frame_system::Module::<T>::block_number();
-            if session_info.status == SessionStatus::Action {
-                ensure!(
-                    block_number >  session_info.deadline,
-                    "deadline does not passes"
-                );
-            } else if session_info.status == SessionStatus::Settle {
-                ensure!(
-                    block_number > session_info.deadline + session_info.timeout,
-                    "while setting"
-                );
-            } else {
+            if !session_info.is_action_timeout(block_number).map_err(Error::<T>::from)? {
+                return Ok(());
+            }
+
+            Self::finalize_on_timeout(session_id, session_info)
+        }
+
+        /// Finalize in case of on-chain action timeout, submitted unsigned by the off-chain worker
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of session
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 1 storage mutation `SessionInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
+        ///   - 1 storage read `SessionInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::finalize_on_action_timeout()]
+        fn finalize_on_action_timeout_unsigned(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            ensure_none(origin)?;
+            let session_info = match SessionInfoMap::<T>::get(session_id) {
+                Some(session) => session,
+                None => Err(Error::<T>::SessionInfoNotExist)?,
+            };
+
+            let block_number = frame_system::Module::<T>::block_number();
+            if !session_info.is_action_timeout(block_number).map_err(Error::<T>::from)? {
                 return Ok(());
             }
 
-            SessionInfoMap::<T>::mutate(&session_id, |info| {
-                session_info.status = SessionStatus::Finalized;
-                *info = Some(session_info)
-            });
+            Self::finalize_on_timeout(session_id, session_info)
+        }
+
+        /// Clear a finalized session whose outcome is consumed
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of session
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 4 storage removals `SessionInfoMap`, `FinalizedAt`, `OutcomeConsumed`, `Deposits`
+        ///   - 1 storage insertion `ClearedSessions`
+        ///   - 1 balance unreservation of the initiator
+        ///   - 4 storage reads `SessionInfoMap`, `FinalizedAt`, `OutcomeConsumed`, `Deposits`
+        /// # </weight>
+        #[weight = T::WeightInfo::clear_session()]
+        fn clear_session(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            let caller = ensure_signed(origin)?;
+            let session_info = match SessionInfoMap::<T>::get(session_id) {
+                Some(info) => info,
+                None => Err(Error::<T>::SessionInfoNotExist)?,
+            };
+            ensure!(
+                session_info.players.contains(&caller),
+                Error::<T>::NotPlayer
+            );
+            Self::ensure_clearable(session_id).map_err(Error::<T>::from)?;
+
+            Self::clear(session_id);
 
             Ok(())
         }
+
+        /// Finalize the sessions whose action deadline passed, at most `MaxFinalizePerBlock` of them,
+        /// and clear the sessions queued for this block, at most `MaxClearPerBlock` of them
+        fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            Self::finalize_expired(block_number).saturating_add(Self::sweep(block_number))
+        }
+
+        /// Submit an unsigned finalization of the expired sessions left over by `on_initialize` of the next block
+        fn offchain_worker(block_number: T::BlockNumber) {
+            Self::submit_expired(block_number)
+        }
     }
 }
 
 decl_event! (
     pub enum Event<T> where
+        <T as system::Trait>::AccountId,
+        <T as system::Trait>::BlockNumber,
         <T as system::Trait>::Hash
     {
+        /// AppInitiated(session_id, players, timeout)
+        AppInitiated(Hash, Vec<AccountId>, BlockNumber),
         /// IntendSettle(session_id, seq_num)
         IntendSettle(Hash, u128),
+        /// ActionApplied(session_id, player, seq_num, action)
+        ActionApplied(Hash, AccountId, u128, u8),
+        /// FinalizedOnTimeout(session_id, loser), the player to move did not move before the action deadline
+        FinalizedOnTimeout(Hash, AccountId),
+        /// TimeBankExhausted(session_id, player), the player to move ran out of its time bank
+        TimeBankExhausted(Hash, AccountId),
+        /// FinalizedByAgreement(session_id, seq_num)
+        FinalizedByAgreement(Hash, u128),
+        /// Finalized(session_id)
+        Finalized(Hash),
+        /// OutcomeConsumed(session_id)
+        OutcomeConsumed(Hash),
+        /// SessionCleared(session_id)
+        SessionCleared(Hash),
     }
 );
 
@@ -300,7 +546,220 @@ decl_error! {
         // SessionInfo is not exist
         SessionInfoNotExist,
         // A scale-codec encoded value can not decode correctly
-        MustBeDecodable
+        MustBeDecodable,
+        // Caller is not a player of the session
+        NotPlayer,
+        // Session is not finalized
+        NotFinalized,
+        // Outcome of the session is not consumed
+        OutcomeNotConsumed,
+        // Retention period of the finalized session does not pass
+        RetentionPeriodNotPassed,
+        // Session state is finalized
+        AlreadyFinalized,
+        // Sequence number is not larger than the on-chain one
+        InvalidSeqNum,
+        // Session is not in action mode
+        NotInActionMode,
+        // Action deadline does not pass
+        DeadlineNotPassed,
+        // Settle period and action period do not pass
+        WhileSettling,
+        // Number of signatures does not match number of players
+        InvalidSignatureCount,
+        // Co-signatures of the state are invalid
+        InvalidSignature,
+        // Players are not in ascending order
+        InvalidPlayers,
+        // Number of players is invalid
+        InvalidPlayerNum,
+        // Session with the session id already exists or was cleared
+        SessionAlreadyExists,
+        // Caller is not the player to move
+        NotYourTurn,
+        // Blocks taken for the action exceed the time bank of the player
+        TimeBankExhausted
+    }
+}
+
+impl<T: Trait> From<DisputeError> for Error<T> {
+    fn from(err: DisputeError) -> Error<T> {
+        match err {
+            DisputeError::AlreadyFinalized => Error::<T>::AlreadyFinalized,
+            DisputeError::InvalidSeqNum => Error::<T>::InvalidSeqNum,
+            DisputeError::NotInActionMode => Error::<T>::NotInActionMode,
+            DisputeError::DeadlineNotPassed => Error::<T>::DeadlineNotPassed,
+            DisputeError::WhileSettling => Error::<T>::WhileSettling,
+            DisputeError::InvalidSignatureCount => Error::<T>::InvalidSignatureCount,
+            DisputeError::InvalidSignature => Error::<T>::InvalidSignature,
+            DisputeError::InvalidPlayers => Error::<T>::InvalidPlayers,
+        }
+    }
+}
+
+impl<T: Trait> From<LifecycleError> for Error<T> {
+    fn from(err: LifecycleError) -> Error<T> {
+        match err {
+            LifecycleError::NotFinalized => Error::<T>::NotFinalized,
+            LifecycleError::OutcomeNotConsumed => Error::<T>::OutcomeNotConsumed,
+            LifecycleError::RetentionPeriodNotPassed => Error::<T>::RetentionPeriodNotPassed,
+        }
+    }
+}
+
+impl<T: Trait> CelerAppQuery<T::Hash, T::BlockNumber> for Module<T> {
+    fn is_finalized(args_query_finalization: Vec<u8>) -> Result<bool, DispatchError> {
+        Self::is_finalized(args_query_finalization)
+    }
+
+    fn get_outcome(args_query_outcome: Vec<u8>) -> Result<Vec<u8>, DispatchError> {
+        Self::get_outcome(args_query_outcome)
+    }
+
+    fn get_state(session_id: T::Hash, _key: u8) -> Option<Vec<u8>> {
+        Self::get_state(session_id).map(|state| state.encode())
+    }
+
+    fn get_status(session_id: T::Hash) -> Option<SessionStatus> {
+        Self::get_status(session_id)
+    }
+
+    fn get_settle_finalized_time(session_id: T::Hash) -> Option<T::BlockNumber> {
+        Self::get_settle_finalized_time(session_id)
+    }
+
+    fn get_action_deadline(session_id: T::Hash) -> Option<T::BlockNumber> {
+        Self::get_action_deadline(session_id)
+    }
+
+    fn get_seq_num(session_id: T::Hash) -> Option<u128> {
+        Self::get_seq_num(session_id)
+    }
+}
+
+#[allow(deprecated)] // ValidateUnsigned
+impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
+    type Call = Call<T>;
+
+    /// Accept the unsigned finalization of a session whose action deadline passed
+    ///
+    /// Parameters:
+    /// `_source`: Source of the transaction
+    /// `call`: Call of the transaction
+    fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+        match call {
+            Call::finalize_on_action_timeout_unsigned(session_id) => Self::validate_finalization(session_id),
+            _ => InvalidTransaction::Call.into(),
+        }
+    }
+}
+
+impl<T: Trait> ConsumeOutcome<T::Hash> for Module<T> {
+    fn consume_outcome(session_id: T::Hash) -> DispatchResult {
+        Self::queue_clear(session_id).map_err(Error::<T>::from)?;
+
+        Ok(())
+    }
+}
+
+impl<T: Trait> SessionLifecycle for Module<T> {
+    type AccountId = T::AccountId;
+    type Hash = T::Hash;
+    type BlockNumber = T::BlockNumber;
+    type Info = SessionInfoOf<T>;
+    type InfoMap = SessionInfoMap<T>;
+    type FinalizedAt = FinalizedAt<T>;
+    type OutcomeConsumed = OutcomeConsumed<T>;
+    type DeadlineQueue = DeadlineQueue<T>;
+    type ClearQueue = ClearQueue<T>;
+    type Currency = T::Currency;
+    type Deposits = Deposits<T>;
+    type ClearedSessions = ClearedSessions<T>;
+
+    const UNSIGNED_TAG_PREFIX: &'static str = "MultiSessionApp";
+
+    fn block_number() -> T::BlockNumber {
+        frame_system::Module::<T>::block_number()
+    }
+
+    fn retention_period() -> T::BlockNumber {
+        T::RetentionPeriod::get()
+    }
+
+    fn max_finalize_per_block() -> u32 {
+        T::MaxFinalizePerBlock::get()
+    }
+
+    fn max_clear_per_block() -> u32 {
+        T::MaxClearPerBlock::get()
+    }
+
+    fn unsigned_priority() -> TransactionPriority {
+        T::UnsignedPriority::get()
+    }
+
+    fn session_deposit() -> SessionDepositConfig<BalanceOf<T>> {
+        T::SessionDeposit::get()
+    }
+
+    fn finalize_expired_weight(queued: u32) -> Weight {
+        T::WeightInfo::finalize_expired(queued)
+    }
+
+    fn sweep_weight(queued: u32) -> Weight {
+        T::WeightInfo::on_initialize(queued)
+    }
+
+    /// Finalize a session whose action deadline passed
+    ///
+    /// The player to move did not move in time and loses, with a game clock it ran out of
+    /// its time bank. In a session of two players the state is set to the winning state
+    /// 1 or 2 of the opponent, see `opponent_winning_state`.
+    ///
+    /// Parameters:
+    /// `session_id`: Id of session
+    /// `session_info`: Info of session
+    fn finalize_on_timeout(
+        session_id: T::Hash,
+        mut session_info: SessionInfoOf<T>
+    ) -> DispatchResult {
+        let loser_index = session_info.player_to_move();
+        let loser = match session_info.players.get(loser_index) {
+            Some(player) => player.clone(),
+            None => Err(Error::<T>::InvalidPlayerNum)?,
+        };
+        if let Some(state) = session_info.opponent_winning_state(loser_index) {
+            session_info.state = state;
+        }
+        let exhausted = session_info.clock.is_some();
+        SessionInfoMap::<T>::mutate(&session_id, |info| {
+            session_info.status = SessionStatus::Finalized;
+            *info = Some(session_info)
+        });
+        Self::deposit_event(RawEvent::FinalizedOnTimeout(session_id, loser.clone()));
+        if exhausted {
+            Self::deposit_event(RawEvent::TimeBankExhausted(session_id, loser));
+        }
+        Self::record_finalized(session_id, &SessionStatus::Finalized);
+
+        Ok(())
+    }
+
+    fn submit_finalization(session_id: T::Hash) -> Result<(), ()> {
+        let call = Call::finalize_on_action_timeout_unsigned(session_id);
+        SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
+    }
+
+    fn on_finalized(session_id: T::Hash) {
+        Self::deposit_event(RawEvent::Finalized(session_id));
+    }
+
+    fn on_outcome_consumed(session_id: T::Hash) {
+        Self::deposit_event(RawEvent::OutcomeConsumed(session_id));
+    }
+
+    fn on_cleared(session_id: T::Hash) {
+        Self::deposit_event(RawEvent::SessionCleared(session_id));
     }
 }
 
@@ -357,18 +816,15 @@ impl<T: Trait> Module<T> {
 
     /// Get Id of session
     ///
-    /// Parameters:
-    /// `nonce`: Nonce of session
-    /// `players`: AccountId of players
-    pub fn get_session_id(
-        nonce: u128,
-        players: Vec<T::AccountId>,
-    ) -> T::Hash {
+    /// The id commits to every parameter of the initiate request, so a request
+    /// with the same nonce and players but other parameters gets another id.
+    ///
+    /// Parameter:
+    /// `initiate_request`: Request initiating the session
+    pub fn get_session_id(initiate_request: &SessionInitiateRequestOf<T>) -> T::Hash {
         let multi_session_app_account = Self::app_account();
         let mut encoded = multi_session_app_account.encode();
-        encoded.extend(nonce.encode());
-        players.into_iter()
-            .for_each(|players| { encoded.extend(players.encode()); });
+        encoded.extend(initiate_request.encode());
         let session_id = T::Hashing::hash(&encoded);
         return session_id;
     }
@@ -409,11 +865,7 @@ impl<T: Trait> Module<T> {
             None => return None,
         };
 
-        if session_info.status == SessionStatus::Settle {
-            return Some(session_info.deadline);
-        }
-
-        return None;
+        return session_info.settle_finalized_time();
     }
 
     /// Get action deadline
@@ -425,13 +877,8 @@ impl<T: Trait> Module<T> {
             Some(session) => session,
             None => return None,
         };
-        if session_info.status == SessionStatus::Action {
-            return Some(session_info.deadline);
-        } else if session_info.status == SessionStatus::Settle {
-            return Some(session_info.deadline + session_info.timeout);
-        } else {
-            return None;
-        }
+
+        return session_info.action_deadline();
     }
 
     /// Get session sequence number
@@ -446,6 +893,18 @@ impl<T: Trait> Module<T> {
         return Some(session_info.seq_num);
     }
 
+    /// Get the remaining blocks of the players in player order, `None` without a game clock
+    ///
+    /// Parameter:
+    /// `session_id`: Id of session
+    pub fn get_time_banks(session_id: T::Hash) -> Option<Vec<T::BlockNumber>> {
+        let session_info = match SessionInfoMap::<T>::get(session_id) {
+            Some(session) => session,
+            None => return None,
+        };
+        return session_info.clock.map(|clock| clock.banks);
+    }
+
 
     /// Get multi session app account id
     pub fn app_account() -> T::AccountId {
@@ -462,25 +921,26 @@ impl<T: Trait> Module<T> {
         state_proof: StateProofOf<T>
     ) -> Result<SessionInfoOf<T>, DispatchError> {
         let app_state = state_proof.app_state;
-        ensure!(
-            state_proof.sigs.len() as u8 == session_info.player_num,
-            "invalid number of players"
-        );
         let encoded = Self::encode_app_state(app_state.clone());
-        Self::valid_signers(state_proof.sigs, &encoded, session_info.players.clone())?;
-        ensure!(
-            session_info.status != SessionStatus::Finalized,
-            "app state is finalized"
-        );
-    
-        ensure!(
-            session_info.seq_num < app_state.seq_num,
-            "invalid sequence number"
-        );
-
-        session_info.seq_num = app_state.seq_num;
-        session_info.deadline = frame_system::Module::<T>::block_number() + session_info.timeout;
-        session_info.status = SessionStatus::Settle;
+        celer_app_core::valid_signers(&state_proof.sigs, &encoded, &session_info.players).map_err(Error::<T>::from)?;
+        session_info.intend_settle(app_state.seq_num, frame_system::Module::<T>::block_number()).map_err(Error::<T>::from)?;
+
+        Ok(session_info)
+    }
+
+    /// Finalize with an off-chain state co-signed as the final state
+    ///
+    /// Parameter:
+    /// `session_info`: Info of session state
+    /// `state_proof`: Off-chain app state signed as final by all players
+    fn agree_final_state(
+        mut session_info: SessionInfoOf<T>,
+        state_proof: StateProofOf<T>
+    ) -> Result<SessionInfoOf<T>, DispatchError> {
+        let app_state = state_proof.app_state;
+        let encoded = Self::encode_final_app_state(app_state.clone());
+        celer_app_core::valid_signers(&state_proof.sigs, &encoded, &session_info.players).map_err(Error::<T>::from)?;
+        session_info.finalize_by_agreement(app_state.seq_num).map_err(Error::<T>::from)?;
 
         Ok(session_info)
     }
@@ -492,74 +952,90 @@ impl<T: Trait> Module<T> {
     fn apply_action(
         mut session_info: SessionInfoOf<T>,
     ) -> Result<SessionInfoOf<T>, DispatchError> {
-        ensure!(
-            session_info.status != SessionStatus::Finalized,
-            "app state is finalized"
-        );
-
-        let block_number = frame_system::Module::<T>::block_number();
-        if session_info.status == SessionStatus::Settle && block_number > session_info.deadline {
-            session_info.seq_num = session_info.seq_num + 1;
-            session_info.deadline = block_number + session_info.timeout;
-            session_info.status = SessionStatus::Action;
-        } else {
-            ensure!(
-                session_info.status ==  SessionStatus::Action,
-                "app not in action mode"
-            );
-            session_info.seq_num = session_info.seq_num + 1;
-            session_info.deadline = block_number + session_info.timeout;
-            session_info.status = SessionStatus::Action;
-        }
+        session_info.apply_action(frame_system::Module::<T>::block_number()).map_err(Error::<T>::from)?;
 
         Ok(session_info)
     }
 
-    /// Verify off-chain state signatures
+    /// Take the blocks of an action from the time bank of the player who moved
     ///
     /// Parameters:
-    /// `signatures`: Signaturs from the players
-    /// `encoded`: Encoded app state
-    /// `signers`: AccountId of player
-    fn valid_signers(
-        signatures: Vec<<T as Trait>::Signature>,
-        encoded: &[u8],
-        signers: Vec<T::AccountId>,
-    ) -> Result<(), DispatchError> {
-        for i in 0..signers.len() {
+    /// `session_info`: Info of session state after the action
+    /// `player_index`: Index of the player to move before the action
+    fn charge_clock(
+        session_info: &mut SessionInfoOf<T>,
+        player_index: usize,
+    ) -> DispatchResult {
+        let block_number = frame_system::Module::<T>::block_number();
+        if let Some(clock) = session_info.clock.as_mut() {
             ensure!(
-                &signatures[i].verify(encoded, &signers[i]),
-                "Check co-sigs failed"
+                clock.charge(player_index, block_number),
+                Error::<T>::TimeBankExhausted
             );
+            // the next player has the rest of its time bank
+            session_info.deadline = block_number.saturating_add(session_info.action_period());
         }
 
         Ok(())
     }
 
-    /// Check whether account is asscending order
+    /// Encode app state signed by the players on this chain
     ///
     /// Parameter:
-    /// `palyers`: AccountId of players
-    fn is_ordered_account(
-        players: Vec<T::AccountId>
-    ) -> Result<(), DispatchError> {
-        let mut prev = &players[0];
-        for i in 1..players.len() {
-            ensure!(
-                prev < &players[i],
-                "player is not ascending order"
-            );
-            prev = &players[i];
-        }
+    /// `app_state`: app state
+    fn encode_app_state(
+        app_state: AppStateOf<T>
+    ) -> Vec<u8> {
+        let genesis_hash = frame_system::Module::<T>::block_hash(T::BlockNumber::zero());
+        Self::signing_payload(app_state, genesis_hash)
+    }
 
-        Ok(())
+    /// Encode app state signed by the players as the final state on this chain
+    ///
+    /// Parameter:
+    /// `app_state`: app state
+    fn encode_final_app_state(
+        app_state: AppStateOf<T>
+    ) -> Vec<u8> {
+        let genesis_hash = frame_system::Module::<T>::block_hash(T::BlockNumber::zero());
+        Self::final_signing_payload(app_state, genesis_hash)
+    }
+
+    /// Get the bytes of an app state the players sign off-chain
+    ///
+    /// The encoded app state is prefixed with the versioned domain separator of
+    /// this runtime module and chain, see `celer_app_core::state_signing_payload`.
+    ///
+    /// Parameters:
+    /// `app_state`: app state
+    /// `genesis_hash`: Hash of the genesis block of the chain
+    pub fn signing_payload(
+        app_state: AppStateOf<T>,
+        genesis_hash: T::Hash
+    ) -> Vec<u8> {
+        celer_app_core::state_signing_payload(MULTI_SESSION_APP_ID, &genesis_hash, &Self::encode_state(app_state))
     }
 
-    /// Encode app state
+    /// Get the bytes of an app state the players sign off-chain as the final state
+    ///
+    /// The encoded app state is prefixed with the final state domain separator of
+    /// this runtime module and chain, see `celer_app_core::final_state_signing_payload`.
+    ///
+    /// Parameters:
+    /// `app_state`: app state
+    /// `genesis_hash`: Hash of the genesis block of the chain
+    pub fn final_signing_payload(
+        app_state: AppStateOf<T>,
+        genesis_hash: T::Hash
+    ) -> Vec<u8> {
+        celer_app_core::final_state_signing_payload(MULTI_SESSION_APP_ID, &genesis_hash, &Self::encode_state(app_state))
+    }
+
+    /// Encode the fields of an app state
     ///
     /// Parameter:
     /// `app_state`: app state
-    fn encode_app_state(
+    fn encode_state(
         app_state: AppStateOf<T>
     ) -> Vec<u8> {
         let mut encoded = app_state.seq_num.encode();
@@ -567,7 +1043,7 @@ impl<T: Trait> Module<T> {
         encoded.extend(app_state.timeout.encode());
         encoded.extend(app_state.session_id.encode());
 
-        return encoded;
+        encoded
     }
 }
 
@@ -7,9 +7,9 @@ use frame_support::{
     parameter_types, weights::Weight
 };
 use frame_system as system;
-use sp_core::{sr25519, Pair, H256};
+use sp_core::{sr25519, Pair, H256, testing::KeyStore, traits::KeystoreExt};
 use pallet_balances;
-use sp_runtime::testing::Header;
+use sp_runtime::testing::{Header, TestXt};
 use sp_runtime::traits::{BlakeTwo256, IdentityLookup};
 use sp_runtime::Perbill;
 
@@ -46,6 +46,15 @@ parameter_types! {
     pub const MaximumBlockLength: u32 = 2 * 1024;
     pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
     pub const ExistentialDeposit: u64 = 1; // should be greater than zero
+    pub const RetentionPeriod: u64 = 10;
+    pub const MaxClearPerBlock: u32 = 2;
+    pub const MaxFinalizePerBlock: u32 = 2;
+    pub const UnsignedPriority: u64 = 100;
+    pub const SessionDeposit: SessionDepositConfig<u64> = SessionDepositConfig {
+        base: 10,
+        per_player: 2,
+        per_byte: 1,
+    };
 	pub const MinimumPeriod: u64 = 5;
 }
 
@@ -118,22 +127,46 @@ impl pallet_balances::Trait for TestRuntime {
     type WeightInfo = ();
 }
 
+pub(crate) type Extrinsic = TestXt<Call, ()>;
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for TestRuntime
+where
+    Call: From<LocalCall>,
+{
+    type OverarchingCall = Call;
+    type Extrinsic = Extrinsic;
+}
+
 impl Trait for TestRuntime {
     type Event = TestEvent;
     type Public = sr25519::Public;
     type Signature = sr25519::Signature;
+    type RetentionPeriod = RetentionPeriod;
+    type MaxClearPerBlock = MaxClearPerBlock;
+    type MaxFinalizePerBlock = MaxFinalizePerBlock;
+    type UnsignedPriority = UnsignedPriority;
+    type Currency = Balances;
+    type SessionDeposit = SessionDeposit;
+    type WeightInfo = ();
 }
 
 pub type MultiSessionApp = Module<TestRuntime>;
 pub type System = frame_system::Module<TestRuntime>;
-type Balances = pallet_balances::Module<TestRuntime>;
+pub type Balances = pallet_balances::Module<TestRuntime>;
 
 pub struct ExtBuilder;
 impl ExtBuilder {
     pub fn build() -> sp_io::TestExternalities {
-        let t = system::GenesisConfig::default()
+        let mut t = system::GenesisConfig::default()
             .build_storage::<TestRuntime>().unwrap();
-        let ext = sp_io::TestExternalities::new(t);
+        pallet_balances::GenesisConfig::<TestRuntime> {
+            balances: vec![
+                (account_pair("Alice").public(), 1000),
+                (account_pair("Bob").public(), 1000),
+            ],
+        }.assimilate_storage(&mut t).unwrap();
+        let mut ext = sp_io::TestExternalities::new(t);
+        ext.register_extension(KeystoreExt(KeyStore::new()));
         ext
     }
 }
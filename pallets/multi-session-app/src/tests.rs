@@ -1,11 +1,17 @@
 use super::*;
 use mock::*;
 use sp_core::{sr25519, Pair, H256};
-use frame_support::{assert_ok, assert_noop};
+use frame_support::{assert_ok, assert_noop, traits::OnInitialize};
+use frame_support::unsigned::ValidateUnsigned;
+use sp_core::offchain::{
+    testing::{TestOffchainExt, TestTransactionPoolExt}, OffchainExt, TransactionPoolExt,
+};
+use sp_runtime::traits::OffchainWorker;
 
 #[test]
 fn test_pass_initiate() {
     ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
         let alice_pair = account_pair("Alice");
         let bob_pair = account_pair("Bob");
         let (players, _) = get_sorted_peer(alice_pair.clone(), bob_pair.clone());
@@ -14,14 +20,152 @@ fn test_pass_initiate() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_ok!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone()
+            )
+        );
+
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
+        let expected_event = TestEvent::multi_app(RawEvent::AppInitiated(session_id, players.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_fail_initiate() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _) = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 1,
+            player_num: 3,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_noop!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayerNum
+        );
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 1,
+            player_num: 2,
+            players: vec![players[1], players[0]],
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_noop!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayers
+        );
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 1,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone()
+            )
+        );
+        assert_noop!(
             MultiSessionApp::session_initiate(
                 Origin::signed(players[0]),
                 initiate_request
+            ),
+            Error::<TestRuntime>::SessionAlreadyExists
+        );
+    })
+}
+
+#[test]
+fn test_pass_initiate_after_front_run_with_other_parameters() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // the opponent initiates first with the same nonce and players but no timeout
+        let front_run_request = SessionInitiateRequest {
+            nonce: 0,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 0,
+            time_bank: None,
+        };
+        assert_ok!(MultiSessionApp::session_initiate(
+            Origin::signed(players[1]),
+            front_run_request.clone()
+        ));
+        let initiate_request = SessionInitiateRequest {
+            nonce: 0,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_ok!(MultiSessionApp::session_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
+        assert_ne!(session_id, MultiSessionApp::get_session_id(&front_run_request));
+        let expected_event = TestEvent::multi_app(RawEvent::AppInitiated(session_id, players.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_fail_update_by_state_with_invalid_sig_num() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair) = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 1,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_ok!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone()
             )
         );
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
+
+        let mut state_proof = get_state_proof(1, 5, 2, session_id, players_pair);
+        state_proof.sigs.pop();
+        assert_noop!(
+            MultiSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidSignatureCount
+        );
     })
 }
 
@@ -37,7 +181,8 @@ fn test_pass_update_by_state_state_is_5() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -46,7 +191,7 @@ fn test_pass_update_by_state_state_is_5() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(1, 5, 2, session_id, players_pair);
     
         assert_ok!(
@@ -55,6 +200,8 @@ fn test_pass_update_by_state_state_is_5() {
                 state_proof
             )
         );
+        let expected_event = TestEvent::multi_app(RawEvent::IntendSettle(session_id, 1));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
         
         let session_info = MultiSessionApp::session_info(session_id).unwrap();
         let expected_session_info = SessionInfo {
@@ -65,6 +212,8 @@ fn test_pass_update_by_state_state_is_5() {
             timeout: 2,
             deadline: 3,
             status: SessionStatus::Settle,
+            clock: None,
+            cosigned_seq_num: Some(1),
         };
         assert_eq!(session_info, expected_session_info);
 
@@ -95,7 +244,8 @@ fn test_fail_update_by_action_before_settle_finalized_time() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -104,7 +254,7 @@ fn test_fail_update_by_action_before_settle_finalized_time() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(1, 5, 2, session_id, players_pair);
         assert_ok!(
             MultiSessionApp::update_by_state(
@@ -119,7 +269,7 @@ fn test_fail_update_by_action_before_settle_finalized_time() {
                 session_id,
                 1
             ),
-            "app not in action mode"
+            Error::<TestRuntime>::NotInActionMode
         );
     })
 }
@@ -135,7 +285,8 @@ fn test_pass_update_by_action_after_settle_finalized_time() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -144,7 +295,7 @@ fn test_pass_update_by_action_after_settle_finalized_time() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(1, 3, 2, session_id, players_pair);
         assert_ok!(
             MultiSessionApp::update_by_state(
@@ -159,11 +310,13 @@ fn test_pass_update_by_action_after_settle_finalized_time() {
 
         assert_ok!(
             MultiSessionApp::update_by_action(
-                Origin::signed(players[0]),
+                Origin::signed(players[1]),
                 session_id,
                 3
             )
         );
+        let expected_event = TestEvent::multi_app(RawEvent::ActionApplied(session_id, players[1], 2, 3));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
     })
 }
 
@@ -178,7 +331,8 @@ fn test_fail_update_by_state_with_invalid_sequence_number() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -187,7 +341,7 @@ fn test_fail_update_by_state_with_invalid_sequence_number() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let mut state_proof = get_state_proof(1, 3, 2, session_id, players_pair.clone());
         assert_ok!(
             MultiSessionApp::update_by_state(
@@ -202,7 +356,7 @@ fn test_fail_update_by_state_with_invalid_sequence_number() {
                 Origin::signed(players[0]),
                 state_proof
             ),
-            "invalid sequence number"
+            Error::<TestRuntime>::InvalidSeqNum
         ); 
     })
 }
@@ -218,7 +372,8 @@ fn test_fail_update_by_state_with_different_player_sigs() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -227,14 +382,14 @@ fn test_fail_update_by_state_with_different_player_sigs() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(1, 3, 2, session_id, vec![players_pair[0].clone(), account_pair("Carl")]);
         assert_noop!(
             MultiSessionApp::update_by_state(
                 Origin::signed(players[0]),
                 state_proof
             ),
-            "Check co-sigs failed"
+            Error::<TestRuntime>::InvalidSignature
         );
     })
 }
@@ -250,7 +405,8 @@ fn test_pass_update_by_state_with_valid_a_seq_sig() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -259,7 +415,7 @@ fn test_pass_update_by_state_with_valid_a_seq_sig() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let mut state_proof = get_state_proof(1, 5, 2, session_id, players_pair.clone());
     
         assert_ok!(
@@ -304,7 +460,8 @@ fn test_fail_update_by_action_after_finalized() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -313,7 +470,7 @@ fn test_fail_update_by_action_after_finalized() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(1, 2, 2, session_id, players_pair.clone());
     
         assert_ok!(
@@ -329,7 +486,7 @@ fn test_fail_update_by_action_after_finalized() {
                 session_id,
                 2
             ),
-            "app state is finalized"
+            Error::<TestRuntime>::AlreadyFinalized
         );
     })
 }
@@ -345,7 +502,8 @@ fn test_fail_update_by_state_after_finalized() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -354,7 +512,7 @@ fn test_fail_update_by_state_after_finalized() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let mut state_proof = get_state_proof(1, 2, 2, session_id, players_pair.clone());
         assert_ok!(
             MultiSessionApp::update_by_state(
@@ -369,7 +527,7 @@ fn test_fail_update_by_state_after_finalized() {
                 Origin::signed(players[0]),
                 state_proof
             ),
-            "app state is finalized"
+            Error::<TestRuntime>::AlreadyFinalized
         );
     })
 }
@@ -386,7 +544,8 @@ fn test_pass_finalize_on_action_timeout() {
             nonce: 1,
             player_num: 2,
             players: players.clone(),
-            timeout: 2
+            timeout: 2,
+            time_bank: None,
         };
         assert_ok!(
             MultiSessionApp::session_initiate(
@@ -395,7 +554,7 @@ fn test_pass_finalize_on_action_timeout() {
             )
         );
 
-        let session_id = MultiSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(1, 2, 2, session_id, players_pair.clone());
         assert_ok!(
             MultiSessionApp::update_by_state(
@@ -414,6 +573,590 @@ fn test_pass_finalize_on_action_timeout() {
     })
 }
 
+#[test]
+fn test_game_clock_time_banks() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair) = get_sorted_peer(alice_pair.clone(), bob_pair.clone());
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 1,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: Some(5),
+        };
+        assert_ok!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone()
+            )
+        );
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
+        assert_eq!(MultiSessionApp::get_time_banks(session_id), Some(vec![5, 5]));
+
+        // the clock of players[1] starts at the settle deadline 3
+        let state_proof = get_state_proof(1, 5, 2, session_id, players_pair.clone());
+        assert_ok!(
+            MultiSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        assert_eq!(MultiSessionApp::get_action_deadline(session_id), Some(8));
+
+        System::set_block_number(6);
+        assert_noop!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                5
+            ),
+            Error::<TestRuntime>::NotYourTurn
+        );
+        assert_ok!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                5
+            )
+        );
+        assert_eq!(MultiSessionApp::get_time_banks(session_id), Some(vec![5, 2]));
+        assert_eq!(MultiSessionApp::get_action_deadline(session_id), Some(11));
+
+        System::set_block_number(7);
+        assert_ok!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                5
+            )
+        );
+        assert_eq!(MultiSessionApp::get_time_banks(session_id), Some(vec![4, 2]));
+        assert_eq!(MultiSessionApp::get_action_deadline(session_id), Some(9));
+
+        // players[1] has 2 blocks left
+        System::set_block_number(9);
+        assert_noop!(
+            MultiSessionApp::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::DeadlineNotPassed
+        );
+
+        System::set_block_number(10);
+        assert_noop!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                5
+            ),
+            Error::<TestRuntime>::TimeBankExhausted
+        );
+        assert_ok!(
+            MultiSessionApp::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        assert_eq!(MultiSessionApp::get_status(session_id), Some(SessionStatus::Finalized));
+        let expected_event = TestEvent::multi_app(RawEvent::FinalizedOnTimeout(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::multi_app(RawEvent::TimeBankExhausted(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        // the bank of players[1] ran out, players[0] wins
+        assert_eq!(MultiSessionApp::get_state(session_id), Some(1));
+        let is_winner = |query_data: u8| MultiSessionApp::get_outcome(
+            MultiSessionArgsQueryOutcome {
+                session_id: session_id,
+                query_data: query_data
+            }.encode()
+        ).unwrap();
+        assert_eq!(is_winner(1), true.encode());
+        assert_eq!(is_winner(2), false.encode());
+    })
+}
+
+#[test]
+fn test_celer_app_query() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 1,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_ok!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone()
+            )
+        );
+
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(1, 5, 2, session_id, players_pair);
+        assert_ok!(
+            MultiSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+
+        assert_eq!(<MultiSessionApp as CelerAppQuery<H256, BlockNumber>>::is_finalized(session_id.encode()).unwrap(), false);
+        let args_query_outcome = MultiSessionArgsQueryOutcome {
+            session_id: session_id,
+            query_data: 5
+        };
+        assert_eq!(
+            <MultiSessionApp as CelerAppQuery<H256, BlockNumber>>::get_outcome(args_query_outcome.encode()).unwrap(),
+            true.encode()
+        );
+        assert_eq!(<MultiSessionApp as CelerAppQuery<H256, BlockNumber>>::get_state(session_id, 0), Some(vec![5]));
+        assert_eq!(<MultiSessionApp as CelerAppQuery<H256, BlockNumber>>::get_status(session_id), Some(SessionStatus::Settle));
+        assert_eq!(<MultiSessionApp as CelerAppQuery<H256, BlockNumber>>::get_settle_finalized_time(session_id), Some(3));
+        assert_eq!(<MultiSessionApp as CelerAppQuery<H256, BlockNumber>>::get_action_deadline(session_id), Some(5));
+        assert_eq!(<MultiSessionApp as CelerAppQuery<H256, BlockNumber>>::get_seq_num(session_id), Some(1));
+        assert_eq!(<MultiSessionApp as CelerAppQuery<H256, BlockNumber>>::get_status(H256::zero()), None);
+    })
+}
+
+#[test]
+fn test_initiate_reserves_deposit() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_session(0, players.clone(), players_pair);
+        // base + 2 players + 1 byte of state
+        assert_eq!(MultiSessionApp::deposit_of(session_id), Some((players[0], 15)));
+        assert_eq!(Balances::reserved_balance(players[0]), 15);
+        assert_eq!(Balances::free_balance(players[0]), 985);
+        assert_eq!(Balances::reserved_balance(players[1]), 0);
+
+        // initiator can not pay the deposit
+        let carl = account_pair("Carl").public();
+        let initiate_request = SessionInitiateRequest {
+            nonce: 1,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_noop!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(carl),
+                initiate_request
+            ),
+            pallet_balances::Error::<TestRuntime, pallet_balances::DefaultInstance>::InsufficientBalance
+        );
+    })
+}
+
+#[test]
+fn test_clear_session() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_session(0, players.clone(), players_pair.clone());
+        assert_noop!(
+            MultiSessionApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::NotFinalized
+        );
+        assert_noop!(
+            <MultiSessionApp as ConsumeOutcome<H256>>::consume_outcome(session_id),
+            Error::<TestRuntime>::NotFinalized
+        );
+
+        System::set_block_number(6);
+        assert_ok!(
+            MultiSessionApp::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        assert_eq!(MultiSessionApp::finalized_at(session_id), Some(6));
+        let expected_event = TestEvent::multi_app(RawEvent::FinalizedOnTimeout(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::multi_app(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_noop!(
+            MultiSessionApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::OutcomeNotConsumed
+        );
+
+        assert_ok!(<MultiSessionApp as ConsumeOutcome<H256>>::consume_outcome(session_id));
+        let expected_event = TestEvent::multi_app(RawEvent::OutcomeConsumed(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_eq!(MultiSessionApp::clear_queue(16), vec![session_id]);
+
+        System::set_block_number(15);
+        let stranger = account_pair("Carl").public();
+        assert_noop!(
+            MultiSessionApp::clear_session(
+                Origin::signed(stranger),
+                session_id
+            ),
+            Error::<TestRuntime>::NotPlayer
+        );
+        assert_noop!(
+            MultiSessionApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::RetentionPeriodNotPassed
+        );
+
+        System::set_block_number(16);
+        assert_ok!(
+            MultiSessionApp::clear_session(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        assert_eq!(MultiSessionApp::get_status(session_id), None);
+        assert_eq!(MultiSessionApp::finalized_at(session_id), None);
+        assert_eq!(MultiSessionApp::outcome_consumed(session_id), false);
+        assert_eq!(MultiSessionApp::deposit_of(session_id), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        let expected_event = TestEvent::multi_app(RawEvent::SessionCleared(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_noop!(
+            MultiSessionApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::SessionInfoNotExist
+        );
+
+        // queued id of the cleared app is skipped
+        MultiSessionApp::on_initialize(16);
+        assert_eq!(
+            System::events().iter().filter(|a| a.event == expected_event).count(),
+            1
+        );
+
+        // the cleared session can not be initiated again to replay its old states
+        assert_eq!(MultiSessionApp::is_cleared(session_id), true);
+        let initiate_request = SessionInitiateRequest {
+            nonce: 0,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_eq!(MultiSessionApp::get_session_id(&initiate_request), session_id);
+        assert_noop!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::SessionAlreadyExists
+        );
+        let state_proof = get_state_proof(1, 5, 2, session_id, players_pair);
+        assert_noop!(
+            MultiSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::SessionInfoNotExist
+        );
+    })
+}
+
+#[test]
+fn test_clear_sessions_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_session(nonce, players.clone(), players_pair.clone()))
+            .collect();
+
+        System::set_block_number(6);
+        for session_id in session_ids.iter() {
+            assert_ok!(
+                MultiSessionApp::finalize_on_action_timeout(
+                    Origin::signed(players[0]),
+                    *session_id
+                )
+            );
+            assert_ok!(<MultiSessionApp as ConsumeOutcome<H256>>::consume_outcome(*session_id));
+        }
+        assert_eq!(MultiSessionApp::clear_queue(16), session_ids);
+
+        // at most MaxClearPerBlock apps are cleared in a block
+        MultiSessionApp::on_initialize(16);
+        assert_eq!(MultiSessionApp::get_status(session_ids[0]), None);
+        assert_eq!(MultiSessionApp::get_status(session_ids[1]), None);
+        assert!(MultiSessionApp::get_status(session_ids[2]).is_some());
+        assert_eq!(MultiSessionApp::clear_queue(16), Vec::<H256>::new());
+        assert_eq!(MultiSessionApp::clear_queue(17), vec![session_ids[2]]);
+
+        assert_eq!(Balances::reserved_balance(players[0]), 15);
+
+        MultiSessionApp::on_initialize(17);
+        assert_eq!(MultiSessionApp::get_status(session_ids[2]), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        assert_eq!(MultiSessionApp::clear_queue(17), Vec::<H256>::new());
+    })
+}
+
+#[test]
+fn test_finalize_expired_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_session(nonce, players.clone(), players_pair.clone()))
+            .collect();
+        // settle period and action period end at block 5
+        assert_eq!(MultiSessionApp::deadline_queue(6), session_ids);
+
+        // the action moves the deadline of the first session to block 6
+        System::set_block_number(4);
+        assert_ok!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[1]),
+                session_ids[0],
+                0
+            )
+        );
+        assert_eq!(MultiSessionApp::deadline_queue(7), vec![session_ids[0]]);
+
+        // at most MaxFinalizePerBlock sessions are finalized in a block,
+        // the first session is skipped since its deadline moved
+        System::set_block_number(6);
+        MultiSessionApp::on_initialize(6);
+        assert_eq!(MultiSessionApp::get_status(session_ids[0]), Some(SessionStatus::Action));
+        assert_eq!(MultiSessionApp::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(MultiSessionApp::get_status(session_ids[2]), Some(SessionStatus::Settle));
+        assert_eq!(MultiSessionApp::deadline_queue(6), Vec::<H256>::new());
+        assert_eq!(MultiSessionApp::deadline_queue(7), vec![session_ids[0], session_ids[2]]);
+        let expected_event = TestEvent::multi_app(RawEvent::FinalizedOnTimeout(session_ids[1], players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        System::set_block_number(7);
+        MultiSessionApp::on_initialize(7);
+        assert_eq!(MultiSessionApp::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(MultiSessionApp::is_finalized(session_ids[2].encode()).unwrap(), true);
+        assert_eq!(MultiSessionApp::deadline_queue(7), Vec::<H256>::new());
+        let expected_event = TestEvent::multi_app(RawEvent::FinalizedOnTimeout(session_ids[0], players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+#[allow(deprecated)] // ValidateUnsigned
+fn test_offchain_worker_submits_finalization() {
+    let (offchain, _) = TestOffchainExt::new();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    let mut ext = ExtBuilder::build();
+    ext.register_extension(OffchainExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+        let session_ids: Vec<H256> = (0..4)
+            .map(|nonce| settle_session(nonce, players.clone(), players_pair.clone()))
+            .collect();
+
+        // the action moves the deadline of the first session to block 6
+        System::set_block_number(4);
+        assert_ok!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[1]),
+                session_ids[0],
+                0
+            )
+        );
+
+        // nothing is submitted before the action deadline passed
+        System::set_block_number(5);
+        MultiSessionApp::offchain_worker(5);
+        assert!(pool_state.read().transactions.is_empty());
+
+        // the sessions beyond MaxFinalizePerBlock are moved to the queue of the next block
+        System::set_block_number(6);
+        MultiSessionApp::on_initialize(6);
+        assert_eq!(MultiSessionApp::get_status(session_ids[0]), Some(SessionStatus::Action));
+        assert_eq!(MultiSessionApp::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(
+            MultiSessionApp::deadline_queue(7),
+            vec![session_ids[0], session_ids[2], session_ids[3]]
+        );
+
+        // on_initialize of the next block finalizes the first MaxFinalizePerBlock sessions of its queue,
+        // the off-chain worker only submits the finalization of the expired session behind them
+        MultiSessionApp::offchain_worker(6);
+        let tx = pool_state.write().transactions.pop().unwrap();
+        assert!(pool_state.read().transactions.is_empty());
+        let tx = Extrinsic::decode(&mut &*tx).unwrap();
+        assert_eq!(tx.signature, None);
+        let call = crate::Call::finalize_on_action_timeout_unsigned(session_ids[3]);
+        assert_eq!(tx.call, mock::Call::MultiSessionApp(call.clone()));
+
+        assert!(MultiSessionApp::validate_unsigned(TransactionSource::External, &call).is_ok());
+        assert_ok!(MultiSessionApp::finalize_on_action_timeout_unsigned(Origin::none(), session_ids[3]));
+        assert_eq!(MultiSessionApp::is_finalized(session_ids[3].encode()).unwrap(), true);
+        let expected_event = TestEvent::multi_app(RawEvent::FinalizedOnTimeout(session_ids[3], players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        // the finalization of a finalized session is stale
+        assert_eq!(
+            MultiSessionApp::validate_unsigned(TransactionSource::External, &call),
+            InvalidTransaction::Stale.into()
+        );
+        System::set_block_number(7);
+        MultiSessionApp::on_initialize(7);
+        assert_eq!(MultiSessionApp::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(MultiSessionApp::is_finalized(session_ids[2].encode()).unwrap(), true);
+        MultiSessionApp::offchain_worker(7);
+        assert!(pool_state.read().transactions.is_empty());
+    })
+}
+
+#[test]
+fn test_turn_order_without_game_clock() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // players[1] is to move after sequence number 1
+        let session_id = settle_session(0, players.clone(), players_pair);
+        System::set_block_number(4);
+        let stranger = account_pair("Carl").public();
+        assert_noop!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(stranger),
+                session_id,
+                5
+            ),
+            Error::<TestRuntime>::NotPlayer
+        );
+        assert_noop!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                5
+            ),
+            Error::<TestRuntime>::NotYourTurn
+        );
+        assert_ok!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                5
+            )
+        );
+        assert_noop!(
+            MultiSessionApp::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                5
+            ),
+            Error::<TestRuntime>::NotYourTurn
+        );
+
+        // players[0] does not move before the action deadline 6 and loses
+        System::set_block_number(7);
+        assert_ok!(
+            MultiSessionApp::finalize_on_action_timeout(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        let expected_event = TestEvent::multi_app(RawEvent::FinalizedOnTimeout(session_id, players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let exhausted_event = TestEvent::multi_app(RawEvent::TimeBankExhausted(session_id, players[0]));
+        assert!(!System::events().iter().any(|a| a.event == exhausted_event));
+        assert_eq!(MultiSessionApp::get_state(session_id), Some(2));
+    })
+}
+
+#[test]
+fn test_finalize_on_action_timeout_of_three_players() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let initiator = account_pair("Alice").public();
+        let mut players_pair = vec![account_pair("Alice"), account_pair("Bob"), account_pair("Carl")];
+        players_pair.sort_by_key(|pair| pair.public());
+        let players: Vec<AccountId> = players_pair.iter().map(|pair| pair.public()).collect();
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 0,
+            player_num: 3,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_ok!(
+            MultiSessionApp::session_initiate(
+                Origin::signed(initiator),
+                initiate_request.clone()
+            )
+        );
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(4, 5, 2, session_id, players_pair);
+        assert_ok!(
+            MultiSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+
+        // players[1] is to move after sequence number 4 and loses,
+        // a session of three players has no winning state so the state is kept
+        System::set_block_number(6);
+        assert_ok!(
+            MultiSessionApp::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        assert_eq!(MultiSessionApp::get_status(session_id), Some(SessionStatus::Finalized));
+        assert_eq!(MultiSessionApp::get_state(session_id), Some(5));
+        let expected_event = TestEvent::multi_app(RawEvent::FinalizedOnTimeout(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
 fn get_state_proof(
     seq: u128,
     state: u8,
@@ -429,12 +1172,162 @@ fn get_state_proof(
     };
 
     let encoded = MultiSessionApp::encode_app_state(app_state.clone());
-    let sig_1 = players_pair[0].sign(&encoded);
-    let sig_2 = players_pair[1].sign(&encoded);
+    let sigs = players_pair.iter().map(|pair| pair.sign(&encoded)).collect();
     let state_proof = StateProof {
         app_state: app_state,
-        sigs: vec![sig_1, sig_2]
+        sigs: sigs
     };
 
     return state_proof;
-}
\ No newline at end of file
+}
+
+fn settle_session(nonce: u128, players: Vec<AccountId>, players_pair: Vec<sr25519::Pair>) -> H256 {
+    let initiate_request = SessionInitiateRequest {
+        nonce: nonce,
+        player_num: 2,
+        players: players.clone(),
+        timeout: 2,
+        time_bank: None,
+    };
+    assert_ok!(
+        MultiSessionApp::session_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        )
+    );
+
+    let session_id = MultiSessionApp::get_session_id(&initiate_request);
+    let state_proof = get_state_proof(1, 5, 2, session_id, players_pair);
+    assert_ok!(
+        MultiSessionApp::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+
+    return session_id;
+}
+
+#[test]
+fn test_finalize_by_agreement() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 0,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_ok!(MultiSessionApp::session_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
+
+        // signatures of an ordinary state cannot finalize the session
+        let state_proof = get_state_proof(2, 5, 2, session_id, players_pair.clone());
+        assert_noop!(
+            MultiSessionApp::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidSignature
+        );
+
+        // the final state is finalized in the same block from Idle
+        let state_proof = get_final_state_proof(2, 5, 2, session_id, players_pair.clone());
+        assert_ok!(MultiSessionApp::finalize_by_agreement(
+            Origin::signed(players[0]),
+            state_proof
+        ));
+        assert_eq!(MultiSessionApp::get_status(session_id), Some(SessionStatus::Finalized));
+        assert_eq!(MultiSessionApp::get_state(session_id), Some(5));
+        assert_eq!(MultiSessionApp::get_seq_num(session_id), Some(2));
+        assert_eq!(MultiSessionApp::is_finalized(session_id.encode()).unwrap(), true);
+        assert_eq!(MultiSessionApp::finalized_at(session_id), Some(1));
+        let expected_event = TestEvent::multi_app(RawEvent::FinalizedByAgreement(session_id, 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        let state_proof = get_final_state_proof(3, 5, 2, session_id, players_pair);
+        assert_noop!(
+            MultiSessionApp::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::AlreadyFinalized
+        );
+    })
+}
+
+fn get_final_state_proof(
+    seq: u128,
+    state: u8,
+    timeout: BlockNumber,
+    session_id: H256,
+    players_pair: Vec<sr25519::Pair>
+) -> StateProof<BlockNumber, H256, Signature> {
+    let app_state = AppState {
+        seq_num: seq,
+        state: state,
+        timeout: timeout,
+        session_id: session_id
+    };
+    let encoded = MultiSessionApp::encode_final_app_state(app_state.clone());
+    let sigs = players_pair.iter().map(|pair| pair.sign(&encoded)).collect();
+
+    StateProof {
+        app_state: app_state,
+        sigs: sigs,
+    }
+}
+
+#[test]
+fn test_fail_update_by_single_signed_state() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = SessionInitiateRequest {
+            nonce: 0,
+            player_num: 2,
+            players: players.clone(),
+            timeout: 2,
+            time_bank: None,
+        };
+        assert_ok!(MultiSessionApp::session_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = MultiSessionApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(1, 5, 2, session_id, players_pair.clone());
+        assert_ok!(MultiSessionApp::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        ));
+
+        // an arbitrary state signed only by the player to move is rejected
+        for &state in [1u8, 2u8, 7u8].iter() {
+            let app_state = AppState {
+                seq_num: 2,
+                state: state,
+                timeout: 2,
+                session_id: session_id
+            };
+            let encoded = MultiSessionApp::encode_app_state(app_state.clone());
+            let state_proof = StateProof {
+                app_state: app_state,
+                sigs: vec![players_pair[1].sign(&encoded)],
+            };
+            assert_noop!(
+                MultiSessionApp::update_by_state(Origin::signed(players[1]), state_proof),
+                Error::<TestRuntime>::InvalidSignatureCount
+            );
+        }
+        assert_eq!(MultiSessionApp::get_state(session_id), Some(5));
+        assert_eq!(MultiSessionApp::get_seq_num(session_id), Some(1));
+        assert_eq!(MultiSessionApp::finalized_at(session_id), None);
+    })
+}
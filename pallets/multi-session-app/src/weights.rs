@@ -0,0 +1,132 @@
+//! Weights for multi_session_app
+//!
+//! These weights are estimates written by hand, they were not generated by the benchmark CLI.
+//! Before a runtime relies on `SubstrateWeight`, regenerate this file with the benchmarks in
+//! `benchmarking.rs`:
+//! `./target/release/node benchmark --chain dev --pallet multi_session_app --extrinsic '*' --steps 50 --repeat 20 --output`
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::weights::{Weight, constants::RocksDbWeight};
+use frame_support::traits::Get;
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for multi_session_app
+pub trait WeightInfo {
+    fn session_initiate(n: u32) -> Weight;
+    fn update_by_state(n: u32) -> Weight;
+    fn finalize_by_agreement(n: u32) -> Weight;
+    fn update_by_action() -> Weight;
+    fn finalize_on_action_timeout() -> Weight;
+    fn clear_session() -> Weight;
+    fn finalize_expired(n: u32) -> Weight;
+    fn on_initialize(n: u32) -> Weight;
+}
+
+/// Weights for multi_session_app using the Substrate node and recommended hardware
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Trait> WeightInfo for SubstrateWeight<T> {
+    fn session_initiate(n: u32) -> Weight {
+        (18_400_000 as Weight)
+            .saturating_add((700_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+    fn update_by_state(n: u32) -> Weight {
+        (2_500_000 as Weight)
+            .saturating_add((23_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_by_agreement(n: u32) -> Weight {
+        (2_500_000 as Weight)
+            .saturating_add((27_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn update_by_action() -> Weight {
+        (25_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_on_action_timeout() -> Weight {
+        (17_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn clear_session() -> Weight {
+        (28_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(5 as Weight))
+            .saturating_add(T::DbWeight::get().writes(6 as Weight))
+    }
+    fn finalize_expired(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((17_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+    fn on_initialize(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((21_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((6 as Weight).saturating_mul(n as Weight)))
+    }
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+    fn session_initiate(n: u32) -> Weight {
+        (18_400_000 as Weight)
+            .saturating_add((700_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(3 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(3 as Weight))
+    }
+    fn update_by_state(n: u32) -> Weight {
+        (2_500_000 as Weight)
+            .saturating_add((23_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_by_agreement(n: u32) -> Weight {
+        (2_500_000 as Weight)
+            .saturating_add((27_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn update_by_action() -> Weight {
+        (25_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_on_action_timeout() -> Weight {
+        (17_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn clear_session() -> Weight {
+        (28_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(5 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(6 as Weight))
+    }
+    fn finalize_expired(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((17_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+    fn on_initialize(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((21_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((6 as Weight).saturating_mul(n as Weight)))
+    }
+}
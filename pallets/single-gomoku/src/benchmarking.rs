@@ -0,0 +1,349 @@
+//! Single gomoku benchmarking
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use super::*;
+use celer_app_core::benchmarking::{co_sign, sorted_players, sr25519};
+use frame_benchmarking::benchmarks;
+use frame_support::traits::OnInitialize;
+use frame_system::RawOrigin;
+use sp_runtime::traits::Bounded;
+
+/// Endow the initiator with enough balance for the storage deposit
+fn fund<T: Trait>(who: &T::AccountId) {
+    T::Currency::make_free_balance_be(who, BalanceOf::<T>::max_value() / 2u32.into());
+}
+
+fn initiate_app<T: Trait>(
+    nonce: u128,
+    players: Vec<T::AccountId>,
+    board_size: u8,
+    rule_set: RuleSet,
+) -> Result<T::Hash, &'static str> {
+    let initiate_request = AppInitiateRequestOf::<T> {
+        nonce: nonce,
+        players: players.clone(),
+        timeout: 2u32.into(),
+        min_stone_offchain: 5,
+        max_stone_onchain: 5,
+        board_size: board_size,
+        win_length: DEFAULT_WIN_LENGTH,
+        rule_set: rule_set,
+        time_bank: None,
+        unilateral: false,
+    };
+    let session_id = Module::<T>::get_session_id(&initiate_request);
+    fund::<T>(&players[0]);
+    Module::<T>::app_initiate(RawOrigin::Signed(players[0].clone()).into(), initiate_request)?;
+
+    Ok(session_id)
+}
+
+/// Board without winner, black to move and stones of both players around the center
+fn board_state() -> Vec<u8> {
+    let mut board_state = vec![0; 227];
+    board_state[1] = 1; // turn
+    for i in 0..4 {
+        board_state[state_index(DEFAULT_BOARD_SIZE, 6, 5 + 2 * i)] = 1;
+        board_state[state_index(DEFAULT_BOARD_SIZE, 8, 5 + 2 * i)] = 2;
+    }
+
+    board_state
+}
+
+/// Largest board size whose board state is not longer than `len`
+fn board_size_of(len: u32) -> u8 {
+    (DEFAULT_WIN_LENGTH..=MAX_BOARD_SIZE).rev()
+        .find(|&board_size| board_state_len(board_size) as u32 <= len)
+        .unwrap_or(DEFAULT_WIN_LENGTH)
+}
+
+/// Empty cell next to the center where black moves on the dense board
+fn move_cell(board_size: u8) -> (u8, u8) {
+    let x = board_size / 2;
+    let y = if (3 * x as usize) % 4 < 2 { x } else { x + 1 };
+    (x, y)
+}
+
+/// Board filled with stones except for the move of black and the last stones, black to move
+///
+/// Every row, column and diagonal repeats two stones of a color, so no run is longer than two
+/// and the board validation scans every cell for a winning run.
+fn dense_board(board_size: u8) -> Vec<u8> {
+    let mut board_state = vec![0; board_state_len(board_size)];
+    board_state[1] = 1; // turn
+    for x in 0..board_size {
+        for y in 0..board_size {
+            let color = if (x as usize + 2 * y as usize) % 4 < 2 { 1 } else { 2 };
+            board_state[state_index(board_size, x, y)] = color;
+        }
+    }
+    let (x, y) = move_cell(board_size);
+    board_state[state_index(board_size, x, y)] = 0;
+    // leave the board one more empty cell and as many black stones as white ones
+    let black_num = board_state[2..].iter().filter(|&&cell| cell == 1).count();
+    let white_num = board_state[2..].iter().filter(|&&cell| cell == 2).count();
+    let mut colors = vec![2];
+    if black_num == white_num {
+        colors.push(1);
+    }
+    for color in colors {
+        if let Some(index) = (2..board_state.len()).rev().find(|&index| board_state[index] == color) {
+            board_state[index] = 0;
+        }
+    }
+
+    board_state
+}
+
+fn get_state_proof<T: Trait>(
+    nonce: u128,
+    seq_num: u128,
+    board_state: Vec<u8>,
+    session_id: T::Hash,
+    keys: &[sr25519::Public],
+) -> StateProofOf<T>
+where
+    <T as Trait>::Signature: From<sr25519::Signature>,
+{
+    let app_state = AppStateOf::<T> {
+        nonce: nonce,
+        seq_num: seq_num,
+        board_state: board_state,
+        timeout: 2u32.into(),
+        session_id: session_id,
+    };
+    let encoded = Module::<T>::encode_app_state(app_state.clone());
+
+    StateProofOf::<T> {
+        app_state: app_state,
+        sigs: co_sign(keys, &encoded),
+    }
+}
+
+/// State proof of an app state signed as the final state
+fn get_final_state_proof<T: Trait>(
+    nonce: u128,
+    seq_num: u128,
+    board_state: Vec<u8>,
+    session_id: T::Hash,
+    keys: &[sr25519::Public],
+) -> StateProofOf<T>
+where
+    <T as Trait>::Signature: From<sr25519::Signature>,
+{
+    let app_state = AppStateOf::<T> {
+        nonce: nonce,
+        seq_num: seq_num,
+        board_state: board_state,
+        timeout: 2u32.into(),
+        session_id: session_id,
+    };
+    let encoded = Module::<T>::encode_final_app_state(app_state.clone());
+
+    StateProofOf::<T> {
+        app_state: app_state,
+        sigs: co_sign(keys, &encoded),
+    }
+}
+
+/// Initiate an app and settle it with a non-terminal state
+fn settle_app<T: Trait>(
+    nonce: u128,
+    players: Vec<T::AccountId>,
+    keys: &[sr25519::Public],
+) -> Result<T::Hash, &'static str>
+where
+    <T as Trait>::Signature: From<sr25519::Signature>,
+{
+    let session_id = initiate_app::<T>(nonce, players.clone(), DEFAULT_BOARD_SIZE, RuleSet::Freestyle)?;
+    let state_proof = get_state_proof::<T>(nonce, 1, board_state(), session_id, keys);
+    Module::<T>::update_by_state(RawOrigin::Signed(players[0].clone()).into(), state_proof)?;
+
+    Ok(session_id)
+}
+
+benchmarks! {
+    where_clause { where
+        T::AccountId: From<sr25519::Public>,
+        <T as Trait>::Signature: From<sr25519::Signature>,
+    }
+
+    _ { }
+
+    app_initiate {
+        let (players, _) = sorted_players::<T::AccountId>(2);
+        let initiate_request = AppInitiateRequestOf::<T> {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2u32.into(),
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        let session_id = Module::<T>::get_session_id(&initiate_request);
+        fund::<T>(&players[0]);
+    }: _(RawOrigin::Signed(players[0].clone()), initiate_request)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Idle));
+    }
+
+    update_by_state {
+        let b in (board_state_len(DEFAULT_WIN_LENGTH) as u32) .. (board_state_len(MAX_BOARD_SIZE) as u32);
+        let board_size = board_size_of(b);
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        // black wins only with exactly five stones under renju
+        let session_id = initiate_app::<T>(0, players.clone(), board_size, RuleSet::Renju)?;
+        let state_proof = get_state_proof::<T>(0, 1, dense_board(board_size), session_id, &keys);
+    }: _(RawOrigin::Signed(players[0].clone()), state_proof)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Settle));
+    }
+
+    finalize_by_agreement {
+        let b in (board_state_len(DEFAULT_WIN_LENGTH) as u32) .. (board_state_len(MAX_BOARD_SIZE) as u32);
+        let board_size = board_size_of(b);
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = initiate_app::<T>(0, players.clone(), board_size, RuleSet::Renju)?;
+        let state_proof = get_final_state_proof::<T>(0, 1, dense_board(board_size), session_id, &keys);
+    }: _(RawOrigin::Signed(players[0].clone()), state_proof)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+    }
+
+    update_by_action {
+        let b in (board_state_len(DEFAULT_WIN_LENGTH) as u32) .. (board_state_len(MAX_BOARD_SIZE) as u32);
+        let board_size = board_size_of(b);
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = initiate_app::<T>(0, players.clone(), board_size, RuleSet::Renju)?;
+        let state_proof = get_state_proof::<T>(0, 1, dense_board(board_size), session_id, &keys);
+        let block_number = frame_system::Module::<T>::block_number();
+        Module::<T>::update_by_state(RawOrigin::Signed(players[0].clone()).into(), state_proof)?;
+        // settle period is over
+        frame_system::Module::<T>::set_block_number(block_number + 3u32.into());
+        // a move of black under renju is checked for forbidden patterns in all directions
+        let (x, y) = move_cell(board_size);
+    }: _(RawOrigin::Signed(players[0].clone()), session_id, vec![x, y])
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Action));
+    }
+
+    finalize_on_action_timeout {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = initiate_app::<T>(0, players.clone(), DEFAULT_BOARD_SIZE, RuleSet::Freestyle)?;
+        let state_proof = get_state_proof::<T>(0, 1, board_state(), session_id, &keys);
+        let block_number = frame_system::Module::<T>::block_number();
+        Module::<T>::update_by_state(RawOrigin::Signed(players[0].clone()).into(), state_proof)?;
+        // settle period and action period are over
+        frame_system::Module::<T>::set_block_number(block_number + 5u32.into());
+    }: _(RawOrigin::Signed(players[0].clone()), session_id)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+    }
+    resign {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = settle_app::<T>(0, players.clone(), &keys)?;
+    }: _(RawOrigin::Signed(players[0].clone()), session_id)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+    }
+
+    offer_draw {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = settle_app::<T>(0, players.clone(), &keys)?;
+    }: _(RawOrigin::Signed(players[0].clone()), session_id)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Settle));
+    }
+
+    accept_draw {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = settle_app::<T>(0, players.clone(), &keys)?;
+        Module::<T>::offer_draw(RawOrigin::Signed(players[0].clone()).into(), session_id)?;
+    }: _(RawOrigin::Signed(players[1].clone()), session_id)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+    }
+
+    clear_session {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = settle_app::<T>(0, players.clone(), &keys)?;
+        let block_number = frame_system::Module::<T>::block_number();
+        // settle period and action period are over
+        frame_system::Module::<T>::set_block_number(block_number + 5u32.into());
+        Module::<T>::finalize_on_action_timeout(RawOrigin::Signed(players[0].clone()).into(), session_id)?;
+        Module::<T>::consume_outcome(session_id)?;
+        // retention period is over
+        frame_system::Module::<T>::set_block_number(block_number + 5u32.into() + T::RetentionPeriod::get());
+    }: _(RawOrigin::Signed(players[0].clone()), session_id)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), None);
+    }
+
+    finalize_expired {
+        let n in 0 .. T::MaxFinalizePerBlock::get();
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let mut session_ids = vec![];
+        for nonce in 0 .. n {
+            session_ids.push(settle_app::<T>(nonce as u128, players.clone(), &keys)?);
+        }
+        // first block after the settle period and action period
+        let expire_at = frame_system::Module::<T>::block_number() + 5u32.into();
+        frame_system::Module::<T>::set_block_number(expire_at);
+    }: { Module::<T>::on_initialize(expire_at); }
+    verify {
+        for session_id in session_ids {
+            assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+        }
+    }
+
+    on_initialize {
+        let n in 0 .. T::MaxClearPerBlock::get();
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let mut session_ids = vec![];
+        for nonce in 0 .. n {
+            session_ids.push(settle_app::<T>(nonce as u128, players.clone(), &keys)?);
+        }
+        let block_number = frame_system::Module::<T>::block_number();
+        // settle period and action period are over
+        frame_system::Module::<T>::set_block_number(block_number + 5u32.into());
+        for session_id in session_ids.iter() {
+            Module::<T>::finalize_on_action_timeout(RawOrigin::Signed(players[0].clone()).into(), *session_id)?;
+            Module::<T>::consume_outcome(*session_id)?;
+        }
+        let clear_at = block_number + 5u32.into() + T::RetentionPeriod::get();
+    }: { Module::<T>::on_initialize(clear_at); }
+    verify {
+        for session_id in session_ids {
+            assert_eq!(Module::<T>::get_status(session_id), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{ExtBuilder, TestRuntime};
+    use frame_support::assert_ok;
+
+    #[test]
+    fn test_benchmarks() {
+        ExtBuilder::build().execute_with(|| {
+            assert_ok!(test_benchmark_app_initiate::<TestRuntime>());
+            assert_ok!(test_benchmark_update_by_state::<TestRuntime>());
+            assert_ok!(test_benchmark_finalize_by_agreement::<TestRuntime>());
+            assert_ok!(test_benchmark_update_by_action::<TestRuntime>());
+            assert_ok!(test_benchmark_finalize_on_action_timeout::<TestRuntime>());
+            assert_ok!(test_benchmark_resign::<TestRuntime>());
+            assert_ok!(test_benchmark_offer_draw::<TestRuntime>());
+            assert_ok!(test_benchmark_accept_draw::<TestRuntime>());
+            assert_ok!(test_benchmark_clear_session::<TestRuntime>());
+            assert_ok!(test_benchmark_finalize_expired::<TestRuntime>());
+            assert_ok!(test_benchmark_on_initialize::<TestRuntime>());
+        });
+    }
+}
@@ -0,0 +1,262 @@
+//! Fuzzing of the dispatchables with arbitrary state proofs and actions
+
+#![cfg(test)]
+
+use super::*;
+use mock::*;
+use sp_core::{sr25519, Pair, H256};
+use frame_support::{assert_ok, traits::OnInitialize};
+use proptest::prelude::*;
+
+/// A call to the runtime module with arbitrary input
+#[derive(Clone, Debug)]
+enum Op {
+    /// Initiate an app of arbitrary signers, board config and time bank
+    Initiate {
+        nonce: u128,
+        players: Vec<usize>,
+        timeout: u64,
+        board_size: u8,
+        win_length: u8,
+        time_bank: Option<u64>,
+    },
+    /// Update by a state proof signed by the first `sig_num` signers
+    UpdateByState { nonce: u128, seq_num: u128, board_state: Vec<u8>, timeout: u64, sig_num: usize },
+    /// Finalize by a state proof signed as final by the first `sig_num` signers
+    FinalizeByAgreement { nonce: u128, seq_num: u128, board_state: Vec<u8>, timeout: u64, sig_num: usize },
+    /// Update by a state proof decoded from arbitrary bytes
+    UpdateByEncodedState(Vec<u8>),
+    UpdateByAction { signer: usize, action: Vec<u8> },
+    FinalizeOnActionTimeout,
+    FinalizeOnActionTimeoutUnsigned,
+    Resign { signer: usize },
+    OfferDraw { signer: usize },
+    AcceptDraw { signer: usize },
+    ConsumeOutcome,
+    ClearSession { signer: usize },
+    Query { key: u8, query_data: GameQuery, args: Vec<u8> },
+    /// Advance blocks and run `on_initialize` of each block
+    AdvanceBlocks(u64),
+}
+
+fn timeout() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        4 => 0u64..4,
+        1 => any::<u64>(),
+    ]
+}
+
+fn board_state(board_size: u8) -> impl Strategy<Value = Vec<u8>> {
+    let cells = board_size as usize * board_size as usize;
+    prop_oneof![
+        // board of valid length with a few stones, any winner and turn
+        3 => (0u8..4, 0u8..4, prop::collection::vec((0usize..cells, 0u8..4), 0..16))
+            .prop_map(|(winner, turn, stones)| {
+                let mut board_state = vec![0; board_state_len(board_size)];
+                board_state[0] = winner;
+                board_state[1] = turn;
+                for (index, stone) in stones {
+                    board_state[2 + index] = stone;
+                }
+                board_state
+            }),
+        // arbitrary bytes of any length
+        1 => prop::collection::vec(any::<u8>(), 0..board_state_len(board_size) + 8),
+    ]
+}
+
+fn game_query() -> impl Strategy<Value = GameQuery> {
+    prop_oneof![
+        (0u8..4).prop_map(GameQuery::IsWinner),
+        Just(GameQuery::Outcome),
+        (0u8..4, any::<u128>())
+            .prop_map(|(player_index, total)| GameQuery::Payout { player_index, total }),
+    ]
+}
+
+fn op(board_size: u8) -> impl Strategy<Value = Op> {
+    prop_oneof![
+        1 => (
+            0u128..2,
+            prop::collection::vec(0usize..3, 0..4),
+            timeout(),
+            0u8..MAX_BOARD_SIZE + 2,
+            0u8..8,
+            prop::option::of(timeout()),
+        )
+            .prop_map(|(nonce, players, timeout, board_size, win_length, time_bank)| {
+                Op::Initiate { nonce, players, timeout, board_size, win_length, time_bank }
+            }),
+        4 => (0u128..2, 0u128..6, board_state(board_size), timeout(), 0usize..4)
+            .prop_map(|(nonce, seq_num, board_state, timeout, sig_num)| {
+                Op::UpdateByState { nonce, seq_num, board_state, timeout, sig_num }
+            }),
+        1 => (0u128..2, 0u128..6, board_state(board_size), timeout(), 0usize..4)
+            .prop_map(|(nonce, seq_num, board_state, timeout, sig_num)| {
+                Op::FinalizeByAgreement { nonce, seq_num, board_state, timeout, sig_num }
+            }),
+        1 => prop::collection::vec(any::<u8>(), 0..512).prop_map(Op::UpdateByEncodedState),
+        4 => (0usize..3, prop::collection::vec(0u8..MAX_BOARD_SIZE + 2, 0..4))
+            .prop_map(|(signer, action)| Op::UpdateByAction { signer, action }),
+        1 => Just(Op::FinalizeOnActionTimeout),
+        1 => Just(Op::FinalizeOnActionTimeoutUnsigned),
+        1 => (0usize..3).prop_map(|signer| Op::Resign { signer }),
+        1 => (0usize..3).prop_map(|signer| Op::OfferDraw { signer }),
+        1 => (0usize..3).prop_map(|signer| Op::AcceptDraw { signer }),
+        1 => Just(Op::ConsumeOutcome),
+        1 => (0usize..3).prop_map(|signer| Op::ClearSession { signer }),
+        1 => (0u8..4, game_query(), prop::collection::vec(any::<u8>(), 0..40))
+            .prop_map(|(key, query_data, args)| Op::Query { key, query_data, args }),
+        2 => (1u64..12).prop_map(Op::AdvanceBlocks),
+    ]
+}
+
+/// Apply a call, errors are expected but a panic fails the case
+fn apply(op: Op, session_id: H256, signers: &[sr25519::Pair]) {
+    let origin = |signer: usize| Origin::signed(signers[signer].public());
+    match op {
+        Op::Initiate { nonce, players, timeout, board_size, win_length, time_bank } => {
+            let initiate_request = AppInitiateRequest {
+                nonce: nonce,
+                players: players.iter().map(|&signer| signers[signer].public()).collect(),
+                timeout: timeout,
+                min_stone_offchain: 5,
+                max_stone_onchain: 5,
+                board_size: board_size,
+                win_length: win_length,
+                rule_set: RuleSet::Freestyle,
+                time_bank: time_bank,
+                unilateral: false,
+            };
+            let _ = SingleGomoku::app_initiate(origin(0), initiate_request);
+        }
+        Op::UpdateByState { nonce, seq_num, board_state, timeout, sig_num } => {
+            let app_state = AppState {
+                nonce: nonce,
+                seq_num: seq_num,
+                board_state: board_state,
+                timeout: timeout,
+                session_id: session_id,
+            };
+            let encoded = SingleGomoku::encode_app_state(app_state.clone());
+            let sigs = (0..sig_num).map(|i| signers[i % signers.len()].sign(&encoded)).collect();
+            let state_proof = StateProof {
+                app_state: app_state,
+                sigs: sigs,
+            };
+            let _ = SingleGomoku::update_by_state(origin(0), state_proof);
+        }
+        Op::FinalizeByAgreement { nonce, seq_num, board_state, timeout, sig_num } => {
+            let app_state = AppState {
+                nonce: nonce,
+                seq_num: seq_num,
+                board_state: board_state,
+                timeout: timeout,
+                session_id: session_id,
+            };
+            let encoded = SingleGomoku::encode_final_app_state(app_state.clone());
+            let sigs = (0..sig_num).map(|i| signers[i % signers.len()].sign(&encoded)).collect();
+            let state_proof = StateProof {
+                app_state: app_state,
+                sigs: sigs,
+            };
+            let _ = SingleGomoku::finalize_by_agreement(origin(0), state_proof);
+        }
+        Op::UpdateByEncodedState(bytes) => {
+            if let Ok(state_proof) = StateProofOf::<TestRuntime>::decode(&mut &bytes[..]) {
+                let _ = SingleGomoku::update_by_state(origin(0), state_proof);
+            }
+        }
+        Op::UpdateByAction { signer, action } => {
+            let _ = SingleGomoku::update_by_action(origin(signer), session_id, action);
+        }
+        Op::FinalizeOnActionTimeout => {
+            let _ = SingleGomoku::finalize_on_action_timeout(origin(0), session_id);
+        }
+        Op::FinalizeOnActionTimeoutUnsigned => {
+            let _ = SingleGomoku::finalize_on_action_timeout_unsigned(Origin::none(), session_id);
+        }
+        Op::Resign { signer } => {
+            let _ = SingleGomoku::resign(origin(signer), session_id);
+        }
+        Op::OfferDraw { signer } => {
+            let _ = SingleGomoku::offer_draw(origin(signer), session_id);
+        }
+        Op::AcceptDraw { signer } => {
+            let _ = SingleGomoku::accept_draw(origin(signer), session_id);
+        }
+        Op::ConsumeOutcome => {
+            let _ = <SingleGomoku as ConsumeOutcome<H256>>::consume_outcome(session_id);
+        }
+        Op::ClearSession { signer } => {
+            let _ = SingleGomoku::clear_session(origin(signer), session_id);
+        }
+        Op::Query { key, query_data, args } => {
+            let args_query_outcome = SingleGomokuArgsQueryOutcome {
+                session_id: session_id,
+                version: GAME_QUERY_VERSION,
+                query_data: query_data,
+            };
+            let _ = SingleGomoku::get_state(session_id, key);
+            let _ = SingleGomoku::is_finalized(session_id.encode());
+            let _ = SingleGomoku::get_outcome(args_query_outcome.encode());
+            let _ = SingleGomoku::is_finalized(args.clone());
+            let _ = SingleGomoku::get_outcome(args);
+        }
+        Op::AdvanceBlocks(n) => {
+            for _ in 0 .. n {
+                let block_number = System::block_number() + 1;
+                System::set_block_number(block_number);
+                SingleGomoku::on_initialize(block_number);
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn fuzz_dispatchables_do_not_panic(
+        min_stone_offchain in 0u8..6,
+        max_stone_onchain in 0u8..6,
+        unilateral in any::<bool>(),
+        time_bank in prop::option::of(0u64..8),
+        rule_set in prop_oneof![Just(RuleSet::Freestyle), Just(RuleSet::Standard), Just(RuleSet::Renju)],
+        (board_size, win_length, ops) in (MIN_BOARD_SIZE..=MAX_BOARD_SIZE).prop_flat_map(|board_size| (
+            Just(board_size),
+            MIN_WIN_LENGTH..=board_size.min(6),
+            prop::collection::vec(op(board_size), 1..32),
+        )),
+    ) {
+        ExtBuilder::build().execute_with(|| {
+            System::set_block_number(1);
+            let (players, mut signers)
+                = get_sorted_peer(account_pair("Alice"), account_pair("Bob"));
+            signers.push(account_pair("Carl"));
+
+            let initiate_request = AppInitiateRequest {
+                nonce: 0,
+                players: players.clone(),
+                timeout: 2,
+                min_stone_offchain: min_stone_offchain,
+                max_stone_onchain: max_stone_onchain,
+                board_size: board_size,
+                win_length: win_length,
+                rule_set: rule_set,
+                time_bank: time_bank,
+                unilateral: unilateral,
+            };
+            assert_ok!(SingleGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone())
+            );
+            let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+            for op in ops {
+                apply(op, session_id, &signers);
+                check_gomoku_state_invariants();
+            }
+        });
+    }
+}
@@ -5,27 +5,55 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+mod benchmarking;
+mod fuzz;
+pub mod weights;
+
 use codec::{Decode, Encode};
 use frame_support::{
     decl_module, decl_storage, decl_event, decl_error, ensure,
     storage::StorageMap,
-    traits::Get,
+    traits::{Currency, Get, ReservableCurrency},
+    weights::Weight,
+};
+use frame_system::{
+    self as system, ensure_none, ensure_signed,
+    offchain::{SendTransactionTypes, SubmitTransaction},
+};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionPriority, TransactionSource, TransactionValidity,
 };
-use frame_system::{self as system, ensure_signed};
 use sp_runtime::traits::{
     Hash, IdentifyAccount, 
-    Member, Verify, Zero, AccountIdConversion, 
+    Member, Verify, Zero, AccountIdConversion, AtLeast32BitUnsigned, Saturating,
 };
 use sp_runtime::{ModuleId, RuntimeDebug, DispatchResult, DispatchError};
 use sp_std::{prelude::*, vec::Vec};
+use celer_app_core::{
+    find_move, judge_move, validate_board, BoardError, CelerAppQuery, ConsumeOutcome, DisputeError,
+    DisputeStateMachine, GameBoard, GameClock, LifecycleError, MoveVerdict, SessionDepositConfig,
+    SessionLifecycle,
+};
+pub use celer_app_core::{
+    valid_board_config, AppStatus, GameOutcome, GameQuery, GameRules, MoveRecord, ReplayError, RuleSet,
+    GAME_QUERY_VERSION, MAX_BOARD_SIZE, MIN_BOARD_SIZE, MIN_WIN_LENGTH,
+};
+pub use weights::WeightInfo;
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct AppInitiateRequest<AccountId, BlockNumber> {
-    nonce: u128,
-    players: Vec<AccountId>,
-    timeout: BlockNumber,
-    min_stone_offchain: u8,
-    max_stone_onchain: u8,
+    pub nonce: u128,
+    pub players: Vec<AccountId>,
+    pub timeout: BlockNumber,
+    pub min_stone_offchain: u8,
+    pub max_stone_onchain: u8,
+    pub board_size: u8,
+    pub win_length: u8,
+    pub rule_set: RuleSet,
+    /// Blocks of every player for the on-chain phase, `None` for a fixed timeout per action
+    pub time_bank: Option<BlockNumber>,
+    /// Accept states signed only by the player who moved that extend the last co-signed state by one move
+    pub unilateral: bool,
 }
 
 pub type AppInitiateRequestOf<T> = AppInitiateRequest<
@@ -35,11 +63,11 @@ pub type AppInitiateRequestOf<T> = AppInitiateRequest<
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct AppState<BlockNumber, Hash> {
-    nonce: u128,
-    seq_num: u128,
-    board_state: Vec<u8>,
-    timeout: BlockNumber,
-    session_id: Hash,
+    pub nonce: u128,
+    pub seq_num: u128,
+    pub board_state: Vec<u8>,
+    pub timeout: BlockNumber,
+    pub session_id: Hash,
 }
 
 pub type AppStateOf<T> = AppState<
@@ -49,8 +77,8 @@ pub type AppStateOf<T> = AppState<
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct StateProof<BlockNumber, Hash, Signature> {
-    app_state: AppState<BlockNumber, Hash>,
-    sigs: Vec<Signature>,
+    pub app_state: AppState<BlockNumber, Hash>,
+    pub sigs: Vec<Signature>,
 }
 
 pub type StateProofOf<T> = StateProof<
@@ -59,14 +87,6 @@ pub type StateProofOf<T> = StateProof<
     <T as Trait>::Signature,
 >;
 
-#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
-pub enum AppStatus {
-    Idle = 0,
-    Settle = 1,
-    Action = 2,
-    Finalized = 3,
-}
-
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct GomokuInfo<AccountId, BlockNumber> {
     nonce: u128,
@@ -76,6 +96,10 @@ pub struct GomokuInfo<AccountId, BlockNumber> {
     deadline: BlockNumber,
     status: AppStatus,
     gomoku_state: GomokuState,
+    clock: Option<GameClock<BlockNumber>>,
+    draw_offer: Option<AccountId>,
+    unilateral: bool,
+    cosigned_seq_num: Option<u128>,
 }
 
 pub type GomokuInfoOf<T> = GomokuInfo<
@@ -83,6 +107,41 @@ pub type GomokuInfoOf<T> = GomokuInfo<
     <T as system::Trait>::BlockNumber,
 >;
 
+pub type MoveRecordOf<T> = MoveRecord<
+    <T as system::Trait>::AccountId,
+    <T as system::Trait>::BlockNumber,
+>;
+
+impl<AccountId, BlockNumber> DisputeStateMachine<BlockNumber> for GomokuInfo<AccountId, BlockNumber>
+where
+    BlockNumber: AtLeast32BitUnsigned + Copy,
+{
+    fn status(&self) -> AppStatus { self.status.clone() }
+    fn set_status(&mut self, status: AppStatus) { self.status = status; }
+    fn seq_num(&self) -> u128 { self.seq_num }
+    fn set_seq_num(&mut self, seq_num: u128) { self.seq_num = seq_num; }
+    fn timeout(&self) -> BlockNumber { self.timeout }
+    fn deadline(&self) -> BlockNumber { self.deadline }
+    fn set_deadline(&mut self, deadline: BlockNumber) { self.deadline = deadline; }
+
+    fn action_period(&self) -> BlockNumber {
+        match (self.clock.as_ref(), self.player_to_move()) {
+            (Some(clock), Some(player_index)) => clock.bank(player_index).unwrap_or(self.timeout),
+            _ => self.timeout,
+        }
+    }
+}
+
+impl<AccountId, BlockNumber> GomokuInfo<AccountId, BlockNumber> {
+    /// Index of the player to move, `None` if no player is to move
+    fn player_to_move(&self) -> Option<usize> {
+        match self.gomoku_state.board_state.as_ref().map(|board_state| board_state[1]) {
+            Some(turn) if turn == 1 || turn == 2 => Some(turn as usize - 1),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub enum StateKey {
     Turn = 0,
@@ -91,35 +150,102 @@ pub enum StateKey {
 }
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
-struct GomokuState {
-    board_state: Option<Vec<u8>>, // 227 length: u8 winner + u8 turn + 15*15 board
+pub struct GomokuState {
+    board_state: Option<Vec<u8>>, // u8 winner + u8 turn + board_size*board_size board
     stone_num: Option<u16>, // number of stones
     stone_num_onchain: Option<u16>, // number of stones places on-chain
     state_key: Option<StateKey>, // key of turn, winner fullstate
     min_stone_offchain: u8, // minimal number of stones before go onchain
     max_stone_onchain: u8, // maximal number of stones after go onchain
+    board_size: u8, // number of rows and columns of the board
+    win_length: u8, // number of stones in a row that wins
+    rule_set: RuleSet, // rule set deciding wins and forbidden moves
 }
 
 #[derive(PartialEq, Eq, Encode, Decode, RuntimeDebug)]
 pub struct SingleGomokuArgsQueryOutcome<Hash> {
     pub session_id: Hash,
-    pub query_data: u8
+    /// Version of the args, `GAME_QUERY_VERSION`
+    pub version: u8,
+    pub query_data: GameQuery
 }
 
 pub type SingleGomokuArgsQueryOutcomeOf<T> = SingleGomokuArgsQueryOutcome<<T as system::Trait>::Hash>;
 
+/// Query args before `GameQuery`, the query data is the winner
+#[derive(PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct SingleGomokuLegacyArgsQueryOutcome<Hash> {
+    pub session_id: Hash,
+    pub query_data: u8
+}
+
+pub type SingleGomokuLegacyArgsQueryOutcomeOf<T> = SingleGomokuLegacyArgsQueryOutcome<<T as system::Trait>::Hash>;
+
 pub const SINGLE_GOMOKU_ID: ModuleId = ModuleId(*b"s_gomoku");
 
-pub trait Trait: system::Trait {
+/// Length of the board state of the default 15*15 board
+pub const BOARD_STATE_LEN: usize = 227;
+
+/// Board size of standard gomoku
+pub const DEFAULT_BOARD_SIZE: u8 = 15;
+
+/// Number of stones in a row that wins standard gomoku
+pub const DEFAULT_WIN_LENGTH: u8 = 5;
+
+pub type BalanceOf<T> =
+    <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+pub trait Trait: SendTransactionTypes<Call<Self>> + system::Trait {
     type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
     type Public: IdentifyAccount<AccountId = Self::AccountId>;
     type Signature: Verify<Signer = <Self as Trait>::Public> + Member + Decode + Encode; 
+    /// Number of blocks a finalized app is kept after finalization
+    type RetentionPeriod: Get<Self::BlockNumber>;
+    /// Maximum number of apps cleared in `on_initialize` of a block
+    type MaxClearPerBlock: Get<u32>;
+    /// Maximum number of expired apps finalized in `on_initialize` of a block
+    type MaxFinalizePerBlock: Get<u32>;
+    /// Priority of the finalizations the off-chain worker submits unsigned
+    type UnsignedPriority: Get<TransactionPriority>;
+    /// Maximum number of moves kept in the move log of an app
+    type MaxMoveLogLength: Get<u32>;
+    /// Currency in which the storage deposit of an app is reserved
+    type Currency: ReservableCurrency<Self::AccountId>;
+    /// Storage deposit reserved from the initiator of an app
+    type SessionDeposit: Get<SessionDepositConfig<BalanceOf<Self>>>;
+    /// Weight information for extrinsics in this pallet
+    type WeightInfo: WeightInfo;
 }
 
 decl_storage! {
     trait Store for Module<T: Trait> as SingleGomoku {
         pub SingleGomokuInfoMap get(fn gomoku_info): 
             map hasher(blake2_128_concat) T::Hash => Option<GomokuInfoOf<T>>;
+        /// Block number at which the app is finalized
+        pub FinalizedAt get(fn finalized_at):
+            map hasher(blake2_128_concat) T::Hash => Option<T::BlockNumber>;
+        /// Whether the outcome of the finalized app is consumed
+        pub OutcomeConsumed get(fn outcome_consumed):
+            map hasher(blake2_128_concat) T::Hash => bool;
+        /// Id of apps to be cleared in `on_initialize` of the block
+        pub ClearQueue get(fn clear_queue):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<T::Hash>;
+        /// Id of apps whose action deadline passes before the block, finalized in its `on_initialize`
+        pub DeadlineQueue get(fn deadline_queue):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<T::Hash>;
+        /// Initiator and reserved storage deposit of the app
+        pub Deposits get(fn deposit_of):
+            map hasher(blake2_128_concat) T::Hash => Option<(T::AccountId, BalanceOf<T>)>;
+        /// Whether the app is cleared, kept so the id can not be initiated again
+        pub ClearedSessions get(fn is_cleared):
+            map hasher(blake2_128_concat) T::Hash => bool;
+        /// On-chain moves of the app in order since the last settled off-chain state, at most `MaxMoveLogLength`
+        pub MoveLog get(fn move_log):
+            map hasher(blake2_128_concat) T::Hash => Vec<MoveRecordOf<T>>;
+        /// Board state the move log starts from and the number of on-chain moves before it, the board settled by
+        /// the last off-chain state advanced past the moves dropped beyond `MaxMoveLogLength`
+        pub MoveLogBase get(fn move_log_base):
+            map hasher(blake2_128_concat) T::Hash => Option<(Vec<u8>, u16)>;
     }
 }
 
@@ -139,28 +265,35 @@ decl_module! {
         /// - Complexity: `O(1)`
         ///   - 1 storage insertion `GomokuInfoMap`
         ///   - 1 storage reads `GomokuxInfoMap`
-        /// - Based on benchmark;
-        ///     17.89　µs
+        ///   - 1 storage read `ClearedSessions`
+        ///   - 1 storage insertion `Deposits`
+        ///   - 1 balance reservation of the initiator
         /// # </weight>
-        #[weight = 18_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::app_initiate()]
         fn app_initiate(
             origin,
             initiate_request: AppInitiateRequestOf<T>
         ) -> DispatchResult {
-            ensure_signed(origin)?;
+            let initiator = ensure_signed(origin)?;
 
-            let session_id = Self::get_session_id(initiate_request.nonce, initiate_request.players.clone());
             ensure!(
-                SingleGomokuInfoMap::<T>::contains_key(&session_id) == false,
-                "AppId already exists"
+                initiate_request.players.len() == 2,
+                Error::<T>::InvalidPlayerNum
             );
+            celer_app_core::ensure_ordered_players(&initiate_request.players).map_err(Error::<T>::from)?;
             ensure!(
-                initiate_request.players.len() == 2,
-                "invalid player length"
+                MIN_BOARD_SIZE <= initiate_request.board_size && initiate_request.board_size <= MAX_BOARD_SIZE,
+                Error::<T>::InvalidBoardSize
             );
             ensure!(
-                initiate_request.players[0] < initiate_request.players[1],
-                "players is not asscending order"
+                valid_board_config(initiate_request.board_size, initiate_request.win_length),
+                Error::<T>::InvalidWinLength
+            );
+
+            let session_id = Self::get_session_id(&initiate_request);
+            ensure!(
+                Self::is_session_used(session_id) == false,
+                Error::<T>::AppAlreadyExists
             );
 
             let gomoku_state = GomokuState {
@@ -170,6 +303,9 @@ decl_module! {
                 state_key: None,
                 min_stone_offchain: initiate_request.min_stone_offchain,
                 max_stone_onchain: initiate_request.max_stone_onchain,
+                board_size: initiate_request.board_size,
+                win_length: initiate_request.win_length,
+                rule_set: initiate_request.rule_set,
             };
             let gomoku_info = GomokuInfoOf::<T> {
                 nonce: initiate_request.nonce,
@@ -179,8 +315,21 @@ decl_module! {
                 deadline: Zero::zero(),
                 status: AppStatus::Idle,
                 gomoku_state: gomoku_state,
+                clock: initiate_request.time_bank.map(|time_bank| GameClock::new(2, time_bank)),
+                draw_offer: None,
+                unilateral: initiate_request.unilateral,
+                cosigned_seq_num: None,
             };
-            SingleGomokuInfoMap::<T>::insert(session_id, gomoku_info);
+            // reserve the storage deposit from the initiator
+            Self::reserve_deposit(
+                session_id,
+                initiator,
+                gomoku_info.players.len(),
+                board_state_len(gomoku_info.gomoku_state.board_size)
+            )?;
+            SingleGomokuInfoMap::<T>::insert(session_id, gomoku_info.clone());
+
+            Self::deposit_event(RawEvent::AppInitiated(session_id, gomoku_info.players, gomoku_info.timeout));
 
             Ok(())
         }
@@ -192,16 +341,14 @@ decl_module! {
         ///
         /// # <weight>
         /// ## Weight
-        /// - Complexity `O(1)`
-        /// # <weight>
-        /// ## Weight
-        /// - Complexity: `O(1)`
+        /// - Complexity: `O(B)`
+        ///   - `B` length of the board state, every cell is scanned for a winning run
         ///   - 1 storage mutation `GomokuInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
+        ///   - 1 storage insertion `MoveLogBase` of the settled board
         ///   - 1 storage read `GomokuInfoMap`
-        /// - Based on benchmark;
-        ///     50.27　µs
         /// # </weight>
-        #[weight = 51_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::update_by_state(state_proof.app_state.board_state.len() as u32)]
         fn update_by_state(
             origin,
             state_proof: StateProofOf<T>
@@ -214,44 +361,137 @@ decl_module! {
                 None => Err(Error::<T>::SingleGomokuInfoNotExist)?,
             };
 
+            // a state signed only by the player who moved extends the co-signed state by one move
+            let unilateral = gomoku_info.unilateral && state_proof.sigs.len() == 1;
             // submit and settle off-chain state
-            let mut new_gomoku_info: GomokuInfoOf<T> = Self::intend_settle(gomoku_info, state_proof.clone())?;
+            let mut new_gomoku_info: GomokuInfoOf<T> = if unilateral {
+                Self::intend_settle_unilateral(gomoku_info, state_proof.clone())?
+            } else {
+                Self::intend_settle(gomoku_info, state_proof.clone())?
+            };
 
             let _state = state_proof.app_state.board_state;
             ensure!(
-                _state.len() == 227,
-                "invalid board state length"
+                _state.len() == board_state_len(new_gomoku_info.gomoku_state.board_size),
+                Error::<T>::InvalidBoardStateLength
             );
-
-            let count = 0;
+            // the board must result from alternating moves under the rule set, a draw fills the board
+            validate_board(
+                &_state[2..],
+                new_gomoku_info.gomoku_state.board_size,
+                new_gomoku_info.gomoku_state.win_length,
+                new_gomoku_info.gomoku_state.rule_set,
+                _state[0],
+                _state[1],
+                board_cells(new_gomoku_info.gomoku_state.board_size) as usize
+            ).map_err(Error::<T>::from)?;
+
+            let count = count_stones(&_state);
             if _state[0] != 0 {
-                new_gomoku_info = Self::win_game(_state[0], new_gomoku_info.clone())?;
+                new_gomoku_info = Self::win_game(session_id, _state[0], new_gomoku_info.clone())?;
+            } else if _state[1] == 0 {
+                new_gomoku_info = Self::draw_game(session_id, new_gomoku_info.clone());
             } else {
-                // advance to _state[2];
-                let mut _state_iter = _state.iter();
-                for _i in 0..3 {
-                    _state_iter.next();
-                }
-                // load other states only if winner is not specified
-                let count = _state_iter.filter(|&x| *x != 0).count() as u8;
-    
                 ensure!(
-                    count >= new_gomoku_info.gomoku_state.min_stone_offchain,
-                    "not enough offchain stones"
+                    count >= new_gomoku_info.gomoku_state.min_stone_offchain as u16,
+                    Error::<T>::NotEnoughOffchainStones
                 );
             }
 
+            // the clock of the player to move starts when the settle period ends
+            let settle_deadline = new_gomoku_info.deadline;
+            if let Some(clock) = new_gomoku_info.clock.as_mut() {
+                clock.start(settle_deadline);
+            }
+
             SingleGomokuInfoMap::<T>::mutate(session_id, |info| {
-                new_gomoku_info.gomoku_state.board_state = Some(_state);
+                new_gomoku_info.gomoku_state.board_state = Some(_state.clone());
                 new_gomoku_info.gomoku_state.stone_num = Some(count);
+                new_gomoku_info.gomoku_state.stone_num_onchain = Some(0);
+                // the co-signed state supersedes a pending draw offer
+                new_gomoku_info.draw_offer = None;
                 *info = Some(new_gomoku_info.clone())
             });
+            // the move log restarts at the settled board
+            MoveLog::<T>::remove(session_id);
+            MoveLogBase::<T>::insert(session_id, (_state, 0));
+            Self::record_finalized(session_id, &new_gomoku_info.status);
+            Self::queue_deadline(session_id, &new_gomoku_info);
             
             Self::deposit_event(RawEvent::IntendSettle(session_id, new_gomoku_info.seq_num));
 
             Ok(())
         }
 
+        /// Finalize with an off-chain state co-signed by all players as the final state
+        ///
+        /// The players sign `final_signing_payload` of the state instead of `signing_payload`,
+        /// so the app is finalized in this block without waiting for the settle period.
+        ///
+        /// Parameters:
+        /// - `state_proof`: Off-chain app state signed as final by all players
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(B)`
+        ///   - `B` length of the board state, every cell is scanned for a winning run
+        ///   - 1 storage mutation `SingleGomokuInfoMap`
+        ///   - 1 storage insertion `FinalizedAt`
+        ///   - 1 storage insertion `MoveLogBase` of the settled board
+        ///   - 1 storage read `SingleGomokuInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::finalize_by_agreement(state_proof.app_state.board_state.len() as u32)]
+        fn finalize_by_agreement(
+            origin,
+            state_proof: StateProofOf<T>
+        ) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let session_id = state_proof.app_state.session_id;
+            let gomoku_info = match SingleGomokuInfoMap::<T>::get(session_id) {
+                Some(info) => info,
+                None => Err(Error::<T>::SingleGomokuInfoNotExist)?,
+            };
+
+            // finalize with the state all players agreed on
+            let mut new_gomoku_info: GomokuInfoOf<T> = Self::agree_final_state(gomoku_info, state_proof.clone())?;
+
+            let _state = state_proof.app_state.board_state;
+            ensure!(
+                _state.len() == board_state_len(new_gomoku_info.gomoku_state.board_size),
+                Error::<T>::InvalidBoardStateLength
+            );
+            // the players may agree on a draw at any number of stones
+            validate_board(
+                &_state[2..],
+                new_gomoku_info.gomoku_state.board_size,
+                new_gomoku_info.gomoku_state.win_length,
+                new_gomoku_info.gomoku_state.rule_set,
+                _state[0],
+                _state[1],
+                0
+            ).map_err(Error::<T>::from)?;
+
+            new_gomoku_info.gomoku_state.stone_num = Some(count_stones(&_state));
+            new_gomoku_info.gomoku_state.stone_num_onchain = Some(0);
+            new_gomoku_info.gomoku_state.board_state = Some(_state.clone());
+            new_gomoku_info.draw_offer = None;
+            // an unfinished final board is a draw
+            if _state[0] != 0 {
+                new_gomoku_info = Self::win_game(session_id, _state[0], new_gomoku_info)?;
+            } else {
+                new_gomoku_info = Self::draw_game(session_id, new_gomoku_info);
+            }
+
+            SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(new_gomoku_info.clone()));
+            MoveLog::<T>::remove(session_id);
+            MoveLogBase::<T>::insert(session_id, (_state, 0));
+            Self::deposit_event(RawEvent::FinalizedByAgreement(session_id, new_gomoku_info.seq_num));
+            Self::record_finalized(session_id, &new_gomoku_info.status);
+
+            Ok(())
+        }
+
         /// Update state according to an on-chain action
         ///
         /// Parameters:
@@ -260,13 +500,15 @@ decl_module! {
         ///
         /// # <weight>
         /// ## Weight
-        /// - Complexity: `O(1)`
+        /// - Complexity: `O(B)`
+        ///   - `B` length of the stored board state, charged for `MAX_BOARD_SIZE`
         ///   - 2 storage mutation `GomokuInfoMap`
+        ///   - 1 storage mutation `MoveLog`, `O(MaxMoveLogLength)`
+        ///   - 1 storage mutation `MoveLogBase` once the move log is full
+        ///   - 1 storage insertion `FinalizedAt` on finalization
         ///   - 1 storage read `GomokuInfoMap`
-        /// - Based on benchmark;
-        ///     47.23　µs
         /// # </weight>
-        #[weight = 48_000_000 + T::DbWeight::get().reads_writes(1, 2)]
+        #[weight = T::WeightInfo::update_by_action(board_state_len(MAX_BOARD_SIZE) as u32)]
         fn update_by_action(
             origin,
             session_id: T::Hash,
@@ -282,46 +524,86 @@ decl_module! {
             let mut new_gomoku_info = Self::apply_action(gomoku_info)?;
 
             let gomoku_state = new_gomoku_info.gomoku_state.clone();
-            let mut board_state = new_gomoku_info.gomoku_state.board_state.unwrap_or(vec![0; 227]);
+            let mut board_state = new_gomoku_info.gomoku_state.board_state
+                .unwrap_or(vec![0; board_state_len(gomoku_state.board_size)]);
             let turn = board_state[1];
+            let player = match (turn as usize).checked_sub(1).and_then(|index| new_gomoku_info.players.get(index)) {
+                Some(player) => player,
+                None => Err(Error::<T>::InvalidTurn)?,
+            };
+            ensure!(
+                caller == *player,
+                Error::<T>::NotYourTurn    
+            );
             ensure!(
-                caller == new_gomoku_info.players[turn as usize - 1],
-                "not your turn"    
+                action.len() == 2,
+                Error::<T>::InvalidActionLength
             );
 
             let x = action[0];
             let y = action[1];
             ensure!(
-                Self::check_boundary(x, y),
-                "out of boundary"
+                check_boundary(gomoku_state.board_size, x, y),
+                Error::<T>::OutOfBoundary
             );
-            let index: usize = Self::state_index(x, y);
+            let index: usize = state_index(gomoku_state.board_size, x, y);
             ensure!(
                 board_state[index] == 0,
-                "slot is occupied"
+                Error::<T>::SlotOccupied
             );
 
             // place the stone
             board_state[index] = turn;
+            let verdict = judge_move(
+                &board_state[2..],
+                gomoku_state.board_size,
+                gomoku_state.win_length,
+                gomoku_state.rule_set,
+                x,
+                y
+            );
+            ensure!(
+                verdict != MoveVerdict::Forbidden,
+                Error::<T>::ForbiddenMove
+            );
+            // take the blocks of the move from the time bank of the player
+            let block_number = frame_system::Module::<T>::block_number();
+            let player_index = new_gomoku_info.player_to_move().unwrap_or(0);
+            if let Some(clock) = new_gomoku_info.clock.as_mut() {
+                ensure!(
+                    clock.charge(player_index, block_number),
+                    Error::<T>::TimeBankExhausted
+                );
+            }
+            // a move of the opponent declines a pending draw offer
+            if new_gomoku_info.draw_offer.as_ref() != Some(&caller) {
+                new_gomoku_info.draw_offer = None;
+            }
             let new_stone_num = gomoku_state.stone_num.unwrap_or(0) + 1;
             let new_stone_num_onchain = gomoku_state.stone_num_onchain.unwrap_or(0) + 1;
             new_gomoku_info.gomoku_state.board_state = Some(board_state.clone());
             new_gomoku_info.gomoku_state.stone_num = Some(new_stone_num);
             new_gomoku_info.gomoku_state.stone_num_onchain = Some(new_stone_num_onchain);
-
-            // check if there is five-in-a-row including this new stone
-            if Self::check_five(board_state.clone(), x, y, 1, 0) // horizontal bidirection
-                || Self::check_five(board_state.clone(), x, y, 0, 1) // vertical bidirection
-                || Self::check_five(board_state.clone(), x, y, 1, 1) // main-diagonal bidirection
-                || Self::check_five(board_state.clone(), x, y, 1, -1) // anti-diagonal bidirection
-            {
-                new_gomoku_info = Self::win_game(turn, new_gomoku_info.clone())?;
+            Self::record_move(session_id, MoveRecord {
+                seq_num: new_gomoku_info.seq_num,
+                player: caller.clone(),
+                x: x,
+                y: y,
+                block: block_number,
+            }, gomoku_state.board_size, &board_state);
+            Self::deposit_event(RawEvent::ActionApplied(session_id, caller, new_gomoku_info.seq_num, action));
+            Self::deposit_event(RawEvent::StonePlaced(session_id, x, y, turn));
+
+            // check if there is a winning run including this new stone
+            if verdict == MoveVerdict::Win {
+                new_gomoku_info = Self::win_game(session_id, turn, new_gomoku_info.clone())?;
+                Self::record_finalized(session_id, &new_gomoku_info.status);
                 SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(new_gomoku_info));
                 return Ok(());
             }
 
-            if new_stone_num == 225 
-                || new_stone_num_onchain as u8 > gomoku_state.max_stone_onchain {
+            if new_stone_num == board_cells(gomoku_state.board_size)
+                || new_stone_num_onchain > gomoku_state.max_stone_onchain as u16 {
                     SingleGomokuInfoMap::<T>::mutate(session_id, |info| {
                         // all slots occupied, game is over with no winner
                         // set turn 0
@@ -332,6 +614,8 @@ decl_module! {
                         new_gomoku_info.gomoku_state.stone_num_onchain = Some(new_stone_num_onchain);
                         *info = Some(new_gomoku_info)
                     });
+                    Self::deposit_event(RawEvent::Draw(session_id));
+                    Self::record_finalized(session_id, &AppStatus::Finalized);
             } else {
                 // toggle turn and update game phase
                 if turn == 1 {
@@ -341,13 +625,19 @@ decl_module! {
                     // set turn 1
                     board_state[1] = 1;
                 }
+                // the next player has the rest of its time bank
+                let next_index = board_state[1] as usize - 1;
+                if let Some(bank) = new_gomoku_info.clock.as_ref().and_then(|clock| clock.bank(next_index)) {
+                    new_gomoku_info.deadline = block_number.saturating_add(bank);
+                }
             
                 SingleGomokuInfoMap::<T>::mutate(session_id, |info| {
                     new_gomoku_info.gomoku_state.board_state = Some(board_state);
                     new_gomoku_info.gomoku_state.stone_num = Some(new_stone_num);
                     new_gomoku_info.gomoku_state.stone_num_onchain = Some(new_stone_num_onchain);
-                    *info = Some(new_gomoku_info)
+                    *info = Some(new_gomoku_info.clone())
                 });
+                Self::queue_deadline(session_id, &new_gomoku_info);
             }
 
             Ok(())
@@ -362,60 +652,245 @@ decl_module! {
         /// ## Weight
         /// - Complexity: `O(1)`
         ///   - 1 storage mutation `GomokuInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
         ///   - 1 storage read `GomokuInfoMapp`
-        /// - Based on benchmark;
-        ///     30.51　µs
         /// # </weight>
-        #[weight = 31_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::finalize_on_action_timeout()]
         fn finalize_on_action_timeout(
             origin,
             session_id: T::Hash
         ) -> DispatchResult {
+            ensure_signed(origin)?;
             let gomoku_info = match SingleGomokuInfoMap::<T>::get(session_id) {
                 Some(info) => info,
                 None => Err(Error::<T>::SingleGomokuInfoNotExist)?,
             };
 
             let block_number = frame_system::Module::<T>::block_number();
-            if gomoku_info.status == AppStatus::Action {
-                ensure!(
-                    block_number > gomoku_info.deadline,
-                    "deadline no passes"
-                );
-            } else if gomoku_info.status == AppStatus::Settle {
-                ensure!(
-                    block_number > gomoku_info.deadline + gomoku_info.timeout,
-                    "while settling"
-                );
-            } else {
+            if !gomoku_info.is_action_timeout(block_number).map_err(Error::<T>::from)? {
                 return Ok(());
             }
 
-            let board_state = match gomoku_info.clone().gomoku_state.board_state {
-                Some(state) => state,
-                None => Err(Error::<T>::EmptyBoardState)?,
+            Self::finalize_on_timeout(session_id, gomoku_info)
+        }
+
+        /// Finalize in case of on-chain action timeout, submitted unsigned by the off-chain worker
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of app
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 1 storage mutation `GomokuInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
+        ///   - 1 storage read `GomokuInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::finalize_on_action_timeout()]
+        fn finalize_on_action_timeout_unsigned(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            ensure_none(origin)?;
+            let gomoku_info = match SingleGomokuInfoMap::<T>::get(session_id) {
+                Some(info) => info,
+                None => Err(Error::<T>::SingleGomokuInfoNotExist)?,
             };
-            if board_state[1] == 1 {
-                let new_gomoku_info = Self::win_game(2, gomoku_info)?;
-                SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(new_gomoku_info.clone()));
-            } else if board_state[1] == 2 {
-                let new_gomoku_info = Self::win_game(1, gomoku_info)?;
-                SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(new_gomoku_info.clone()));
-            } else {
+
+            let block_number = frame_system::Module::<T>::block_number();
+            if !gomoku_info.is_action_timeout(block_number).map_err(Error::<T>::from)? {
                 return Ok(());
             }
 
+            Self::finalize_on_timeout(session_id, gomoku_info)
+        }
+
+        /// Resign the game, the opponent of the caller wins
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of app
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 1 storage mutation `GomokuInfoMap`
+        ///   - 1 storage insertion `FinalizedAt`
+        ///   - 1 storage read `GomokuInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::resign()]
+        fn resign(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            let caller = ensure_signed(origin)?;
+            let gomoku_info = Self::disputed_game(session_id)?;
+            let winner = match gomoku_info.players.iter().position(|player| *player == caller) {
+                Some(player_index) => 2 - player_index as u8,
+                None => Err(Error::<T>::NotPlayer)?,
+            };
+
+            let new_gomoku_info = Self::win_game(session_id, winner, gomoku_info)?;
+            Self::deposit_event(RawEvent::Resigned(session_id, caller));
+            Self::record_finalized(session_id, &new_gomoku_info.status);
+            SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(new_gomoku_info));
+
+            Ok(())
+        }
+
+        /// Offer a draw to the opponent on the turn of the caller
+        ///
+        /// The offer stands until the opponent accepts it or places a stone.
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of app
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 1 storage mutation `GomokuInfoMap`
+        ///   - 1 storage read `GomokuInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::offer_draw()]
+        fn offer_draw(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            let caller = ensure_signed(origin)?;
+            let mut gomoku_info = Self::disputed_game(session_id)?;
+            let player_index = match gomoku_info.players.iter().position(|player| *player == caller) {
+                Some(player_index) => player_index,
+                None => Err(Error::<T>::NotPlayer)?,
+            };
+            ensure!(
+                gomoku_info.player_to_move() == Some(player_index),
+                Error::<T>::NotYourTurn
+            );
+            ensure!(
+                gomoku_info.draw_offer.is_none(),
+                Error::<T>::DrawAlreadyOffered
+            );
+
+            gomoku_info.draw_offer = Some(caller.clone());
+            SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(gomoku_info));
+            Self::deposit_event(RawEvent::DrawOffered(session_id, caller));
+
+            Ok(())
+        }
+
+        /// Accept the draw offered by the opponent
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of app
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 1 storage mutation `GomokuInfoMap`
+        ///   - 1 storage insertion `FinalizedAt`
+        ///   - 1 storage read `GomokuInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::accept_draw()]
+        fn accept_draw(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            let caller = ensure_signed(origin)?;
+            let gomoku_info = Self::disputed_game(session_id)?;
+            ensure!(
+                gomoku_info.players.contains(&caller),
+                Error::<T>::NotPlayer
+            );
+            match gomoku_info.draw_offer {
+                Some(ref offerer) if *offerer != caller => {},
+                _ => Err(Error::<T>::NoDrawOffer)?,
+            }
+
+            let new_gomoku_info = Self::draw_game(session_id, gomoku_info);
+            Self::record_finalized(session_id, &new_gomoku_info.status);
+            SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(new_gomoku_info));
+
+            Ok(())
+        }
+
+        /// Clear a finalized app whose outcome is consumed
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of app
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 4 storage removals `SingleGomokuInfoMap`, `FinalizedAt`, `OutcomeConsumed`, `Deposits`
+        ///   - 1 storage insertion `ClearedSessions`
+        ///   - 1 balance unreservation of the initiator
+        ///   - 4 storage reads `SingleGomokuInfoMap`, `FinalizedAt`, `OutcomeConsumed`, `Deposits`
+        /// # </weight>
+        #[weight = T::WeightInfo::clear_session()]
+        fn clear_session(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            let caller = ensure_signed(origin)?;
+            let gomoku_info = match SingleGomokuInfoMap::<T>::get(session_id) {
+                Some(info) => info,
+                None => Err(Error::<T>::SingleGomokuInfoNotExist)?,
+            };
+            ensure!(
+                gomoku_info.players.contains(&caller),
+                Error::<T>::NotPlayer
+            );
+            Self::ensure_clearable(session_id).map_err(Error::<T>::from)?;
+
+            Self::clear(session_id);
+
             Ok(())
         }
+
+        /// Finalize the apps whose action deadline passed, at most `MaxFinalizePerBlock` of them,
+        /// and clear the apps queued for this block, at most `MaxClearPerBlock` of them
+        fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            Self::finalize_expired(block_number).saturating_add(Self::sweep(block_number))
+        }
+
+        /// Submit an unsigned finalization of the expired apps left over by `on_initialize` of the next block
+        fn offchain_worker(block_number: T::BlockNumber) {
+            Self::submit_expired(block_number)
+        }
     }
 }
 
 decl_event! (
     pub enum Event<T> where 
+        <T as system::Trait>::AccountId,
+        <T as system::Trait>::BlockNumber,
         <T as system::Trait>::Hash
     {
+        /// AppInitiated(session_id, players, timeout)
+        AppInitiated(Hash, Vec<AccountId>, BlockNumber),
         /// IntendSettle(session_id, seq_num)
         IntendSettle(Hash, u128),
+        /// ActionApplied(session_id, player, seq_num, action)
+        ActionApplied(Hash, AccountId, u128, Vec<u8>),
+        /// StonePlaced(session_id, x, y, color)
+        StonePlaced(Hash, u8, u8, u8),
+        /// GameWon(session_id, winner)
+        GameWon(Hash, AccountId),
+        /// Draw(session_id)
+        Draw(Hash),
+        /// FinalizedOnTimeout(session_id, loser)
+        FinalizedOnTimeout(Hash, AccountId),
+        /// Resigned(session_id, loser)
+        Resigned(Hash, AccountId),
+        /// DrawOffered(session_id, player)
+        DrawOffered(Hash, AccountId),
+        /// FinalizedByAgreement(session_id, seq_num)
+        FinalizedByAgreement(Hash, u128),
+        /// Finalized(session_id)
+        Finalized(Hash),
+        /// OutcomeConsumed(session_id)
+        OutcomeConsumed(Hash),
+        /// SessionCleared(session_id)
+        SessionCleared(Hash),
     }
 );
 
@@ -426,7 +901,268 @@ decl_error! {
         // BoardState is empty
         EmptyBoardState,
         // A scale-codec encoded value can not decode correctly
-        MustBeDecodable
+        MustBeDecodable,
+        // Caller is not a player of the app
+        NotPlayer,
+        // App is not finalized
+        NotFinalized,
+        // Outcome of the app is not consumed
+        OutcomeNotConsumed,
+        // Retention period of the finalized app does not pass
+        RetentionPeriodNotPassed,
+        // App state is finalized
+        AlreadyFinalized,
+        // Sequence number is not larger than the on-chain one
+        InvalidSeqNum,
+        // App is not in action mode
+        NotInActionMode,
+        // Action deadline does not pass
+        DeadlineNotPassed,
+        // Settle period and action period do not pass
+        WhileSettling,
+        // Number of signatures does not match number of players
+        InvalidSignatureCount,
+        // Co-signatures of the state are invalid
+        InvalidSignature,
+        // Players are not in ascending order
+        InvalidPlayers,
+        // Number of players is invalid
+        InvalidPlayerNum,
+        // App with the session id already exists or was cleared
+        AppAlreadyExists,
+        // Length of the board state is invalid
+        InvalidBoardStateLength,
+        // Fewer stones than min_stone_offchain are placed off-chain
+        NotEnoughOffchainStones,
+        // Caller is not the player to move
+        NotYourTurn,
+        // Turn of the board state is not a player
+        InvalidTurn,
+        // Action is not (x, y)
+        InvalidActionLength,
+        // Stone is placed out of the board
+        OutOfBoundary,
+        // Slot of the board is occupied
+        SlotOccupied,
+        // Nonce of the state does not match the app
+        NonceMismatch,
+        // Winner is invalid
+        InvalidWinnerState,
+        // Player index of the query is out of range
+        InvalidPlayerIndex,
+        // Version of the query args is not GAME_QUERY_VERSION
+        UnsupportedQueryVersion,
+        // Board size is out of range
+        InvalidBoardSize,
+        // Win length is out of range of the board size
+        InvalidWinLength,
+        // Stone is a forbidden move under the rule set
+        ForbiddenMove,
+        // Cell of the board state is neither empty, BLACK nor WHITE
+        InvalidBoardCell,
+        // Numbers of stones of the board state cannot result from alternating moves
+        InvalidStoneCount,
+        // Blocks taken for the move exceed the time bank of the player
+        TimeBankExhausted,
+        // A draw offer is already pending
+        DrawAlreadyOffered,
+        // Opponent of the caller has not offered a draw
+        NoDrawOffer,
+        // On-chain state is not a co-signed state a unilateral state can extend
+        NoCosignedState,
+        // Unilateral state does not add exactly one stone of the player who moved
+        InvalidUnilateralMove
+    }
+}
+
+impl<T: Trait> From<DisputeError> for Error<T> {
+    fn from(err: DisputeError) -> Error<T> {
+        match err {
+            DisputeError::AlreadyFinalized => Error::<T>::AlreadyFinalized,
+            DisputeError::InvalidSeqNum => Error::<T>::InvalidSeqNum,
+            DisputeError::NotInActionMode => Error::<T>::NotInActionMode,
+            DisputeError::DeadlineNotPassed => Error::<T>::DeadlineNotPassed,
+            DisputeError::WhileSettling => Error::<T>::WhileSettling,
+            DisputeError::InvalidSignatureCount => Error::<T>::InvalidSignatureCount,
+            DisputeError::InvalidSignature => Error::<T>::InvalidSignature,
+            DisputeError::InvalidPlayers => Error::<T>::InvalidPlayers,
+        }
+    }
+}
+
+impl<T: Trait> From<LifecycleError> for Error<T> {
+    fn from(err: LifecycleError) -> Error<T> {
+        match err {
+            LifecycleError::NotFinalized => Error::<T>::NotFinalized,
+            LifecycleError::OutcomeNotConsumed => Error::<T>::OutcomeNotConsumed,
+            LifecycleError::RetentionPeriodNotPassed => Error::<T>::RetentionPeriodNotPassed,
+        }
+    }
+}
+
+impl<T: Trait> From<BoardError> for Error<T> {
+    fn from(err: BoardError) -> Error<T> {
+        match err {
+            BoardError::InvalidCell => Error::<T>::InvalidBoardCell,
+            BoardError::InvalidTurn => Error::<T>::InvalidTurn,
+            BoardError::InvalidStoneCount => Error::<T>::InvalidStoneCount,
+            BoardError::InvalidWinner => Error::<T>::InvalidWinnerState,
+        }
+    }
+}
+
+impl<T: Trait> CelerAppQuery<T::Hash, T::BlockNumber> for Module<T> {
+    fn is_finalized(args_query_finalization: Vec<u8>) -> Result<bool, DispatchError> {
+        Self::is_finalized(args_query_finalization)
+    }
+
+    fn get_outcome(args_query_outcome: Vec<u8>) -> Result<Vec<u8>, DispatchError> {
+        Self::get_outcome(args_query_outcome)
+    }
+
+    fn get_state(session_id: T::Hash, key: u8) -> Option<Vec<u8>> {
+        Self::get_state(session_id, key)
+    }
+
+    fn get_status(session_id: T::Hash) -> Option<AppStatus> {
+        Self::get_status(session_id)
+    }
+
+    fn get_settle_finalized_time(session_id: T::Hash) -> Option<T::BlockNumber> {
+        Self::get_settle_finalized_time(session_id)
+    }
+
+    fn get_action_deadline(session_id: T::Hash) -> Option<T::BlockNumber> {
+        Self::get_action_deadline(session_id)
+    }
+
+    fn get_seq_num(session_id: T::Hash) -> Option<u128> {
+        Self::get_seq_num(session_id)
+    }
+}
+
+#[allow(deprecated)] // ValidateUnsigned
+impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
+    type Call = Call<T>;
+
+    /// Accept the unsigned finalization of an app whose action deadline passed
+    ///
+    /// Parameters:
+    /// `_source`: Source of the transaction
+    /// `call`: Call of the transaction
+    fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+        match call {
+            Call::finalize_on_action_timeout_unsigned(session_id) => Self::validate_finalization(session_id),
+            _ => InvalidTransaction::Call.into(),
+        }
+    }
+}
+
+impl<T: Trait> ConsumeOutcome<T::Hash> for Module<T> {
+    fn consume_outcome(session_id: T::Hash) -> DispatchResult {
+        Self::queue_clear(session_id).map_err(Error::<T>::from)?;
+
+        Ok(())
+    }
+}
+
+impl<T: Trait> SessionLifecycle for Module<T> {
+    type AccountId = T::AccountId;
+    type Hash = T::Hash;
+    type BlockNumber = T::BlockNumber;
+    type Info = GomokuInfoOf<T>;
+    type InfoMap = SingleGomokuInfoMap<T>;
+    type FinalizedAt = FinalizedAt<T>;
+    type OutcomeConsumed = OutcomeConsumed<T>;
+    type DeadlineQueue = DeadlineQueue<T>;
+    type ClearQueue = ClearQueue<T>;
+    type Currency = T::Currency;
+    type Deposits = Deposits<T>;
+    type ClearedSessions = ClearedSessions<T>;
+
+    const UNSIGNED_TAG_PREFIX: &'static str = "SingleGomoku";
+
+    fn block_number() -> T::BlockNumber {
+        frame_system::Module::<T>::block_number()
+    }
+
+    fn retention_period() -> T::BlockNumber {
+        T::RetentionPeriod::get()
+    }
+
+    fn max_finalize_per_block() -> u32 {
+        T::MaxFinalizePerBlock::get()
+    }
+
+    fn max_clear_per_block() -> u32 {
+        T::MaxClearPerBlock::get()
+    }
+
+    fn unsigned_priority() -> TransactionPriority {
+        T::UnsignedPriority::get()
+    }
+
+    fn session_deposit() -> SessionDepositConfig<BalanceOf<T>> {
+        T::SessionDeposit::get()
+    }
+
+    fn finalize_expired_weight(queued: u32) -> Weight {
+        T::WeightInfo::finalize_expired(queued)
+    }
+
+    fn sweep_weight(queued: u32) -> Weight {
+        T::WeightInfo::on_initialize(queued)
+    }
+
+    /// Finalize an app whose action deadline passed, the player to move loses
+    ///
+    /// Parameters:
+    /// `session_id`: Id of app
+    /// `gomoku_info`: Info of gomoku state
+    fn finalize_on_timeout(
+        session_id: T::Hash,
+        gomoku_info: GomokuInfoOf<T>
+    ) -> DispatchResult {
+        let board_state = match gomoku_info.clone().gomoku_state.board_state {
+            Some(state) => state,
+            None => Err(Error::<T>::EmptyBoardState)?,
+        };
+        if board_state[1] == 1 {
+            let loser = gomoku_info.players[0].clone();
+            let new_gomoku_info = Self::win_game(session_id, 2, gomoku_info)?;
+            Self::deposit_event(RawEvent::FinalizedOnTimeout(session_id, loser));
+            Self::record_finalized(session_id, &new_gomoku_info.status);
+            SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(new_gomoku_info.clone()));
+        } else if board_state[1] == 2 {
+            let loser = gomoku_info.players[1].clone();
+            let new_gomoku_info = Self::win_game(session_id, 1, gomoku_info)?;
+            Self::deposit_event(RawEvent::FinalizedOnTimeout(session_id, loser));
+            Self::record_finalized(session_id, &new_gomoku_info.status);
+            SingleGomokuInfoMap::<T>::mutate(session_id, |info| *info = Some(new_gomoku_info.clone()));
+        } else {
+            return Ok(());
+        }
+
+        Ok(())
+    }
+
+    fn submit_finalization(session_id: T::Hash) -> Result<(), ()> {
+        let call = Call::finalize_on_action_timeout_unsigned(session_id);
+        SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
+    }
+
+    fn on_finalized(session_id: T::Hash) {
+        Self::deposit_event(RawEvent::Finalized(session_id));
+    }
+
+    fn on_outcome_consumed(session_id: T::Hash) {
+        Self::deposit_event(RawEvent::OutcomeConsumed(session_id));
+    }
+
+    fn on_cleared(session_id: T::Hash) {
+        MoveLog::<T>::remove(session_id);
+        MoveLogBase::<T>::remove(session_id);
+        Self::deposit_event(RawEvent::SessionCleared(session_id));
     }
 }
 
@@ -459,46 +1195,82 @@ impl<T: Trait> Module<T> {
     /// Query the single gomoku app outcome
     /// 
     /// Parameter:
-    /// `args_query_outcome`: enoced SingleGomokuArgsQueryOutcome
+    /// `args_query_outcome`: enoced SingleGomokuArgsQueryOutcome, or SingleGomokuLegacyArgsQueryOutcome
     ///
-    /// Return the encoded boolean value
+    /// Return the encoded boolean value for `GameQuery::IsWinner`, the encoded
+    /// `GameOutcome` for `GameQuery::Outcome` and the encoded u128 payout for `GameQuery::Payout`.
+    /// The legacy args return the encoded boolean value of whether the winner equals the query data.
     pub fn get_outcome(
         args_query_outcome: Vec<u8>,
     ) -> Result<Vec<u8>, DispatchError> {
+        // the legacy args end after the u8 query data, the versioned args are longer
+        let mut input = &args_query_outcome[..];
+        if let Ok(legacy_query) = SingleGomokuLegacyArgsQueryOutcomeOf::<T>::decode(&mut input) {
+            if input.is_empty() {
+                return Self::get_legacy_outcome(legacy_query);
+            }
+        }
         let query_outcome: SingleGomokuArgsQueryOutcomeOf<T> = SingleGomokuArgsQueryOutcome::decode(&mut &args_query_outcome[..])
             .map_err(|_| Error::<T>::MustBeDecodable)?;
+        ensure!(
+            query_outcome.version == GAME_QUERY_VERSION,
+            Error::<T>::UnsupportedQueryVersion
+        );
         let gomoku_info = match SingleGomokuInfoMap::<T>::get(query_outcome.session_id) {
             Some(info) => info,
             None => Err(Error::<T>::SingleGomokuInfoNotExist)?,
         };
+
+        match query_outcome.query_data {
+            GameQuery::IsWinner(winner) => {
+                let is_winner = match gomoku_info.gomoku_state.board_state {
+                    Some(board_state) => board_state[0] == winner,
+                    None => false,
+                };
+                Ok(is_winner.encode())
+            },
+            GameQuery::Outcome => Ok(Self::game_outcome(&gomoku_info).encode()),
+            GameQuery::Payout { player_index, total } => {
+                let outcome = Self::game_outcome(&gomoku_info);
+                ensure!(outcome != GameOutcome::Undecided, Error::<T>::NotFinalized);
+                match outcome.payout(player_index, gomoku_info.players.len() as u8, total) {
+                    Some(payout) => Ok(payout.encode()),
+                    None => Err(Error::<T>::InvalidPlayerIndex)?,
+                }
+            },
+        }
+    }
+
+    /// Query the outcome with the legacy args
+    ///
+    /// Parameter:
+    /// `legacy_query`: Session id and winner of the query
+    fn get_legacy_outcome(
+        legacy_query: SingleGomokuLegacyArgsQueryOutcomeOf<T>,
+    ) -> Result<Vec<u8>, DispatchError> {
+        let gomoku_info = match SingleGomokuInfoMap::<T>::get(legacy_query.session_id) {
+            Some(info) => info,
+            None => Err(Error::<T>::SingleGomokuInfoNotExist)?,
+        };
         let board_state = match gomoku_info.gomoku_state.board_state {
             Some(state) => state,
             None => Err(Error::<T>::EmptyBoardState)?,
         };
 
-        if board_state[0] == query_outcome.query_data {
-            // If outcome is true, return encoded true value
-            return Ok(true.encode());
-        } else {
-            // If outcome is false, return encoded false value
-            return Ok(false.encode());
-        }
+        Ok((board_state[0] == legacy_query.query_data).encode())
     }
 
     /// Get Id of app
     ///
-    /// Parameters:
-    /// `nonce`: Nonce of app
-    /// `players`: AccountId of players
-    pub fn get_session_id(
-        nonce: u128,
-        players: Vec<T::AccountId>,
-    ) -> T::Hash {
+    /// The id commits to every parameter of the initiate request, so a request
+    /// with the same nonce and players but other parameters gets another id.
+    ///
+    /// Parameter:
+    /// `initiate_request`: Request initiating the app
+    pub fn get_session_id(initiate_request: &AppInitiateRequestOf<T>) -> T::Hash {
         let single_gomoku_app_account = Self::app_account();
         let mut encoded = single_gomoku_app_account.encode();
-        encoded.extend(nonce.encode());
-        encoded.extend(players[0].encode());
-        encoded.extend(players[1].encode());
+        encoded.extend(initiate_request.encode());
         let session_id = T::Hashing::hash(&encoded);
         return session_id;
     }
@@ -513,7 +1285,10 @@ impl<T: Trait> Module<T> {
             Some(info) => info,
             None => return None
         };
-        let board_state = gomoku_info.gomoku_state.board_state.unwrap();
+        let board_state = match gomoku_info.gomoku_state.board_state {
+            Some(state) => state,
+            None => return None,
+        };
         if key == StateKey::Winner as u8 {
             return Some(vec![board_state[0]]);
         } else if key == StateKey::Turn as u8 {
@@ -547,11 +1322,8 @@ impl<T: Trait> Module<T> {
             Some(info) => info,
             None => return None
         };
-        if gomoku_info.status == AppStatus::Settle {
-            return Some(gomoku_info.deadline);
-        }
 
-        return None;
+        return gomoku_info.settle_finalized_time();
     }
 
     /// Get action deadline
@@ -563,13 +1335,8 @@ impl<T: Trait> Module<T> {
             Some(info) => info,
             None => return None
         };
-        if gomoku_info.status == AppStatus::Action {
-            return Some(gomoku_info.deadline);
-        } else if gomoku_info.status ==  AppStatus::Settle {
-            return Some(gomoku_info.deadline + gomoku_info.timeout);
-        } else {
-            return None;
-        }
+
+        return gomoku_info.action_deadline();
     }
 
     /// Get app sequence number
@@ -584,6 +1351,65 @@ impl<T: Trait> Module<T> {
         return Some(gomoku_info.seq_num);
     }
 
+    /// Get the remaining blocks of the players in player order, `None` without a game clock
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    pub fn get_time_banks(session_id: T::Hash) -> Option<Vec<T::BlockNumber>> {
+        let gomoku_info = match SingleGomokuInfoMap::<T>::get(session_id) {
+            Some(info) => info,
+            None => return None
+        };
+        return gomoku_info.clock.map(|clock| clock.banks);
+    }
+
+    /// Get the rules of the game for replaying its move log
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    pub fn get_game_rules(session_id: T::Hash) -> Option<GameRules> {
+        let gomoku_state = match SingleGomokuInfoMap::<T>::get(session_id) {
+            Some(info) => info.gomoku_state,
+            None => return None
+        };
+        return Some(GameRules {
+            board_size: gomoku_state.board_size,
+            win_length: gomoku_state.win_length,
+            rule_set: gomoku_state.rule_set,
+            max_stone_onchain: gomoku_state.max_stone_onchain,
+        });
+    }
+
+    /// Replay moves of the move log on a board and get the resulting board state
+    ///
+    /// The players alternate starting with the turn of the initial board, the player
+    /// recorded with a move is not checked.
+    ///
+    /// Parameters:
+    /// `rules`: Rules of the game
+    /// `initial_board`: u8 winner + u8 turn + board_size*board_size board settled by the last off-chain state
+    /// `moves`: Moves applied on-chain since the off-chain state in order
+    pub fn replay(
+        rules: &GameRules,
+        initial_board: Vec<u8>,
+        moves: &[MoveRecordOf<T>]
+    ) -> Result<Vec<u8>, ReplayError> {
+        if initial_board.len() != board_state_len(rules.board_size) {
+            return Err(ReplayError::InvalidBoard);
+        }
+        let initial = GameBoard {
+            cells: initial_board[2..].to_vec(),
+            winner: initial_board[0],
+            turn: initial_board[1],
+        };
+        let coordinates: Vec<(u8, u8)> = moves.iter().map(|record| (record.x, record.y)).collect();
+        let board = celer_app_core::replay(rules, initial, &coordinates)?;
+
+        let mut board_state = vec![board.winner, board.turn];
+        board_state.extend(board.cells);
+        Ok(board_state)
+    }
+
     /// Get single gomoku app account id
     pub fn app_account() -> T::AccountId {
         SINGLE_GOMOKU_ID.into_account()
@@ -600,92 +1426,133 @@ impl<T: Trait> Module<T> {
     ) -> Result<GomokuInfoOf<T>, DispatchError> {
         let app_state = state_proof.app_state;
         let encoded = Self::encode_app_state(app_state.clone());
-        Self::valid_signers(state_proof.sigs, &encoded, gomoku_info.players.clone())?;
+        celer_app_core::valid_signers(&state_proof.sigs, &encoded, &gomoku_info.players).map_err(Error::<T>::from)?;
         ensure!(
-            gomoku_info.status != AppStatus::Finalized,
-            "app state is finalized"
+            app_state.nonce == gomoku_info.nonce,
+            Error::<T>::NonceMismatch
         );
+        gomoku_info.intend_settle(app_state.seq_num, frame_system::Module::<T>::block_number()).map_err(Error::<T>::from)?;
+        gomoku_info.cosigned_seq_num = Some(app_state.seq_num);
+
+        Ok(gomoku_info)
+    }
+
+    /// Submit and settle an off-chain state signed only by the player who moved
+    ///
+    /// The state must extend the co-signed state on-chain by one stone of its player
+    /// to move, the winner and turn of the board are checked by `update_by_state`.
+    ///
+    /// Parameter:
+    /// `gomoku_info`: Info of gomoku state
+    /// `state_proof`: Off-chain app state signed by the player who moved
+    fn intend_settle_unilateral(
+        mut gomoku_info: GomokuInfoOf<T>,
+        state_proof: StateProofOf<T>
+    ) -> Result<GomokuInfoOf<T>, DispatchError> {
         ensure!(
-            app_state.nonce == gomoku_info.nonce,
-            "nonce not match"
+            gomoku_info.cosigned_seq_num == Some(gomoku_info.seq_num),
+            Error::<T>::NoCosignedState
         );
+        let app_state = state_proof.app_state;
         ensure!(
-            gomoku_info.seq_num < app_state.seq_num,
-            "invalid sequence number"
+            app_state.seq_num == gomoku_info.seq_num.saturating_add(1),
+            Error::<T>::InvalidSeqNum
+        );
+        let gomoku_state = &gomoku_info.gomoku_state;
+        let prev = match gomoku_state.board_state.as_ref() {
+            Some(board_state) => board_state,
+            None => Err(Error::<T>::EmptyBoardState)?,
+        };
+        // the player to move of the co-signed board made the move
+        let turn = prev[1];
+        let mover = match turn {
+            1 | 2 => gomoku_info.players[turn as usize - 1].clone(),
+            _ => Err(Error::<T>::InvalidTurn)?,
+        };
+        let encoded = Self::encode_app_state(app_state.clone());
+        celer_app_core::valid_signers(&state_proof.sigs, &encoded, &[mover]).map_err(Error::<T>::from)?;
+        ensure!(
+            app_state.nonce == gomoku_info.nonce,
+            Error::<T>::NonceMismatch
         );
 
-        gomoku_info.seq_num = app_state.seq_num;
-        gomoku_info.deadline = frame_system::Module::<T>::block_number() + gomoku_info.timeout;
-        gomoku_info.status = AppStatus::Settle;
+        let next = &app_state.board_state;
+        ensure!(
+            next.len() == prev.len(),
+            Error::<T>::InvalidBoardStateLength
+        );
+        let (x, y) = match find_move(&prev[2..], &next[2..], gomoku_state.board_size, turn) {
+            Some(stone) => stone,
+            None => Err(Error::<T>::InvalidUnilateralMove)?,
+        };
+        let verdict = judge_move(
+            &next[2..],
+            gomoku_state.board_size,
+            gomoku_state.win_length,
+            gomoku_state.rule_set,
+            x,
+            y
+        );
+        ensure!(
+            verdict != MoveVerdict::Forbidden,
+            Error::<T>::ForbiddenMove
+        );
+        gomoku_info.intend_settle(app_state.seq_num, frame_system::Module::<T>::block_number()).map_err(Error::<T>::from)?;
 
         Ok(gomoku_info)
     }
 
-    /// Apply an action to the on-chain state
+    /// Finalize with an off-chain state co-signed as the final state
     ///
     /// Parameter:
     /// `gomoku_info`: Info of gomoku state
-    fn apply_action(
-        mut gomoku_info: GomokuInfoOf<T>
+    /// `state_proof`: Off-chain app state signed as final by all players
+    fn agree_final_state(
+        mut gomoku_info: GomokuInfoOf<T>,
+        state_proof: StateProofOf<T>
     ) -> Result<GomokuInfoOf<T>, DispatchError> {
+        let app_state = state_proof.app_state;
+        let encoded = Self::encode_final_app_state(app_state.clone());
+        celer_app_core::valid_signers(&state_proof.sigs, &encoded, &gomoku_info.players).map_err(Error::<T>::from)?;
         ensure!(
-            gomoku_info.status != AppStatus::Finalized,
-            "app state is finalized"
+            app_state.nonce == gomoku_info.nonce,
+            Error::<T>::NonceMismatch
         );
+        gomoku_info.finalize_by_agreement(app_state.seq_num).map_err(Error::<T>::from)?;
 
-        let block_number =  frame_system::Module::<T>::block_number();
-        if gomoku_info.status == AppStatus::Settle && block_number > gomoku_info.deadline {
-            gomoku_info.seq_num = gomoku_info.seq_num + 1;
-            gomoku_info.deadline = block_number + gomoku_info.timeout;
-            gomoku_info.status = AppStatus::Action;
-        } else {
-            ensure!(
-                gomoku_info.status ==  AppStatus::Action,
-                "app not in action mode"
-            );
-            gomoku_info.seq_num = gomoku_info.seq_num + 1;
-            gomoku_info.deadline = block_number + gomoku_info.timeout;
-            gomoku_info.status = AppStatus::Action;
-        }
-
-        Ok(gomoku_info)        
+        Ok(gomoku_info)
     }
 
-    /// Verify off-chain state signatures
+    /// Apply an action to the on-chain state
     ///
-    /// Parameters:
-    /// `signatures`: Signaturs from the players
-    /// `encoded`: Encoded app state
-    /// `signers`: AccountId of players
-    fn valid_signers(
-        signatures: Vec<<T as Trait>::Signature>,
-        encoded: &[u8],
-        signers: Vec<T::AccountId>,
-    ) -> DispatchResult {
-        for i in 0..2 {
-            ensure!(
-                &signatures[i].verify(encoded, &signers[i]),
-                "Check co-sigs failed"
-            );
-        };
-        Ok(())
+    /// Parameter:
+    /// `gomoku_info`: Info of gomoku state
+    fn apply_action(
+        mut gomoku_info: GomokuInfoOf<T>
+    ) -> Result<GomokuInfoOf<T>, DispatchError> {
+        gomoku_info.apply_action(frame_system::Module::<T>::block_number()).map_err(Error::<T>::from)?;
+
+        Ok(gomoku_info)
     }
 
     /// Set game states when there is a winner
     ///
     /// Parameters:
+    /// `session_id`: Id of app
     /// `winner`: Id of winner
     /// `gomoku_info`: Info of gomoku state
     fn win_game(
+        session_id: T::Hash,
         winner: u8, 
         mut gomoku_info: GomokuInfoOf<T>,
     ) -> Result<GomokuInfoOf<T>, DispatchError> {
         ensure!(
             u8::min_value() <= winner && winner <= 2,
-            "invalid winner state"
+            Error::<T>::InvalidWinnerState
         );
 
-        let mut new_board_state = gomoku_info.gomoku_state.board_state.unwrap_or(vec![0; 227]);
+        let mut new_board_state = gomoku_info.gomoku_state.board_state
+            .unwrap_or(vec![0; board_state_len(gomoku_info.gomoku_state.board_size)]);
         // set winner
         new_board_state[0] = winner;
 
@@ -694,6 +1561,7 @@ impl<T: Trait> Module<T> {
             new_board_state[1] = 0; 
             gomoku_info.status = AppStatus::Finalized;
             gomoku_info.gomoku_state.board_state = Some(new_board_state);
+            Self::deposit_event(RawEvent::GameWon(session_id, gomoku_info.players[winner as usize - 1].clone()));
         } else {
             gomoku_info.gomoku_state.board_state = Some(new_board_state);
         }
@@ -701,91 +1569,160 @@ impl<T: Trait> Module<T> {
         return Ok(gomoku_info);
     }
 
-    /// Check if there is five in a row in agiven direction
+    /// Finalize a game with no winner
     ///
     /// Parameters:
-    /// `_x`: x coordinate on the board
-    /// `_y`: y coordinate on the board
-    /// `_xdir`: direction (-1 or 0 or 1) in x axis
-    /// `_ydir`: direction (-1 or 0 or 1) in y axis
-    fn check_five(
-        _board_state: Vec<u8>,
-        _x: u8,
-        _y: u8,
-        _xdir: i8,
-        _ydir: i8,
-    ) -> bool {
-        let mut count: u8 = 0;
-        count += Self::count_stone(_board_state.clone(), _x, _y, _xdir, _ydir).unwrap();
-        count += Self::count_stone(_board_state, _x, _y, -1 * _xdir, -1 * _ydir).unwrap() - 1; // reverse direction
-        if count >= 5 {
-            return true
-        } else {
-            return false;
+    /// `session_id`: Id of app
+    /// `gomoku_info`: Info of gomoku
+    fn draw_game(
+        session_id: T::Hash,
+        mut gomoku_info: GomokuInfoOf<T>,
+    ) -> GomokuInfoOf<T> {
+        let mut new_board_state = gomoku_info.gomoku_state.board_state
+            .unwrap_or(vec![0; board_state_len(gomoku_info.gomoku_state.board_size)]);
+        // set winner 0 and turn 0
+        new_board_state[0] = 0;
+        new_board_state[1] = 0;
+        gomoku_info.status = AppStatus::Finalized;
+        gomoku_info.gomoku_state.board_state = Some(new_board_state);
+        gomoku_info.draw_offer = None;
+        Self::deposit_event(RawEvent::Draw(session_id));
+
+        return gomoku_info;
+    }
+
+    /// Get a game in dispute, which a player can resign or agree to draw
+    ///
+    /// Parameter:
+    /// `session_id`: Id of app
+    fn disputed_game(session_id: T::Hash) -> Result<GomokuInfoOf<T>, DispatchError> {
+        let gomoku_info = match SingleGomokuInfoMap::<T>::get(session_id) {
+            Some(info) => info,
+            None => Err(Error::<T>::SingleGomokuInfoNotExist)?,
+        };
+        match gomoku_info.status {
+            AppStatus::Settle | AppStatus::Action => {},
+            AppStatus::Finalized => Err(Error::<T>::AlreadyFinalized)?,
+            AppStatus::Idle => Err(Error::<T>::NotInActionMode)?,
         }
+        ensure!(
+            gomoku_info.gomoku_state.board_state.is_some(),
+            Error::<T>::EmptyBoardState
+        );
+
+        Ok(gomoku_info)
     }
 
-    /// Count the maximum consecutive stones in a given direction
+    /// Get the outcome of a game
+    ///
+    /// Parameter:
+    /// `gomoku_info`: Info of gomoku
+    fn game_outcome(gomoku_info: &GomokuInfoOf<T>) -> GameOutcome {
+        if gomoku_info.status != AppStatus::Finalized {
+            return GameOutcome::Undecided;
+        }
+        match gomoku_info.gomoku_state.board_state.as_ref().map(|board_state| board_state[0]) {
+            Some(1) => GameOutcome::Winner(0),
+            Some(2) => GameOutcome::Winner(1),
+            _ => GameOutcome::Draw,
+        }
+    }
+
+    /// Append a move to the move log of an app
+    ///
+    /// Beyond `MaxMoveLogLength` moves the oldest moves are dropped and the base board of the
+    /// log advances past them, so replaying the log from its base still gives the board.
     ///
     /// Parameters:
-    /// `_x`: x coordinate on the board
-    /// `_y`: y coordinate on the board
-    /// `_xdir`: direction (-1 or 0 or 1) in x axis
-    /// `_ydir`: direction (-1 or 0 or 1) in y axis
-    fn count_stone(
-        _board_state: Vec<u8>, 
-        _x: u8, 
-        _y: u8, 
-        _xdir: i8, 
-        _ydir: i8
-    ) -> Option<u8> {
-        let mut count: u8 = 1;
-        while count <= 5 {
-            let x = (_x as i8 + _xdir * count as i8) as u8;
-            let y = (_y as i8 + _ydir * count as i8) as u8;
-            if Self::check_boundary(x, y) 
-                && (_board_state[Self::state_index(x, y)] == _board_state[Self::state_index(_x, _y)]) {
-                    count += 1;
-            } else {
-                return Some(count);
+    /// `session_id`: Id of app
+    /// `record`: Move applied by `update_by_action`
+    /// `board_size`: Number of rows and columns of the board
+    /// `board_state`: Board state with the stone of the move placed
+    fn record_move(session_id: T::Hash, record: MoveRecordOf<T>, board_size: u8, board_state: &[u8]) {
+        let color = board_state[state_index(board_size, record.x, record.y)];
+        let mut moves = MoveLog::<T>::get(session_id);
+        moves.push(record);
+        let max_len = T::MaxMoveLogLength::get() as usize;
+        if moves.len() > max_len {
+            let dropped = moves.len() - max_len;
+            moves.drain(..dropped);
+
+            // the base is the board before the kept moves, with the color of the first kept move to move
+            let mut base = board_state.to_vec();
+            base[0] = 0;
+            base[1] = match moves.first() {
+                Some(first) => board_state[state_index(board_size, first.x, first.y)],
+                None => if color == 1 { 2 } else { 1 },
+            };
+            for kept in moves.iter() {
+                base[state_index(board_size, kept.x, kept.y)] = 0;
             }
+            let moves_before = MoveLogBase::<T>::get(session_id)
+                .map(|(_, moves_before)| moves_before)
+                .unwrap_or(0)
+                .saturating_add(dropped as u16);
+            MoveLogBase::<T>::insert(session_id, (base, moves_before));
         }
+        MoveLog::<T>::insert(session_id, moves);
+    }
+
+    /// Encode app state signed by the players on this chain
+    ///
+    /// Parameter:
+    /// `app_state`: app state
+    fn encode_app_state(
+        app_state: AppStateOf<T>
+    ) -> Vec<u8> {
+        let genesis_hash = frame_system::Module::<T>::block_hash(T::BlockNumber::zero());
+        Self::signing_payload(app_state, genesis_hash)
+    }
 
-        return None;
+    /// Encode app state signed by the players as the final state on this chain
+    ///
+    /// Parameter:
+    /// `app_state`: app state
+    fn encode_final_app_state(
+        app_state: AppStateOf<T>
+    ) -> Vec<u8> {
+        let genesis_hash = frame_system::Module::<T>::block_hash(T::BlockNumber::zero());
+        Self::final_signing_payload(app_state, genesis_hash)
     }
 
-    /// Check if coordinate (x, y) is valid
+    /// Get the bytes of an app state the players sign off-chain
+    ///
+    /// The encoded app state is prefixed with the versioned domain separator of
+    /// this runtime module and chain, see `celer_app_core::state_signing_payload`.
     ///
     /// Parameters:
-    /// `_x`: x coordinate on the board
-    /// `_y`: y coordinate on the board
-    fn check_boundary(x: u8, y: u8) -> bool {
-        // board dimention is 15*15
-        let board_dimention = 15;
-        if x < board_dimention && y < board_dimention {
-            return true;
-        } else {
-            return false;
-        }
+    /// `app_state`: app state
+    /// `genesis_hash`: Hash of the genesis block of the chain
+    pub fn signing_payload(
+        app_state: AppStateOf<T>,
+        genesis_hash: T::Hash
+    ) -> Vec<u8> {
+        celer_app_core::state_signing_payload(SINGLE_GOMOKU_ID, &genesis_hash, &Self::encode_state(app_state))
     }
 
-    /// Check if coordinate (x, y) is valid
+    /// Get the bytes of an app state the players sign off-chain as the final state
+    ///
+    /// The encoded app state is prefixed with the final state domain separator of
+    /// this runtime module and chain, see `celer_app_core::final_state_signing_payload`.
     ///
     /// Parameters:
-    /// `_x`: x coordinate on the board
-    /// `_y`: y coordinate on the board
-    fn state_index(x: u8, y: u8) -> usize {
-        // board dimention is 15*15
-        let board_dimention = 15;
-        let index: usize = (2 + board_dimention * x + y) as usize;
-        return index;
+    /// `app_state`: app state
+    /// `genesis_hash`: Hash of the genesis block of the chain
+    pub fn final_signing_payload(
+        app_state: AppStateOf<T>,
+        genesis_hash: T::Hash
+    ) -> Vec<u8> {
+        celer_app_core::final_state_signing_payload(SINGLE_GOMOKU_ID, &genesis_hash, &Self::encode_state(app_state))
     }
 
-    /// Encode app state
+    /// Encode the fields of an app state
     ///
     /// Parameter:
     /// `app_state`: app state
-    fn encode_app_state(
+    fn encode_state(
         app_state: AppStateOf<T>
     ) -> Vec<u8> {
         let mut encoded = app_state.nonce.encode();
@@ -795,7 +1732,56 @@ impl<T: Trait> Module<T> {
         encoded.extend(app_state.timeout.encode());
         encoded.extend(app_state.session_id.encode());
 
-        return encoded;
+        encoded
+    }
+
+}
+
+/// Length of the board state
+///
+/// Parameter:
+/// `board_size`: Number of rows and columns of the board
+pub fn board_state_len(board_size: u8) -> usize {
+    2 + board_cells(board_size) as usize
+}
+
+/// Number of cells of the board
+///
+/// Parameter:
+/// `board_size`: Number of rows and columns of the board
+fn board_cells(board_size: u8) -> u16 {
+    board_size as u16 * board_size as u16
+}
+
+/// Check if coordinate (x, y) is valid
+///
+/// Parameters:
+/// `board_size`: Number of rows and columns of the board
+/// `_x`: x coordinate on the board
+/// `_y`: y coordinate on the board
+fn check_boundary(board_size: u8, x: u8, y: u8) -> bool {
+    if x < board_size && y < board_size {
+        return true;
+    } else {
+        return false;
     }
+}
 
-} 
\ No newline at end of file
+/// Get the index of coordinate (x, y) in the board state
+///
+/// Parameters:
+/// `board_size`: Number of rows and columns of the board
+/// `_x`: x coordinate on the board
+/// `_y`: y coordinate on the board
+fn state_index(board_size: u8, x: u8, y: u8) -> usize {
+    let index: usize = 2 + board_size as usize * x as usize + y as usize;
+    return index;
+}
+
+/// Count the stones on the board
+///
+/// Parameter:
+/// `board_state`: u8 winner + u8 turn + board_size*board_size board
+fn count_stones(board_state: &[u8]) -> u16 {
+    board_state.iter().skip(2).filter(|&x| *x != 0).count() as u16
+}
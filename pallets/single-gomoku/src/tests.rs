@@ -1,12 +1,18 @@
 use super::*;
 use mock::*;
 use sp_core::{sr25519, Pair, H256};
-use frame_support::{assert_ok, assert_noop};
+use frame_support::{assert_ok, assert_noop, traits::OnInitialize};
+use frame_support::unsigned::ValidateUnsigned;
+use sp_core::offchain::{
+    testing::{TestOffchainExt, TestTransactionPoolExt}, OffchainExt, TransactionPoolExt,
+};
+use sp_runtime::traits::OffchainWorker;
 
 
 #[test]
 fn test_pass_initiate() {
     ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
         let alice_pair = account_pair("Alice");
         let bob_pair = account_pair("Bob");        
         let (players, _) 
@@ -18,18 +24,29 @@ fn test_pass_initiate() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
-            initiate_request)
+            initiate_request.clone())
         );
+        check_gomoku_state_invariants();
+
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+        let expected_event = TestEvent::single_gomoku(RawEvent::AppInitiated(session_id, players.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
     })
 }
 
 #[test]
 fn test_pass_update_by_state_and_player_2_win() {
     ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
         let alice_pair = account_pair("Alice");
         let bob_pair = account_pair("Bob");        
         let (players, players_pair) 
@@ -41,17 +58,27 @@ fn test_pass_update_by_state_and_player_2_win() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         let mut board_state = vec![0; 227];
         board_state[0] = 2; // winner
         board_state[1] = 0; // turn
+        for y in 0..5 {
+            board_state[2 + y] = 2; // (0, y)
+            board_state[2 + 30 + 2 * y] = 1; // (2, 2y)
+        }
         let state_proof = get_state_proof(0, 1, board_state, 0, session_id, players_pair);
         assert_ok!(
             SingleGomoku::update_by_state(
@@ -59,14 +86,20 @@ fn test_pass_update_by_state_and_player_2_win() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
         assert_eq!(
             SingleGomoku::is_finalized(session_id.encode()).unwrap(),
             true,
         );
+        let expected_event = TestEvent::single_gomoku(RawEvent::GameWon(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_gomoku(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
 
         let args_query_outcome = SingleGomokuArgsQueryOutcome {
             session_id: session_id,
-            query_data: 2
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::IsWinner(2)
         };
         assert_eq!(
             SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(), 
@@ -89,14 +122,20 @@ fn test_pass_state_new_game_and_update_by_state() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         let mut board_state = vec![0; 227];
         board_state[0] = 0;
         board_state[1] = 1;
@@ -107,6 +146,7 @@ fn test_pass_state_new_game_and_update_by_state() {
         board_state[6] = 2;
         board_state[7] = 2;
         board_state[8] = 1;
+        board_state[9] = 1;
         let state_proof = get_state_proof(0, 1, board_state, 0, session_id, players_pair);
         assert_ok!(
             SingleGomoku::update_by_state(
@@ -114,6 +154,7 @@ fn test_pass_state_new_game_and_update_by_state() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
 
         let onchain_state = SingleGomoku::get_state(session_id, 2).unwrap();
         assert_eq!(onchain_state[0], 0);
@@ -125,6 +166,7 @@ fn test_pass_state_new_game_and_update_by_state() {
         assert_eq!(onchain_state[6], 2);
         assert_eq!(onchain_state[7], 2);
         assert_eq!(onchain_state[8], 1);
+        assert_eq!(onchain_state[9], 1);
     })
 }
 
@@ -142,14 +184,20 @@ fn test_fail_update_by_state_with_invalid_seq_num() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         let board_state = vec![0; 227];
         let state_proof = get_state_proof(0, 0, board_state, 0, session_id, players_pair);
         assert_noop!(
@@ -157,8 +205,9 @@ fn test_fail_update_by_state_with_invalid_seq_num() {
                 Origin::signed(players[0]),
                 state_proof
             ),
-            "invalid sequence number"
+            Error::<TestRuntime>::InvalidSeqNum
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -176,14 +225,20 @@ fn test_pass_update_by_state_with_higher_seq() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         let mut board_state_1 = vec![0; 227];
         board_state_1[0] = 0;
         board_state_1[1] = 1;
@@ -194,6 +249,7 @@ fn test_pass_update_by_state_with_higher_seq() {
         board_state_1[6] = 2;
         board_state_1[7] = 2;
         board_state_1[8] = 1;
+        board_state_1[9] = 1;
         let state_proof = get_state_proof(0, 1, board_state_1.clone(), 0, session_id, players_pair.clone());
         assert_ok!(
             SingleGomoku::update_by_state(
@@ -201,6 +257,7 @@ fn test_pass_update_by_state_with_higher_seq() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
 
         let mut board_state_2 = vec![0; 227];
         board_state_2[0] = 0; // winner
@@ -219,6 +276,7 @@ fn test_pass_update_by_state_with_higher_seq() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
 
         let onchain_state = SingleGomoku::get_state(session_id, 2).unwrap();
         assert_eq!(onchain_state[0], 0);
@@ -250,14 +308,20 @@ fn test_player2_palces_stone_at_3_12_and_player1_takes_the_turn() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
        
         // place stone 
         place_stone(session_id, players.clone(), players_pair);
@@ -271,6 +335,7 @@ fn test_player2_palces_stone_at_3_12_and_player1_takes_the_turn() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
         let turn = SingleGomoku::get_state(session_id, 0).unwrap();
         assert_eq!(turn, vec![1]);
     })
@@ -290,14 +355,20 @@ fn test_fail_player2_tries_to_place_another_stone() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         
         // place stone
         place_stone(session_id, players.clone(), players_pair);
@@ -311,6 +382,7 @@ fn test_fail_player2_tries_to_place_another_stone() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         assert_noop!(
             SingleGomoku::update_by_action(
@@ -318,8 +390,9 @@ fn test_fail_player2_tries_to_place_another_stone() {
                 session_id,
                 vec![4, 12]
             ),
-            "not your turn"
+            Error::<TestRuntime>::NotYourTurn
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -337,14 +410,20 @@ fn test_fail_player1_place_a_stone_at_occupied_slot_3_12() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         
         // place stone
         place_stone(session_id, players.clone(), players_pair);
@@ -358,6 +437,7 @@ fn test_fail_player1_place_a_stone_at_occupied_slot_3_12() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         assert_noop!(
             SingleGomoku::update_by_action(
@@ -365,8 +445,9 @@ fn test_fail_player1_place_a_stone_at_occupied_slot_3_12() {
                 session_id,
                 vec![3, 12]
             ),
-            "slot is occupied"
+            Error::<TestRuntime>::SlotOccupied
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -384,14 +465,20 @@ fn test_player1_places_a_stone_at_0_4_and_wins() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         
         // place stone
         place_stone(session_id, players.clone(), players_pair);
@@ -405,6 +492,7 @@ fn test_player1_places_a_stone_at_0_4_and_wins() {
                 vec![3, 12]
             )
         );
+        check_gomoku_state_invariants();
 
         assert_ok!(
             SingleGomoku::update_by_action(
@@ -413,9 +501,21 @@ fn test_player1_places_a_stone_at_0_4_and_wins() {
                 vec![0, 4]
             )
         );
+        check_gomoku_state_invariants();
         let turn = SingleGomoku::get_state(session_id, 0).unwrap();
         assert_eq!(turn, vec![0]);
 
+        let expected_events = vec![
+            TestEvent::single_gomoku(RawEvent::ActionApplied(session_id, players[1], 3, vec![3, 12])),
+            TestEvent::single_gomoku(RawEvent::StonePlaced(session_id, 3, 12, 2)),
+            TestEvent::single_gomoku(RawEvent::ActionApplied(session_id, players[0], 4, vec![0, 4])),
+            TestEvent::single_gomoku(RawEvent::StonePlaced(session_id, 0, 4, 1)),
+            TestEvent::single_gomoku(RawEvent::GameWon(session_id, players[0])),
+            TestEvent::single_gomoku(RawEvent::Finalized(session_id)),
+        ];
+        let events: Vec<TestEvent> = System::events().into_iter().map(|a| a.event).collect();
+        assert_eq!(events, expected_events);
+
         assert_eq!(
             SingleGomoku::is_finalized(session_id.encode()).unwrap(),
             true,
@@ -423,12 +523,168 @@ fn test_player1_places_a_stone_at_0_4_and_wins() {
 
         let args_query_outcome = SingleGomokuArgsQueryOutcome {
             session_id: session_id,
-            query_data: 1
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::IsWinner(1)
         };
         assert_eq!(
             SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(), 
             true.encode()    
         );
+
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome
+        };
+        assert_eq!(
+            SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Winner(0).encode()
+        );
+        let payout = |player_index: u8| SingleGomoku::get_outcome(
+            SingleGomokuArgsQueryOutcome {
+                session_id: session_id,
+                version: GAME_QUERY_VERSION,
+                query_data: GameQuery::Payout { player_index: player_index, total: 100 }
+            }.encode()
+        );
+        assert_eq!(payout(0).unwrap(), 100u128.encode());
+        assert_eq!(payout(1).unwrap(), 0u128.encode());
+        assert_noop!(payout(2), Error::<TestRuntime>::InvalidPlayerIndex);
+    })
+}
+
+#[test]
+fn test_draw_when_onchain_stones_exceed_max_stone_onchain() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");        
+        let (players, players_pair) 
+            = get_sorted_peer(alice_pair, bob_pair);
+        
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 0,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        check_gomoku_state_invariants();
+
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+        // place stone
+        place_stone(session_id, players.clone(), players_pair);
+
+        let settle_finalized_time = SingleGomoku::get_settle_finalized_time(session_id).unwrap();
+        System::set_block_number(settle_finalized_time + 1);
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![3, 12]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(
+            SingleGomoku::is_finalized(session_id.encode()).unwrap(),
+            true,
+        );
+        let expected_event = TestEvent::single_gomoku(RawEvent::Draw(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_gomoku(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_gomoku(RawEvent::GameWon(session_id, players[1]));
+        assert!(!System::events().iter().any(|a| a.event == expected_event));
+
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome
+        };
+        assert_eq!(
+            SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Draw.encode()
+        );
+        let payout = |player_index: u8| SingleGomoku::get_outcome(
+            SingleGomokuArgsQueryOutcome {
+                session_id: session_id,
+                version: GAME_QUERY_VERSION,
+                query_data: GameQuery::Payout { player_index: player_index, total: 101 }
+            }.encode()
+        );
+        assert_eq!(payout(0).unwrap(), 51u128.encode());
+        assert_eq!(payout(1).unwrap(), 50u128.encode());
+    })
+}
+
+#[test]
+fn test_draw_when_onchain_stones_exceed_max_stone_onchain_of_255() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 0,
+            max_stone_onchain: 255,
+            board_size: MAX_BOARD_SIZE,
+            win_length: MAX_BOARD_SIZE,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+        let mut board_state = vec![0; board_state_len(MAX_BOARD_SIZE)];
+        board_state[1] = 1; // turn
+        let state_proof = get_state_proof(0, 1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+
+        // fill the board row by row, no row, column or diagonal of 19 stones is completed
+        System::set_block_number(4);
+        for i in 0..256u16 {
+            assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), false);
+            let x = (i / MAX_BOARD_SIZE as u16) as u8;
+            let y = (i % MAX_BOARD_SIZE as u16) as u8;
+            assert_ok!(
+                SingleGomoku::update_by_action(
+                    Origin::signed(players[i as usize % 2]),
+                    session_id,
+                    vec![x, y]
+                )
+            );
+        }
+        check_gomoku_state_invariants();
+
+        // the 256th on-chain stone exceeds max_stone_onchain
+        assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), true);
+        assert_eq!(SingleGomoku::gomoku_info(session_id).unwrap().gomoku_state.stone_num_onchain, Some(256));
+        let expected_event = TestEvent::single_gomoku(RawEvent::Draw(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
     })
 }
 
@@ -446,14 +702,20 @@ fn test_fail_finalize_on_action_timeout_before_action_deadline() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         
         let mut board_state = vec![0; 227];
         board_state[0] = 0; // winner
@@ -472,14 +734,16 @@ fn test_fail_finalize_on_action_timeout_before_action_deadline() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
 
         assert_noop!(
             SingleGomoku::finalize_on_action_timeout(
                 Origin::signed(players[0]),
                 session_id
             ),
-            "while settling"
+            Error::<TestRuntime>::WhileSettling
         );
+        check_gomoku_state_invariants();
     })
 }
 
@@ -497,14 +761,20 @@ fn test_pass_finalize_on_action_timeout_after_action_deadline() {
             timeout: 2,
             min_stone_offchain: 5,
             max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
         };
 
         assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
             initiate_request.clone())
         );
+        check_gomoku_state_invariants();
 
-        let session_id = SingleGomoku::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
         
         let mut board_state = vec![0; 227];
         board_state[0] = 0; // winner
@@ -523,6 +793,7 @@ fn test_pass_finalize_on_action_timeout_after_action_deadline() {
                 state_proof
             )
         );
+        check_gomoku_state_invariants();
 
         let deadline = SingleGomoku::get_action_deadline(session_id).unwrap();
         System::set_block_number(deadline + 1);
@@ -532,14 +803,22 @@ fn test_pass_finalize_on_action_timeout_after_action_deadline() {
                 session_id
             )
         );
+        check_gomoku_state_invariants();
         assert_eq!(
             SingleGomoku::is_finalized(session_id.encode()).unwrap(),
             true,
         );
+        let expected_event = TestEvent::single_gomoku(RawEvent::GameWon(session_id, players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_gomoku(RawEvent::FinalizedOnTimeout(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_gomoku(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
 
         let args_query_outcome = SingleGomokuArgsQueryOutcome {
             session_id: session_id,
-            query_data: 1
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::IsWinner(1)
         };
         assert_eq!(
             SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(), 
@@ -548,67 +827,1956 @@ fn test_pass_finalize_on_action_timeout_after_action_deadline() {
     })
 }
 
-fn get_state_proof(
-    nonce: u128,
-    seq: u128,
-    board_state: Vec<u8>,
-    timeout: BlockNumber,
-    session_id: H256,
-    players_pair: Vec<sr25519::Pair>,
-) -> StateProof<BlockNumber, H256, Signature> {
-    let app_state = AppState {
-        nonce: nonce,
-        seq_num: seq,
-        board_state: board_state,
-        timeout: timeout,
-        session_id: session_id,
-    };
-    let encoded = SingleGomoku::encode_app_state(app_state.clone());
-    let sig_1 = players_pair[0].sign(&encoded);
-    let sig_2 = players_pair[1].sign(&encoded);
-    let state_proof = StateProof {
-        app_state: app_state,
-        sigs: vec![sig_1, sig_2]
-    };
+#[test]
+fn test_fail_initiate() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
 
-    return state_proof;
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: vec![players[0]],
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            SingleGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayerNum
+        );
+        check_gomoku_state_invariants();
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: vec![players[1], players[0]],
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            SingleGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayers
+        );
+        check_gomoku_state_invariants();
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        check_gomoku_state_invariants();
+        assert_noop!(
+            SingleGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::AppAlreadyExists
+        );
+        check_gomoku_state_invariants();
+    })
 }
 
-fn place_stone(session_id: H256, players: Vec<AccountId>, players_pair: Vec<sr25519::Pair>) {
-    let mut board_state_1 = vec![0; 227];
-    board_state_1[0] = 0;
-    board_state_1[1] = 1;
-    board_state_1[2] = 2;
-    board_state_1[3] = 2;
-    board_state_1[4] = 1;
-    board_state_1[5] = 1;
-    board_state_1[6] = 2;
-    board_state_1[7] = 2;
-    board_state_1[8] = 1;
-    let state_proof = get_state_proof(0, 1, board_state_1.clone(), 0, session_id, players_pair.clone());
-    assert_ok!(
-        SingleGomoku::update_by_state(
+#[test]
+fn test_pass_initiate_after_front_run_with_other_parameters() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // the opponent initiates first with the same nonce and players but no timeout and no on-chain stones
+        let front_run_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 0,
+            min_stone_offchain: 5,
+            max_stone_onchain: 0,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[1]),
+            front_run_request.clone()
+        ));
+        check_gomoku_state_invariants();
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
-            state_proof
-        )
-    );
+            initiate_request.clone()
+        ));
+        check_gomoku_state_invariants();
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+        assert_ne!(session_id, SingleGomoku::get_session_id(&front_run_request));
+        let expected_event = TestEvent::single_gomoku(RawEvent::AppInitiated(session_id, players.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
 
-    let mut board_state_2 = vec![0; 227];
-    board_state_2[0] = 0; // winner
-    board_state_2[1] = 2; // turn
-    board_state_2[2] = 1; // (0, 0)
-    board_state_2[3] = 1; // (0, 1)
-    board_state_2[4] = 1; // (0, 2)
-    board_state_2[5] = 1; // (0, 3)
-    board_state_2[101] = 2; 
-    board_state_2[102] = 2;
-    board_state_2[103] = 2;
-    let state_proof = get_state_proof(0, 2, board_state_2, 0, session_id, players_pair);
-    assert_ok!(
-        SingleGomoku::update_by_state(
+#[test]
+fn test_fail_update_by_state_with_invalid_state_proof() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
             Origin::signed(players[0]),
-            state_proof
-        )
-    );
+            initiate_request.clone()
+        ));
+        check_gomoku_state_invariants();
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+        let state_proof = get_state_proof(0, 1, vec![0; 10], 2, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidBoardStateLength
+        );
+        check_gomoku_state_invariants();
+
+        let mut board_state = vec![0; 227];
+        board_state[1] = 1;
+        for i in 2..8 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let mut state_proof = get_state_proof(0, 1, board_state.clone(), 2, session_id, players_pair.clone());
+        state_proof.sigs.pop();
+        assert_noop!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidSignatureCount
+        );
+        check_gomoku_state_invariants();
+
+        let state_proof = get_state_proof(1, 1, board_state, 2, session_id, players_pair);
+        assert_noop!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::NonceMismatch
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_fail_update_by_action_out_of_boundary() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair);
+        System::set_block_number(4);
+        assert_noop!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![15, 0]
+            ),
+            Error::<TestRuntime>::OutOfBoundary
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_fail_update_by_action_with_invalid_turn_or_action() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
 
-}
\ No newline at end of file
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        System::set_block_number(4);
+        assert_noop!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![7]
+            ),
+            Error::<TestRuntime>::InvalidActionLength
+        );
+        check_gomoku_state_invariants();
+
+        let session_id = settle_app(1, players.clone(), players_pair.clone());
+        let mut board_state = vec![0; 227];
+        board_state[1] = 0; // turn of no player
+        for i in 2..8 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(1, 2, board_state, 2, session_id, players_pair);
+        assert_noop!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidTurn
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_fail_update_by_state_with_inconsistent_board() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+
+        // cell of no player
+        let mut board_state = vec![0; 227];
+        board_state[1] = 1; // turn
+        board_state[2] = 3; // (0, 0)
+        board_state[3] = 2; // (0, 1)
+        let state_proof = get_state_proof(0, 2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidBoardCell
+        );
+        check_gomoku_state_invariants();
+
+        // two black stones in a row without a white one
+        let mut board_state = vec![0; 227];
+        board_state[1] = 2; // turn
+        board_state[2] = 1; // (0, 0)
+        board_state[3] = 1; // (0, 1)
+        let state_proof = get_state_proof(0, 2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidStoneCount
+        );
+        check_gomoku_state_invariants();
+
+        // player 2 to move after its own stone
+        let mut board_state = vec![0; 227];
+        board_state[1] = 2; // turn
+        board_state[2] = 1; // (0, 0)
+        board_state[3] = 2; // (0, 1)
+        let state_proof = get_state_proof(0, 2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidStoneCount
+        );
+        check_gomoku_state_invariants();
+
+        // winner without a five
+        let mut board_state = vec![0; 227];
+        board_state[0] = 1; // winner
+        for y in 0..4 {
+            board_state[2 + y] = 1; // (0, y)
+        }
+        for y in 0..3 {
+            board_state[2 + 30 + y] = 2; // (2, y)
+        }
+        let state_proof = get_state_proof(0, 2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidWinnerState
+        );
+        check_gomoku_state_invariants();
+
+        // five of player 1 while the game goes on
+        let mut board_state = vec![0; 227];
+        board_state[1] = 2; // turn
+        for y in 0..5 {
+            board_state[2 + y] = 1; // (0, y)
+        }
+        for y in 0..4 {
+            board_state[2 + 30 + y] = 2; // (2, y)
+        }
+        let state_proof = get_state_proof(0, 2, board_state, 2, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidWinnerState
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_player1_wins_with_a_run_longer_than_five() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        let mut board_state = vec![0; 227];
+        board_state[1] = 1; // turn
+        for y in &[0, 1, 2, 4, 5, 6] {
+            board_state[2 + y] = 1; // (0, y)
+            board_state[2 + 30 + 2 * y] = 2; // (2, 2y)
+        }
+        let state_proof = get_state_proof(0, 2, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        System::set_block_number(7);
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![0, 3]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_state(session_id, 1).unwrap(), vec![1]);
+        assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), true);
+    })
+}
+
+#[test]
+fn test_celer_app_query() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        check_gomoku_state_invariants();
+
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+        let mut board_state = vec![0; 227];
+        board_state[1] = 1;
+        for i in 2..8 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(0, 1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+
+        assert_eq!(<SingleGomoku as CelerAppQuery<H256, BlockNumber>>::is_finalized(session_id.encode()).unwrap(), false);
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::IsWinner(0)
+        };
+        assert_eq!(
+            <SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_outcome(args_query_outcome.encode()).unwrap(),
+            true.encode()
+        );
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome
+        };
+        assert_eq!(
+            <SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Undecided.encode()
+        );
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Payout { player_index: 0, total: 100 }
+        };
+        assert_noop!(
+            <SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_outcome(args_query_outcome.encode()),
+            Error::<TestRuntime>::NotFinalized
+        );
+        assert_eq!(<SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_state(session_id, 0), Some(vec![1]));
+        assert_eq!(<SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_status(session_id), Some(AppStatus::Settle));
+        assert_eq!(<SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_settle_finalized_time(session_id), Some(3));
+        assert_eq!(<SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_action_deadline(session_id), Some(5));
+        assert_eq!(<SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_seq_num(session_id), Some(1));
+        assert_eq!(<SingleGomoku as CelerAppQuery<H256, BlockNumber>>::get_status(H256::zero()), None);
+    })
+}
+
+#[test]
+fn test_initiate_reserves_deposit() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair);
+        // base + 2 players + BOARD_STATE_LEN bytes of board
+        assert_eq!(SingleGomoku::deposit_of(session_id), Some((players[0], 241)));
+        assert_eq!(Balances::reserved_balance(players[0]), 241);
+        assert_eq!(Balances::free_balance(players[0]), 759);
+        assert_eq!(Balances::reserved_balance(players[1]), 0);
+
+        // initiator can not pay the deposit
+        let carl = account_pair("Carl").public();
+        let initiate_request = AppInitiateRequest {
+            nonce: 1,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            SingleGomoku::app_initiate(
+                Origin::signed(carl),
+                initiate_request
+            ),
+            pallet_balances::Error::<TestRuntime, pallet_balances::DefaultInstance>::InsufficientBalance
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_clear_session() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        assert_noop!(
+            SingleGomoku::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::NotFinalized
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            <SingleGomoku as ConsumeOutcome<H256>>::consume_outcome(session_id),
+            Error::<TestRuntime>::NotFinalized
+        );
+
+        System::set_block_number(6);
+        assert_ok!(
+            SingleGomoku::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::finalized_at(session_id), Some(6));
+        assert_noop!(
+            SingleGomoku::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::OutcomeNotConsumed
+        );
+        check_gomoku_state_invariants();
+
+        assert_ok!(<SingleGomoku as ConsumeOutcome<H256>>::consume_outcome(session_id));
+        let expected_event = TestEvent::single_gomoku(RawEvent::OutcomeConsumed(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_eq!(SingleGomoku::clear_queue(16), vec![session_id]);
+
+        System::set_block_number(15);
+        let stranger = account_pair("Carl").public();
+        assert_noop!(
+            SingleGomoku::clear_session(
+                Origin::signed(stranger),
+                session_id
+            ),
+            Error::<TestRuntime>::NotPlayer
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            SingleGomoku::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::RetentionPeriodNotPassed
+        );
+        check_gomoku_state_invariants();
+
+        System::set_block_number(16);
+        assert_ok!(
+            SingleGomoku::clear_session(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_id), None);
+        assert_eq!(SingleGomoku::finalized_at(session_id), None);
+        assert_eq!(SingleGomoku::outcome_consumed(session_id), false);
+        assert_eq!(SingleGomoku::deposit_of(session_id), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        let expected_event = TestEvent::single_gomoku(RawEvent::SessionCleared(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_noop!(
+            SingleGomoku::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::SingleGomokuInfoNotExist
+        );
+        check_gomoku_state_invariants();
+
+        // queued id of the cleared app is skipped
+        SingleGomoku::on_initialize(16);
+        check_gomoku_state_invariants();
+        assert_eq!(
+            System::events().iter().filter(|a| a.event == expected_event).count(),
+            1
+        );
+
+        // the cleared app can not be initiated again to replay its old states
+        assert_eq!(SingleGomoku::is_cleared(session_id), true);
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_eq!(SingleGomoku::get_session_id(&initiate_request), session_id);
+        assert_noop!(
+            SingleGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::AppAlreadyExists
+        );
+        let mut board_state = vec![0; 227];
+        board_state[1] = 1;
+        for i in 2..8 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(0, 1, board_state, 2, session_id, players_pair);
+        assert_noop!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::SingleGomokuInfoNotExist
+        );
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_clear_sessions_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+
+        System::set_block_number(6);
+        for session_id in session_ids.iter() {
+            assert_ok!(
+                SingleGomoku::finalize_on_action_timeout(
+                    Origin::signed(players[0]),
+                    *session_id
+                )
+            );
+            check_gomoku_state_invariants();
+            assert_ok!(<SingleGomoku as ConsumeOutcome<H256>>::consume_outcome(*session_id));
+        }
+        assert_eq!(SingleGomoku::clear_queue(16), session_ids);
+
+        // at most MaxClearPerBlock apps are cleared in a block
+        SingleGomoku::on_initialize(16);
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_ids[0]), None);
+        assert_eq!(SingleGomoku::get_status(session_ids[1]), None);
+        assert!(SingleGomoku::get_status(session_ids[2]).is_some());
+        assert_eq!(SingleGomoku::clear_queue(16), Vec::<H256>::new());
+        assert_eq!(SingleGomoku::clear_queue(17), vec![session_ids[2]]);
+
+        assert_eq!(Balances::reserved_balance(players[0]), 241);
+
+        SingleGomoku::on_initialize(17);
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_ids[2]), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        assert_eq!(SingleGomoku::clear_queue(17), Vec::<H256>::new());
+    })
+}
+
+#[test]
+fn test_finalize_expired_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+        // settle period and action period end at block 5
+        assert_eq!(SingleGomoku::deadline_queue(6), session_ids);
+
+        // the action moves the deadline of the first app to block 6
+        System::set_block_number(4);
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_ids[0],
+                vec![5, 5]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::deadline_queue(7), vec![session_ids[0]]);
+
+        // at most MaxFinalizePerBlock apps are finalized in a block,
+        // the first app is skipped since its deadline moved
+        System::set_block_number(6);
+        SingleGomoku::on_initialize(6);
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_ids[0]), Some(AppStatus::Action));
+        assert_eq!(SingleGomoku::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(SingleGomoku::get_status(session_ids[2]), Some(AppStatus::Settle));
+        assert_eq!(SingleGomoku::deadline_queue(6), Vec::<H256>::new());
+        assert_eq!(SingleGomoku::deadline_queue(7), vec![session_ids[0], session_ids[2]]);
+        let expected_event = TestEvent::single_gomoku(RawEvent::FinalizedOnTimeout(session_ids[1], players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        System::set_block_number(7);
+        SingleGomoku::on_initialize(7);
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(SingleGomoku::is_finalized(session_ids[2].encode()).unwrap(), true);
+        assert_eq!(SingleGomoku::deadline_queue(7), Vec::<H256>::new());
+        let expected_event = TestEvent::single_gomoku(RawEvent::FinalizedOnTimeout(session_ids[0], players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+#[allow(deprecated)] // ValidateUnsigned
+fn test_offchain_worker_submits_finalization() {
+    let (offchain, _) = TestOffchainExt::new();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    let mut ext = ExtBuilder::build();
+    ext.register_extension(OffchainExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+        let session_ids: Vec<H256> = (0..4)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+
+        // the action moves the deadline of the first app to block 6
+        System::set_block_number(4);
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_ids[0],
+                vec![5, 5]
+            )
+        );
+        check_gomoku_state_invariants();
+
+        // nothing is submitted before the action deadline passed
+        System::set_block_number(5);
+        SingleGomoku::offchain_worker(5);
+        assert!(pool_state.read().transactions.is_empty());
+
+        // the apps beyond MaxFinalizePerBlock are moved to the queue of the next block
+        System::set_block_number(6);
+        SingleGomoku::on_initialize(6);
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_ids[0]), Some(AppStatus::Action));
+        assert_eq!(SingleGomoku::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(
+            SingleGomoku::deadline_queue(7),
+            vec![session_ids[0], session_ids[2], session_ids[3]]
+        );
+
+        // on_initialize of the next block finalizes the first MaxFinalizePerBlock apps of its queue,
+        // the off-chain worker only submits the finalization of the expired app behind them
+        SingleGomoku::offchain_worker(6);
+        let tx = pool_state.write().transactions.pop().unwrap();
+        assert!(pool_state.read().transactions.is_empty());
+        let tx = Extrinsic::decode(&mut &*tx).unwrap();
+        assert_eq!(tx.signature, None);
+        let call = crate::Call::finalize_on_action_timeout_unsigned(session_ids[3]);
+        assert_eq!(tx.call, mock::Call::SingleGomoku(call.clone()));
+
+        assert!(SingleGomoku::validate_unsigned(TransactionSource::External, &call).is_ok());
+        assert_ok!(SingleGomoku::finalize_on_action_timeout_unsigned(Origin::none(), session_ids[3]));
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::is_finalized(session_ids[3].encode()).unwrap(), true);
+        let expected_event = TestEvent::single_gomoku(RawEvent::FinalizedOnTimeout(session_ids[3], players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        // the finalization of a finalized app is stale
+        assert_eq!(
+            SingleGomoku::validate_unsigned(TransactionSource::External, &call),
+            InvalidTransaction::Stale.into()
+        );
+        System::set_block_number(7);
+        SingleGomoku::on_initialize(7);
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(SingleGomoku::is_finalized(session_ids[2].encode()).unwrap(), true);
+        SingleGomoku::offchain_worker(7);
+        assert!(pool_state.read().transactions.is_empty());
+    })
+}
+
+#[test]
+fn test_fail_initiate_with_invalid_board_config() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = |board_size: u8, win_length: u8| AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: board_size,
+            win_length: win_length,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_noop!(
+            SingleGomoku::app_initiate(Origin::signed(players[0]), initiate_request(MIN_BOARD_SIZE - 1, 3)),
+            Error::<TestRuntime>::InvalidBoardSize
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            SingleGomoku::app_initiate(Origin::signed(players[0]), initiate_request(MAX_BOARD_SIZE + 1, 5)),
+            Error::<TestRuntime>::InvalidBoardSize
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            SingleGomoku::app_initiate(Origin::signed(players[0]), initiate_request(9, MIN_WIN_LENGTH - 1)),
+            Error::<TestRuntime>::InvalidWinLength
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            SingleGomoku::app_initiate(Origin::signed(players[0]), initiate_request(9, 10)),
+            Error::<TestRuntime>::InvalidWinLength
+        );
+        check_gomoku_state_invariants();
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request(MAX_BOARD_SIZE, MAX_BOARD_SIZE)
+        ));
+        check_gomoku_state_invariants();
+    })
+}
+
+#[test]
+fn test_player1_wins_with_four_in_a_row_on_9x9_board() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 0,
+            max_stone_onchain: 5,
+            board_size: 9,
+            win_length: 4,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        check_gomoku_state_invariants();
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+        // board state of the default board size is rejected
+        let state_proof = get_state_proof(0, 1, vec![0; BOARD_STATE_LEN], 0, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidBoardStateLength
+        );
+        check_gomoku_state_invariants();
+
+        let mut board_state = vec![0; board_state_len(9)];
+        assert_eq!(board_state.len(), 83);
+        board_state[0] = 0; // winner
+        board_state[1] = 2; // turn
+        board_state[2] = 1; // (0, 0)
+        board_state[3] = 1; // (0, 1)
+        board_state[4] = 1; // (0, 2)
+        board_state[2 + 9 * 4 + 4] = 2; // (4, 4)
+        board_state[2 + 9 * 4 + 5] = 2; // (4, 5)
+        let state_proof = get_state_proof(0, 1, board_state, 0, session_id, players_pair);
+        assert_ok!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+
+        let settle_finalized_time = SingleGomoku::get_settle_finalized_time(session_id).unwrap();
+        System::set_block_number(settle_finalized_time + 1);
+        assert_noop!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![9, 0]
+            ),
+            Error::<TestRuntime>::OutOfBoundary
+        );
+        check_gomoku_state_invariants();
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![8, 8]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![0, 3]
+            )
+        );
+        check_gomoku_state_invariants();
+
+        assert_eq!(
+            SingleGomoku::is_finalized(session_id.encode()).unwrap(),
+            true,
+        );
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome
+        };
+        assert_eq!(
+            SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Winner(0).encode()
+        );
+    })
+}
+
+#[test]
+fn test_overline_does_not_win_under_standard_rule_set() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // (7, 2) ~ (7, 5) and (7, 7) of player 1
+        let mut stones = vec![(7, 2, 1), (7, 3, 1), (7, 4, 1), (7, 5, 1), (7, 7, 1)];
+        stones.extend(vec![(0, 0, 2), (0, 2, 2), (14, 14, 2), (14, 12, 2), (14, 10, 2)]);
+        let session_id = settle_rule_set_app(RuleSet::Standard, stones, players.clone(), players_pair);
+
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![7, 6]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), false);
+        // turn goes to player 2
+        assert_eq!(SingleGomoku::get_state(session_id, 0).unwrap(), vec![2]);
+    })
+}
+
+#[test]
+fn test_fail_player1_plays_forbidden_move_under_renju_rule_set() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // (7, 7) makes two open threes of player 1
+        let mut stones = vec![(7, 5, 1), (7, 6, 1), (5, 7, 1), (6, 7, 1)];
+        stones.extend(vec![(0, 0, 2), (0, 2, 2), (14, 14, 2), (14, 12, 2)]);
+        let session_id = settle_rule_set_app(RuleSet::Renju, stones, players.clone(), players_pair);
+
+        assert_noop!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![7, 7]
+            ),
+            Error::<TestRuntime>::ForbiddenMove
+        );
+        check_gomoku_state_invariants();
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![7, 8]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_state(session_id, 0).unwrap(), vec![2]);
+    })
+}
+
+#[test]
+fn test_draw_when_board_fills_up_after_update_by_state() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: 3,
+            win_length: 3,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        check_gomoku_state_invariants();
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+        // 1 2 1
+        // 1 2 2
+        // 2 1 _
+        let mut board_state = vec![0; board_state_len(3)];
+        board_state[1] = 1; // turn
+        board_state[2..10].copy_from_slice(&[1, 2, 1, 1, 2, 2, 2, 1]);
+        let state_proof = get_state_proof(0, 1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::gomoku_info(session_id).unwrap().gomoku_state.stone_num, Some(8));
+
+        System::set_block_number(4);
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![2, 2]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_state(session_id, 0).unwrap(), vec![0]);
+        assert_eq!(SingleGomoku::get_state(session_id, 1).unwrap(), vec![0]);
+        assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), true);
+        let expected_event = TestEvent::single_gomoku(RawEvent::Draw(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_draw_with_full_board_by_update_by_state_and_finalize_by_agreement() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // 1 2 1
+        // 1 2 2
+        // 2 1 1
+        let mut board_state = vec![0; board_state_len(3)];
+        board_state[2..].copy_from_slice(&[1, 2, 1, 1, 2, 2, 2, 1, 1]);
+
+        for &agreed in [false, true].iter() {
+            let initiate_request = AppInitiateRequest {
+                nonce: agreed as u128,
+                players: players.clone(),
+                timeout: 2,
+                min_stone_offchain: 5,
+                max_stone_onchain: 5,
+                board_size: 3,
+                win_length: 3,
+                rule_set: RuleSet::Freestyle,
+                time_bank: None,
+                unilateral: false,
+            };
+            assert_ok!(SingleGomoku::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone())
+            );
+            let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+            // the finished draw has turn 0
+            if agreed {
+                let state_proof = get_final_state_proof(1, 1, board_state.clone(), 2, session_id, players_pair.clone());
+                assert_ok!(SingleGomoku::finalize_by_agreement(Origin::signed(players[0]), state_proof));
+            } else {
+                let state_proof = get_state_proof(0, 1, board_state.clone(), 2, session_id, players_pair.clone());
+                assert_ok!(SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof));
+            }
+            check_gomoku_state_invariants();
+            assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), true);
+            assert_eq!(SingleGomoku::get_state(session_id, 2).unwrap(), board_state);
+            let expected_event = TestEvent::single_gomoku(RawEvent::Draw(session_id));
+            assert!(System::events().iter().any(|a| a.event == expected_event));
+            let args_query_outcome = SingleGomokuArgsQueryOutcome {
+                session_id: session_id,
+                version: GAME_QUERY_VERSION,
+                query_data: GameQuery::Outcome
+            };
+            assert_eq!(
+                SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+                GameOutcome::Draw.encode()
+            );
+        }
+
+        // a board with an empty cell is not over
+        let initiate_request = AppInitiateRequest {
+            nonce: 2,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: 3,
+            win_length: 3,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+        board_state[10] = 0;
+        let state_proof = get_state_proof(2, 1, board_state, 2, session_id, players_pair);
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidTurn
+        );
+    })
+}
+
+#[test]
+fn test_move_log_and_replay() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        let initial_board = SingleGomoku::get_state(session_id, 2).unwrap();
+        assert_eq!(SingleGomoku::move_log(session_id), vec![]);
+        assert_eq!(SingleGomoku::move_log_base(session_id), Some((initial_board.clone(), 0)));
+
+        let moves = [(0, 7, 7), (1, 8, 8), (0, 7, 8), (1, 8, 9), (0, 7, 9)];
+        for (i, &(player, x, y)) in moves.iter().enumerate() {
+            System::set_block_number(4 + i as u64);
+            assert_ok!(
+                SingleGomoku::update_by_action(
+                    Origin::signed(players[player]),
+                    session_id,
+                    vec![x, y]
+                )
+            );
+            check_gomoku_state_invariants();
+            if i == 2 {
+                let rules = SingleGomoku::get_game_rules(session_id).unwrap();
+                let move_log = SingleGomoku::move_log(session_id);
+                assert_eq!(
+                    move_log,
+                    vec![
+                        MoveRecord { seq_num: 2, player: players[0], x: 7, y: 7, block: 4 },
+                        MoveRecord { seq_num: 3, player: players[1], x: 8, y: 8, block: 5 },
+                        MoveRecord { seq_num: 4, player: players[0], x: 7, y: 8, block: 6 },
+                    ]
+                );
+                assert_eq!(
+                    SingleGomoku::replay(&rules, initial_board.clone(), &move_log),
+                    Ok(SingleGomoku::get_state(session_id, 2).unwrap())
+                );
+                assert_eq!(
+                    SingleGomoku::replay(&rules, initial_board.clone(), &[move_log[0].clone(), move_log[0].clone()]),
+                    Err(ReplayError::SlotOccupied(1))
+                );
+                assert_eq!(
+                    SingleGomoku::replay(&rules, initial_board[1..].to_vec(), &move_log),
+                    Err(ReplayError::InvalidBoard)
+                );
+            }
+        }
+
+        // only the latest MaxMoveLogLength moves are kept
+        let move_log = SingleGomoku::move_log(session_id);
+        assert_eq!(move_log.len(), 4);
+        assert_eq!(move_log[0].seq_num, 3);
+        assert_eq!(move_log[3], MoveRecord { seq_num: 6, player: players[0], x: 7, y: 9, block: 8 });
+
+        // the base board of the move log advances past the dropped move
+        let (base, moves_before) = SingleGomoku::move_log_base(session_id).unwrap();
+        assert_eq!(moves_before, 1);
+        let mut expected_base = initial_board.clone();
+        expected_base[1] = 2; // turn of the first kept move
+        expected_base[114] = 1; // (7, 7)
+        assert_eq!(base, expected_base);
+        let rules = SingleGomoku::get_game_rules(session_id).unwrap();
+        let rules = GameRules { max_stone_onchain: rules.max_stone_onchain - moves_before as u8, ..rules };
+        assert_eq!(
+            SingleGomoku::replay(&rules, base, &move_log),
+            Ok(SingleGomoku::get_state(session_id, 2).unwrap())
+        );
+
+        // a settled off-chain state restarts the move log
+        let board_state = SingleGomoku::get_state(session_id, 2).unwrap();
+        let state_proof = get_state_proof(0, 7, board_state.clone(), 2, session_id, players_pair);
+        assert_ok!(SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof));
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::move_log(session_id), vec![]);
+        assert_eq!(SingleGomoku::move_log_base(session_id), Some((board_state, 0)));
+    })
+}
+
+#[test]
+fn test_replay_move_log_from_storage() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // the settled board is the base of the move log
+        let session_id = settle_app(0, players.clone(), players_pair);
+        let settled_board = SingleGomoku::get_state(session_id, 2).unwrap();
+        assert_eq!(SingleGomoku::move_log_base(session_id), Some((settled_board.clone(), 0)));
+        assert_eq!(replay_from_storage(session_id), settled_board);
+
+        let moves = [(0, 7, 7), (1, 8, 8), (0, 7, 8)];
+        for (i, &(player, x, y)) in moves.iter().enumerate() {
+            System::set_block_number(4 + i as u64);
+            assert_ok!(
+                SingleGomoku::update_by_action(
+                    Origin::signed(players[player]),
+                    session_id,
+                    vec![x, y]
+                )
+            );
+            // the base and the log in storage give the current board without the off-chain state
+            assert_eq!(replay_from_storage(session_id), SingleGomoku::get_state(session_id, 2).unwrap());
+        }
+        assert_eq!(SingleGomoku::move_log_base(session_id), Some((settled_board, 0)));
+    })
+}
+
+/// Replay the move log of an app from its base board, reading both from storage
+fn replay_from_storage(session_id: H256) -> Vec<u8> {
+    let (base, moves_before) = SingleGomoku::move_log_base(session_id).unwrap();
+    let rules = SingleGomoku::get_game_rules(session_id).unwrap();
+    let rules = GameRules { max_stone_onchain: rules.max_stone_onchain - moves_before as u8, ..rules };
+    SingleGomoku::replay(&rules, base, &SingleGomoku::move_log(session_id)).unwrap()
+}
+
+#[test]
+fn test_game_clock_time_banks() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: Some(5),
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        check_gomoku_state_invariants();
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+        assert_eq!(SingleGomoku::get_time_banks(session_id), Some(vec![5, 5]));
+
+        // the clock of black starts at the settle deadline 3
+        let mut board_state = vec![0; 227];
+        board_state[1] = 1;
+        for i in 2..8 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_state_proof(0, 1, board_state, 2, session_id, players_pair);
+        assert_ok!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_action_deadline(session_id), Some(8));
+
+        System::set_block_number(5);
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![7, 7]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_time_banks(session_id), Some(vec![3, 5]));
+        assert_eq!(SingleGomoku::get_action_deadline(session_id), Some(10));
+
+        System::set_block_number(9);
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![8, 8]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_time_banks(session_id), Some(vec![3, 1]));
+        assert_eq!(SingleGomoku::get_action_deadline(session_id), Some(12));
+
+        // black has 3 blocks left
+        System::set_block_number(13);
+        assert_noop!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![7, 8]
+            ),
+            Error::<TestRuntime>::TimeBankExhausted
+        );
+        assert_ok!(
+            SingleGomoku::finalize_on_action_timeout(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_state(session_id, 1).unwrap(), vec![2]);
+        assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), true);
+        let expected_event = TestEvent::single_gomoku(RawEvent::FinalizedOnTimeout(session_id, players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_resign() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        assert_noop!(
+            SingleGomoku::resign(
+                Origin::signed(account_pair("Carl").public()),
+                session_id
+            ),
+            Error::<TestRuntime>::NotPlayer
+        );
+
+        // a player can resign when it is not its turn
+        assert_ok!(
+            SingleGomoku::resign(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(SingleGomoku::get_state(session_id, 0).unwrap(), vec![0]);
+        let expected_event = TestEvent::single_gomoku(RawEvent::Resigned(session_id, players[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_gomoku(RawEvent::GameWon(session_id, players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome,
+        };
+        assert_eq!(
+            SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Winner(0 as u8).encode()
+        );
+
+        assert_noop!(
+            SingleGomoku::resign(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::AlreadyFinalized
+        );
+    })
+}
+
+#[test]
+fn test_legacy_query_args() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        let legacy_query = |query_data: u8| SingleGomoku::get_outcome(
+            SingleGomokuLegacyArgsQueryOutcome {
+                session_id: session_id,
+                query_data: query_data
+            }.encode()
+        );
+        // the former encoding of a session id and a u8 winner, 1 is not read as GameQuery::Outcome
+        assert_eq!((session_id, 1u8).encode(), SingleGomokuLegacyArgsQueryOutcome { session_id: session_id, query_data: 1 }.encode());
+        assert_eq!(legacy_query(0).unwrap(), true.encode());
+        assert_eq!(legacy_query(1).unwrap(), false.encode());
+
+        // players[0] resigns, player 2 wins
+        assert_ok!(SingleGomoku::resign(Origin::signed(players[0]), session_id));
+        assert_eq!(legacy_query(2).unwrap(), true.encode());
+        assert_eq!(legacy_query(1).unwrap(), false.encode());
+        assert_eq!(legacy_query(0).unwrap(), false.encode());
+        assert_noop!(
+            SingleGomoku::get_outcome((H256::from_low_u64_be(1), 1u8).encode()),
+            Error::<TestRuntime>::SingleGomokuInfoNotExist
+        );
+
+        // the versioned args of another version are rejected
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION + 1,
+            query_data: GameQuery::Outcome
+        };
+        assert_noop!(
+            SingleGomoku::get_outcome(args_query_outcome.encode()),
+            Error::<TestRuntime>::UnsupportedQueryVersion
+        );
+    })
+}
+
+#[test]
+fn test_offer_and_accept_draw() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        assert_noop!(
+            SingleGomoku::offer_draw(
+                Origin::signed(players[1]),
+                session_id
+            ),
+            Error::<TestRuntime>::NotYourTurn
+        );
+        assert_ok!(
+            SingleGomoku::offer_draw(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        let expected_event = TestEvent::single_gomoku(RawEvent::DrawOffered(session_id, players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_noop!(
+            SingleGomoku::offer_draw(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::DrawAlreadyOffered
+        );
+        assert_noop!(
+            SingleGomoku::accept_draw(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::NoDrawOffer
+        );
+
+        // the offer stands after the move of the offerer and is declined by the move of the opponent
+        System::set_block_number(4);
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[0]),
+                session_id,
+                vec![7, 7]
+            )
+        );
+        assert_ok!(
+            SingleGomoku::update_by_action(
+                Origin::signed(players[1]),
+                session_id,
+                vec![8, 8]
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_noop!(
+            SingleGomoku::accept_draw(
+                Origin::signed(players[1]),
+                session_id
+            ),
+            Error::<TestRuntime>::NoDrawOffer
+        );
+
+        assert_ok!(
+            SingleGomoku::offer_draw(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        assert_ok!(
+            SingleGomoku::accept_draw(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(SingleGomoku::get_state(session_id, 1).unwrap(), vec![0]);
+        assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), true);
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome,
+        };
+        assert_eq!(
+            SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Draw.encode()
+        );
+        let expected_event = TestEvent::single_gomoku(RawEvent::Draw(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_finalize_by_agreement() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: false,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+        let mut board_state = vec![0; 227];
+        board_state[0] = 1; // winner
+        board_state[1] = 0; // turn
+        for y in 0..5 {
+            board_state[2 + y] = 1; // (0, y)
+        }
+        for y in 0..4 {
+            board_state[2 + 30 + 2 * y] = 2; // (2, 2y)
+        }
+
+        // signatures of an ordinary state cannot finalize the app
+        let state_proof = get_state_proof(0, 1, board_state.clone(), 0, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidSignature
+        );
+
+        // the final state is finalized in the same block from Idle
+        let state_proof = get_final_state_proof(0, 1, board_state.clone(), 0, session_id, players_pair.clone());
+        assert_ok!(
+            SingleGomoku::finalize_by_agreement(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(SingleGomoku::get_state(session_id, 1).unwrap(), vec![1]);
+        assert_eq!(SingleGomoku::is_finalized(session_id.encode()).unwrap(), true);
+        assert_eq!(SingleGomoku::finalized_at(session_id), Some(1));
+        let expected_event = TestEvent::single_gomoku(RawEvent::GameWon(session_id, players[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_gomoku(RawEvent::FinalizedByAgreement(session_id, 1));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        let state_proof = get_final_state_proof(0, 2, board_state, 0, session_id, players_pair.clone());
+        assert_noop!(
+            SingleGomoku::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::AlreadyFinalized
+        );
+
+        // an unfinished final board of a settled app is a draw
+        let session_id = settle_app(1, players.clone(), players_pair.clone());
+        let mut board_state = vec![0; 227];
+        board_state[1] = 1;
+        for i in 2..8 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let state_proof = get_final_state_proof(1, 1, board_state, 0, session_id, players_pair);
+        assert_ok!(
+            SingleGomoku::finalize_by_agreement(
+                Origin::signed(players[1]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        assert_eq!(SingleGomoku::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(SingleGomoku::get_state(session_id, 0).unwrap(), vec![0]);
+        let args_query_outcome = SingleGomokuArgsQueryOutcome {
+            session_id: session_id,
+            version: GAME_QUERY_VERSION,
+            query_data: GameQuery::Outcome,
+        };
+        assert_eq!(
+            SingleGomoku::get_outcome(args_query_outcome.encode()).unwrap(),
+            GameOutcome::Draw.encode()
+        );
+        let expected_event = TestEvent::single_gomoku(RawEvent::Draw(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_unilateral_state_update() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+            min_stone_offchain: 5,
+            max_stone_onchain: 5,
+            board_size: DEFAULT_BOARD_SIZE,
+            win_length: DEFAULT_WIN_LENGTH,
+            rule_set: RuleSet::Freestyle,
+            time_bank: None,
+            unilateral: true,
+        };
+        assert_ok!(SingleGomoku::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone())
+        );
+        let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+        // a unilateral state needs a co-signed state on-chain to extend
+        let mut board_state = vec![0; 227];
+        board_state[1] = 1;
+        for i in 2..8 {
+            board_state[i] = (i % 2 + 1) as u8;
+        }
+        let mut next_board_state = board_state.clone();
+        next_board_state[1] = 2; // turn
+        next_board_state[2 + 15 * 7 + 7] = 1; // (7, 7)
+        let state_proof = get_unilateral_state_proof(0, 1, next_board_state.clone(), 2, session_id, &players_pair[0]);
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::NoCosignedState
+        );
+
+        let state_proof = get_state_proof(0, 1, board_state.clone(), 2, session_id, players_pair.clone());
+        assert_ok!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+
+        // only the player to move of the co-signed board signs the next state
+        let state_proof = get_unilateral_state_proof(0, 2, next_board_state.clone(), 2, session_id, &players_pair[1]);
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[1]), state_proof),
+            Error::<TestRuntime>::InvalidSignature
+        );
+        let state_proof = get_unilateral_state_proof(0, 3, next_board_state.clone(), 2, session_id, &players_pair[0]);
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidSeqNum
+        );
+        let mut two_stones = next_board_state.clone();
+        two_stones[2 + 15 * 7 + 8] = 1; // (7, 8)
+        let state_proof = get_unilateral_state_proof(0, 2, two_stones, 2, session_id, &players_pair[0]);
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidUnilateralMove
+        );
+
+        let state_proof = get_unilateral_state_proof(0, 2, next_board_state, 2, session_id, &players_pair[0]);
+        assert_ok!(
+            SingleGomoku::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+        check_gomoku_state_invariants();
+        let expected_event = TestEvent::single_gomoku(RawEvent::IntendSettle(session_id, 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_eq!(SingleGomoku::get_seq_num(session_id), Some(2));
+        assert_eq!(SingleGomoku::get_state(session_id, 0).unwrap(), vec![2]);
+
+        // a unilateral state cannot be extended by another one
+        let mut board_state = board_state;
+        board_state[2 + 15 * 7 + 7] = 1;
+        board_state[2 + 15 * 8 + 8] = 2; // (8, 8)
+        let state_proof = get_unilateral_state_proof(0, 3, board_state, 2, session_id, &players_pair[1]);
+        assert_noop!(
+            SingleGomoku::update_by_state(Origin::signed(players[1]), state_proof),
+            Error::<TestRuntime>::NoCosignedState
+        );
+    })
+}
+
+fn get_state_proof(
+    nonce: u128,
+    seq: u128,
+    board_state: Vec<u8>,
+    timeout: BlockNumber,
+    session_id: H256,
+    players_pair: Vec<sr25519::Pair>,
+) -> StateProof<BlockNumber, H256, Signature> {
+    let app_state = AppState {
+        nonce: nonce,
+        seq_num: seq,
+        board_state: board_state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = SingleGomoku::encode_app_state(app_state.clone());
+    let sig_1 = players_pair[0].sign(&encoded);
+    let sig_2 = players_pair[1].sign(&encoded);
+    let state_proof = StateProof {
+        app_state: app_state,
+        sigs: vec![sig_1, sig_2]
+    };
+
+    return state_proof;
+}
+
+fn get_final_state_proof(
+    nonce: u128,
+    seq: u128,
+    board_state: Vec<u8>,
+    timeout: BlockNumber,
+    session_id: H256,
+    players_pair: Vec<sr25519::Pair>,
+) -> StateProof<BlockNumber, H256, Signature> {
+    let app_state = AppState {
+        nonce: nonce,
+        seq_num: seq,
+        board_state: board_state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = SingleGomoku::encode_final_app_state(app_state.clone());
+    let sigs = players_pair.iter().map(|pair| pair.sign(&encoded)).collect();
+
+    StateProof {
+        app_state: app_state,
+        sigs: sigs,
+    }
+}
+
+fn get_unilateral_state_proof(
+    nonce: u128,
+    seq: u128,
+    board_state: Vec<u8>,
+    timeout: BlockNumber,
+    session_id: H256,
+    mover_pair: &sr25519::Pair,
+) -> StateProof<BlockNumber, H256, Signature> {
+    let app_state = AppState {
+        nonce: nonce,
+        seq_num: seq,
+        board_state: board_state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = SingleGomoku::encode_app_state(app_state.clone());
+
+    StateProof {
+        app_state: app_state,
+        sigs: vec![mover_pair.sign(&encoded)],
+    }
+}
+
+fn place_stone(session_id: H256, players: Vec<AccountId>, players_pair: Vec<sr25519::Pair>) {
+    let mut board_state_1 = vec![0; 227];
+    board_state_1[0] = 0;
+    board_state_1[1] = 1;
+    board_state_1[2] = 2;
+    board_state_1[3] = 2;
+    board_state_1[4] = 1;
+    board_state_1[5] = 1;
+    board_state_1[6] = 2;
+    board_state_1[7] = 2;
+    board_state_1[8] = 1;
+    board_state_1[9] = 1;
+    let state_proof = get_state_proof(0, 1, board_state_1.clone(), 0, session_id, players_pair.clone());
+    assert_ok!(
+        SingleGomoku::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+    check_gomoku_state_invariants();
+
+    let mut board_state_2 = vec![0; 227];
+    board_state_2[0] = 0; // winner
+    board_state_2[1] = 2; // turn
+    board_state_2[2] = 1; // (0, 0)
+    board_state_2[3] = 1; // (0, 1)
+    board_state_2[4] = 1; // (0, 2)
+    board_state_2[5] = 1; // (0, 3)
+    board_state_2[101] = 2; 
+    board_state_2[102] = 2;
+    board_state_2[103] = 2;
+    let state_proof = get_state_proof(0, 2, board_state_2, 0, session_id, players_pair);
+    assert_ok!(
+        SingleGomoku::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+    check_gomoku_state_invariants();
+
+}
+
+fn settle_app(nonce: u128, players: Vec<AccountId>, players_pair: Vec<sr25519::Pair>) -> H256 {
+    let initiate_request = AppInitiateRequest {
+        nonce: nonce,
+        players: players.clone(),
+        timeout: 2,
+        min_stone_offchain: 5,
+        max_stone_onchain: 5,
+        board_size: DEFAULT_BOARD_SIZE,
+        win_length: DEFAULT_WIN_LENGTH,
+        rule_set: RuleSet::Freestyle,
+        time_bank: None,
+        unilateral: false,
+    };
+    assert_ok!(SingleGomoku::app_initiate(
+        Origin::signed(players[0]),
+        initiate_request.clone())
+    );
+    check_gomoku_state_invariants();
+
+    let session_id = SingleGomoku::get_session_id(&initiate_request);
+    let mut board_state = vec![0; 227];
+    board_state[1] = 1;
+    for i in 2..8 {
+        board_state[i] = (i % 2 + 1) as u8;
+    }
+    let state_proof = get_state_proof(nonce, 1, board_state, 2, session_id, players_pair);
+    assert_ok!(
+        SingleGomoku::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+    check_gomoku_state_invariants();
+
+    return session_id;
+}
+
+fn settle_rule_set_app(
+    rule_set: RuleSet,
+    stones: Vec<(usize, usize, u8)>,
+    players: Vec<AccountId>,
+    players_pair: Vec<sr25519::Pair>,
+) -> H256 {
+    let initiate_request = AppInitiateRequest {
+        nonce: 0,
+        players: players.clone(),
+        timeout: 2,
+        min_stone_offchain: 0,
+        max_stone_onchain: 5,
+        board_size: DEFAULT_BOARD_SIZE,
+        win_length: DEFAULT_WIN_LENGTH,
+        rule_set: rule_set,
+        time_bank: None,
+        unilateral: false,
+    };
+    assert_ok!(SingleGomoku::app_initiate(
+        Origin::signed(players[0]),
+        initiate_request.clone())
+    );
+    check_gomoku_state_invariants();
+    let session_id = SingleGomoku::get_session_id(&initiate_request);
+
+    let mut board_state = vec![0; BOARD_STATE_LEN];
+    board_state[1] = 1; // turn
+    for (x, y, stone) in stones {
+        board_state[2 + 15 * x + y] = stone;
+    }
+    let state_proof = get_state_proof(0, 1, board_state, 0, session_id, players_pair);
+    assert_ok!(
+        SingleGomoku::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+    check_gomoku_state_invariants();
+    let settle_finalized_time = SingleGomoku::get_settle_finalized_time(session_id).unwrap();
+    System::set_block_number(settle_finalized_time + 1);
+
+    return session_id;
+}
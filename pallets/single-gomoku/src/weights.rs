@@ -0,0 +1,165 @@
+//! Weights for single_gomoku
+//!
+//! These weights are estimates written by hand, they were not generated by the benchmark CLI.
+//! Before a runtime relies on `SubstrateWeight`, regenerate this file with the benchmarks in
+//! `benchmarking.rs`:
+//! `./target/release/node benchmark --chain dev --pallet single_gomoku --extrinsic '*' --steps 50 --repeat 20 --output`
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::weights::{Weight, constants::RocksDbWeight};
+use frame_support::traits::Get;
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for single_gomoku
+pub trait WeightInfo {
+    fn app_initiate() -> Weight;
+    fn update_by_state(b: u32) -> Weight;
+    fn finalize_by_agreement(b: u32) -> Weight;
+    fn update_by_action(b: u32) -> Weight;
+    fn finalize_on_action_timeout() -> Weight;
+    fn resign() -> Weight;
+    fn offer_draw() -> Weight;
+    fn accept_draw() -> Weight;
+    fn clear_session() -> Weight;
+    fn finalize_expired(n: u32) -> Weight;
+    fn on_initialize(n: u32) -> Weight;
+}
+
+/// Weights for single_gomoku using the Substrate node and recommended hardware
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Trait> WeightInfo for SubstrateWeight<T> {
+    fn app_initiate() -> Weight {
+        (18_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(3 as Weight))
+            .saturating_add(T::DbWeight::get().writes(3 as Weight))
+    }
+    fn update_by_state(b: u32) -> Weight {
+        (46_000_000 as Weight)
+            .saturating_add((450_000 as Weight).saturating_mul(b as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_by_agreement(b: u32) -> Weight {
+        (50_000_000 as Weight)
+            .saturating_add((450_000 as Weight).saturating_mul(b as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn update_by_action(b: u32) -> Weight {
+        (40_000_000 as Weight)
+            .saturating_add((60_000 as Weight).saturating_mul(b as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_on_action_timeout() -> Weight {
+        (31_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn resign() -> Weight {
+        (24_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn offer_draw() -> Weight {
+        (15_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+    }
+    fn accept_draw() -> Weight {
+        (23_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes(2 as Weight))
+    }
+    fn clear_session() -> Weight {
+        (28_000_000 as Weight)
+            .saturating_add(T::DbWeight::get().reads(5 as Weight))
+            .saturating_add(T::DbWeight::get().writes(6 as Weight))
+    }
+    fn finalize_expired(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((31_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+    fn on_initialize(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((21_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(T::DbWeight::get().reads(1 as Weight))
+            .saturating_add(T::DbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(T::DbWeight::get().writes(1 as Weight))
+            .saturating_add(T::DbWeight::get().writes((6 as Weight).saturating_mul(n as Weight)))
+    }
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+    fn app_initiate() -> Weight {
+        (18_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(3 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(3 as Weight))
+    }
+    fn update_by_state(b: u32) -> Weight {
+        (46_000_000 as Weight)
+            .saturating_add((450_000 as Weight).saturating_mul(b as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_by_agreement(b: u32) -> Weight {
+        (50_000_000 as Weight)
+            .saturating_add((450_000 as Weight).saturating_mul(b as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn update_by_action(b: u32) -> Weight {
+        (40_000_000 as Weight)
+            .saturating_add((60_000 as Weight).saturating_mul(b as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn finalize_on_action_timeout() -> Weight {
+        (31_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn resign() -> Weight {
+        (24_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn offer_draw() -> Weight {
+        (15_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+    }
+    fn accept_draw() -> Weight {
+        (23_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(2 as Weight))
+    }
+    fn clear_session() -> Weight {
+        (28_000_000 as Weight)
+            .saturating_add(RocksDbWeight::get().reads(5 as Weight))
+            .saturating_add(RocksDbWeight::get().writes(6 as Weight))
+    }
+    fn finalize_expired(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((31_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+    fn on_initialize(n: u32) -> Weight {
+        (2_000_000 as Weight)
+            .saturating_add((21_000_000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(RocksDbWeight::get().reads(1 as Weight))
+            .saturating_add(RocksDbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(RocksDbWeight::get().writes(1 as Weight))
+            .saturating_add(RocksDbWeight::get().writes((6 as Weight).saturating_mul(n as Weight)))
+    }
+}
@@ -0,0 +1,236 @@
+//! Single session app benchmarking
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use super::*;
+use celer_app_core::benchmarking::{co_sign, sorted_players, sr25519};
+use frame_benchmarking::benchmarks;
+use frame_support::traits::OnInitialize;
+use frame_system::RawOrigin;
+use sp_runtime::traits::Bounded;
+
+/// Endow the initiator with enough balance for the storage deposit
+fn fund<T: Trait>(who: &T::AccountId) {
+    T::Currency::make_free_balance_be(who, BalanceOf::<T>::max_value() / 2u32.into());
+}
+
+fn initiate_app<T: Trait>(nonce: u128, players: Vec<T::AccountId>) -> Result<T::Hash, &'static str> {
+    let initiate_request = AppInitiateRequestOf::<T> {
+        nonce: nonce,
+        players: players.clone(),
+        timeout: 2u32.into(),
+    };
+    let session_id = Module::<T>::get_session_id(&initiate_request);
+    fund::<T>(&players[0]);
+    Module::<T>::app_initiate(RawOrigin::Signed(players[0].clone()).into(), initiate_request)?;
+
+    Ok(session_id)
+}
+
+fn get_state_proof<T: Trait>(
+    nonce: u128,
+    seq_num: u128,
+    state: u8,
+    session_id: T::Hash,
+    keys: &[sr25519::Public],
+) -> StateProofOf<T>
+where
+    <T as Trait>::Signature: From<sr25519::Signature>,
+{
+    let app_state = AppStateOf::<T> {
+        nonce: nonce,
+        seq_num: seq_num,
+        state: state,
+        timeout: 2u32.into(),
+        session_id: session_id,
+    };
+    let encoded = Module::<T>::encode_app_state(app_state.clone());
+
+    StateProofOf::<T> {
+        app_state: app_state,
+        sigs: co_sign(keys, &encoded),
+    }
+}
+
+/// State proof of an app state signed as the final state
+fn get_final_state_proof<T: Trait>(
+    nonce: u128,
+    seq_num: u128,
+    state: u8,
+    session_id: T::Hash,
+    keys: &[sr25519::Public],
+) -> StateProofOf<T>
+where
+    <T as Trait>::Signature: From<sr25519::Signature>,
+{
+    let app_state = AppStateOf::<T> {
+        nonce: nonce,
+        seq_num: seq_num,
+        state: state,
+        timeout: 2u32.into(),
+        session_id: session_id,
+    };
+    let encoded = Module::<T>::encode_final_app_state(app_state.clone());
+
+    StateProofOf::<T> {
+        app_state: app_state,
+        sigs: co_sign(keys, &encoded),
+    }
+}
+
+/// Initiate an app and settle it with a non-terminal state
+fn settle_app<T: Trait>(
+    nonce: u128,
+    players: Vec<T::AccountId>,
+    keys: &[sr25519::Public],
+) -> Result<T::Hash, &'static str>
+where
+    <T as Trait>::Signature: From<sr25519::Signature>,
+{
+    let session_id = initiate_app::<T>(nonce, players.clone())?;
+    let state_proof = get_state_proof::<T>(nonce, 1, 5, session_id, keys);
+    Module::<T>::update_by_state(RawOrigin::Signed(players[0].clone()).into(), state_proof)?;
+
+    Ok(session_id)
+}
+
+benchmarks! {
+    where_clause { where
+        T::AccountId: From<sr25519::Public>,
+        <T as Trait>::Signature: From<sr25519::Signature>,
+    }
+
+    _ { }
+
+    app_initiate {
+        let (players, _) = sorted_players::<T::AccountId>(2);
+        let initiate_request = AppInitiateRequestOf::<T> {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2u32.into(),
+        };
+        let session_id = Module::<T>::get_session_id(&initiate_request);
+        fund::<T>(&players[0]);
+    }: _(RawOrigin::Signed(players[0].clone()), initiate_request)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Idle));
+    }
+
+    update_by_state {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = initiate_app::<T>(0, players.clone())?;
+        let state_proof = get_state_proof::<T>(0, 1, 5, session_id, &keys);
+    }: _(RawOrigin::Signed(players[0].clone()), state_proof)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Settle));
+    }
+
+    finalize_by_agreement {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = initiate_app::<T>(0, players.clone())?;
+        let state_proof = get_final_state_proof::<T>(0, 1, 5, session_id, &keys);
+    }: _(RawOrigin::Signed(players[0].clone()), state_proof)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+    }
+
+    update_by_action {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = initiate_app::<T>(0, players.clone())?;
+        let state_proof = get_state_proof::<T>(0, 1, 5, session_id, &keys);
+        let block_number = frame_system::Module::<T>::block_number();
+        Module::<T>::update_by_state(RawOrigin::Signed(players[0].clone()).into(), state_proof)?;
+        // settle period is over
+        frame_system::Module::<T>::set_block_number(block_number + 3u32.into());
+    }: _(RawOrigin::Signed(players[0].clone()), session_id, 1)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+    }
+
+    finalize_on_action_timeout {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = initiate_app::<T>(0, players.clone())?;
+        let state_proof = get_state_proof::<T>(0, 1, 5, session_id, &keys);
+        let block_number = frame_system::Module::<T>::block_number();
+        Module::<T>::update_by_state(RawOrigin::Signed(players[0].clone()).into(), state_proof)?;
+        // settle period and action period are over
+        frame_system::Module::<T>::set_block_number(block_number + 5u32.into());
+    }: _(RawOrigin::Signed(players[0].clone()), session_id)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+    }
+    clear_session {
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let session_id = settle_app::<T>(0, players.clone(), &keys)?;
+        let block_number = frame_system::Module::<T>::block_number();
+        // settle period and action period are over
+        frame_system::Module::<T>::set_block_number(block_number + 5u32.into());
+        Module::<T>::finalize_on_action_timeout(RawOrigin::Signed(players[0].clone()).into(), session_id)?;
+        Module::<T>::consume_outcome(session_id)?;
+        // retention period is over
+        frame_system::Module::<T>::set_block_number(block_number + 5u32.into() + T::RetentionPeriod::get());
+    }: _(RawOrigin::Signed(players[0].clone()), session_id)
+    verify {
+        assert_eq!(Module::<T>::get_status(session_id), None);
+    }
+
+    finalize_expired {
+        let n in 0 .. T::MaxFinalizePerBlock::get();
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let mut session_ids = vec![];
+        for nonce in 0 .. n {
+            session_ids.push(settle_app::<T>(nonce as u128, players.clone(), &keys)?);
+        }
+        // first block after the settle period and action period
+        let expire_at = frame_system::Module::<T>::block_number() + 5u32.into();
+        frame_system::Module::<T>::set_block_number(expire_at);
+    }: { Module::<T>::on_initialize(expire_at); }
+    verify {
+        for session_id in session_ids {
+            assert_eq!(Module::<T>::get_status(session_id), Some(AppStatus::Finalized));
+        }
+    }
+
+    on_initialize {
+        let n in 0 .. T::MaxClearPerBlock::get();
+        let (players, keys) = sorted_players::<T::AccountId>(2);
+        let mut session_ids = vec![];
+        for nonce in 0 .. n {
+            session_ids.push(settle_app::<T>(nonce as u128, players.clone(), &keys)?);
+        }
+        let block_number = frame_system::Module::<T>::block_number();
+        // settle period and action period are over
+        frame_system::Module::<T>::set_block_number(block_number + 5u32.into());
+        for session_id in session_ids.iter() {
+            Module::<T>::finalize_on_action_timeout(RawOrigin::Signed(players[0].clone()).into(), *session_id)?;
+            Module::<T>::consume_outcome(*session_id)?;
+        }
+        let clear_at = block_number + 5u32.into() + T::RetentionPeriod::get();
+    }: { Module::<T>::on_initialize(clear_at); }
+    verify {
+        for session_id in session_ids {
+            assert_eq!(Module::<T>::get_status(session_id), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{ExtBuilder, TestRuntime};
+    use frame_support::assert_ok;
+
+    #[test]
+    fn test_benchmarks() {
+        ExtBuilder::build().execute_with(|| {
+            assert_ok!(test_benchmark_app_initiate::<TestRuntime>());
+            assert_ok!(test_benchmark_update_by_state::<TestRuntime>());
+            assert_ok!(test_benchmark_finalize_by_agreement::<TestRuntime>());
+            assert_ok!(test_benchmark_update_by_action::<TestRuntime>());
+            assert_ok!(test_benchmark_finalize_on_action_timeout::<TestRuntime>());
+            assert_ok!(test_benchmark_clear_session::<TestRuntime>());
+            assert_ok!(test_benchmark_finalize_expired::<TestRuntime>());
+            assert_ok!(test_benchmark_on_initialize::<TestRuntime>());
+        });
+    }
+}
@@ -0,0 +1,178 @@
+//! Fuzzing of the dispatchables with arbitrary state proofs and actions
+
+#![cfg(test)]
+
+use super::*;
+use mock::*;
+use sp_core::{sr25519, Pair, H256};
+use frame_support::{assert_ok, traits::OnInitialize};
+use proptest::prelude::*;
+
+/// A call to the runtime module with arbitrary input
+#[derive(Clone, Debug)]
+enum Op {
+    /// Initiate an app of arbitrary signers
+    Initiate { nonce: u128, players: Vec<usize>, timeout: u64 },
+    /// Update by a state proof signed by the first `sig_num` signers
+    UpdateByState { nonce: u128, seq_num: u128, state: u8, timeout: u64, sig_num: usize },
+    /// Finalize by a state proof signed as final by the first `sig_num` signers
+    FinalizeByAgreement { nonce: u128, seq_num: u128, state: u8, timeout: u64, sig_num: usize },
+    /// Update by a state proof decoded from arbitrary bytes
+    UpdateByEncodedState(Vec<u8>),
+    UpdateByAction { signer: usize, action: u8 },
+    FinalizeOnActionTimeout,
+    FinalizeOnActionTimeoutUnsigned,
+    ConsumeOutcome,
+    ClearSession { signer: usize },
+    Query { query_data: u8, args: Vec<u8> },
+    /// Advance blocks and run `on_initialize` of each block
+    AdvanceBlocks(u64),
+}
+
+fn timeout() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        4 => 0u64..4,
+        1 => any::<u64>(),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        1 => (0u128..2, prop::collection::vec(0usize..3, 0..4), timeout())
+            .prop_map(|(nonce, players, timeout)| Op::Initiate { nonce, players, timeout }),
+        4 => (0u128..2, 0u128..6, any::<u8>(), timeout(), 0usize..4)
+            .prop_map(|(nonce, seq_num, state, timeout, sig_num)| {
+                Op::UpdateByState { nonce, seq_num, state, timeout, sig_num }
+            }),
+        1 => (0u128..2, 0u128..6, any::<u8>(), timeout(), 0usize..4)
+            .prop_map(|(nonce, seq_num, state, timeout, sig_num)| {
+                Op::FinalizeByAgreement { nonce, seq_num, state, timeout, sig_num }
+            }),
+        1 => prop::collection::vec(any::<u8>(), 0..512).prop_map(Op::UpdateByEncodedState),
+        4 => (0usize..3, any::<u8>())
+            .prop_map(|(signer, action)| Op::UpdateByAction { signer, action }),
+        1 => Just(Op::FinalizeOnActionTimeout),
+        1 => Just(Op::FinalizeOnActionTimeoutUnsigned),
+        1 => Just(Op::ConsumeOutcome),
+        1 => (0usize..3).prop_map(|signer| Op::ClearSession { signer }),
+        1 => (any::<u8>(), prop::collection::vec(any::<u8>(), 0..40))
+            .prop_map(|(query_data, args)| Op::Query { query_data, args }),
+        2 => (1u64..12).prop_map(Op::AdvanceBlocks),
+    ]
+}
+
+/// Apply a call, errors are expected but a panic fails the case
+fn apply(op: Op, session_id: H256, signers: &[sr25519::Pair]) {
+    let origin = |signer: usize| Origin::signed(signers[signer].public());
+    match op {
+        Op::Initiate { nonce, players, timeout } => {
+            let initiate_request = AppInitiateRequest {
+                nonce: nonce,
+                players: players.iter().map(|&signer| signers[signer].public()).collect(),
+                timeout: timeout,
+            };
+            let _ = SingleSessionApp::app_initiate(origin(0), initiate_request);
+        }
+        Op::UpdateByState { nonce, seq_num, state, timeout, sig_num } => {
+            let app_state = AppState {
+                nonce: nonce,
+                seq_num: seq_num,
+                state: state,
+                timeout: timeout,
+                session_id: session_id,
+            };
+            let encoded = SingleSessionApp::encode_app_state(app_state.clone());
+            let sigs = (0..sig_num).map(|i| signers[i % signers.len()].sign(&encoded)).collect();
+            let state_proof = StateProof {
+                app_state: app_state,
+                sigs: sigs,
+            };
+            let _ = SingleSessionApp::update_by_state(origin(0), state_proof);
+        }
+        Op::FinalizeByAgreement { nonce, seq_num, state, timeout, sig_num } => {
+            let app_state = AppState {
+                nonce: nonce,
+                seq_num: seq_num,
+                state: state,
+                timeout: timeout,
+                session_id: session_id,
+            };
+            let encoded = SingleSessionApp::encode_final_app_state(app_state.clone());
+            let sigs = (0..sig_num).map(|i| signers[i % signers.len()].sign(&encoded)).collect();
+            let state_proof = StateProof {
+                app_state: app_state,
+                sigs: sigs,
+            };
+            let _ = SingleSessionApp::finalize_by_agreement(origin(0), state_proof);
+        }
+        Op::UpdateByEncodedState(bytes) => {
+            if let Ok(state_proof) = StateProofOf::<TestRuntime>::decode(&mut &bytes[..]) {
+                let _ = SingleSessionApp::update_by_state(origin(0), state_proof);
+            }
+        }
+        Op::UpdateByAction { signer, action } => {
+            let _ = SingleSessionApp::update_by_action(origin(signer), session_id, action);
+        }
+        Op::FinalizeOnActionTimeout => {
+            let _ = SingleSessionApp::finalize_on_action_timeout(origin(0), session_id);
+        }
+        Op::FinalizeOnActionTimeoutUnsigned => {
+            let _ = SingleSessionApp::finalize_on_action_timeout_unsigned(Origin::none(), session_id);
+        }
+        Op::ConsumeOutcome => {
+            let _ = <SingleSessionApp as ConsumeOutcome<H256>>::consume_outcome(session_id);
+        }
+        Op::ClearSession { signer } => {
+            let _ = SingleSessionApp::clear_session(origin(signer), session_id);
+        }
+        Op::Query { query_data, args } => {
+            let args_query_outcome = SingleSessionArgsQueryOutcome {
+                session_id: session_id,
+                query_data: query_data,
+            };
+            let _ = SingleSessionApp::get_state(session_id);
+            let _ = SingleSessionApp::is_finalized(session_id.encode());
+            let _ = SingleSessionApp::get_outcome(args_query_outcome.encode());
+            let _ = SingleSessionApp::is_finalized(args.clone());
+            let _ = SingleSessionApp::get_outcome(args);
+        }
+        Op::AdvanceBlocks(n) => {
+            for _ in 0 .. n {
+                let block_number = System::block_number() + 1;
+                System::set_block_number(block_number);
+                SingleSessionApp::on_initialize(block_number);
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn fuzz_dispatchables_do_not_panic(
+        ops in prop::collection::vec(op(), 1..32),
+    ) {
+        ExtBuilder::build().execute_with(|| {
+            System::set_block_number(1);
+            let (players, mut signers)
+                = get_sorted_peer(account_pair("Alice"), account_pair("Bob"));
+            signers.push(account_pair("Carl"));
+
+            let initiate_request = AppInitiateRequest {
+                nonce: 0,
+                players: players.clone(),
+                timeout: 2,
+            };
+            assert_ok!(SingleSessionApp::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request.clone())
+            );
+            let session_id = SingleSessionApp::get_session_id(&initiate_request);
+
+            for op in ops {
+                apply(op, session_id, &signers);
+            }
+        });
+    }
+}
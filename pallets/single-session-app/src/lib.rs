@@ -5,25 +5,42 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+mod benchmarking;
+mod fuzz;
+pub mod weights;
+
 use codec::{Decode, Encode};
 use frame_support::{
     decl_module, decl_storage, decl_event, decl_error, ensure,
     storage::StorageMap,
-    traits::Get,
+    traits::{Currency, Get, ReservableCurrency},
+    weights::Weight,
+};
+use frame_system::{
+    self as system, ensure_none, ensure_signed,
+    offchain::{SendTransactionTypes, SubmitTransaction},
+};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionPriority, TransactionSource, TransactionValidity,
 };
-use frame_system::{self as system, ensure_signed};
 use sp_runtime::traits::{
-    Hash, IdentifyAccount, 
-    Member, Verify, Zero, AccountIdConversion, 
+    Hash, IdentifyAccount, AtLeast32BitUnsigned,
+    Member, Verify, Zero, AccountIdConversion,
 };
 use sp_runtime::{ModuleId, RuntimeDebug, DispatchResult, DispatchError};
 use sp_std::{prelude::*, vec::Vec};
+use celer_app_core::{
+    AppLogicError, CelerAppQuery, ConsumeOutcome, DisputeError, DisputeStateMachine, LifecycleError,
+    SessionDepositConfig, SessionLifecycle, StateChannelApp,
+};
+pub use celer_app_core::{AppStatus, SingleSessionLogic};
+pub use weights::WeightInfo;
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct AppInitiateRequest<AccountId, BlockNumber> {
-    nonce: u128,
-    players: Vec<AccountId>,
-    timeout: BlockNumber,
+    pub nonce: u128,
+    pub players: Vec<AccountId>,
+    pub timeout: BlockNumber,
 }
 
 pub type AppInitiateRequestOf<T> = AppInitiateRequest<
@@ -33,11 +50,11 @@ pub type AppInitiateRequestOf<T> = AppInitiateRequest<
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct AppState<BlockNumber, Hash> {
-    nonce: u128,
-    seq_num: u128,
-    state: u8,
-    timeout: BlockNumber,
-    session_id: Hash,
+    pub nonce: u128,
+    pub seq_num: u128,
+    pub state: u8,
+    pub timeout: BlockNumber,
+    pub session_id: Hash,
 }
 
 pub type AppStateOf<T> = AppState<
@@ -47,8 +64,8 @@ pub type AppStateOf<T> = AppState<
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct StateProof<BlockNumber, Hash, Signature> {
-    app_state: AppState<BlockNumber, Hash>,
-    sigs: Vec<Signature>,
+    pub app_state: AppState<BlockNumber, Hash>,
+    pub sigs: Vec<Signature>,
 }
 
 pub type StateProofOf<T> = StateProof<
@@ -57,14 +74,6 @@ pub type StateProofOf<T> = StateProof<
     <T as Trait>::Signature,
 >;
 
-#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
-pub enum AppStatus {
-    Idle = 0,
-    Settle = 1,
-    Action = 2,
-    Finalized = 3,
-}
-
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Encode, Decode, RuntimeDebug)]
 pub struct AppInfo<AccountId, BlockNumber> {
     state: u8,
@@ -81,6 +90,19 @@ pub type AppInfoOf<T> = AppInfo<
     <T as system::Trait>::BlockNumber,
 >;
 
+impl<AccountId, BlockNumber> DisputeStateMachine<BlockNumber> for AppInfo<AccountId, BlockNumber>
+where
+    BlockNumber: AtLeast32BitUnsigned + Copy,
+{
+    fn status(&self) -> AppStatus { self.status.clone() }
+    fn set_status(&mut self, status: AppStatus) { self.status = status; }
+    fn seq_num(&self) -> u128 { self.seq_num }
+    fn set_seq_num(&mut self, seq_num: u128) { self.seq_num = seq_num; }
+    fn timeout(&self) -> BlockNumber { self.timeout }
+    fn deadline(&self) -> BlockNumber { self.deadline }
+    fn set_deadline(&mut self, deadline: BlockNumber) { self.deadline = deadline; }
+}
+
 #[derive(PartialEq, Eq, Encode, Decode, RuntimeDebug)]
 pub struct SingleSessionArgsQueryOutcome<Hash> {
     pub session_id: Hash,
@@ -91,16 +113,51 @@ pub type SingleSessionArgsQueryOutcomeOf<T> = SingleSessionArgsQueryOutcome<<T a
 
 pub const SINGLE_SESSION_APP_ID: ModuleId = ModuleId(*b"_single_");
 
-pub trait Trait: system::Trait {
+pub type BalanceOf<T> =
+    <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+pub trait Trait: SendTransactionTypes<Call<Self>> + system::Trait {
     type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
     type Public: IdentifyAccount<AccountId = Self::AccountId>;
     type Signature: Verify<Signer = <Self as Trait>::Public> + Member + Decode + Encode; 
+    /// Number of blocks a finalized app is kept after finalization
+    type RetentionPeriod: Get<Self::BlockNumber>;
+    /// Maximum number of apps cleared in `on_initialize` of a block
+    type MaxClearPerBlock: Get<u32>;
+    /// Maximum number of expired apps finalized in `on_initialize` of a block
+    type MaxFinalizePerBlock: Get<u32>;
+    /// Priority of the finalizations the off-chain worker submits unsigned
+    type UnsignedPriority: Get<TransactionPriority>;
+    /// Currency in which the storage deposit of an app is reserved
+    type Currency: ReservableCurrency<Self::AccountId>;
+    /// Storage deposit reserved from the initiator of an app
+    type SessionDeposit: Get<SessionDepositConfig<BalanceOf<Self>>>;
+    /// Weight information for extrinsics in this pallet
+    type WeightInfo: WeightInfo;
 }
 
 decl_storage! {
     trait Store for Module<T: Trait> as SingleSessionApp {
         pub AppInfoMap get(fn app_info): 
             map hasher(blake2_128_concat) T::Hash => Option<AppInfoOf<T>>;
+        /// Block number at which the app is finalized
+        pub FinalizedAt get(fn finalized_at):
+            map hasher(blake2_128_concat) T::Hash => Option<T::BlockNumber>;
+        /// Whether the outcome of the finalized app is consumed
+        pub OutcomeConsumed get(fn outcome_consumed):
+            map hasher(blake2_128_concat) T::Hash => bool;
+        /// Id of apps to be cleared in `on_initialize` of the block
+        pub ClearQueue get(fn clear_queue):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<T::Hash>;
+        /// Id of apps whose action deadline passes before the block, finalized in its `on_initialize`
+        pub DeadlineQueue get(fn deadline_queue):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<T::Hash>;
+        /// Initiator and reserved storage deposit of the app
+        pub Deposits get(fn deposit_of):
+            map hasher(blake2_128_concat) T::Hash => Option<(T::AccountId, BalanceOf<T>)>;
+        /// Whether the app is cleared, kept so the id can not be initiated again
+        pub ClearedSessions get(fn is_cleared):
+            map hasher(blake2_128_concat) T::Hash => bool;
     }
 }
 
@@ -120,26 +177,31 @@ decl_module!  {
         /// - Complexity: `O(1)`
         ///   - 1 storage insertion `AppInfoMap`
         ///   - 1 storage reads `AppInfoMap`
-        /// - Based on benchmark;
-        ///     18.44　µs
+        ///   - 1 storage read `ClearedSessions`
+        ///   - 1 storage insertion `Deposits`
+        ///   - 1 balance reservation of the initiator
         /// # </weight>
-        #[weight = 19_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::app_initiate()]
         fn app_initiate(
             origin,
             initiate_request: AppInitiateRequestOf<T>
         ) -> DispatchResult {
-            let session_id = Self::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+            let initiator = ensure_signed(origin)?;
+
             ensure!(
-                AppInfoMap::<T>::contains_key(&session_id) == false,
-                "AppId alreads exists"
+                initiate_request.players.len() == 2,
+                Error::<T>::InvalidPlayerNum
             );
+            celer_app_core::ensure_ordered_players(&initiate_request.players).map_err(Error::<T>::from)?;
+
+            let session_id = Self::get_session_id(&initiate_request);
             ensure!(
-                initiate_request.players[0] < initiate_request.players[1], 
-                "players is not asscending order"
+                Self::is_session_used(session_id) == false,
+                Error::<T>::AppAlreadyExists
             );
 
             let app_info = AppInfoOf::<T> {
-                state: 0,
+                state: SingleSessionLogic::initial_state(()).map_err(Error::<T>::from)?,
                 nonce: initiate_request.nonce,
                 players: initiate_request.players,
                 seq_num: 0,
@@ -147,7 +209,11 @@ decl_module!  {
                 deadline: Zero::zero(),
                 status: AppStatus::Idle,
             };
-            AppInfoMap::<T>::insert(session_id, app_info);
+            // reserve the storage deposit from the initiator
+            Self::reserve_deposit(session_id, initiator, app_info.players.len(), app_info.state.encode().len())?;
+            AppInfoMap::<T>::insert(session_id, app_info.clone());
+
+            Self::deposit_event(RawEvent::AppInitiated(session_id, app_info.players, app_info.timeout));
         
             Ok(())
         }
@@ -164,11 +230,10 @@ decl_module!  {
         /// ## Weight
         /// - Complexity: `O(1)`
         ///   - 1 storage mutation `AppInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
         ///   - 1 storage read `AppInfoMap`
-        /// - Based on benchmark;
-        ///     44.68　µs
         /// # </weight>
-        #[weight = 45_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::update_by_state()]
         fn update_by_state(
             origin,
             state_proof: StateProofOf<T>
@@ -183,16 +248,14 @@ decl_module!  {
 
             // submit ad settle off-chain state
             let mut new_app_info: AppInfoOf<T> = Self::intend_settle(app_info, state_proof.clone())?;
-            
-            let state = state_proof.app_state.state;
-            if state == 1 || state == 2 {
-                new_app_info.state = state;
+            SingleSessionLogic::settle_offchain_state(&mut new_app_info.state, state_proof.app_state.state);
+            if SingleSessionLogic::is_terminal(&new_app_info.state) {
                 new_app_info.status = AppStatus::Finalized;
-            } else {
-                new_app_info.state = state;
             }
             
             AppInfoMap::<T>::mutate(&session_id, |app_info| *app_info = Some(new_app_info.clone()));
+            Self::record_finalized(session_id, &new_app_info.status);
+            Self::queue_deadline(session_id, &new_app_info);
 
             // Emit IntendSettle event
             Self::deposit_event(RawEvent::IntendSettle(session_id, new_app_info.seq_num));
@@ -201,6 +264,45 @@ decl_module!  {
         }
         
 
+        /// Finalize with an off-chain state co-signed by all players as the final state
+        ///
+        /// The players sign `final_signing_payload` of the state instead of `signing_payload`,
+        /// so the app is finalized in this block without waiting for the settle period.
+        ///
+        /// Parameters:
+        /// - `state_proof`: Off-chain app state signed as final by all players
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 1 storage mutation `AppInfoMap`
+        ///   - 1 storage insertion `FinalizedAt`
+        ///   - 1 storage read `AppInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::finalize_by_agreement()]
+        fn finalize_by_agreement(
+            origin,
+            state_proof: StateProofOf<T>
+        ) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let session_id = state_proof.app_state.session_id;
+            let app_info = match AppInfoMap::<T>::get(session_id) {
+                Some(app) => app,
+                None => Err(Error::<T>::AppInfoNotExist)?,
+            };
+
+            // finalize with the state all players agreed on
+            let mut new_app_info: AppInfoOf<T> = Self::agree_final_state(app_info, state_proof.clone())?;
+            SingleSessionLogic::settle_offchain_state(&mut new_app_info.state, state_proof.app_state.state);
+
+            AppInfoMap::<T>::mutate(&session_id, |app_info| *app_info = Some(new_app_info.clone()));
+            Self::deposit_event(RawEvent::FinalizedByAgreement(session_id, new_app_info.seq_num));
+            Self::record_finalized(session_id, &new_app_info.status);
+
+            Ok(())
+        }
+
         /// Update state according to an on-chain action
         ///
         /// Parameters:
@@ -211,28 +313,34 @@ decl_module!  {
         /// ## Weight
         /// - Complexity: `O(1)`
         ///   - 1 storage mutation `AppInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
         ///   - 1 storage read `AppInfoMap`
-        /// - Based on benchmark;
-        ///     23.92　µs
         /// # </weight>
-        #[weight = 24_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::update_by_action()]
         fn update_by_action(
             origin,
             session_id: T::Hash,
             action: u8
         ) -> DispatchResult {
-            ensure_signed(origin)?;
+            let caller = ensure_signed(origin)?;
             let app_info = match AppInfoMap::<T>::get(session_id) {
                 Some(app) => app,
                 None => Err(Error::<T>::AppInfoNotExist)?,
             };
+            let player_index = match app_info.players.iter().position(|player| *player == caller) {
+                Some(index) => index as u8,
+                None => Err(Error::<T>::NotPlayer)?,
+            };
 
             // apply an action to the on-chain state
-            let mut new_app_info: AppInfoOf<T> = Self::apply_action(app_info)?;
-        
-            if action == 1 || action == 2 {
+            let mut new_app_info: AppInfoOf<T> = Self::apply_action(app_info, player_index, action)?;
+            Self::deposit_event(RawEvent::ActionApplied(session_id, caller, new_app_info.seq_num, action));
+
+            if SingleSessionLogic::is_terminal(&new_app_info.state) {
                 new_app_info.status = AppStatus::Finalized;
-            } 
+            }
+            Self::record_finalized(session_id, &new_app_info.status);
+            Self::queue_deadline(session_id, &new_app_info);
             AppInfoMap::<T>::mutate(&session_id, |app_info| *app_info = Some(new_app_info));
 
             Ok(())
@@ -247,52 +355,128 @@ decl_module!  {
         /// ## Weight
         /// - Complexity: `O(1)`
         ///   - 1 storage mutation `AppInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
         ///   - 1 storage read `AppInfoMapp`
-        /// - Based on benchmark;
-        ///    21.59 　µs
         /// # </weight>
-        #[weight = 22_000_000 + T::DbWeight::get().reads_writes(1, 1)]
+        #[weight = T::WeightInfo::finalize_on_action_timeout()]
         fn finalize_on_action_timeout(
             origin,
             session_id: T::Hash
         ) -> DispatchResult {
             ensure_signed(origin)?;
-            let mut app_info = match AppInfoMap::<T>::get(session_id) {
+            let app_info = match AppInfoMap::<T>::get(session_id) {
                 Some(app) => app,
                 None => Err(Error::<T>::AppInfoNotExist)?,
             };
             
             let block_number = frame_system::Module::<T>::block_number();
-            if app_info.status == AppStatus::Action {
-                ensure!(
-                    block_number >  app_info.deadline,
-                    "deadline no passes"
-                );
-            } else if app_info.status == AppStatus::Settle {
-                ensure!(
-                    block_number > app_info.deadline + app_info.timeout,
-                    "while setting"
-                );
-            } else {
+            if !app_info.is_action_timeout(block_number).map_err(Error::<T>::from)? {
                 return Ok(());
             }
 
-            AppInfoMap::<T>::mutate(&session_id, |info| {
-                app_info.status = AppStatus::Finalized;
-                *info = Some(app_info)
-            });
+            Self::finalize_on_timeout(session_id, app_info)
+        }
+
+        /// Finalize in case of on-chain action timeout, submitted unsigned by the off-chain worker
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of app
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 1 storage mutation `AppInfoMap`
+        ///   - 1 storage insertion `FinalizedAt` on finalization
+        ///   - 1 storage read `AppInfoMap`
+        /// # </weight>
+        #[weight = T::WeightInfo::finalize_on_action_timeout()]
+        fn finalize_on_action_timeout_unsigned(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            ensure_none(origin)?;
+            let app_info = match AppInfoMap::<T>::get(session_id) {
+                Some(app) => app,
+                None => Err(Error::<T>::AppInfoNotExist)?,
+            };
+
+            let block_number = frame_system::Module::<T>::block_number();
+            if !app_info.is_action_timeout(block_number).map_err(Error::<T>::from)? {
+                return Ok(());
+            }
+
+            Self::finalize_on_timeout(session_id, app_info)
+        }
+
+        /// Clear a finalized app whose outcome is consumed
+        ///
+        /// Parameters:
+        /// - `session_id`: Id of app
+        ///
+        /// # <weight>
+        /// ## Weight
+        /// - Complexity: `O(1)`
+        ///   - 4 storage removals `AppInfoMap`, `FinalizedAt`, `OutcomeConsumed`, `Deposits`
+        ///   - 1 storage insertion `ClearedSessions`
+        ///   - 1 balance unreservation of the initiator
+        ///   - 4 storage reads `AppInfoMap`, `FinalizedAt`, `OutcomeConsumed`, `Deposits`
+        /// # </weight>
+        #[weight = T::WeightInfo::clear_session()]
+        fn clear_session(
+            origin,
+            session_id: T::Hash
+        ) -> DispatchResult {
+            let caller = ensure_signed(origin)?;
+            let app_info = match AppInfoMap::<T>::get(session_id) {
+                Some(info) => info,
+                None => Err(Error::<T>::AppInfoNotExist)?,
+            };
+            ensure!(
+                app_info.players.contains(&caller),
+                Error::<T>::NotPlayer
+            );
+            Self::ensure_clearable(session_id).map_err(Error::<T>::from)?;
+
+            Self::clear(session_id);
 
             Ok(())
         }
+
+        /// Finalize the apps whose action deadline passed, at most `MaxFinalizePerBlock` of them,
+        /// and clear the apps queued for this block, at most `MaxClearPerBlock` of them
+        fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            Self::finalize_expired(block_number).saturating_add(Self::sweep(block_number))
+        }
+
+        /// Submit an unsigned finalization of the expired apps left over by `on_initialize` of the next block
+        fn offchain_worker(block_number: T::BlockNumber) {
+            Self::submit_expired(block_number)
+        }
     }
 }
 
 decl_event! (
     pub enum Event<T> where 
+        <T as system::Trait>::AccountId,
+        <T as system::Trait>::BlockNumber,
         <T as system::Trait>::Hash
     {
+        /// AppInitiated(session_id, players, timeout)
+        AppInitiated(Hash, Vec<AccountId>, BlockNumber),
         /// IntendSettle(session_id, seq_num)
         IntendSettle(Hash, u128),
+        /// ActionApplied(session_id, player, seq_num, action)
+        ActionApplied(Hash, AccountId, u128, u8),
+        /// FinalizedOnTimeout(session_id), players take no turns so there is no loser
+        FinalizedOnTimeout(Hash),
+        /// FinalizedByAgreement(session_id, seq_num)
+        FinalizedByAgreement(Hash, u128),
+        /// Finalized(session_id)
+        Finalized(Hash),
+        /// OutcomeConsumed(session_id)
+        OutcomeConsumed(Hash),
+        /// SessionCleared(session_id)
+        SessionCleared(Hash),
     }
 );
 
@@ -301,7 +485,224 @@ decl_error! {
         // AppInfo is not exist
         AppInfoNotExist,
         // A scale-codec encoded value can not decode correctly
-        MustBeDecodable
+        MustBeDecodable,
+        // Caller is not a player of the app
+        NotPlayer,
+        // App is not finalized
+        NotFinalized,
+        // Outcome of the app is not consumed
+        OutcomeNotConsumed,
+        // Retention period of the finalized app does not pass
+        RetentionPeriodNotPassed,
+        // App state is finalized
+        AlreadyFinalized,
+        // Sequence number is not larger than the on-chain one
+        InvalidSeqNum,
+        // App is not in action mode
+        NotInActionMode,
+        // Action deadline does not pass
+        DeadlineNotPassed,
+        // Settle period and action period do not pass
+        WhileSettling,
+        // Number of signatures does not match number of players
+        InvalidSignatureCount,
+        // Co-signatures of the state are invalid
+        InvalidSignature,
+        // Players are not in ascending order
+        InvalidPlayers,
+        // Number of players is invalid
+        InvalidPlayerNum,
+        // App with the session id already exists or was cleared
+        AppAlreadyExists,
+        // Nonce of the state does not match the app
+        NonceMismatch,
+        // Config is rejected by the app logic
+        InvalidConfig,
+        // Off-chain state is rejected by the app logic
+        InvalidOffchainState,
+        // Action is rejected by the app logic
+        InvalidAction,
+        // Caller is not the player to move
+        NotYourTurn,
+        // App logic can not encode the outcome as queried
+        InvalidQuery
+    }
+}
+
+impl<T: Trait> From<DisputeError> for Error<T> {
+    fn from(err: DisputeError) -> Error<T> {
+        match err {
+            DisputeError::AlreadyFinalized => Error::<T>::AlreadyFinalized,
+            DisputeError::InvalidSeqNum => Error::<T>::InvalidSeqNum,
+            DisputeError::NotInActionMode => Error::<T>::NotInActionMode,
+            DisputeError::DeadlineNotPassed => Error::<T>::DeadlineNotPassed,
+            DisputeError::WhileSettling => Error::<T>::WhileSettling,
+            DisputeError::InvalidSignatureCount => Error::<T>::InvalidSignatureCount,
+            DisputeError::InvalidSignature => Error::<T>::InvalidSignature,
+            DisputeError::InvalidPlayers => Error::<T>::InvalidPlayers,
+        }
+    }
+}
+
+impl<T: Trait> From<AppLogicError> for Error<T> {
+    fn from(err: AppLogicError) -> Error<T> {
+        match err {
+            AppLogicError::InvalidConfig => Error::<T>::InvalidConfig,
+            AppLogicError::InvalidOffchainState => Error::<T>::InvalidOffchainState,
+            AppLogicError::InvalidAction => Error::<T>::InvalidAction,
+            AppLogicError::NotYourTurn => Error::<T>::NotYourTurn,
+            AppLogicError::InvalidQuery => Error::<T>::InvalidQuery,
+        }
+    }
+}
+
+impl<T: Trait> From<LifecycleError> for Error<T> {
+    fn from(err: LifecycleError) -> Error<T> {
+        match err {
+            LifecycleError::NotFinalized => Error::<T>::NotFinalized,
+            LifecycleError::OutcomeNotConsumed => Error::<T>::OutcomeNotConsumed,
+            LifecycleError::RetentionPeriodNotPassed => Error::<T>::RetentionPeriodNotPassed,
+        }
+    }
+}
+
+impl<T: Trait> CelerAppQuery<T::Hash, T::BlockNumber> for Module<T> {
+    fn is_finalized(args_query_finalization: Vec<u8>) -> Result<bool, DispatchError> {
+        Self::is_finalized(args_query_finalization)
+    }
+
+    fn get_outcome(args_query_outcome: Vec<u8>) -> Result<Vec<u8>, DispatchError> {
+        Self::get_outcome(args_query_outcome)
+    }
+
+    fn get_state(session_id: T::Hash, _key: u8) -> Option<Vec<u8>> {
+        Self::get_state(session_id).map(|state| state.encode())
+    }
+
+    fn get_status(session_id: T::Hash) -> Option<AppStatus> {
+        Self::get_status(session_id)
+    }
+
+    fn get_settle_finalized_time(session_id: T::Hash) -> Option<T::BlockNumber> {
+        Self::get_settle_finalized_time(session_id)
+    }
+
+    fn get_action_deadline(session_id: T::Hash) -> Option<T::BlockNumber> {
+        Self::get_action_deadline(session_id)
+    }
+
+    fn get_seq_num(session_id: T::Hash) -> Option<u128> {
+        Self::get_seq_num(session_id)
+    }
+}
+
+#[allow(deprecated)] // ValidateUnsigned
+impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
+    type Call = Call<T>;
+
+    /// Accept the unsigned finalization of an app whose action deadline passed
+    ///
+    /// Parameters:
+    /// `_source`: Source of the transaction
+    /// `call`: Call of the transaction
+    fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+        match call {
+            Call::finalize_on_action_timeout_unsigned(session_id) => Self::validate_finalization(session_id),
+            _ => InvalidTransaction::Call.into(),
+        }
+    }
+}
+
+impl<T: Trait> ConsumeOutcome<T::Hash> for Module<T> {
+    fn consume_outcome(session_id: T::Hash) -> DispatchResult {
+        Self::queue_clear(session_id).map_err(Error::<T>::from)?;
+
+        Ok(())
+    }
+}
+
+impl<T: Trait> SessionLifecycle for Module<T> {
+    type AccountId = T::AccountId;
+    type Hash = T::Hash;
+    type BlockNumber = T::BlockNumber;
+    type Info = AppInfoOf<T>;
+    type InfoMap = AppInfoMap<T>;
+    type FinalizedAt = FinalizedAt<T>;
+    type OutcomeConsumed = OutcomeConsumed<T>;
+    type DeadlineQueue = DeadlineQueue<T>;
+    type ClearQueue = ClearQueue<T>;
+    type Currency = T::Currency;
+    type Deposits = Deposits<T>;
+    type ClearedSessions = ClearedSessions<T>;
+
+    const UNSIGNED_TAG_PREFIX: &'static str = "SingleSessionApp";
+
+    fn block_number() -> T::BlockNumber {
+        frame_system::Module::<T>::block_number()
+    }
+
+    fn retention_period() -> T::BlockNumber {
+        T::RetentionPeriod::get()
+    }
+
+    fn max_finalize_per_block() -> u32 {
+        T::MaxFinalizePerBlock::get()
+    }
+
+    fn max_clear_per_block() -> u32 {
+        T::MaxClearPerBlock::get()
+    }
+
+    fn unsigned_priority() -> TransactionPriority {
+        T::UnsignedPriority::get()
+    }
+
+    fn session_deposit() -> SessionDepositConfig<BalanceOf<T>> {
+        T::SessionDeposit::get()
+    }
+
+    fn finalize_expired_weight(queued: u32) -> Weight {
+        T::WeightInfo::finalize_expired(queued)
+    }
+
+    fn sweep_weight(queued: u32) -> Weight {
+        T::WeightInfo::on_initialize(queued)
+    }
+
+    /// Finalize an app whose action deadline passed
+    ///
+    /// Parameters:
+    /// `session_id`: Id of app
+    /// `app_info`: Info of app state
+    fn finalize_on_timeout(
+        session_id: T::Hash,
+        mut app_info: AppInfoOf<T>
+    ) -> DispatchResult {
+        AppInfoMap::<T>::mutate(&session_id, |info| {
+            app_info.status = AppStatus::Finalized;
+            *info = Some(app_info)
+        });
+        Self::deposit_event(RawEvent::FinalizedOnTimeout(session_id));
+        Self::record_finalized(session_id, &AppStatus::Finalized);
+
+        Ok(())
+    }
+
+    fn submit_finalization(session_id: T::Hash) -> Result<(), ()> {
+        let call = Call::finalize_on_action_timeout_unsigned(session_id);
+        SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
+    }
+
+    fn on_finalized(session_id: T::Hash) {
+        Self::deposit_event(RawEvent::Finalized(session_id));
+    }
+
+    fn on_outcome_consumed(session_id: T::Hash) {
+        Self::deposit_event(RawEvent::OutcomeConsumed(session_id));
+    }
+
+    fn on_cleared(session_id: T::Hash) {
+        Self::deposit_event(RawEvent::SessionCleared(session_id));
     }
 }
 
@@ -347,29 +748,22 @@ impl<T: Trait> Module<T> {
             None => Err(Error::<T>::AppInfoNotExist)?,
         };
 
-        if app_info.state == query_outcome.query_data {
-            // If outcome is true, return encoded true value
-            return Ok(true.encode());
-        } else {
-            // If outcome is false, return encoded false value
-            return Ok(false.encode());
-        }
+        let outcome = SingleSessionLogic::outcome(&app_info.state, query_outcome.query_data)
+            .map_err(Error::<T>::from)?;
+        Ok(outcome)
     }
 
     /// Get Id of app
     ///
-    /// Parameters:
-    /// `nonce`: Nonce of app
-    /// `players`: AccountId of players
-    fn get_session_id(
-        nonce: u128,
-        players: Vec<T::AccountId>,
-    ) -> T::Hash {
+    /// The id commits to every parameter of the initiate request, so a request
+    /// with the same nonce and players but other parameters gets another id.
+    ///
+    /// Parameter:
+    /// `initiate_request`: Request initiating the app
+    pub fn get_session_id(initiate_request: &AppInitiateRequestOf<T>) -> T::Hash {
         let app_account = Self::app_account();
         let mut encoded = app_account.encode();
-        encoded.extend(nonce.encode());
-        encoded.extend(players[0].encode());
-        encoded.extend(players[1].encode());
+        encoded.extend(initiate_request.encode());
         let session_id = T::Hashing::hash(&encoded);
         return session_id;
     }
@@ -410,11 +804,7 @@ impl<T: Trait> Module<T> {
             None => return None,
         };
 
-        if app_info.status == AppStatus::Settle {
-            return Some(app_info.deadline);
-        }
-
-        return None;
+        return app_info.settle_finalized_time();
     }
 
     /// Get action deadline
@@ -426,13 +816,8 @@ impl<T: Trait> Module<T> {
             Some(app) => app,
             None => return None,
         };
-        if app_info.status == AppStatus::Action {
-            return Some(app_info.deadline);
-        } else if app_info.status == AppStatus::Settle {
-            return Some(app_info.deadline + app_info.timeout);
-        } else {
-            return None;
-        }
+
+        return app_info.action_deadline();
     }
 
     /// Get app sequence number
@@ -463,80 +848,114 @@ impl<T: Trait> Module<T> {
     ) -> Result<AppInfoOf<T>, DispatchError> {
         let app_state = state_proof.app_state;
         let encoded = Self::encode_app_state(app_state.clone());
-        Self::valid_signers(state_proof.sigs, &encoded, app_info.players.clone())?;
-        ensure!(
-            app_info.status != AppStatus::Finalized,
-            "app state is finalized"
-        );
+        celer_app_core::valid_signers(&state_proof.sigs, &encoded, &app_info.players).map_err(Error::<T>::from)?;
         ensure!(
             app_state.nonce == app_info.nonce,
-            "nonce not match"
+            Error::<T>::NonceMismatch
         );
-        ensure!(
-            app_info.seq_num < app_state.seq_num,
-            "invalid sequence number"
-        );
-
-        app_info.seq_num = app_state.seq_num;
-        app_info.deadline = frame_system::Module::<T>::block_number() + app_info.timeout;
-        app_info.status = AppStatus::Settle;
+        SingleSessionLogic::validate_offchain_state(&app_info.state, &app_state.state).map_err(Error::<T>::from)?;
+        app_info.intend_settle(app_state.seq_num, frame_system::Module::<T>::block_number()).map_err(Error::<T>::from)?;
 
         Ok(app_info)
     }
 
-    /// Apply an action to the on-chain state
+    /// Finalize with an off-chain state co-signed as the final state
     ///
     /// Parameter:
     /// `app_info`: Info of app state
-    fn apply_action(
-        mut app_info: AppInfoOf<T>
+    /// `state_proof`: Off-chain app state signed as final by all players
+    fn agree_final_state(
+        mut app_info: AppInfoOf<T>,
+        state_proof: StateProofOf<T>
     ) -> Result<AppInfoOf<T>, DispatchError> {
+        let app_state = state_proof.app_state;
+        let encoded = Self::encode_final_app_state(app_state.clone());
+        celer_app_core::valid_signers(&state_proof.sigs, &encoded, &app_info.players).map_err(Error::<T>::from)?;
         ensure!(
-            app_info.status != AppStatus::Finalized,
-            "app state is finalized"
+            app_state.nonce == app_info.nonce,
+            Error::<T>::NonceMismatch
         );
-
-        let block_number =  frame_system::Module::<T>::block_number();
-        if app_info.status == AppStatus::Settle && block_number > app_info.deadline {
-            app_info.seq_num = app_info.seq_num + 1;
-            app_info.deadline = block_number + app_info.timeout;
-            app_info.status = AppStatus::Action;
-        } else {
-            ensure!(
-                app_info.status ==  AppStatus::Action,
-                "app not in action mode"
-            );
-            app_info.seq_num = app_info.seq_num + 1;
-            app_info.deadline = block_number + app_info.timeout;
-            app_info.status = AppStatus::Action;
-        }
+        SingleSessionLogic::validate_offchain_state(&app_info.state, &app_state.state).map_err(Error::<T>::from)?;
+        app_info.finalize_by_agreement(app_state.seq_num).map_err(Error::<T>::from)?;
 
         Ok(app_info)
     }
 
-    /// Verify off-chain state signatures
+    /// Apply an action to the on-chain state
     ///
     /// Parameters:
-    /// `signatures`: Signaturs from the players
-    /// `encoded`: Encoded app state
-    /// `signers`: AccountId of player
-    fn valid_signers(
-        signatures: Vec<<T as Trait>::Signature>,
-        encoded: &[u8],
-        signers: Vec<T::AccountId>,
-    ) -> DispatchResult {
-        for i in 0..2 {
-            ensure!(&signatures[i].verify(encoded, &signers[i]), "Check co-sigs failed")
-        }
-        Ok(())
+    /// `app_info`: Info of app state
+    /// `player_index`: Index of the caller in the players
+    /// `action`: Action data
+    fn apply_action(
+        mut app_info: AppInfoOf<T>,
+        player_index: u8,
+        action: u8,
+    ) -> Result<AppInfoOf<T>, DispatchError> {
+        app_info.apply_action(frame_system::Module::<T>::block_number()).map_err(Error::<T>::from)?;
+        SingleSessionLogic::apply_action(&mut app_info.state, player_index, action).map_err(Error::<T>::from)?;
+
+        Ok(app_info)
     }
 
-    /// Encode app state
+    /// Encode app state signed by the players on this chain
     ///
     /// Parameter:
     /// `app_state`: app state
     fn encode_app_state(
         app_state: AppStateOf<T>
+    ) -> Vec<u8> {
+        let genesis_hash = frame_system::Module::<T>::block_hash(T::BlockNumber::zero());
+        Self::signing_payload(app_state, genesis_hash)
+    }
+
+    /// Encode app state signed by the players as the final state on this chain
+    ///
+    /// Parameter:
+    /// `app_state`: app state
+    fn encode_final_app_state(
+        app_state: AppStateOf<T>
+    ) -> Vec<u8> {
+        let genesis_hash = frame_system::Module::<T>::block_hash(T::BlockNumber::zero());
+        Self::final_signing_payload(app_state, genesis_hash)
+    }
+
+    /// Get the bytes of an app state the players sign off-chain
+    ///
+    /// The encoded app state is prefixed with the versioned domain separator of
+    /// this runtime module and chain, see `celer_app_core::state_signing_payload`.
+    ///
+    /// Parameters:
+    /// `app_state`: app state
+    /// `genesis_hash`: Hash of the genesis block of the chain
+    pub fn signing_payload(
+        app_state: AppStateOf<T>,
+        genesis_hash: T::Hash
+    ) -> Vec<u8> {
+        celer_app_core::state_signing_payload(SINGLE_SESSION_APP_ID, &genesis_hash, &Self::encode_state(app_state))
+    }
+
+    /// Get the bytes of an app state the players sign off-chain as the final state
+    ///
+    /// The encoded app state is prefixed with the final state domain separator of
+    /// this runtime module and chain, see `celer_app_core::final_state_signing_payload`.
+    ///
+    /// Parameters:
+    /// `app_state`: app state
+    /// `genesis_hash`: Hash of the genesis block of the chain
+    pub fn final_signing_payload(
+        app_state: AppStateOf<T>,
+        genesis_hash: T::Hash
+    ) -> Vec<u8> {
+        celer_app_core::final_state_signing_payload(SINGLE_SESSION_APP_ID, &genesis_hash, &Self::encode_state(app_state))
+    }
+
+    /// Encode the fields of an app state
+    ///
+    /// Parameter:
+    /// `app_state`: app state
+    fn encode_state(
+        app_state: AppStateOf<T>
     ) -> Vec<u8> {
         let mut encoded = app_state.nonce.encode();
         encoded.extend(app_state.seq_num.encode());
@@ -544,7 +963,7 @@ impl<T: Trait> Module<T> {
         encoded.extend(app_state.timeout.encode());
         encoded.extend(app_state.session_id.encode());
 
-        return encoded;
+        encoded
     }
 
 }
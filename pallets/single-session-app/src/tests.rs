@@ -1,11 +1,17 @@
 use super::*;
 use mock::*;
 use sp_core::{sr25519, Pair, H256};
-use frame_support::{assert_ok, assert_noop};
+use frame_support::{assert_ok, assert_noop, traits::OnInitialize};
+use frame_support::unsigned::ValidateUnsigned;
+use sp_core::offchain::{
+    testing::{TestOffchainExt, TestTransactionPoolExt}, OffchainExt, TransactionPoolExt,
+};
+use sp_runtime::traits::OffchainWorker;
 
 #[test]
 fn test_pass_initiate() {
     ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
         let alice_pair = account_pair("Alice");
         let bob_pair = account_pair("Bob");        
         let (players_peers, _) 
@@ -19,7 +25,195 @@ fn test_pass_initiate() {
         
         assert_ok!(SingleSessionApp::app_initiate(
             Origin::signed(players_peers[0]),
-            initiate_request)
+            initiate_request.clone())
+        );
+
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
+        let expected_event = TestEvent::single_app(RawEvent::AppInitiated(session_id, players_peers.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_fail_initiate() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: vec![players[0]],
+            timeout: 2,
+        };
+        assert_noop!(
+            SingleSessionApp::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayerNum
+        );
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: vec![players[1], players[0]],
+            timeout: 2,
+        };
+        assert_noop!(
+            SingleSessionApp::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::InvalidPlayers
+        );
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+        };
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        assert_noop!(
+            SingleSessionApp::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::AppAlreadyExists
+        );
+    })
+}
+
+#[test]
+fn test_pass_initiate_after_front_run_with_other_parameters() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, _)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        // the opponent initiates first with the same nonce and players but no timeout
+        let front_run_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 0,
+        };
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(players[1]),
+            front_run_request.clone()
+        ));
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+        };
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
+        assert_ne!(session_id, SingleSessionApp::get_session_id(&front_run_request));
+        let expected_event = TestEvent::single_app(RawEvent::AppInitiated(session_id, players.clone(), 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+fn test_fail_update_by_state_with_invalid_state_proof() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+        };
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
+
+        let mut state_proof = get_state_proof(0, 1, 5, 2, session_id, players_pair.clone());
+        state_proof.sigs.pop();
+        assert_noop!(
+            SingleSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidSignatureCount
+        );
+
+        let mut state_proof = get_state_proof(0, 1, 5, 2, session_id, players_pair.clone());
+        state_proof.sigs.swap(0, 1);
+        assert_noop!(
+            SingleSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidSignature
+        );
+
+        let state_proof = get_state_proof(1, 1, 5, 2, session_id, players_pair);
+        assert_noop!(
+            SingleSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::NonceMismatch
+        );
+    })
+}
+
+#[test]
+fn test_fail_update_by_state_signed_for_other_chain() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+        };
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
+
+        let app_state = AppState {
+            nonce: 0,
+            seq_num: 1,
+            state: 5,
+            timeout: 2,
+            session_id: session_id,
+        };
+        assert_eq!(
+            SingleSessionApp::signing_payload(app_state.clone(), System::block_hash(0)),
+            SingleSessionApp::encode_app_state(app_state.clone())
+        );
+
+        let encoded = SingleSessionApp::signing_payload(app_state.clone(), H256::repeat_byte(1));
+        let state_proof = StateProof {
+            app_state: app_state,
+            sigs: vec![players_pair[0].sign(&encoded), players_pair[1].sign(&encoded)],
+        };
+        assert_noop!(
+            SingleSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::InvalidSignature
         );
     })
 }
@@ -43,13 +237,20 @@ fn test_fail_update_by_action() {
             initiate_request.clone()
         ));
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
+        assert_noop!(
+            SingleSessionApp::update_by_action(
+            Origin::signed(account_pair("Carl").public()),
+            session_id,
+            1),
+            Error::<TestRuntime>::NotPlayer
+        );
         assert_noop!(
             SingleSessionApp::update_by_action(
             Origin::signed(players_peers[0]),
             session_id,
             1),
-            "app not in action mode"
+            Error::<TestRuntime>::NotInActionMode
         );
     })
 }
@@ -73,7 +274,7 @@ fn test_pass_update_by_state_state_is_5() {
             initiate_request.clone()
         ));
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(0, 2, 5, 2, session_id, players_pair);
         assert_ok!(
             SingleSessionApp::update_by_state(
@@ -132,7 +333,7 @@ fn test_fail_update_by_action_before_settle_finalized_time_should_fail() {
             initiate_request.clone()
         ));
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(0, 2, 5, 2, session_id, players_pair);
         assert_ok!(
             SingleSessionApp::update_by_state(
@@ -147,7 +348,7 @@ fn test_fail_update_by_action_before_settle_finalized_time_should_fail() {
                 session_id,
                 1
             ),
-            "app not in action mode"
+            Error::<TestRuntime>::NotInActionMode
         );
     })
 }
@@ -171,7 +372,7 @@ fn test_pass_update_by_action_after_settle_finalized_time() {
             initiate_request.clone()
         ));
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(0, 2, 5, 2, session_id, players_pair);
         assert_ok!(
             SingleSessionApp::update_by_state(
@@ -189,15 +390,29 @@ fn test_pass_update_by_action_after_settle_finalized_time() {
                 1
             )
         );
+        let expected_event = TestEvent::single_app(RawEvent::ActionApplied(session_id, players_peers[0], 3, 1));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_app(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
 
+        // the action replaces the settled state
+        assert_eq!(SingleSessionApp::get_state(session_id), Some(1));
         let args_query_outcome = SingleSessionArgsQueryOutcome {
             session_id: session_id,
-            query_data: 5
+            query_data: 1
         };
         assert_eq!(
             SingleSessionApp::get_outcome(args_query_outcome.encode()).unwrap(),
             true.encode()
-        );    
+        );
+        let args_query_outcome = SingleSessionArgsQueryOutcome {
+            session_id: session_id,
+            query_data: 5
+        };
+        assert_eq!(
+            SingleSessionApp::get_outcome(args_query_outcome.encode()).unwrap(),
+            false.encode()
+        );
     })
 }
 
@@ -219,14 +434,14 @@ fn test_fail_update_by_state_with_invlaid_seq_num() {
             initiate_request.clone()
         ));
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(0, 0, 5, 2, session_id, players_pair);
         assert_noop!(
             SingleSessionApp::update_by_state(
                 Origin::signed(players_peers[0]),
                 state_proof
             ),
-            "invalid sequence number"
+            Error::<TestRuntime>::InvalidSeqNum
         );
     })
 }
@@ -250,7 +465,7 @@ fn test_pass_update_by_state_state_is_2() {
             initiate_request.clone()
         ));
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(0, 2, 2, 2, session_id, players_pair);
         assert_ok!(
             SingleSessionApp::update_by_state(
@@ -260,6 +475,8 @@ fn test_pass_update_by_state_state_is_2() {
         );
         let expected_event = TestEvent::single_app(RawEvent::IntendSettle(session_id, 2));       
         assert!(System::events().iter().any(|a| a.event == expected_event)); 
+        let expected_event = TestEvent::single_app(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
 
         assert_eq!(
             SingleSessionApp::is_finalized(session_id.encode()).unwrap(), 
@@ -294,7 +511,7 @@ fn test_fail_update_by_action_after_finalized() {
             initiate_request.clone()
         ));
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(0, 2, 2, 2, session_id, players_pair);
         assert_ok!(
             SingleSessionApp::update_by_state(
@@ -309,7 +526,7 @@ fn test_fail_update_by_action_after_finalized() {
                 session_id,
                 1
             ),
-            "app state is finalized"
+            Error::<TestRuntime>::AlreadyFinalized
         );
     })
 }
@@ -332,7 +549,7 @@ fn test_fail_update_by_state_after_finalized() {
             initiate_request.clone()
         ));
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
         let mut state_proof = get_state_proof(0, 2, 2, 2, session_id, players_pair.clone());
         assert_ok!(
             SingleSessionApp::update_by_state(
@@ -347,7 +564,7 @@ fn test_fail_update_by_state_after_finalized() {
                 Origin::signed(players_peers[0]),
                 state_proof
             ),
-            "app state is finalized"
+            Error::<TestRuntime>::AlreadyFinalized
         );
     })
 }
@@ -372,7 +589,7 @@ fn test_pass_finalize_on_action_timeout() {
             )
         );
 
-        let session_id = SingleSessionApp::get_session_id(initiate_request.nonce, initiate_request.players.clone());
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
         let state_proof = get_state_proof(0, 1, 2, 2, session_id, players_pair.clone());
         assert_ok!(
             SingleSessionApp::update_by_state(
@@ -392,6 +609,412 @@ fn test_pass_finalize_on_action_timeout() {
 }
 
 
+#[test]
+fn test_celer_app_query() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+        };
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
+        let state_proof = get_state_proof(0, 1, 5, 2, session_id, players_pair);
+        assert_ok!(
+            SingleSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            )
+        );
+
+        assert_eq!(<SingleSessionApp as CelerAppQuery<H256, BlockNumber>>::is_finalized(session_id.encode()).unwrap(), false);
+        let args_query_outcome = SingleSessionArgsQueryOutcome {
+            session_id: session_id,
+            query_data: 5
+        };
+        assert_eq!(
+            <SingleSessionApp as CelerAppQuery<H256, BlockNumber>>::get_outcome(args_query_outcome.encode()).unwrap(),
+            true.encode()
+        );
+        assert_eq!(<SingleSessionApp as CelerAppQuery<H256, BlockNumber>>::get_state(session_id, 0), Some(vec![5]));
+        assert_eq!(<SingleSessionApp as CelerAppQuery<H256, BlockNumber>>::get_status(session_id), Some(AppStatus::Settle));
+        assert_eq!(<SingleSessionApp as CelerAppQuery<H256, BlockNumber>>::get_settle_finalized_time(session_id), Some(3));
+        assert_eq!(<SingleSessionApp as CelerAppQuery<H256, BlockNumber>>::get_action_deadline(session_id), Some(5));
+        assert_eq!(<SingleSessionApp as CelerAppQuery<H256, BlockNumber>>::get_seq_num(session_id), Some(1));
+        assert_eq!(<SingleSessionApp as CelerAppQuery<H256, BlockNumber>>::get_status(H256::zero()), None);
+    })
+}
+
+#[test]
+fn test_initiate_reserves_deposit() {
+    ExtBuilder::build().execute_with(|| {
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair);
+        // base + 2 players + 1 byte of state
+        assert_eq!(SingleSessionApp::deposit_of(session_id), Some((players[0], 15)));
+        assert_eq!(Balances::reserved_balance(players[0]), 15);
+        assert_eq!(Balances::free_balance(players[0]), 985);
+        assert_eq!(Balances::reserved_balance(players[1]), 0);
+
+        // initiator can not pay the deposit
+        let carl = account_pair("Carl").public();
+        let initiate_request = AppInitiateRequest {
+            nonce: 1,
+            players: players.clone(),
+            timeout: 2,
+        };
+        assert_noop!(
+            SingleSessionApp::app_initiate(
+                Origin::signed(carl),
+                initiate_request
+            ),
+            pallet_balances::Error::<TestRuntime, pallet_balances::DefaultInstance>::InsufficientBalance
+        );
+    })
+}
+
+#[test]
+fn test_clear_session() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_id = settle_app(0, players.clone(), players_pair.clone());
+        assert_noop!(
+            SingleSessionApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::NotFinalized
+        );
+        assert_noop!(
+            <SingleSessionApp as ConsumeOutcome<H256>>::consume_outcome(session_id),
+            Error::<TestRuntime>::NotFinalized
+        );
+
+        System::set_block_number(6);
+        assert_ok!(
+            SingleSessionApp::finalize_on_action_timeout(
+                Origin::signed(players[0]),
+                session_id
+            )
+        );
+        assert_eq!(SingleSessionApp::finalized_at(session_id), Some(6));
+        let expected_event = TestEvent::single_app(RawEvent::FinalizedOnTimeout(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        let expected_event = TestEvent::single_app(RawEvent::Finalized(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_noop!(
+            SingleSessionApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::OutcomeNotConsumed
+        );
+
+        assert_ok!(<SingleSessionApp as ConsumeOutcome<H256>>::consume_outcome(session_id));
+        let expected_event = TestEvent::single_app(RawEvent::OutcomeConsumed(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_eq!(SingleSessionApp::clear_queue(16), vec![session_id]);
+
+        System::set_block_number(15);
+        let stranger = account_pair("Carl").public();
+        assert_noop!(
+            SingleSessionApp::clear_session(
+                Origin::signed(stranger),
+                session_id
+            ),
+            Error::<TestRuntime>::NotPlayer
+        );
+        assert_noop!(
+            SingleSessionApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::RetentionPeriodNotPassed
+        );
+
+        System::set_block_number(16);
+        assert_ok!(
+            SingleSessionApp::clear_session(
+                Origin::signed(players[1]),
+                session_id
+            )
+        );
+        assert_eq!(SingleSessionApp::get_status(session_id), None);
+        assert_eq!(SingleSessionApp::finalized_at(session_id), None);
+        assert_eq!(SingleSessionApp::outcome_consumed(session_id), false);
+        assert_eq!(SingleSessionApp::deposit_of(session_id), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        let expected_event = TestEvent::single_app(RawEvent::SessionCleared(session_id));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+        assert_noop!(
+            SingleSessionApp::clear_session(
+                Origin::signed(players[0]),
+                session_id
+            ),
+            Error::<TestRuntime>::AppInfoNotExist
+        );
+
+        // queued id of the cleared app is skipped
+        SingleSessionApp::on_initialize(16);
+        assert_eq!(
+            System::events().iter().filter(|a| a.event == expected_event).count(),
+            1
+        );
+
+        // the cleared app can not be initiated again to replay its old states
+        assert_eq!(SingleSessionApp::is_cleared(session_id), true);
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+        };
+        assert_eq!(SingleSessionApp::get_session_id(&initiate_request), session_id);
+        assert_noop!(
+            SingleSessionApp::app_initiate(
+                Origin::signed(players[0]),
+                initiate_request
+            ),
+            Error::<TestRuntime>::AppAlreadyExists
+        );
+        let state_proof = get_state_proof(0, 1, 5, 2, session_id, players_pair);
+        assert_noop!(
+            SingleSessionApp::update_by_state(
+                Origin::signed(players[0]),
+                state_proof
+            ),
+            Error::<TestRuntime>::AppInfoNotExist
+        );
+    })
+}
+
+#[test]
+fn test_clear_sessions_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+
+        System::set_block_number(6);
+        for session_id in session_ids.iter() {
+            assert_ok!(
+                SingleSessionApp::finalize_on_action_timeout(
+                    Origin::signed(players[0]),
+                    *session_id
+                )
+            );
+            assert_ok!(<SingleSessionApp as ConsumeOutcome<H256>>::consume_outcome(*session_id));
+        }
+        assert_eq!(SingleSessionApp::clear_queue(16), session_ids);
+
+        // at most MaxClearPerBlock apps are cleared in a block
+        SingleSessionApp::on_initialize(16);
+        assert_eq!(SingleSessionApp::get_status(session_ids[0]), None);
+        assert_eq!(SingleSessionApp::get_status(session_ids[1]), None);
+        assert!(SingleSessionApp::get_status(session_ids[2]).is_some());
+        assert_eq!(SingleSessionApp::clear_queue(16), Vec::<H256>::new());
+        assert_eq!(SingleSessionApp::clear_queue(17), vec![session_ids[2]]);
+
+        assert_eq!(Balances::reserved_balance(players[0]), 15);
+
+        SingleSessionApp::on_initialize(17);
+        assert_eq!(SingleSessionApp::get_status(session_ids[2]), None);
+        assert_eq!(Balances::reserved_balance(players[0]), 0);
+        assert_eq!(SingleSessionApp::clear_queue(17), Vec::<H256>::new());
+    })
+}
+
+#[test]
+fn test_finalize_expired_on_initialize() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+        // settle period and action period end at block 5
+        assert_eq!(SingleSessionApp::deadline_queue(6), session_ids);
+
+        // the action moves the deadline of the first app to block 6
+        System::set_block_number(4);
+        assert_ok!(
+            SingleSessionApp::update_by_action(
+                Origin::signed(players[0]),
+                session_ids[0],
+                0
+            )
+        );
+        assert_eq!(SingleSessionApp::deadline_queue(7), vec![session_ids[0]]);
+
+        // at most MaxFinalizePerBlock apps are finalized in a block,
+        // the first app is skipped since its deadline moved
+        System::set_block_number(6);
+        SingleSessionApp::on_initialize(6);
+        assert_eq!(SingleSessionApp::get_status(session_ids[0]), Some(AppStatus::Action));
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(SingleSessionApp::get_status(session_ids[2]), Some(AppStatus::Settle));
+        assert_eq!(SingleSessionApp::deadline_queue(6), Vec::<H256>::new());
+        assert_eq!(SingleSessionApp::deadline_queue(7), vec![session_ids[0], session_ids[2]]);
+        let expected_event = TestEvent::single_app(RawEvent::FinalizedOnTimeout(session_ids[1]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        System::set_block_number(7);
+        SingleSessionApp::on_initialize(7);
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[2].encode()).unwrap(), true);
+        assert_eq!(SingleSessionApp::deadline_queue(7), Vec::<H256>::new());
+        let expected_event = TestEvent::single_app(RawEvent::FinalizedOnTimeout(session_ids[0]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+    })
+}
+
+#[test]
+#[allow(deprecated)] // ValidateUnsigned
+fn test_offchain_worker_submits_finalization() {
+    let (offchain, _) = TestOffchainExt::new();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    let mut ext = ExtBuilder::build();
+    ext.register_extension(OffchainExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+        let session_ids: Vec<H256> = (0..4)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+
+        // the action moves the deadline of the first app to block 6
+        System::set_block_number(4);
+        assert_ok!(
+            SingleSessionApp::update_by_action(
+                Origin::signed(players[0]),
+                session_ids[0],
+                0
+            )
+        );
+
+        // nothing is submitted before the action deadline passed
+        System::set_block_number(5);
+        SingleSessionApp::offchain_worker(5);
+        assert!(pool_state.read().transactions.is_empty());
+
+        // the apps beyond MaxFinalizePerBlock are moved to the queue of the next block
+        System::set_block_number(6);
+        SingleSessionApp::on_initialize(6);
+        assert_eq!(SingleSessionApp::get_status(session_ids[0]), Some(AppStatus::Action));
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(
+            SingleSessionApp::deadline_queue(7),
+            vec![session_ids[0], session_ids[2], session_ids[3]]
+        );
+
+        // on_initialize of the next block finalizes the first MaxFinalizePerBlock apps of its queue,
+        // the off-chain worker only submits the finalization of the expired app behind them
+        SingleSessionApp::offchain_worker(6);
+        let tx = pool_state.write().transactions.pop().unwrap();
+        assert!(pool_state.read().transactions.is_empty());
+        let tx = Extrinsic::decode(&mut &*tx).unwrap();
+        assert_eq!(tx.signature, None);
+        let call = crate::Call::finalize_on_action_timeout_unsigned(session_ids[3]);
+        assert_eq!(tx.call, mock::Call::SingleSessionApp(call.clone()));
+
+        assert!(SingleSessionApp::validate_unsigned(TransactionSource::External, &call).is_ok());
+        assert_ok!(SingleSessionApp::finalize_on_action_timeout_unsigned(Origin::none(), session_ids[3]));
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[3].encode()).unwrap(), true);
+        let expected_event = TestEvent::single_app(RawEvent::FinalizedOnTimeout(session_ids[3]));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        // the finalization of a finalized app is stale
+        assert_eq!(
+            SingleSessionApp::validate_unsigned(TransactionSource::External, &call),
+            InvalidTransaction::Stale.into()
+        );
+        System::set_block_number(7);
+        SingleSessionApp::on_initialize(7);
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[2].encode()).unwrap(), true);
+        SingleSessionApp::offchain_worker(7);
+        assert!(pool_state.read().transactions.is_empty());
+    })
+}
+
+#[test]
+#[allow(deprecated)] // ValidateUnsigned
+fn test_offchain_worker_scans_expired_apps() {
+    let (offchain, _) = TestOffchainExt::new();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    let mut ext = ExtBuilder::build();
+    ext.register_extension(OffchainExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+        let session_ids: Vec<H256> = (0..3)
+            .map(|nonce| settle_app(nonce, players.clone(), players_pair.clone()))
+            .collect();
+        assert_eq!(SingleSessionApp::deadline_queue(6), session_ids);
+
+        // the third app is queued for a later block than its action deadline
+        DeadlineQueue::<TestRuntime>::mutate(6, |queue| queue.retain(|id| *id != session_ids[2]));
+        DeadlineQueue::<TestRuntime>::insert(9, vec![session_ids[2]]);
+
+        System::set_block_number(6);
+        SingleSessionApp::on_initialize(6);
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[0].encode()).unwrap(), true);
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[1].encode()).unwrap(), true);
+        assert_eq!(SingleSessionApp::get_status(session_ids[2]), Some(AppStatus::Settle));
+        assert_eq!(SingleSessionApp::deadline_queue(7), Vec::<H256>::new());
+
+        // the queue of the next block is empty, the scan still finds the expired app
+        SingleSessionApp::offchain_worker(6);
+        let tx = pool_state.write().transactions.pop().unwrap();
+        assert!(pool_state.read().transactions.is_empty());
+        let tx = Extrinsic::decode(&mut &*tx).unwrap();
+        let call = crate::Call::finalize_on_action_timeout_unsigned(session_ids[2]);
+        assert_eq!(tx.call, mock::Call::SingleSessionApp(call.clone()));
+        assert_ok!(SingleSessionApp::finalize_on_action_timeout_unsigned(Origin::none(), session_ids[2]));
+        assert_eq!(SingleSessionApp::is_finalized(session_ids[2].encode()).unwrap(), true);
+
+        // finalized apps are not submitted again
+        SingleSessionApp::offchain_worker(6);
+        assert!(pool_state.read().transactions.is_empty());
+    })
+}
+
 fn get_state_proof(
     nonce: u128, 
     seq: u128, 
@@ -418,3 +1041,99 @@ fn get_state_proof(
     return state_proof;
 }
 
+
+fn settle_app(nonce: u128, players: Vec<AccountId>, players_pair: Vec<sr25519::Pair>) -> H256 {
+    let initiate_request = AppInitiateRequest {
+        nonce: nonce,
+        players: players.clone(),
+        timeout: 2,
+    };
+    assert_ok!(SingleSessionApp::app_initiate(
+        Origin::signed(players[0]),
+        initiate_request.clone()
+    ));
+
+    let session_id = SingleSessionApp::get_session_id(&initiate_request);
+    let state_proof = get_state_proof(nonce, 1, 5, 2, session_id, players_pair);
+    assert_ok!(
+        SingleSessionApp::update_by_state(
+            Origin::signed(players[0]),
+            state_proof
+        )
+    );
+
+    return session_id;
+}
+
+#[test]
+fn test_finalize_by_agreement() {
+    ExtBuilder::build().execute_with(|| {
+        System::set_block_number(1);
+        let alice_pair = account_pair("Alice");
+        let bob_pair = account_pair("Bob");
+        let (players, players_pair)
+            = get_sorted_peer(alice_pair, bob_pair);
+
+        let initiate_request = AppInitiateRequest {
+            nonce: 0,
+            players: players.clone(),
+            timeout: 2,
+        };
+        assert_ok!(SingleSessionApp::app_initiate(
+            Origin::signed(players[0]),
+            initiate_request.clone()
+        ));
+        let session_id = SingleSessionApp::get_session_id(&initiate_request);
+
+        // signatures of an ordinary state cannot finalize the app
+        let state_proof = get_state_proof(0, 2, 5, 2, session_id, players_pair.clone());
+        assert_noop!(
+            SingleSessionApp::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::InvalidSignature
+        );
+
+        // the final state is finalized in the same block from Idle
+        let state_proof = get_final_state_proof(0, 2, 5, 2, session_id, players_pair.clone());
+        assert_ok!(SingleSessionApp::finalize_by_agreement(
+            Origin::signed(players[0]),
+            state_proof
+        ));
+        assert_eq!(SingleSessionApp::get_status(session_id), Some(AppStatus::Finalized));
+        assert_eq!(SingleSessionApp::get_state(session_id), Some(5));
+        assert_eq!(SingleSessionApp::get_seq_num(session_id), Some(2));
+        assert_eq!(SingleSessionApp::is_finalized(session_id.encode()).unwrap(), true);
+        assert_eq!(SingleSessionApp::finalized_at(session_id), Some(1));
+        let expected_event = TestEvent::single_app(RawEvent::FinalizedByAgreement(session_id, 2));
+        assert!(System::events().iter().any(|a| a.event == expected_event));
+
+        let state_proof = get_final_state_proof(0, 3, 5, 2, session_id, players_pair);
+        assert_noop!(
+            SingleSessionApp::finalize_by_agreement(Origin::signed(players[0]), state_proof),
+            Error::<TestRuntime>::AlreadyFinalized
+        );
+    })
+}
+
+fn get_final_state_proof(
+    nonce: u128,
+    seq: u128,
+    state: u8,
+    timeout: BlockNumber,
+    session_id: H256,
+    players_pair: Vec<sr25519::Pair>
+) -> StateProof<BlockNumber, H256, Signature> {
+    let app_state = AppState {
+        nonce: nonce,
+        seq_num: seq,
+        state: state,
+        timeout: timeout,
+        session_id: session_id,
+    };
+    let encoded = SingleSessionApp::encode_final_app_state(app_state.clone());
+    let sigs = players_pair.iter().map(|pair| pair.sign(&encoded)).collect();
+
+    StateProof {
+        app_state: app_state,
+        sigs: sigs,
+    }
+}